@@ -1,12 +1,49 @@
 use eframe::egui::{self, Color32, Pos2, Rect, Sense, Stroke, TextureHandle, Vec2, Align2, FontId, FontFamily};
-use image::io::Reader as ImageReader;
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use rfd::FileDialog;
 
 use crate::models::*;
 use crate::utils::*;
+use crate::history::*;
+use crate::notify::Notifications;
+use crate::storage::*;
+use crate::routes::RoutePreview;
+use crate::economy::*;
+use crate::validation::*;
+use crate::operations::*;
+use crate::migration::*;
+use crate::building_visibility::draw_dashed_outline;
+use crate::assets::*;
+use crate::cli::{load_data_file, DataFile};
+use crate::i18n::*;
+use crate::prep::PrepRecording;
+use crate::camera::CameraRecording;
+use crate::prep_run::PrepRun;
+use crate::tabs::{current_tab_strategies, StrategyTab, TabRequest};
+use crate::events::EventRef;
+use crate::wave_filter::WaveFilter;
+use crate::transform::ResizeDialog;
+use crate::terrain_image::TerrainImageImport;
+use crate::calibration::{AlignHandle, Calibration};
+use crate::grid_detect::GridDetection;
+use crate::references::ReferenceImage;
+use crate::building_info::BuildingEdit;
+use crate::renumber::RenumberDialog;
+use crate::wave_shift::WaveShiftDialog;
+use crate::orphans::OrphanCleanupDialog;
+use crate::strategy_import::StrategyImportDialog;
+use crate::partial_import::PartialImportDialog;
+use crate::legacy::LegacyImportDialog;
+use crate::roundtrip::RoundTripDialog;
+use crate::dirty::{DirtyState, DiscardAction};
+use crate::export_summary::ExportDialog;
+use crate::scripting::ScriptConsole;
+use crate::plugins::{self, PluginRegistry};
+use crate::http_api::{self, HttpApi};
+use crate::live_sync::{self, LiveSync};
+use crate::procedural::ProceduralBrush;
+use crate::time_model::{set_time_model, with_time_model};
 
 pub struct MapEditor {
     pub(crate) texture: Option<TextureHandle>,
@@ -27,20 +64,115 @@ pub struct MapEditor {
     pub(crate) current_edit_layer_type: BuildingType,
     pub(crate) current_brush: i8,
     pub(crate) brush_radius: i32, 
+    pub(crate) terrain_tool: TerrainTool,
+    pub(crate) procedural_brush: ProceduralBrush,
+    // 矩形等形状工具的起点格子 (列, 行) 与填充值
+    pub(crate) terrain_shape_origin: Option<(i32, i32)>,
+    pub(crate) terrain_shape_value: i8,
+    pub(crate) terrain_types: Vec<TerrainType>,
+    pub(crate) time_model: TimeModel,
+    pub(crate) enemy_routes: Vec<EnemyRoute>,
+    pub(crate) selected_route_idx: usize,
+    pub(crate) route_point_kind: RoutePointKind,
+    // 正在拖动的路线点（当前路线中的下标）
+    pub(crate) route_drag: Option<usize>,
+    pub(crate) show_route_preview: bool,
+    pub(crate) route_preview: Option<Vec<RoutePreview>>,
+    pub(crate) route_preview_major_z: i32,
+    // 图层显示：隐藏的主层级 / 子层类型，以及地形叠加的不透明度（不影响导出数据）
+    pub(crate) hidden_major_z: HashSet<i32>,
+    pub(crate) hidden_layer_types: HashSet<BuildingType>,
+    pub(crate) terrain_opacity: f32,
     pub(crate) zoom: f32,
     pub(crate) pan: Vec2,
     pub(crate) mode: EditMode,
     pub(crate) building_templates: Vec<BuildingTemplate>,
     pub(crate) selected_building_idx: usize,
+    pub(crate) building_filter: String,
+    pub(crate) placement_snap: PlacementSnap,
     pub(crate) selected_upgrade_target_idx: usize, 
     pub(crate) placed_buildings: Vec<PlacedBuilding>,
     pub(crate) next_uid: usize,
     pub(crate) map_filename: String,
+    pub(crate) export_format: DataFormat,
+    pub(crate) base_image_path: Option<String>,
+    pub(crate) reference_images: Vec<ReferenceImage>,
+    pub(crate) preset_name: Option<String>,
     pub(crate) presets: Vec<MapPreset>,
+    pub(crate) asset_root: PathBuf,
+    pub(crate) config_watcher: ConfigWatcher,
+    pub(crate) auto_reload_configs: bool,
+    pub(crate) show_minimap: bool,
+    pub(crate) show_rulers: bool,
+    pub(crate) show_heatmap: bool,
+    pub(crate) show_consistency: bool,
+    pub(crate) consistency_rules: Vec<ConsistencyRule>,
+    pub(crate) show_building_tooltips: bool,
+    pub(crate) show_terrain_legend: bool,
+    pub(crate) show_build_order: bool,
+    // 光标所在格子在当前编辑层的地形值，光标不在网格内时为 None
+    pub(crate) hovered_terrain: Option<i8>,
+    pub(crate) wave_filter: WaveFilter,
+    pub(crate) minimap_drag: bool,
+    pub(crate) languages: Vec<Language>,
+    pub(crate) language: String,
+    // 界面主题与画布背景色：地形叠加色的半透明效果在不同底图上需要不同的背景衬托
+    pub(crate) dark_theme: bool,
+    pub(crate) canvas_background: Color32,
     pub current_wave_num: i32,
     pub current_is_late: bool,
     pub(crate) upgrade_events: Vec<UpgradeEvent>,
+    pub(crate) economy: Option<EconomyConfig>,
+    pub(crate) show_economy: bool,
+    pub(crate) show_script_console: bool,
+    pub(crate) script_console: ScriptConsole,
+    pub(crate) plugins: PluginRegistry,
+    pub(crate) http_api: Option<HttpApi>,
+    pub(crate) http_api_port: u16,
+    pub(crate) live_sync: Option<LiveSync>,
+    pub(crate) live_sync_port: u16,
+    // 校验结果窗口；点击条目后高亮的格子区域 (列, 行, 宽, 高)
+    pub(crate) validation_findings: Option<Vec<Finding>>,
+    pub(crate) finding_highlight: Option<(usize, usize, usize, usize)>,
+    pub(crate) canvas_rect: Rect,
     pub(crate) demolish_events: Vec<DemolishEvent>,
+    pub(crate) sell_events: Vec<SellEvent>,
+    pub(crate) relocate_events: Vec<RelocateEvent>,
+    // 迁移模式中已选中、等待放置的建筑 uid
+    pub(crate) relocate_pick: Option<usize>,
+    // 升级模式下点选的单体升级目标，以及升级列表中悬停的单体升级
+    pub(crate) upgrade_target_uid: Option<usize>,
+    pub(crate) upgrade_hover_uid: Option<usize>,
+    pub(crate) editing_event: Option<EventRef>,
+    // 事件列表中勾选的条目与批量平移的波数
+    pub(crate) checked_events: HashSet<EventRef>,
+    pub(crate) bulk_wave_shift: i32,
+    // 技能模式：待添加的技能名称，以及是否需要在地图上点选目标格子
+    pub(crate) ability_events: Vec<AbilityEvent>,
+    pub(crate) ability_name: String,
+    pub(crate) ability_targeted: bool,
+    pub(crate) wave_notes: HashMap<i32, String>,
+    pub(crate) locked_waves: BTreeSet<i32>,
+    // 正在编辑备注的建筑 uid 与编辑中的文本；选择模式下右键菜单对应的建筑
+    pub(crate) comment_edit: Option<(usize, String)>,
+    pub(crate) building_edit: Option<BuildingEdit>,
+    pub(crate) renumber_dialog: Option<RenumberDialog>,
+    pub(crate) wave_shift_dialog: Option<WaveShiftDialog>,
+    pub(crate) orphan_cleanup_dialog: Option<OrphanCleanupDialog>,
+    pub(crate) strategy_import_dialog: Option<StrategyImportDialog>,
+    pub(crate) partial_import_dialog: Option<PartialImportDialog>,
+    pub(crate) legacy_import_dialog: Option<LegacyImportDialog>,
+    pub(crate) roundtrip_dialog: Option<RoundTripDialog>,
+    pub(crate) dirty_state: DirtyState,
+    pub(crate) export_dialog: Option<ExportDialog>,
+    pub(crate) context_menu_uid: Option<usize>,
+    pub(crate) resize_dialog: Option<ResizeDialog>,
+    pub(crate) terrain_image_import: Option<TerrainImageImport>,
+    pub(crate) calibration: Option<Calibration>,
+    pub(crate) grid_detection: Option<GridDetection>,
+    // 地形模式下在画布上显示网格对齐手柄；正在拖动的手柄
+    pub(crate) show_align_handles: bool,
+    pub(crate) align_drag: Option<AlignHandle>,
     pub(crate) hover_info: String,
     pub(crate) building_configs: Vec<BuildingConfig>,
     pub(crate) building_config_icons: Vec<Option<TextureHandle>>,
@@ -50,51 +182,58 @@ pub struct MapEditor {
     pub(crate) viewport_height: f32,
     pub(crate) viewport_safe_areas: Vec<Rect>,
     pub(crate) prep_actions: Vec<PrepAction>,
+    // 准备动作试运行：倒计时秒数、客户区原点的屏幕坐标、运行中的后台线程与日志
+    pub(crate) prep_run: Option<PrepRun>,
+    pub(crate) prep_run_countdown: f32,
+    pub(crate) prep_run_origin: (i32, i32),
+    pub(crate) prep_run_log: Vec<String>,
+    pub(crate) prep_recording: Option<PrepRecording>,
+    pub(crate) camera_keyframes: Vec<CameraKeyframe>,
+    pub(crate) camera_recording: Option<CameraRecording>,
+    pub(crate) viewport_presets: Vec<ViewportPreset>,
+    pub(crate) viewport_preset_name: String,
+    // 当前标签页的策略撤销历史；地形等共用数据的撤销历史在 shared_history
+    pub(crate) history: History<StrategySnapshot>,
+    pub(crate) shared_history: History<SharedEntry>,
+    // 本帧整图变换前各标签页的策略，帧结束时记入共用历史
+    pub(crate) pending_tab_undo: Option<Vec<(usize, MapBuildingsExport)>>,
+    // 最近一条撤销历史的序号
+    pub(crate) history_seq: u64,
+    // 策略标签页，当前标签页的策略即编辑器中的数据
+    pub(crate) strategy_tabs: Vec<StrategyTab>,
+    pub(crate) active_tab: usize,
+    pub(crate) tab_request: Option<TabRequest>,
+    pub(crate) notifications: Notifications,
+    pub(crate) selected_uids: Vec<usize>,
+    pub(crate) selection_box_origin: Option<Pos2>,
+    pub(crate) demolish_box_origin: Option<Pos2>,
+    pub(crate) bulk_demolish_template: String,
+    pub(crate) building_drag: Option<BuildingDrag>,
+    pub(crate) building_clipboard: Vec<BuildingExport>,
+    pub(crate) stamps: Vec<Stamp>,
+    pub(crate) building_visibility: BuildingVisibility,
+    pub(crate) stamp_name: String,
+    pub(crate) active_stamp: Option<usize>,
+    pub(crate) playback_active: bool,
+    pub(crate) playback_speed: f32,
+    pub(crate) playback_progress: f32,
+    pub(crate) history_request: Option<HistoryRequest>,
 }
 
 impl MapEditor {
-    fn load_icon(ctx: &egui::Context, path: &str) -> Option<TextureHandle> {
-        let full_path = fix_path(path);
-        if let Ok(img_reader) = ImageReader::open(&full_path) {
-            if let Ok(img) = img_reader.decode() {
-                let size = [img.width() as _, img.height() as _];
-                let color_image = egui::ColorImage::from_rgba_unmultiplied(size, img.to_rgba8().as_flat_samples().as_slice());
-                return Some(ctx.load_texture(&full_path, color_image, Default::default()));
-            }
-        }
-        None
+    fn load_icon(ctx: &egui::Context, root: &Path, path: &str) -> Result<TextureHandle, String> {
+        let full_path = resolve_asset(root, path);
+        let img = read_image(&full_path)?;
+        let size = [img.width() as _, img.height() as _];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, img.to_rgba8().as_flat_samples().as_slice());
+        Ok(ctx.load_texture(full_path.to_string_lossy(), color_image, Default::default()))
     }
 
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut b_templates = Vec::new();
-        let mut b_configs = Vec::new();
-        let mut b_config_icons = Vec::new();
-        if let Ok(config_str) = fs::read_to_string("maps/buildings_config.json") {
-            if let Ok(configs) = serde_json::from_str::<Vec<BuildingConfig>>(&config_str) {
-                b_configs = configs.clone();
-                for cfg in configs {
-                    let icon = Self::load_icon(&cc.egui_ctx, &cfg.icon_path);
-                    b_templates.push(BuildingTemplate {
-                        name: cfg.name,
-                        b_type: cfg.b_type,
-                        width: cfg.width, height: cfg.height,
-                        color: Color32::from_rgba_unmultiplied(cfg.color[0], cfg.color[1], cfg.color[2], cfg.color[3]),
-                        icon: icon.clone(),
-                    });
-                    b_config_icons.push(icon);
-                }
-            }
-        }
-        if b_templates.is_empty() {
-            b_templates.push(BuildingTemplate { name: "默认 (1x1)".into(), b_type: BuildingType::Floor, width: 1, height: 1, color: Color32::GRAY, icon: None });
-            b_config_icons.push(None);
-        }
-
-        let mut map_presets = Vec::new();
-        if let Ok(pre_str) = fs::read_to_string("maps/map_presets.json") {
-            if let Ok(presets) = serde_json::from_str::<Vec<MapPreset>>(&pre_str) { map_presets = presets; }
-        }
+    fn load_tier_icons(ctx: &egui::Context, root: &Path, config: &BuildingConfig, errors: &mut Vec<String>) -> Vec<Option<TextureHandle>> {
+        config.upgrades.iter().map(|l| l.icon_path.as_ref().and_then(|p| Self::load_icon(ctx, root, p).map_err(|e| errors.push(e)).ok())).collect()
+    }
 
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let mut editor = Self {
             texture: None, grid_width: 32.0, grid_height: 32.0, offset_x: 0.0, offset_y: 0.0, 
             map_bottom: 1080.0, map_right: 1920.0,
@@ -103,24 +242,46 @@ impl MapEditor {
             layers_data: HashMap::new(), 
             current_edit_layer_type: BuildingType::Floor,
             current_brush: 0, brush_radius: 0,
+            terrain_tool: TerrainTool::Brush, procedural_brush: ProceduralBrush::default(), terrain_shape_origin: None, terrain_shape_value: 0,
+            terrain_types: default_terrain_types(),
+            time_model: TimeModel::default(),
+            enemy_routes: Vec::new(), selected_route_idx: 0, route_point_kind: RoutePointKind::Spawn, route_drag: None,
+            show_route_preview: true, route_preview: None, route_preview_major_z: 0,
+            hidden_major_z: HashSet::new(), hidden_layer_types: HashSet::new(), terrain_opacity: 1.0,
             zoom: 1.0, pan: Vec2::ZERO, mode: EditMode::Terrain,
-            building_templates: b_templates, selected_building_idx: 0, selected_upgrade_target_idx: 0,
+            building_templates: Vec::new(), selected_building_idx: 0, building_filter: String::new(), placement_snap: PlacementSnap::Cell, selected_upgrade_target_idx: 0,
             placed_buildings: Vec::new(), next_uid: 1000,
-            map_filename: "terrain_01.json".to_string(),
-            presets: map_presets, current_wave_num: 1, current_is_late: false,
-            upgrade_events: Vec::new(), demolish_events: Vec::new(),
+            map_filename: "terrain_01.json".to_string(), export_format: DataFormat::Json,
+            base_image_path: None, reference_images: Vec::new(), preset_name: None,
+            presets: Vec::new(), asset_root: default_asset_root(), config_watcher: ConfigWatcher::default(), auto_reload_configs: false, show_minimap: true, minimap_drag: false, show_rulers: true, show_heatmap: false, show_consistency: false, consistency_rules: default_consistency_rules(), show_building_tooltips: true, show_terrain_legend: true, show_build_order: false, hovered_terrain: None, wave_filter: WaveFilter::default(),
+            languages: available_languages(LANG_DIR), language: std::env::var(LANG_ENV).unwrap_or_else(|_| SOURCE_LANGUAGE.into()),
+            dark_theme: true, canvas_background: canvas_default_background(true),
+            current_wave_num: 1, current_is_late: false,
+            economy: None, show_economy: false, show_script_console: false, script_console: ScriptConsole::default(), plugins: plugins::registry(), http_api: None, http_api_port: http_api::DEFAULT_PORT, live_sync: None, live_sync_port: live_sync::DEFAULT_PORT,
+            validation_findings: None, finding_highlight: None, canvas_rect: Rect::NOTHING,
+            upgrade_events: Vec::new(), demolish_events: Vec::new(), sell_events: Vec::new(), relocate_events: Vec::new(), relocate_pick: None, upgrade_target_uid: None, upgrade_hover_uid: None, editing_event: None, checked_events: HashSet::new(), bulk_wave_shift: 1,
+            ability_events: Vec::new(), ability_name: String::new(), ability_targeted: true, wave_notes: HashMap::new(), locked_waves: BTreeSet::new(),
+            comment_edit: None, building_edit: None, renumber_dialog: None, wave_shift_dialog: None, orphan_cleanup_dialog: None, strategy_import_dialog: None, partial_import_dialog: None, legacy_import_dialog: None, roundtrip_dialog: None, dirty_state: DirtyState::default(), export_dialog: None, context_menu_uid: None, resize_dialog: None, terrain_image_import: None, calibration: None, grid_detection: None, show_align_handles: false, align_drag: None,
             hover_info: String::new(),
-            building_configs: b_configs,
-            building_config_icons: b_config_icons,
+            building_configs: Vec::new(),
+            building_config_icons: Vec::new(),
             editing_building_idx: None,
             viewport_pos: Vec2::ZERO,
             viewport_width: 1920.0,
             viewport_height: 1080.0,
             viewport_safe_areas: Vec::new(),
             prep_actions: Vec::new(),
+            prep_run: None, prep_run_countdown: 3.0, prep_run_origin: (0, 0), prep_run_log: Vec::new(), prep_recording: None,
+            camera_keyframes: Vec::new(), camera_recording: None, viewport_presets: Vec::new(), viewport_preset_name: String::new(),
+            history: History::new(DEFAULT_HISTORY_DEPTH), shared_history: History::new(DEFAULT_HISTORY_DEPTH), pending_tab_undo: None, history_seq: 0,
+            strategy_tabs: vec![StrategyTab::new(trf!("策略 {}", 1), DEFAULT_HISTORY_DEPTH)], active_tab: 0, tab_request: None,
+            notifications: Notifications::default(),
+            history_request: None,
+            selected_uids: Vec::new(), selection_box_origin: None, demolish_box_origin: None, bulk_demolish_template: String::new(), building_drag: None, building_clipboard: Vec::new(), stamps: Vec::new(), building_visibility: BuildingVisibility::default(), stamp_name: String::new(), active_stamp: None,
+            playback_active: false, playback_speed: 1.0, playback_progress: 0.0,
         };
 
-        let default_grid = vec![vec![-1; 40]; 40];
+        let default_grid = Grid::new(40, 40, -1);
         editor.layers_data.insert(0, LayerData {
             major_z: 0,
             name: "Default Layer".into(),
@@ -130,98 +291,433 @@ impl MapEditor {
             elevation_grid: None, 
         });
 
+        editor.load_asset_configs(&cc.egui_ctx);
+        let language = editor.language.clone();
+        editor.switch_language(&language);
+        editor.mark_saved();
         editor
     }
 
-    fn apply_preset(&mut self, ctx: &egui::Context, preset: &MapPreset) {
-        let image_p = fix_path(&preset.image_path);
-        let terrain_p = fix_path(&preset.terrain_path);
-        let building_configs_p = fix_path(&preset.building_configs_path);
-        let strategy_p = fix_path(&preset.strategy_path);
-        
-        if let Ok(img_reader) = ImageReader::open(&image_p) {
-            if let Ok(img) = img_reader.decode() {
+    fn switch_language(&mut self, code: &str) {
+        match set_language(LANG_DIR, code) {
+            Ok(()) => self.language = code.to_string(),
+            Err(e) => {
+                self.notifications.warn(e);
+                self.language = SOURCE_LANGUAGE.into();
+                let _ = set_language(LANG_DIR, SOURCE_LANGUAGE);
+            }
+        }
+    }
+
+    // 语言选择器的文字不翻译，各语言的使用者都能认出
+    fn show_language_selector(&mut self, ui: &mut egui::Ui) {
+        let current = self.languages.iter().find(|l| l.code == self.language).map_or(self.language.clone(), |l| l.name.clone());
+        let mut selected = None;
+        egui::ComboBox::from_id_source("language").selected_text(format!("🌐 {}", current)).show_ui(ui, |ui| {
+            for lang in &self.languages {
+                if ui.selectable_label(lang.code == self.language, &lang.name).clicked() { selected = Some(lang.code.clone()); }
+            }
+        });
+        if let Some(code) = selected { self.switch_language(&code); }
+    }
+
+    // 切换主题时，背景色若仍是旧主题的默认值则一并切换
+    fn show_appearance_settings(&mut self, ui: &mut egui::Ui) {
+        let label = if self.dark_theme { tr("🌙 深色") } else { tr("☀ 浅色") };
+        if ui.button(label).on_hover_text(tr("切换深色/浅色界面")).clicked() {
+            if self.canvas_background == canvas_default_background(self.dark_theme) { self.canvas_background = canvas_default_background(!self.dark_theme); }
+            self.dark_theme = !self.dark_theme;
+            ui.ctx().set_visuals(if self.dark_theme { egui::Visuals::dark() } else { egui::Visuals::light() });
+        }
+        ui.label(tr("画布背景:"));
+        ui.color_edit_button_srgba(&mut self.canvas_background);
+        if ui.small_button("↺").on_hover_text(tr("恢复主题默认背景色")).clicked() { self.canvas_background = canvas_default_background(self.dark_theme); }
+    }
+
+    // 从资源根目录读取地形调色板、防御塔列表与地图预设
+    fn load_asset_configs(&mut self, ctx: &egui::Context) {
+        match load_terrain_types(resolve_asset(&self.asset_root, TERRAIN_TYPES_FILE)) {
+            Ok(types) => self.terrain_types = types,
+            Err(e) => self.notifications.warn(trf!("{}，使用内置地形类型", e)),
+        }
+        let configs = match read_data::<Vec<BuildingConfig>>(resolve_asset(&self.asset_root, BUILDING_CONFIGS_FILE)) {
+            Ok(configs) => configs,
+            Err(e) => { self.notifications.warn(e); Vec::new() }
+        };
+        self.apply_building_configs(ctx, configs);
+        match read_data::<Vec<MapPreset>>(resolve_asset(&self.asset_root, PRESETS_FILE)) {
+            Ok(presets) => self.presets = presets,
+            Err(e) => self.notifications.warn(e),
+        }
+        match load_consistency_rules(resolve_asset(&self.asset_root, CONSISTENCY_RULES_FILE)) {
+            Ok(rules) => self.consistency_rules = rules,
+            Err(e) => self.notifications.warn(e),
+        }
+        self.load_stamps();
+        self.load_building_visibility();
+        self.track_asset_files();
+    }
+
+    fn track_asset_files(&mut self) {
+        let mut watched: Vec<PathBuf> = [TERRAIN_TYPES_FILE, BUILDING_CONFIGS_FILE, PRESETS_FILE, CONSISTENCY_RULES_FILE].iter().map(|f| resolve_asset(&self.asset_root, f)).collect();
+        watched.extend(self.building_configs.iter().map(|c| resolve_asset(&self.asset_root, &c.icon_path)));
+        watched.extend(self.building_configs.iter().flat_map(|c| &c.upgrades).filter_map(|l| l.icon_path.as_ref()).map(|p| resolve_asset(&self.asset_root, p)));
+        self.config_watcher.track(watched);
+    }
+
+    fn reload_asset_configs(&mut self, ctx: &egui::Context) {
+        self.load_asset_configs(ctx);
+        self.notifications.info(trf!("已重新加载 {} 下的配置", self.asset_root.display()));
+    }
+
+    fn pick_asset_root(&mut self, ctx: &egui::Context) {
+        if let Some(dir) = FileDialog::new().set_directory(&self.asset_root).pick_folder() {
+            self.asset_root = dir;
+            self.load_asset_configs(ctx);
+            self.notifications.info(trf!("资源根目录: {}", self.asset_root.display()));
+        }
+    }
+
+    fn load_base_image(&mut self, ctx: &egui::Context, path: &str) -> bool {
+        match read_image(path) {
+            Ok(img) => {
                 let size = [img.width() as _, img.height() as _];
                 let color_image = egui::ColorImage::from_rgba_unmultiplied(size, img.to_rgba8().as_flat_samples().as_slice());
-                self.texture = Some(ctx.load_texture(&image_p, color_image, Default::default()));
+                self.texture = Some(ctx.load_texture(path, color_image, Default::default()));
                 self.map_bottom = size[1] as f32;
+                self.base_image_path = Some(path.to_string());
+                self.grid_detection = None;
+                true
             }
+            Err(e) => { self.notifications.error(e); false }
         }
-        if let Ok(content) = fs::read_to_string(&terrain_p) {
-            if let Ok(data) = serde_json::from_str::<MapTerrainExport>(&content) {
-                self.grid_width = data.meta.grid_pixel_width; self.grid_height = data.meta.grid_pixel_height; self.offset_x = data.meta.offset_x; self.offset_y = data.meta.offset_y;
-                if data.meta.bottom > 0.0 { self.map_bottom = data.meta.bottom; }
-                if data.meta.right > 0.0 { self.map_right = data.meta.right; }
-                self.camera_speed_up = data.meta.camera_speed_up;
-                self.camera_speed_down = data.meta.camera_speed_down;
-                self.camera_speed_left = data.meta.camera_speed_left;
-                self.camera_speed_right = data.meta.camera_speed_right;
-                self.viewport_safe_areas = data.meta.viewport_safe_areas.iter().map(|a| (*a).into()).collect();
-                self.prep_actions = data.meta.prep_actions;
-                self.layers_data.clear();
-                for mut layer in data.layers {
-                    layer.normalize();
-                    if !layer.floor_grid.is_empty() {
-                        self.grid_rows = layer.floor_grid.len();
-                        self.grid_cols = layer.floor_grid[0].len();
-                    }
-                    self.layers_data.insert(layer.major_z, layer);
-                }
-                self.resize_grids();
-                self.map_filename = Path::new(&terrain_p).file_name().unwrap().to_string_lossy().into();
+    }
+
+    pub(crate) fn apply_terrain_data(&mut self, mut data: MapTerrainExport) {
+        data.normalize();
+        self.grid_width = data.meta.grid_pixel_width; self.grid_height = data.meta.grid_pixel_height; self.offset_x = data.meta.offset_x; self.offset_y = data.meta.offset_y;
+        if data.meta.bottom > 0.0 { self.map_bottom = data.meta.bottom; }
+        if data.meta.right > 0.0 { self.map_right = data.meta.right; }
+        self.camera_speed_up = data.meta.camera_speed_up;
+        self.camera_speed_down = data.meta.camera_speed_down;
+        self.camera_speed_left = data.meta.camera_speed_left;
+        self.camera_speed_right = data.meta.camera_speed_right;
+        self.viewport_safe_areas = data.meta.viewport_safe_areas.iter().map(|a| (*a).into()).collect();
+        self.prep_actions = data.meta.prep_actions;
+        self.camera_keyframes = data.meta.camera_keyframes;
+        self.camera_recording = None;
+        self.viewport_presets = data.meta.viewport_presets;
+        self.time_model = data.meta.time_model;
+        set_time_model(&self.time_model);
+        self.enemy_routes = data.routes;
+        self.selected_route_idx = 0;
+        self.layers_data.clear();
+        for layer in data.layers {
+            if !layer.floor_grid.is_empty() {
+                self.grid_rows = layer.floor_grid.rows();
+                self.grid_cols = layer.floor_grid.cols();
+            }
+            self.layers_data.insert(layer.major_z, layer);
+        }
+        self.resize_grids();
+        self.ensure_current_layer();
+    }
+
+    pub(crate) fn apply_buildings_data(&mut self, data: MapBuildingsExport) {
+        self.placed_buildings = data.buildings.iter().map(|b| {
+            let template = self.building_templates.iter().find(|t| t.name == b.name);
+            let color = template.map(|t| t.color).unwrap_or(Color32::GRAY);
+            PlacedBuilding { 
+                uid: b.uid, 
+                template_name: b.name.clone(), 
+                b_type: b.b_type,
+                grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, 
+                color, wave_num: b.wave_num, is_late: b.is_late, viewport: b.viewport.clone(), comment: b.comment.clone(), offset: b.offset, offset_seconds: b.offset_seconds, order: b.order,
+            }
+        }).collect();
+        self.next_uid = self.placed_buildings.iter().map(|b| b.uid).max().unwrap_or(1000) + 1;
+        self.upgrade_events = data.upgrades;
+        self.demolish_events = data.demolishes; 
+        self.sell_events = data.sells;
+        self.relocate_events = data.relocations;
+        self.ability_events = data.abilities;
+        self.wave_notes = data.wave_notes;
+        self.locked_waves = data.locked_waves;
+        self.relocate_pick = None;
+        self.upgrade_target_uid = None;
+        self.editing_event = None;
+        self.checked_events.clear();
+        self.economy = data.economy;
+    }
+
+    fn apply_building_configs(&mut self, ctx: &egui::Context, data: Vec<BuildingConfig>) {
+        self.building_configs = data;
+        self.building_config_icons.clear();
+        let mut icon_errors = Vec::new();
+        let root = self.asset_root.clone();
+        self.building_templates = self.building_configs.iter().map(|config| {
+            let icon = Self::load_icon(ctx, &root, &config.icon_path).map_err(|e| icon_errors.push(e)).ok();
+            let tier_icons = Self::load_tier_icons(ctx, &root, config, &mut icon_errors);
+            // 与 building_configs 一一对应，加载失败的也占一格 None
+            self.building_config_icons.push(icon.clone());
+            BuildingTemplate {
+                name: config.name.clone(),
+                b_type: config.b_type,
+                width: config.width,
+                height: config.height,
+                color: Color32::from_rgba_unmultiplied(
+                    config.color[0], config.color[1], 
+                    config.color[2], config.color[3]
+                ),
+                icon,
+                category: config.category.trim().to_string(),
+                tags: config.tags.clone(),
+                tier_icons,
             }
+        }).collect();
+        if self.building_templates.is_empty() {
+            self.building_templates.push(BuildingTemplate { name: "默认 (1x1)".into(), b_type: BuildingType::Floor, width: 1, height: 1, color: Color32::GRAY, icon: None, category: String::new(), tags: Vec::new(), tier_icons: Vec::new() });
         }
+        self.selected_building_idx = self.selected_building_idx.min(self.building_templates.len() - 1);
+        self.selected_upgrade_target_idx = self.selected_upgrade_target_idx.min(self.building_templates.len() - 1);
+        self.editing_building_idx = None;
+        if !icon_errors.is_empty() {
+            self.notifications.warn(trf!("{} 个建筑图标加载失败，将以纯色显示。首个错误: {}", icon_errors.len(), icon_errors[0]));
+        }
+    }
+
+    // 建筑配置模式下修改图标路径后立即重新加载该图标，失败时以纯色显示
+    fn reload_config_icon(&mut self, ctx: &egui::Context, idx: usize) {
+        let Some(config) = self.building_configs.get(idx) else { return; };
+        let mut errors = Vec::new();
+        let icon = Self::load_icon(ctx, &self.asset_root, &config.icon_path).map_err(|e| errors.push(e)).ok();
+        let tier_icons = Self::load_tier_icons(ctx, &self.asset_root, config, &mut errors);
+        if let Some(slot) = self.building_config_icons.get_mut(idx) { *slot = icon.clone(); }
+        if let Some(template) = self.building_templates.get_mut(idx).filter(|t| t.name == config.name) { template.icon = icon; template.tier_icons = tier_icons; }
+        for e in errors { self.notifications.warn(trf!("图标加载失败: {}", e)); }
+        self.track_asset_files();
+    }
+
+    fn browse_config_icon(&mut self, ctx: &egui::Context, idx: usize) {
+        let dir = self.building_configs.get(idx).and_then(|c| resolve_asset(&self.asset_root, &c.icon_path).parent().map(Path::to_path_buf)).filter(|d| d.is_dir()).unwrap_or_else(|| self.asset_root.clone());
+        let Some(path) = FileDialog::new().set_directory(dir).add_filter(tr("图片文件"), IMAGE_EXTENSIONS).pick_file() else { return; };
+        let Some(config) = self.building_configs.get_mut(idx) else { return; };
+        config.icon_path = portable_path(&self.asset_root, &path);
+        self.reload_config_icon(ctx, idx);
+    }
+
+    pub(crate) fn apply_preset(&mut self, ctx: &egui::Context, preset: &MapPreset) {
+        let image_p = resolve_asset(&self.asset_root, &preset.image_path);
+        let terrain_p = resolve_asset(&self.asset_root, &preset.terrain_path);
+        let building_configs_p = resolve_asset(&self.asset_root, &preset.building_configs_path);
+        let strategy_p = resolve_asset(&self.asset_root, &preset.strategy_path);
         
-        // 加载建筑列表
-        if let Ok(content) = fs::read_to_string(&building_configs_p) {
-            if let Ok(data) = serde_json::from_str::<Vec<BuildingConfig>>(&content) {
-                self.building_configs = data;
-                self.building_config_icons.clear();
-                self.building_templates = self.building_configs.iter().map(|config| {
-                    let icon = Self::load_icon(ctx, &config.icon_path);
-                    self.building_config_icons.push(icon.clone());
-                    BuildingTemplate {
-                        name: config.name.clone(),
-                        b_type: config.b_type,
-                        width: config.width,
-                        height: config.height,
-                        color: Color32::from_rgba_unmultiplied(
-                            config.color[0], config.color[1], 
-                            config.color[2], config.color[3]
-                        ),
-                        icon,
-                    }
-                }).collect();
+        self.load_base_image(ctx, &image_p.to_string_lossy());
+        match read_migrated::<MapTerrainExport>(&terrain_p, migrate_terrain) {
+            Ok((data, report)) => {
+                self.report_migration(&terrain_p, &report);
+                self.apply_terrain_data(data);
+                self.map_filename = terrain_p.file_name().unwrap().to_string_lossy().into();
             }
+            Err(e) => self.notifications.error(e),
+        }
+        
+        // 加载建筑列表
+        match read_data::<Vec<BuildingConfig>>(&building_configs_p) {
+            Ok(data) => self.apply_building_configs(ctx, data),
+            Err(e) => self.notifications.error(e),
         }
         
         // 加载策略
-        if let Ok(content) = fs::read_to_string(&strategy_p) {
-            if let Ok(data) = serde_json::from_str::<MapBuildingsExport>(&content) {
-                self.placed_buildings = data.buildings.iter().map(|b| {
-                    let template = self.building_templates.iter().find(|t| t.name == b.name);
-                    let color = template.map(|t| t.color).unwrap_or(Color32::GRAY);
-                    PlacedBuilding { 
-                        uid: b.uid, 
-                        template_name: b.name.clone(), 
-                        b_type: b.b_type,
-                        grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, 
-                        color, wave_num: b.wave_num, is_late: b.is_late 
-                    }
-                }).collect();
-                self.next_uid = self.placed_buildings.iter().map(|b| b.uid).max().unwrap_or(1000) + 1;
-                self.upgrade_events = data.upgrades;
-                self.demolish_events = data.demolishes; 
+        match read_migrated::<MapBuildingsExport>(&strategy_p, migrate_strategy) {
+            Ok((data, report)) => { self.report_migration(&strategy_p, &report); self.apply_buildings_data(data); }
+            Err(e) => self.notifications.error(e),
+        }
+        self.notifications.info(trf!("已加载预设: {}", preset.name));
+        self.preset_name = Some(preset.name.clone());
+        self.mark_saved();
+    }
+
+    pub(crate) fn capture_snapshot(&self) -> EditSnapshot {
+        EditSnapshot { shared: self.capture_shared(), strategy: self.capture_strategy() }
+    }
+
+    fn capture_shared(&self) -> SharedSnapshot {
+        SharedSnapshot {
+            grid_rows: self.grid_rows,
+            grid_cols: self.grid_cols,
+            grid_width: self.grid_width,
+            grid_height: self.grid_height,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            layers_data: self.layers_data.clone(),
+            building_configs: self.building_configs.clone(),
+            building_config_icons: self.building_config_icons.clone(),
+            prep_actions: self.prep_actions.clone(),
+            camera_keyframes: self.camera_keyframes.clone(),
+            viewport_presets: self.viewport_presets.clone(),
+            viewport_safe_areas: self.viewport_safe_areas.clone(),
+            enemy_routes: self.enemy_routes.clone(),
+        }
+    }
+
+    fn capture_strategy(&self) -> StrategySnapshot {
+        StrategySnapshot {
+            placed_buildings: self.placed_buildings.clone(),
+            next_uid: self.next_uid,
+            upgrade_events: self.upgrade_events.clone(),
+            demolish_events: self.demolish_events.clone(),
+            sell_events: self.sell_events.clone(),
+            relocate_events: self.relocate_events.clone(),
+            ability_events: self.ability_events.clone(),
+            wave_notes: self.wave_notes.clone(),
+            locked_waves: self.locked_waves.clone(),
+            economy: self.economy.clone(),
+        }
+    }
+
+    fn restore_strategy(&mut self, snap: StrategySnapshot) {
+        self.placed_buildings = snap.placed_buildings;
+        self.next_uid = snap.next_uid;
+        self.upgrade_events = snap.upgrade_events;
+        self.demolish_events = snap.demolish_events;
+        self.sell_events = snap.sell_events;
+        self.relocate_events = snap.relocate_events;
+        self.ability_events = snap.ability_events;
+        self.wave_notes = snap.wave_notes;
+        self.locked_waves = snap.locked_waves;
+        self.economy = snap.economy;
+        // 勾选与正在编辑的事件按列表下标记录，撤销 / 重做后下标可能指向别的事件
+        self.checked_events.clear();
+        self.editing_event = None;
+        if self.relocate_pick.is_some_and(|uid| !self.placed_buildings.iter().any(|b| b.uid == uid)) { self.relocate_pick = None; }
+    }
+
+    fn restore_shared(&mut self, snap: SharedSnapshot) {
+        self.grid_rows = snap.grid_rows;
+        self.grid_cols = snap.grid_cols;
+        self.grid_width = snap.grid_width;
+        self.grid_height = snap.grid_height;
+        self.offset_x = snap.offset_x;
+        self.offset_y = snap.offset_y;
+        self.layers_data = snap.layers_data;
+        self.building_configs = snap.building_configs;
+        self.building_config_icons = snap.building_config_icons;
+        self.prep_actions = snap.prep_actions;
+        self.camera_keyframes = snap.camera_keyframes;
+        self.viewport_presets = snap.viewport_presets;
+        self.viewport_safe_areas = snap.viewport_safe_areas;
+        self.enemy_routes = snap.enemy_routes;
+        self.selected_route_idx = self.selected_route_idx.min(self.enemy_routes.len().saturating_sub(1));
+        self.route_drag = None;
+        self.route_preview = None;
+        self.ensure_current_layer();
+        if let Some(idx) = self.editing_building_idx {
+            if idx >= self.building_configs.len() { self.editing_building_idx = None; }
+        }
+    }
+
+    // 撤销/重做在帧开始时执行，避免被本帧的变更检测当作一次新的编辑
+    // 撤销取两份历史中序号最大（最近）的一条，重做取序号最小（最近撤销）的一条，序号相同的一起恢复
+    fn apply_history_request(&mut self, req: HistoryRequest) {
+        let (shared, strategy) = match req {
+            HistoryRequest::Undo => (self.shared_history.undo_seq(), self.history.undo_seq()),
+            HistoryRequest::Redo => (self.shared_history.redo_seq(), self.history.redo_seq()),
+        };
+        let seq = match req {
+            HistoryRequest::Undo => shared.max(strategy),
+            HistoryRequest::Redo => shared.into_iter().chain(strategy).min(),
+        };
+        if seq.is_none() { return; }
+        if shared == seq {
+            let snapshot = self.capture_shared();
+            let active = self.build_buildings_export();
+            let tabs = &self.strategy_tabs;
+            let current = |entry: &SharedEntry| SharedEntry { snapshot, strategies: current_tab_strategies(tabs, &active, &entry.strategies) };
+            let target = if req == HistoryRequest::Undo { self.shared_history.undo(current) } else { self.shared_history.redo(current) };
+            if let Some(entry) = target {
+                self.restore_shared(entry.snapshot);
+                self.restore_tab_strategies(entry.strategies);
+            }
+        }
+        if strategy == seq {
+            let current = self.capture_strategy();
+            let target = if req == HistoryRequest::Undo { self.history.undo(|_| current) } else { self.history.redo(|_| current) };
+            if let Some(snap) = target { self.restore_strategy(snap); }
+        }
+    }
+
+    pub(crate) fn can_undo(&self) -> bool { self.history.can_undo() || self.shared_history.can_undo() }
+    pub(crate) fn can_redo(&self) -> bool { self.history.can_redo() || self.shared_history.can_redo() }
+
+    // 每帧结束时把帧开始时的状态分别记入共用历史与当前标签页的历史
+    fn record_history(&mut self, before: EditSnapshot, after: &EditSnapshot, gesture_active: bool) {
+        let seq = self.history_seq + 1;
+        let shared_changed = before.shared != after.shared;
+        let strategy_changed = before.strategy != after.strategy;
+        // 整图变换记下了所有标签页（含当前标签页）的策略，整体作为共用历史的一条，不再单独记入当前标签页
+        let (shared, strategy) = match self.pending_tab_undo.take() {
+            Some(strategies) => { self.shared_history.push(seq, SharedEntry { snapshot: before.shared, strategies }); (true, false) }
+            None => {
+                let shared = self.shared_history.observe(seq, SharedEntry { snapshot: before.shared, strategies: Vec::new() }, shared_changed, gesture_active);
+                (shared, self.history.observe(seq, before.strategy, strategy_changed, gesture_active))
+            }
+        };
+        if shared || strategy {
+            self.history_seq = seq;
+            if !shared { self.shared_history.clear_redo(); }
+            if !strategy { self.history.clear_redo(); }
+        }
+    }
+
+    // 策略中最后一个事件的时间值，回放到此处停止
+    fn strategy_end_time(&self) -> i32 {
+        let builds = self.placed_buildings.iter().map(|b| get_time_value(b.wave_num, b.is_late));
+        let upgrades = self.upgrade_events.iter().map(|e| get_time_value(e.wave_num, e.is_late));
+        let demolishes = self.demolish_events.iter().map(|e| get_time_value(e.wave_num, e.is_late));
+        let sells = self.sell_events.iter().map(|e| get_time_value(e.wave_num, e.is_late));
+        let relocations = self.relocate_events.iter().map(|e| get_time_value(e.wave_num, e.is_late));
+        let abilities = self.ability_events.iter().map(|e| get_time_value(e.wave_num, e.is_late));
+        builds.chain(upgrades).chain(demolishes).chain(sells).chain(relocations).chain(abilities).max().unwrap_or(get_time_value(1, false))
+    }
+
+    fn toggle_playback(&mut self) {
+        if self.playback_active {
+            self.playback_active = false;
+            return;
+        }
+        if get_time_value(self.current_wave_num, self.current_is_late) >= self.strategy_end_time() {
+            self.current_wave_num = 1;
+            self.current_is_late = false;
+        }
+        self.playback_progress = 0.0;
+        self.playback_active = true;
+    }
+
+    // 回放：按 playback_speed（时间格/秒）推进当前波次
+    fn advance_playback(&mut self, dt: f32) {
+        self.playback_progress += dt * self.playback_speed;
+        while self.playback_progress >= 1.0 {
+            self.playback_progress -= 1.0;
+            let t_next = get_time_value(self.current_wave_num, self.current_is_late) + 1;
+            if t_next > self.strategy_end_time() {
+                self.playback_active = false;
+                self.playback_progress = 0.0;
+                return;
             }
+            (self.current_wave_num, self.current_is_late) = wave_from_time_value(t_next);
         }
     }
 
-    fn get_building_demolish_time(&self, uid: usize) -> i32 {
-        self.demolish_events.iter().find(|d| d.uid == uid).map(|d| get_time_value(d.wave_num, d.is_late)).unwrap_or(i32::MAX)
+    // 建筑被拆除或出售的时间值，没有则为 i32::MAX
+    pub(crate) fn get_building_demolish_time(&self, uid: usize) -> i32 {
+        let demolish = self.demolish_events.iter().filter(|d| d.uid == uid).map(|d| get_time_value(d.wave_num, d.is_late));
+        let sell = self.sell_events.iter().filter(|s| s.uid == uid).map(|s| get_time_value(s.wave_num, s.is_late));
+        demolish.chain(sell).min().unwrap_or(i32::MAX)
     }
 
     fn check_terrain_capability(&self, terrain_id: i8, b_type: BuildingType) -> bool {
-        if terrain_id < 0 { return false; }
+        if !self.terrain_types.iter().any(|t| t.id == terrain_id && t.buildable) { return false; }
         match b_type {
             BuildingType::Floor => true,
             BuildingType::Wall => true,
@@ -230,6 +726,14 @@ impl MapEditor {
     }
 
     fn can_place_building(&self, start_r: usize, start_c: usize, w: usize, h: usize, b_type: BuildingType) -> bool {
+        if self.current_wave_locked() { return false; }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        self.can_place_building_during(start_r, start_c, w, h, b_type, t_current, t_current + 1, &[])
+    }
+
+    // 检查在时间区间 [t_from, t_until) 内放置是否合法；ignore_uids 中的建筑（例如正在移动的建筑本身）不参与重叠判断
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn can_place_building_during(&self, start_r: usize, start_c: usize, w: usize, h: usize, b_type: BuildingType, t_from: i32, t_until: i32, ignore_uids: &[usize]) -> bool {
         if start_r + h > self.grid_rows || start_c + w > self.grid_cols { return false; }
         
         let layer = self.layers_data.get(&self.current_major_z).unwrap();
@@ -237,139 +741,334 @@ impl MapEditor {
         
         if target_grid.is_empty() { return false; }
 
-        let base_height = target_grid[start_r][start_c];
-        if base_height < 0 { return false; } 
+        let base_height = target_grid.get(start_r, start_c);
 
         for r in start_r..(start_r + h) {
             for c in start_c..(start_c + w) {
-                let cell_h = target_grid[r][c];
+                let cell_h = target_grid.get(r, c);
                 if cell_h != base_height { return false; }
                 if !self.check_terrain_capability(cell_h, b_type) { return false; }
             }
         }
 
-        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
         for b in &self.placed_buildings {
-            if b.b_type != b_type { continue; }
+            if b.b_type != b_type || ignore_uids.contains(&b.uid) { continue; }
 
-            if start_c < b.grid_x + b.width && start_c + w > b.grid_x && start_r < b.grid_y + b.height && start_r + h > b.grid_y {
-                let t_create = get_time_value(b.wave_num, b.is_late);
-                let t_demolish = self.get_building_demolish_time(b.uid);
-                if t_current >= t_create && t_current < t_demolish { return false; }
+            // 迁移过的建筑逐段检查各时间段所在的位置
+            for (t_start, t_end, x, y) in self.building_segments(b) {
+                if start_c < x + b.width && start_c + w > x && start_r < y + b.height && start_r + h > y && t_from < t_end && t_until > t_start { return false; }
             }
         }
         true
     }
 
-    fn resize_grids(&mut self) {
-        for layer in self.layers_data.values_mut() {
-            for grid in [&mut layer.floor_grid, &mut layer.wall_grid, &mut layer.ceiling_grid] {
-                if grid.is_empty() {
-                    *grid = vec![vec![-1; self.grid_cols]; self.grid_rows];
-                } else {
-                    grid.resize(self.grid_rows, vec![-1; self.grid_cols]);
-                    for row in grid.iter_mut() { row.resize(self.grid_cols, -1); }
+    // 当前时间点覆盖点 at（单位：格，可带小数）的建筑索引
+    fn active_building_at(&self, at: Pos2) -> Option<usize> {
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        self.placed_buildings.iter().position(|b| self.building_covers_at(b, at, t_current))
+    }
+
+    fn building_range(&self, b: &PlacedBuilding) -> Option<f32> {
+        let tier = self.upgrade_tier_at(&b.template_name, Some(b.uid), get_time_value(self.current_wave_num, self.current_is_late));
+        self.building_configs.iter().find(|c| c.name == b.template_name).and_then(|c| c.range_at_tier(tier))
+    }
+
+    // 射程覆盖的格子：格子中心到建筑中心的距离不超过射程
+    fn draw_building_range(&self, painter: &egui::Painter, origin: Pos2, cell: Vec2, b: &PlacedBuilding, range: f32) {
+        let (x, y) = self.building_pos_at(b, get_time_value(self.current_wave_num, self.current_is_late));
+        let (center_c, center_r) = (x as f32 + b.offset[0] + b.width as f32 / 2.0, y as f32 + b.offset[1] + b.height as f32 / 2.0);
+        let (c0, c1) = ((center_c - range).floor().max(0.0) as usize, ((center_c + range).ceil() as usize).min(self.grid_cols));
+        let (r0, r1) = ((center_r - range).floor().max(0.0) as usize, ((center_r + range).ceil() as usize).min(self.grid_rows));
+        let fill = Color32::from_rgba_unmultiplied(255, 160, 0, 50);
+        for r in r0..r1 {
+            for c in c0..c1 {
+                let (dx, dy) = (c as f32 + 0.5 - center_c, r as f32 + 0.5 - center_r);
+                if dx * dx + dy * dy <= range * range {
+                    painter.rect_filled(Rect::from_min_size(origin + Vec2::new(c as f32 * cell.x, r as f32 * cell.y), cell), 0.0, fill);
                 }
             }
         }
+        let center = origin + Vec2::new(center_c * cell.x, center_r * cell.y);
+        painter.circle_stroke(center, range * cell.x.min(cell.y), Stroke::new(1.5, Color32::from_rgb(255, 160, 0)));
     }
 
-    fn pick_and_load_image(&mut self, ctx: &egui::Context) {
-        if let Some(path) = FileDialog::new().add_filter("图片文件", &["png", "jpg", "jpeg", "bmp"]).pick_file() {
-            if let Ok(img_reader) = ImageReader::open(&path) {
-                if let Ok(img) = img_reader.decode() {
-                    let size = [img.width() as _, img.height() as _];
-                    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, img.to_rgba8().as_flat_samples().as_slice());
-                    self.texture = Some(ctx.load_texture(path.to_string_lossy(), color_image, Default::default()));
-                    self.map_bottom = size[1] as f32;
+    // 整组移动建筑（全部合法才生效），保留 uid/波次，并同步更新关联的拆除 / 出售 / 迁移事件坐标
+    pub(crate) fn move_buildings(&mut self, uids: &[usize], dx: i32, dy: i32) -> bool {
+        if !self.can_move_buildings(uids, dx, dy) { return false; }
+        for b in self.placed_buildings.iter_mut().filter(|b| uids.contains(&b.uid)) {
+            b.grid_x = (b.grid_x as i32 + dx) as usize;
+            b.grid_y = (b.grid_y as i32 + dy) as usize;
+        }
+        let shift = |x: &mut usize, d: i32| *x = (*x as i32 + d) as usize;
+        for e in self.demolish_events.iter_mut().filter(|e| uids.contains(&e.uid)) { shift(&mut e.grid_x, dx); shift(&mut e.grid_y, dy); }
+        for e in self.sell_events.iter_mut().filter(|e| uids.contains(&e.uid)) { shift(&mut e.grid_x, dx); shift(&mut e.grid_y, dy); }
+        for e in self.relocate_events.iter_mut().filter(|e| uids.contains(&e.uid)) {
+            shift(&mut e.from_x, dx); shift(&mut e.from_y, dy); shift(&mut e.to_x, dx); shift(&mut e.to_y, dy);
+        }
+        true
+    }
+
+    fn can_move_buildings(&self, uids: &[usize], dx: i32, dy: i32) -> bool {
+        if uids.iter().any(|uid| self.building_locked(*uid)) { return false; }
+        self.placed_buildings.iter().filter(|b| uids.contains(&b.uid)).all(|b| {
+            self.building_segments(b).into_iter().all(|(t_from, t_until, x, y)| {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                nx >= 0 && ny >= 0 && self.can_place_building_during(ny as usize, nx as usize, b.width, b.height, b.b_type, t_from, t_until, uids)
+            })
+        })
+    }
+
+    // 已有拆除或出售事件、或当前时间尚未建成的建筑不能拆除；返回是否添加了拆除事件
+    pub(crate) fn add_demolish_event(&mut self, uid: usize) -> bool {
+        if self.current_wave_locked() { return false; }
+        if self.demolish_events.iter().any(|e| e.uid == uid) || self.sell_events.iter().any(|e| e.uid == uid) { return false; }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        if !self.placed_buildings.iter().any(|b| b.uid == uid && get_time_value(b.wave_num, b.is_late) < t_current) { return false; }
+        if self.refuse_locked(self.relocations_locked_from(uid, t_current)) { return false; }
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return false; };
+        let (grid_x, grid_y) = self.building_pos_at(b, t_current);
+        self.demolish_events.push(DemolishEvent { uid: b.uid, name: b.template_name.clone(), grid_x, grid_y, width: b.width, height: b.height, wave_num: self.current_wave_num, is_late: self.current_is_late, viewport: self.current_viewport_preset(), offset_seconds: None, order: None });
+        self.relocate_events.retain(|r| r.uid != uid || get_time_value(r.wave_num, r.is_late) < t_current);
+        true
+    }
+
+    pub(crate) fn delete_buildings(&mut self, uids: &[usize]) {
+        if self.refuse_locked(uids.iter().any(|uid| self.building_locked(*uid) || self.building_events_locked(*uid))) { return; }
+        self.placed_buildings.retain(|b| !uids.contains(&b.uid));
+        self.prune_orphan_events();
+        self.selected_uids.retain(|uid| !uids.contains(uid));
+    }
+
+    // 复制选中建筑，坐标归一化到组的左上角
+    fn copy_selection(&mut self) -> Option<String> {
+        let selected: Vec<&PlacedBuilding> = self.placed_buildings.iter().filter(|b| self.selected_uids.contains(&b.uid)).collect();
+        let min_x = selected.iter().map(|b| b.grid_x).min()?;
+        let min_y = selected.iter().map(|b| b.grid_y).min()?;
+        self.building_clipboard = selected.iter().map(|b| BuildingExport {
+            uid: b.uid,
+            name: b.template_name.clone(),
+            b_type: b.b_type,
+            grid_x: b.grid_x - min_x, grid_y: b.grid_y - min_y, width: b.width, height: b.height,
+            wave_num: b.wave_num, is_late: b.is_late, viewport: b.viewport.clone(), comment: b.comment.clone(), offset: b.offset, offset_seconds: b.offset_seconds, order: b.order,
+        }).collect();
+        serde_json::to_string(&BuildingClipboard { minke_buildings: self.building_clipboard.clone() }).ok()
+    }
+
+    // 以 (cx, cy) 为左上角粘贴，分配新 uid 并使用当前波次；任一建筑不可放置则整组放弃
+    pub(crate) fn paste_buildings(&mut self, group: &[BuildingExport], cx: i32, cy: i32) -> bool {
+        if group.is_empty() || cx < 0 || cy < 0 { return false; }
+        let (cx, cy) = (cx as usize, cy as usize);
+        if !group.iter().all(|b| self.can_place_building(cy + b.grid_y, cx + b.grid_x, b.width, b.height, b.b_type)) { return false; }
+        self.selected_uids.clear();
+        let viewport = self.current_viewport_preset();
+        for b in group {
+            let color = self.building_templates.iter().find(|t| t.name == b.name).map(|t| t.color).unwrap_or(Color32::GRAY);
+            self.placed_buildings.push(PlacedBuilding {
+                uid: self.next_uid,
+                template_name: b.name.clone(),
+                b_type: b.b_type,
+                grid_x: cx + b.grid_x, grid_y: cy + b.grid_y, width: b.width, height: b.height,
+                color, wave_num: self.current_wave_num, is_late: self.current_is_late, viewport: viewport.clone(), comment: b.comment.clone(), offset: b.offset, offset_seconds: None, order: None,
+            });
+            self.selected_uids.push(self.next_uid);
+            self.next_uid += 1;
+        }
+        true
+    }
+
+    fn show_selection_ops(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(trf!("选中建筑: {} 个", self.selected_uids.len()));
+            if self.selected_uids.len() == 1 {
+                if let Some(b) = self.placed_buildings.iter().find(|b| b.uid == self.selected_uids[0]) {
+                    ui.label(format!("{} (UID {})", b.template_name, b.uid));
+                    if let Some(v) = &b.viewport { ui.label(trf!("观察框预设: {}", v)); }
+                    ui.label(trf!("位置: ({}, {})  尺寸: {}x{}", b.grid_x, b.grid_y, b.width, b.height));
+                    ui.label(trf!("建造时间: {}", event_time_label(b.wave_num, b.is_late, b.offset_seconds)));
+                    if let Some(c) = &b.comment { ui.label(trf!("备注: {}", c)); }
+                    let uid = b.uid;
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("属性…")).on_hover_text(tr("也可以在画布上双击建筑")).clicked() { self.open_building_edit(uid); }
+                        if ui.button(tr("编辑备注…")).clicked() { self.open_comment_editor(uid); }
+                    });
                 }
+            } else if self.selected_uids.is_empty() {
+                ui.label(tr("点击或框选地图上的建筑"));
             }
+            if !self.selected_uids.is_empty() { ui.weak(tr("方向键移动一格，Shift + 方向键移动 5 格")); }
+            ui.add_enabled_ui(!self.selected_uids.is_empty(), |ui| {
+                ui.vertical_centered_justified(|ui| {
+                    let uids = self.selected_uids.clone();
+                    if ui.button(tr("设为当前波次")).clicked() && !self.refuse_locked(self.current_wave_locked() || uids.iter().any(|uid| self.building_locked(*uid))) {
+                        self.move_buildings_to_current_wave(&uids);
+                    }
+                    if ui.add_enabled(!self.current_wave_locked(), egui::Button::new(tr("在当前波次标记拆除"))).clicked() {
+                        for uid in &uids { self.add_demolish_event(*uid); }
+                    }
+                    if ui.button(tr("删除选中建筑")).clicked() { self.delete_buildings(&uids); }
+                    if ui.button(tr("导出选中…")).on_hover_text(tr("把选中的建筑及引用它们的事件写成一份策略文件")).clicked() { self.export_selection(); }
+                    if ui.button(tr("取消选择")).clicked() { self.selected_uids.clear(); }
+                });
+                self.show_save_stamp(ui);
+            });
+            ui.separator();
+            ui.vertical_centered_justified(|ui| {
+                if ui.add_enabled(!self.placed_buildings.is_empty(), egui::Button::new(tr("🔢 重新编号 UID…"))).on_hover_text(tr("把所有建筑的 UID 改为连续编号，并同步改写引用它们的事件")).clicked() { self.open_renumber_dialog(); }
+                if ui.button(tr("⏩ 整体平移波次…")).on_hover_text(tr("把全部或指定波次范围内的建筑与事件一起提前或推后若干波")).clicked() { self.open_wave_shift_dialog(); }
+                if ui.button(tr("🧹 清理孤立事件…")).on_hover_text(tr("删除目标建筑已不存在的拆除 / 出售 / 迁移 / 升级事件，以及模板已不在防御塔列表中的升级")).clicked() { self.open_orphan_cleanup(); }
+            });
+        });
+    }
+
+    // 以 (c, r) 为中心、radius 为半径的方形笔刷落笔
+    fn paint_terrain_square(&mut self, c: i32, r: i32, radius: i32, val: i8) {
+        let span = |center: i32| (center - radius).max(0) as usize..(center + radius + 1).max(0) as usize;
+        let grid = self.layers_data.get_mut(&self.current_major_z).unwrap().get_grid_mut(self.current_edit_layer_type);
+        grid.fill(span(r), span(c), val);
+    }
+
+    // 用 val 填充当前编辑层中两个角点围成的矩形（超出网格的部分自动裁剪）
+    fn fill_terrain_rect(&mut self, (c0, r0): (i32, i32), (c1, r1): (i32, i32), val: i8) {
+        let span = |a: i32, b: i32| a.min(b).max(0) as usize..(a.max(b) + 1).max(0) as usize;
+        let grid = self.layers_data.get_mut(&self.current_major_z).unwrap().get_grid_mut(self.current_edit_layer_type);
+        grid.fill(span(r0, r1), span(c0, c1), val);
+    }
+
+    // 当前编辑层不存在时（导入数据、撤销新建层）回退到最低的层级
+    fn ensure_current_layer(&mut self) {
+        if !self.layers_data.contains_key(&self.current_major_z) {
+            if let Some(&z) = self.layers_data.keys().min() { self.current_major_z = z; }
         }
     }
 
-    fn import_terrain(&mut self) {
-        if let Some(path) = FileDialog::new().set_directory("output").add_filter("JSON地形", &["json"]).pick_file() {
-            if let Ok(content) = fs::read_to_string(path) {
-                if let Ok(data) = serde_json::from_str::<MapTerrainExport>(&content) {
-                    self.grid_width = data.meta.grid_pixel_width; self.grid_height = data.meta.grid_pixel_height; self.offset_x = data.meta.offset_x; self.offset_y = data.meta.offset_y;
-                    if data.meta.bottom > 0.0 { self.map_bottom = data.meta.bottom; }
-                    if data.meta.right > 0.0 { self.map_right = data.meta.right; }
-                    self.camera_speed_up = data.meta.camera_speed_up;
-                    self.camera_speed_down = data.meta.camera_speed_down;
-                    self.camera_speed_left = data.meta.camera_speed_left;
-                    self.camera_speed_right = data.meta.camera_speed_right;
-                    self.viewport_safe_areas = data.meta.viewport_safe_areas.iter().map(|a| (*a).into()).collect();
-                    self.prep_actions = data.meta.prep_actions;
-                    self.layers_data.clear();
-                    for mut layer in data.layers {
-                        layer.normalize();
-                        if !layer.floor_grid.is_empty() {
-                            self.grid_rows = layer.floor_grid.len();
-                            self.grid_cols = layer.floor_grid[0].len();
-                        }
-                        self.layers_data.insert(layer.major_z, layer);
-                    }
-                    self.resize_grids(); 
+    fn add_major_layer(&mut self) {
+        let major_z = self.layers_data.keys().max().map_or(0, |z| z + 1);
+        let empty = Grid::new(self.grid_rows, self.grid_cols, -1);
+        self.layers_data.insert(major_z, LayerData {
+            major_z,
+            name: format!("Layer {}", major_z),
+            floor_grid: empty.clone(),
+            wall_grid: empty.clone(),
+            ceiling_grid: empty,
+            elevation_grid: None,
+        });
+        self.current_major_z = major_z;
+    }
+
+    fn show_layer_visibility(&mut self, ui: &mut egui::Ui) {
+        ui.label(tr("主层级 (勾选显示 / 单选编辑):"));
+        let mut zs: Vec<i32> = self.layers_data.keys().copied().collect();
+        zs.sort();
+        for z in zs {
+            ui.horizontal(|ui| {
+                let mut visible = !self.hidden_major_z.contains(&z);
+                if ui.checkbox(&mut visible, "").changed() {
+                    if visible { self.hidden_major_z.remove(&z); } else { self.hidden_major_z.insert(z); }
                 }
+                let name = self.layers_data.get(&z).map(|l| l.name.clone()).unwrap_or_default();
+                ui.radio_value(&mut self.current_major_z, z, format!("{}: {}", z, name));
+            });
+        }
+        if ui.button(tr("➕ 新建层")).clicked() { self.add_major_layer(); }
+
+        ui.label(tr("子层显示:"));
+        ui.horizontal(|ui| {
+            for (l_type, label) in [(BuildingType::Floor, tr("地面")), (BuildingType::Wall, tr("墙壁")), (BuildingType::Ceiling, tr("吊顶"))] {
+                let mut visible = !self.hidden_layer_types.contains(&l_type);
+                if ui.checkbox(&mut visible, label).changed() {
+                    if visible { self.hidden_layer_types.remove(&l_type); } else { self.hidden_layer_types.insert(l_type); }
+                }
+            }
+        });
+        ui.add(egui::Slider::new(&mut self.terrain_opacity, 0.0..=1.0).text(tr("地形不透明度")));
+        ui.separator();
+        self.show_building_visibility(ui);
+    }
+
+    pub(crate) fn resize_grids(&mut self) {
+        for layer in self.layers_data.values_mut() {
+            for grid in [&mut layer.floor_grid, &mut layer.wall_grid, &mut layer.ceiling_grid] {
+                grid.resize(self.grid_rows, self.grid_cols, -1);
             }
         }
     }
 
-    fn import_buildings(&mut self) {
-        if let Some(path) = FileDialog::new().set_directory("output").add_filter("JSON策略", &["json"]).pick_file() {
-            if let Ok(content) = fs::read_to_string(path) {
-                if let Ok(data) = serde_json::from_str::<MapBuildingsExport>(&content) {
-                    self.placed_buildings = data.buildings.iter().map(|b| {
-                        let template = self.building_templates.iter().find(|t| t.name == b.name);
-                        let color = template.map(|t| t.color).unwrap_or(Color32::GRAY);
-                        PlacedBuilding { 
-                            uid: b.uid, 
-                            template_name: b.name.clone(), 
-                            b_type: b.b_type,
-                            grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, 
-                            color, wave_num: b.wave_num, is_late: b.is_late 
-                        }
-                    }).collect();
-                    self.next_uid = self.placed_buildings.iter().map(|b| b.uid).max().unwrap_or(1000) + 1;
-                    self.upgrade_events = data.upgrades;
-                    self.demolish_events = data.demolishes; 
-                }
+    pub(crate) fn report_migration(&mut self, path: &Path, report: &MigrationReport) {
+        if !report.is_empty() { self.notifications.info(format!("{}: {}", path.display(), report.summary())); }
+    }
+
+    fn pick_and_load_image(&mut self, ctx: &egui::Context) {
+        if let Some(path) = FileDialog::new().add_filter(tr("图片文件"), IMAGE_EXTENSIONS).pick_file() {
+            self.load_base_image(ctx, &path.to_string_lossy());
+        }
+    }
+
+    pub(crate) fn import_terrain(&mut self) {
+        if let Some(path) = FileDialog::new().set_directory("output").add_filter(tr("地形文件"), DATA_EXTENSIONS).pick_file() {
+            match read_migrated::<MapTerrainExport>(&path, migrate_terrain) {
+                Ok((data, report)) => { self.report_migration(&path, &report); self.apply_terrain_data(data); self.notifications.info(trf!("已导入地形: {}", path.display())); }
+                Err(e) => self.notifications.error(e),
             }
         }
     }
 
-    fn import_building_configs(&mut self, ctx: &egui::Context) {
-        if let Some(path) = FileDialog::new().set_directory("output").add_filter("JSON防御塔列表", &["json"]).pick_file() {
-            if let Ok(content) = fs::read_to_string(path) {
-                if let Ok(data) = serde_json::from_str::<Vec<BuildingConfig>>(&content) {
-                    self.building_configs = data;
-                    self.building_config_icons.clear();
-                    self.building_templates = self.building_configs.iter().map(|config| {
-                        let icon = Self::load_icon(ctx, &config.icon_path);
-                        self.building_config_icons.push(icon.clone());
-                        BuildingTemplate {
-                            name: config.name.clone(),
-                            b_type: config.b_type,
-                            width: config.width,
-                            height: config.height,
-                            color: Color32::from_rgba_unmultiplied(
-                                config.color[0], config.color[1], 
-                                config.color[2], config.color[3]
-                            ),
-                            icon,
-                        }
-                    }).collect();
-                }
+    pub(crate) fn import_building_configs(&mut self, ctx: &egui::Context) {
+        if let Some(path) = FileDialog::new().set_directory("output").add_filter(tr("防御塔列表"), DATA_EXTENSIONS).pick_file() {
+            match read_data::<Vec<BuildingConfig>>(&path) {
+                Ok(data) => { self.apply_building_configs(ctx, data); self.notifications.info(trf!("已导入防御塔列表: {}", path.display())); }
+                Err(e) => self.notifications.error(e),
             }
         }
     }
 
-    fn export_terrain(&self) {
-        let map_name = self.map_filename.split('.').next().unwrap_or("地图");
-        let export_dir = PathBuf::from("output").join(map_name);
-        let _ = fs::create_dir_all(&export_dir);
-        
-        let out = export_dir.join(format!("{}地图.json", map_name));
+    // 拖入窗口的文件：图片作为底图，数据文件按内容识别为地形/策略/防御塔列表/调色板/工程
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if dropped.is_empty() { return; }
+        // 只拖入图片时只换底图，不会覆盖数据，不必确认
+        let is_image = |p: &PathBuf| p.extension().and_then(|e| e.to_str()).is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        if dropped.iter().all(is_image) { self.import_dropped_files(ctx, dropped); } else { self.request_discard(ctx, DiscardAction::DroppedFiles(dropped)); }
+    }
+
+    pub(crate) fn import_dropped_files(&mut self, ctx: &egui::Context, dropped: Vec<PathBuf>) {
+        for path in dropped {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+            if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+                if self.load_base_image(ctx, &path.to_string_lossy()) { self.notifications.info(trf!("已设置底图: {}", path.display())); }
+                continue;
+            }
+            if ext == PROJECT_EXTENSION { self.open_project_from(ctx, &path); continue; }
+            let (data, report) = match load_data_file(&path) {
+                Ok(loaded) => loaded,
+                Err(e) => { self.notifications.error(e); continue; }
+            };
+            self.report_migration(&path, &report);
+            let kind = match data {
+                DataFile::Terrain(t) => { self.apply_terrain_data(t); tr("地形") }
+                DataFile::Strategy(s) => { self.apply_buildings_data(s); self.rename_active_tab(&path); tr("策略") }
+                DataFile::BuildingConfigs(c) => { self.apply_building_configs(ctx, c); tr("防御塔列表") }
+                DataFile::TerrainTypes(t) => { self.terrain_types = t; tr("地形类型") }
+                DataFile::Project(_) => { self.open_project_from(ctx, &path); continue; }
+            };
+            self.notifications.info(trf!("已导入{}: {}", kind, path.display()));
+        }
+    }
+
+    // 有文件悬停在窗口上时给出提示
+    fn draw_drop_hint(&self, ctx: &egui::Context) {
+        if ctx.input(|i| i.raw.hovered_files.is_empty()) { return; }
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_hint")));
+        let screen = ctx.screen_rect();
+        painter.rect_filled(screen, 0.0, Color32::from_black_alpha(160));
+        painter.text(screen.center(), Align2::CENTER_CENTER, tr("松开以导入 (图片 → 底图, 数据文件 → 按类型导入)"), FontId::proportional(24.0), Color32::WHITE);
+    }
+
+    pub(crate) fn export_map_name(&self) -> String {
+        self.map_filename.split('.').next().unwrap_or("地图").to_string()
+    }
+
+    pub(crate) fn build_terrain_export(&self) -> MapTerrainExport {
         let meta = MapMeta { 
             grid_pixel_width: self.grid_width, 
             grid_pixel_height: self.grid_height, 
@@ -383,40 +1082,314 @@ impl MapEditor {
             camera_speed_right: self.camera_speed_right,
             viewport_safe_areas: self.viewport_safe_areas.iter().map(|r| (*r).into()).collect(),
             prep_actions: self.prep_actions.clone(),
+            camera_keyframes: self.camera_keyframes.clone(),
+            viewport_presets: self.viewport_presets.clone(),
+            time_model: self.time_model.clone(),
         };
         let mut layers: Vec<LayerData> = self.layers_data.values().cloned().collect();
         layers.sort_by_key(|l| l.major_z);
-        if let Ok(json) = serde_json::to_string_pretty(&MapTerrainExport { map_name: map_name.to_string(), meta, layers }) { let _ = fs::write(out, json); }
+        MapTerrainExport { format_version: FORMAT_VERSION, map_name: self.export_map_name(), meta, layers, routes: self.enemy_routes.clone() }
     }
 
-    fn export_buildings(&self) {
-        // 从map_filename中提取地图名称（去除.json扩展名）
-        let map_name = self.map_filename.split('.').next().unwrap_or("地图");
-        let export_dir = PathBuf::from("output").join(map_name);
-        let _ = fs::create_dir_all(&export_dir);
-        
+    pub(crate) fn build_buildings_export(&self) -> MapBuildingsExport {
         let b_exp: Vec<BuildingExport> = self.placed_buildings.iter().map(|b| BuildingExport { 
             uid: b.uid, 
             name: b.template_name.clone(),
             b_type: b.b_type,
             grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, 
-            wave_num: b.wave_num, is_late: b.is_late 
+            wave_num: b.wave_num, is_late: b.is_late, viewport: b.viewport.clone(), comment: b.comment.clone(), offset: b.offset, offset_seconds: b.offset_seconds, order: b.order,
         }).collect();
-        let out = export_dir.join(format!("{}策略.json", map_name));
-        if let Ok(json) = serde_json::to_string_pretty(&MapBuildingsExport { map_name: map_name.to_string(), buildings: b_exp, upgrades: self.upgrade_events.clone(), demolishes: self.demolish_events.clone() }) { let _ = fs::write(out, json); }
+        MapBuildingsExport { format_version: FORMAT_VERSION, map_name: self.export_map_name(), buildings: b_exp, upgrades: self.upgrade_events.clone(), demolishes: self.demolish_events.clone(), sells: self.sell_events.clone(), relocations: self.relocate_events.clone(), abilities: self.ability_events.clone(), wave_notes: self.wave_notes.clone(), locked_waves: self.locked_waves.clone(), economy: self.economy.clone() }
+    }
+
+    pub(crate) fn export_paths(&self) -> ExportPaths {
+        export_paths("output", &self.export_map_name(), self.export_format)
+    }
+
+    // 写文件并把结果反馈到通知栏
+    pub(crate) fn write_output<T: serde::Serialize>(&mut self, path: PathBuf, value: &T) -> bool {
+        match write_data(&path, value) {
+            Ok(()) => { self.notifications.info(trf!("已导出: {}", path.display())); true }
+            Err(e) => { self.notifications.error(e); false }
+        }
+    }
+
+    fn export_building_configs(&mut self) {
+        let out = self.export_paths().building_configs;
+        let data = self.building_configs.clone();
+        self.write_output(out, &data);
+    }
+
+    // 当前波次的画布快照，按底图像素尺寸绘制
+    fn export_snapshot(&mut self) {
+        let map_name = self.export_map_name();
+        let wave = wave_label(self.current_wave_num, self.current_is_late);
+        let dialog = FileDialog::new().set_directory(format!("output/{}", map_name)).set_file_name(format!("{}_{}.png", map_name, wave)).add_filter("PNG", &["png"]);
+        if let Some(path) = dialog.save_file() {
+            let (img, errors) = self.render_snapshot();
+            for e in errors { self.notifications.warn(e); }
+            match write_png(&path, &img) {
+                Ok(()) => self.notifications.info(trf!("已导出: {}", path.display())),
+                Err(e) => self.notifications.error(e),
+            }
+        }
+    }
+
+    fn export_operations_csv(&mut self) {
+        let map_name = self.export_map_name();
+        let dialog = FileDialog::new().set_directory(format!("output/{}", map_name)).set_file_name(format!("{}操作序列.csv", map_name)).add_filter("CSV", &["csv"]);
+        if let Some(path) = dialog.save_file() {
+            let csv = operations_csv(&flatten_operations(&self.build_buildings_export()));
+            match write_text(&path, &csv) {
+                Ok(()) => self.notifications.info(trf!("已导出: {}", path.display())),
+                Err(e) => self.notifications.error(e),
+            }
+        }
+    }
+
+    // 建造、升级、拆除等合并成一条按时间排好的操作列表
+    fn export_operations(&mut self) {
+        let out = self.export_paths().operations;
+        let data = operations_export(&self.build_buildings_export());
+        self.write_output(out, &data);
+    }
+
+    // 每波一个 operations 文件，写到 output/<地图名>/waves/
+    fn export_wave_operations(&mut self) {
+        let dir = self.export_paths().waves;
+        match write_wave_files(&dir, &operations_by_wave(&self.build_buildings_export()), self.export_format) {
+            Ok(paths) => self.notifications.info(trf!("已导出 {} 个波次文件: {}", paths.len(), dir.display())),
+            Err(e) => self.notifications.error(e),
+        }
+    }
+
+    // 每个操作带从关卡开始算起的秒数，需要时间模型中的每波时长
+    fn export_timeline(&mut self) {
+        match timed_operations(&self.build_buildings_export(), &self.time_model) {
+            Some(timeline) => { let out = self.export_paths().timeline; self.write_output(out, &timeline); }
+            None => self.notifications.warn(tr("请先在时间划分中填写每波时长")),
+        }
+    }
+
+    // 工程文件：地形 + 策略 + 防御塔列表 + 底图/预设来源，打包为单个 .minke 文件
+    pub(crate) fn save_project(&mut self) {
+        let map_name = self.export_map_name();
+        let dialog = FileDialog::new().set_directory("output").set_file_name(format!("{}.{}", map_name, PROJECT_EXTENSION)).add_filter(tr("MINKE 工程"), &[PROJECT_EXTENSION]);
+        if let Some(path) = dialog.save_file() {
+            let project = self.build_project_file(path.parent().unwrap_or(Path::new(".")));
+            if self.write_output(path, &project) { self.mark_saved(); }
+        }
+    }
+
+    // 底图路径相对于工程文件保存，工程目录整体移动后仍能找到
+    pub(crate) fn build_project_file(&self, project_dir: &Path) -> ProjectFile {
+        ProjectFile {
+            format_version: FORMAT_VERSION,
+            project_name: self.export_map_name(),
+            base_image_path: self.base_image_path.as_ref().map(|p| portable_path(project_dir, Path::new(p))),
+            preset_name: self.preset_name.clone(),
+            map_filename: self.map_filename.clone(),
+            terrain: self.build_terrain_export(),
+            strategy: self.build_buildings_export(),
+            building_configs: self.building_configs.clone(),
+            reference_images: self.reference_image_entries(project_dir),
+        }
+    }
+
+    pub(crate) fn open_project(&mut self, ctx: &egui::Context) {
+        if let Some(path) = FileDialog::new().set_directory("output").add_filter(tr("MINKE 工程"), &[PROJECT_EXTENSION]).pick_file() {
+            self.open_project_from(ctx, &path);
+        }
+    }
+
+    fn open_project_from(&mut self, ctx: &egui::Context, path: &Path) {
+        let project = match read_migrated::<ProjectFile>(path, migrate_project) {
+            Ok((project, report)) => { self.report_migration(path, &report); project }
+            Err(e) => { self.notifications.error(e); return; }
+        };
+        let project_dir = path.parent().unwrap_or(Path::new("."));
+        let image_p = project.base_image_path.as_ref().map(|p| resolve_relative_to(project_dir, p).to_string_lossy().into_owned());
+        // 工程没有底图或底图加载失败时不能沿用上一个工程的底图
+        if !image_p.as_ref().is_some_and(|p| self.load_base_image(ctx, p)) { self.texture = None; }
+        self.apply_terrain_data(project.terrain);
+        // 先加载防御塔列表，策略中的建筑颜色依赖模板
+        self.apply_building_configs(ctx, project.building_configs);
+        self.apply_buildings_data(project.strategy);
+        self.load_reference_images(ctx, project_dir, project.reference_images);
+        self.base_image_path = image_p;
+        self.preset_name = project.preset_name;
+        self.map_filename = if project.map_filename.is_empty() { format!("{}.json", project.project_name) } else { project.map_filename };
+        self.notifications.info(trf!("已打开工程: {}", path.display()));
+        self.mark_saved();
+    }
+
+    // 未启用收入配置时返回 None，不做预算限制
+    fn money_remaining(&self) -> Option<i32> {
+        let economy = self.economy.as_ref()?;
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        Some(money_at(&self.build_buildings_export(), &self.building_configs, economy, t_current))
+    }
+
+    fn show_wave_settings(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("波次设置:"));
+            ui.horizontal(|ui| {
+                ui.label(tr("当前波次:"));
+                ui.add(egui::DragValue::new(&mut self.current_wave_num).clamp_range(1..=100));
+                if with_time_model(TimeModel::has_late_phase) { ui.checkbox(&mut self.current_is_late, tr("后期")); }
+            });
+            self.show_wave_note_editor(ui);
+            self.show_wave_lock(ui);
+            if let Some(money) = self.money_remaining() {
+                let color = if money < 0 { Color32::RED } else { ui.visuals().text_color() };
+                ui.colored_label(color, trf!("当前剩余资金: {}", money));
+            }
+            ui.horizontal(|ui| {
+                if ui.button(if self.playback_active { tr("⏸ 暂停回放") } else { tr("▶ 回放策略") }).clicked() { self.toggle_playback(); }
+                ui.add(egui::Slider::new(&mut self.playback_speed, 0.25..=8.0).logarithmic(true).text(tr("格/秒")));
+            });
+        });
+    }
+
+    fn show_economy_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_economy;
+        egui::Window::new(tr("经济")).open(&mut open).default_size([460.0, 400.0]).show(ctx, |ui| {
+            let mut enabled = self.economy.is_some();
+            if ui.checkbox(&mut enabled, tr("启用收入配置")).changed() { self.economy = enabled.then(EconomyConfig::default); }
+            if let Some(economy) = &mut self.economy {
+                ui.horizontal(|ui| {
+                    ui.label(tr("初始资金:"));
+                    ui.add(egui::DragValue::new(&mut economy.initial_money).clamp_range(0..=1_000_000));
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("出售返还:"));
+                    ui.add(egui::DragValue::new(&mut economy.sell_refund_percent).clamp_range(0..=100).suffix("%"));
+                });
+                ui.label(tr("每波收入 (波次开始时到账):"));
+                let mut delete_idx = None;
+                for (i, income) in economy.wave_income.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(tr("第"));
+                            ui.add(egui::DragValue::new(&mut income.wave_num).clamp_range(0..=999));
+                            ui.label(tr("波:"));
+                            ui.add(egui::DragValue::new(&mut income.amount).clamp_range(0..=1_000_000));
+                            if ui.button("[X]").clicked() { delete_idx = Some(i); }
+                        });
+                    });
+                }
+                if let Some(idx) = delete_idx { economy.wave_income.remove(idx); }
+                if ui.button(tr("添加收入")).clicked() {
+                    let wave_num = economy.wave_income.iter().map(|w| w.wave_num + 1).max().unwrap_or(1);
+                    economy.wave_income.push(WaveIncome { wave_num, amount: 0 });
+                }
+            }
+            ui.separator();
+
+            let economy = self.economy.clone().unwrap_or_default();
+            let ledger = wave_ledger(&self.build_buildings_export(), &self.building_configs, &economy);
+            if ledger.is_empty() { ui.label(tr("暂无建造或升级")); return; }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("economy_ledger").striped(true).show(ui, |ui| {
+                    for header in [tr("波次"), tr("建造"), tr("升级"), tr("收入"), tr("出售返还"), tr("累计支出"), tr("结余")] { ui.strong(header); }
+                    ui.end_row();
+                    for w in &ledger {
+                        let color = if self.economy.is_some() && w.overspent() { Color32::RED } else { ui.visuals().text_color() };
+                        ui.colored_label(color, format!("W{}", w.wave_num));
+                        ui.label(w.build_cost.to_string());
+                        ui.label(w.upgrade_cost.to_string());
+                        ui.label(w.income.to_string());
+                        ui.label(w.sell_refund.to_string());
+                        ui.label(w.cumulative_spend.to_string());
+                        if self.economy.is_some() { ui.colored_label(color, w.balance().to_string()); } else { ui.label("-"); }
+                        ui.end_row();
+                    }
+                });
+            });
+            if self.economy.is_some() {
+                let over: Vec<String> = ledger.iter().filter(|w| w.overspent()).map(|w| format!("W{}", w.wave_num)).collect();
+                if !over.is_empty() { ui.colored_label(Color32::RED, trf!("⚠ 超支波次: {}", over.join(", "))); }
+            }
+        });
+        self.show_economy = open;
+    }
+
+    fn run_validation(&mut self) {
+        let mut findings = validate_terrain(&self.build_terrain_export(), &self.terrain_types);
+        findings.extend(validate_building_configs(&self.building_configs));
+        findings.extend(validate_strategy(&self.build_buildings_export(), Some((self.grid_rows, self.grid_cols)), Some(&self.building_configs)));
+        findings.extend(validate_viewport_refs(&self.build_buildings_export(), &self.viewport_presets));
+        findings.extend(self.plugin_findings());
+        let errors = findings.iter().filter(|f| f.severity == Severity::Error).count();
+        self.notifications.info(trf!("校验完成: {} 个错误, {} 个警告", errors, findings.len() - errors));
+        self.validation_findings = Some(findings);
+    }
+
+    // 把画布平移到问题位置；建筑类问题同时切换到选择模式并跳到其建造波次，
+    // 升级事件的问题切换到升级模式、跳到事件所在波次并展开该事件的编辑行
+    fn jump_to_finding(&mut self, target: FindingTarget) {
+        let area = match target {
+            FindingTarget::Upgrade(i) => {
+                let Some(ev) = self.upgrade_events.get(i) else { return; };
+                (self.current_wave_num, self.current_is_late) = (ev.wave_num, ev.is_late);
+                if !self.wave_filter.contains(ev.wave_num) { self.wave_filter.enabled = false; }
+                self.mode = EditMode::Upgrade;
+                self.editing_event = Some(EventRef::Upgrade(i));
+                let Some(b) = ev.uid.and_then(|uid| self.placed_buildings.iter().find(|b| b.uid == uid)) else { self.finding_highlight = None; return; };
+                let t = get_time_value(ev.wave_num, ev.is_late);
+                let (x, y) = self.building_pos_at(b, t);
+                (x, y, b.width, b.height)
+            }
+            FindingTarget::Building(uid) => {
+                let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return; };
+                let area = (b.grid_x, b.grid_y, b.width, b.height);
+                self.current_wave_num = b.wave_num;
+                self.current_is_late = b.is_late;
+                self.mode = EditMode::Select;
+                self.selected_uids = vec![uid];
+                area
+            }
+            FindingTarget::Cell { grid_x, grid_y } => (grid_x, grid_y, 1, 1),
+        };
+        let (x, y, w, h) = area;
+        let center = Vec2::new((x as f32 + w as f32 / 2.0) * self.grid_width + self.offset_x, (y as f32 + h as f32 / 2.0) * self.grid_height + self.offset_y);
+        self.pan = self.canvas_rect.size() / 2.0 - center * self.zoom;
+        self.finding_highlight = Some(area);
+    }
+
+    fn show_validation_window(&mut self, ctx: &egui::Context) {
+        let Some(findings) = &self.validation_findings else { return; };
+        let mut open = true;
+        let mut rerun = false;
+        let mut jump = None;
+        egui::Window::new(tr("校验结果")).open(&mut open).default_size([520.0, 320.0]).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(tr("重新校验")).clicked() { rerun = true; }
+                ui.label(tr("点击条目跳转到对应位置"));
+            });
+            ui.separator();
+            if findings.is_empty() { ui.label(tr("✔ 没有发现问题")); }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for f in findings {
+                    let (tag, color) = match f.severity { Severity::Error => (tr("[错误]"), Color32::RED), Severity::Warning => (tr("[警告]"), Color32::YELLOW) };
+                    let text = egui::RichText::new(format!("{} {}", tag, f.message)).color(color);
+                    match f.target {
+                        Some(target) => { if ui.selectable_label(false, text).clicked() { jump = Some(target); } }
+                        None => { ui.label(text); }
+                    }
+                }
+            });
+        });
+        if let Some(target) = jump { self.jump_to_finding(target); }
+        if rerun { self.run_validation(); }
+        if !open { self.validation_findings = None; self.finding_highlight = None; }
     }
 
     fn show_building_config_ui(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
-            if ui.button("保存配置").clicked() {
-                let map_name = self.map_filename.split('.').next().unwrap_or("地图");
-                let export_dir = PathBuf::from("output").join(map_name);
-                let _ = fs::create_dir_all(&export_dir);
-                
-                let out = export_dir.join(format!("{}防御塔列表.json", map_name));
-                if let Ok(json) = serde_json::to_string_pretty(&self.building_configs) { let _ = fs::write(out, json); }
-            }
-            if ui.button("添加建筑").clicked() {
+            if ui.button(tr("保存配置")).clicked() { self.export_building_configs(); }
+            if ui.button(tr("添加建筑")).clicked() {
                 self.building_configs.push(BuildingConfig {
                     name: "新建筑".to_string(),
                     b_type: BuildingType::Floor,
@@ -424,8 +1397,13 @@ impl MapEditor {
                     width: 2,
                     height: 1,
                     color: [128, 128, 128, 255],
-                    icon_path: "maps/icons/默认.png".to_string(),
+                    icon_path: "icons/默认.png".to_string(),
                     cost: 100,
+                    upgrade_cost: None,
+                    range: None,
+                    category: String::new(),
+                    tags: Vec::new(),
+                    upgrades: Vec::new(),
                 });
                 self.building_config_icons.push(None);
             }
@@ -439,9 +1417,9 @@ impl MapEditor {
             for b_type in &[BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling] {
                 ui.group(|ui| {
                     let type_name = match b_type {
-                        BuildingType::Floor => "地面建筑",
-                        BuildingType::Wall => "墙壁建筑",
-                        BuildingType::Ceiling => "吊顶建筑",
+                        BuildingType::Floor => tr("地面建筑"),
+                        BuildingType::Wall => tr("墙壁建筑"),
+                        BuildingType::Ceiling => tr("吊顶建筑"),
                     };
                     ui.label(type_name);
 
@@ -535,29 +1513,97 @@ impl MapEditor {
 
 impl eframe::App for MapEditor {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        set_time_model(&self.time_model);
+        self.capture_prep_recording(ctx);
+        if !ctx.wants_keyboard_input() {
+            ctx.input_mut(|i| {
+                if i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z) || i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y) {
+                    self.history_request = Some(HistoryRequest::Redo);
+                } else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z) {
+                    self.history_request = Some(HistoryRequest::Undo);
+                }
+            });
+        }
+        // 撤销 / 重做与切换标签在快照之前应用，实时同步需要单独知道数据变了
+        let restored = self.history_request.is_some() || self.tab_request.is_some();
+        if let Some(req) = self.history_request.take() { self.apply_history_request(req); }
+        if let Some(req) = self.tab_request.take() { self.apply_tab_request(req); }
+        if self.playback_active {
+            self.advance_playback(ctx.input(|i| i.stable_dt));
+            ctx.request_repaint();
+        }
+        let frame_start_snapshot = self.capture_snapshot();
+        // 键盘微调与删除在快照之后执行，才能进入撤销历史、标记未保存并触发实时同步
+        self.handle_nudge_keys(ctx);
+        self.handle_delete_keys(ctx);
+        self.handle_dropped_files(ctx);
+        self.poll_http_api();
+        if self.auto_reload_configs {
+            if self.config_watcher.poll() { self.reload_asset_configs(ctx); }
+            ctx.request_repaint_after(WATCH_INTERVAL);
+        }
+
         egui::SidePanel::left("control").resizable(false).default_width(320.0).show(ctx, |ui| {
             ui.style_mut().spacing.item_spacing.y = 8.0;
-            ui.vertical_centered_justified(|ui| { ui.heading("MINKE 策略编辑器"); });
+            ui.vertical_centered_justified(|ui| { ui.heading(tr("MINKE 策略编辑器")); });
+            ui.horizontal(|ui| {
+                self.show_language_selector(ui);
+                self.show_appearance_settings(ui);
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(self.can_undo(), egui::Button::new(tr("撤销 (Ctrl+Z)"))).clicked() { self.history_request = Some(HistoryRequest::Undo); }
+                if ui.add_enabled(self.can_redo(), egui::Button::new(tr("重做 (Ctrl+Y)"))).clicked() { self.history_request = Some(HistoryRequest::Redo); }
+                ui.label(tr("历史深度:"));
+                if ui.add(egui::DragValue::new(&mut self.history.max_depth).clamp_range(1..=1000)).changed() {
+                    self.history.trim();
+                    self.shared_history.max_depth = self.history.max_depth;
+                    self.shared_history.trim();
+                }
+            });
+            let log_label = match self.notifications.error_count() {
+                0 => trf!("查看日志 ({})", self.notifications.log.len()),
+                n => trf!("查看日志 ({}, {} 个错误)", self.notifications.log.len(), n),
+            };
+            ui.horizontal(|ui| {
+                if ui.button(log_label).clicked() { self.notifications.show_log = !self.notifications.show_log; }
+                if ui.button(tr("💰 经济")).clicked() { self.show_economy = !self.show_economy; }
+                if ui.button(tr("🔍 校验")).clicked() { self.run_validation(); }
+                if ui.button(tr("📜 脚本")).clicked() { self.show_script_console = !self.show_script_console; }
+                ui.toggle_value(&mut self.show_minimap, tr("🗺 小地图"));
+                ui.toggle_value(&mut self.show_rulers, tr("📏 标尺"));
+                ui.toggle_value(&mut self.show_building_tooltips, tr("💬 建筑提示")).on_hover_text(tr("悬停在建筑上时在光标旁显示详细信息"));
+                ui.toggle_value(&mut self.show_terrain_legend, tr("🎨 图例")).on_hover_text(tr("列出各地形类型的颜色与含义，并高亮光标处的地形"));
+                ui.toggle_value(&mut self.show_build_order, tr("📋 建造顺序")).on_hover_text(tr("按执行顺序列出全部操作，可拖动调整同一时刻内的先后"));
+                ui.toggle_value(&mut self.show_heatmap, tr("🔥 热力图")).on_hover_text(tr("按整个策略中先后占用每个格子的建筑数量着色，压暗的是从未使用的可建造格"));
+                ui.toggle_value(&mut self.show_consistency, tr("🧱 子层一致性")).on_hover_text(tr("标出地面 / 墙壁 / 吊顶互相矛盾的格子，规则见资源根目录的 consistency_rules.json"));
+                self.show_plugin_overlay_toggles(ui);
+                self.show_live_sync_status(ui);
+            });
+            self.show_wave_filter(ui);
 
             // 侧边栏移除了 "当前状态监视"，改为悬浮绘制
 
             ui.separator();
-            ui.columns(6, |cols| {
-                cols[0].vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, EditMode::Terrain, "地形"); });
-                cols[1].vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, EditMode::Building, "布局"); });
-                cols[2].vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, EditMode::Upgrade, "升级"); });
-                cols[3].vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, EditMode::Demolish, "拆除"); });
-                cols[4].vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, EditMode::BuildingConfig, "建筑"); });
-                cols[5].vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, EditMode::PrepActions, "准备"); });
-            });
+            // 模式较多，分两行排列
+            let mode_rows = [
+                vec![(EditMode::Terrain, tr("地形")), (EditMode::Route, tr("路线")), (EditMode::Building, tr("布局")), (EditMode::Select, tr("选择")), (EditMode::Upgrade, tr("升级")), (EditMode::Demolish, tr("拆除"))],
+                vec![(EditMode::Sell, tr("出售")), (EditMode::Relocate, tr("迁移")), (EditMode::Ability, tr("技能")), (EditMode::BuildingConfig, tr("建筑")), (EditMode::PrepActions, tr("准备"))],
+            ];
+            for row in mode_rows {
+                ui.columns(row.len(), |cols| {
+                    for (col, (mode, label)) in cols.iter_mut().zip(row) {
+                        col.vertical_centered_justified(|ui| { ui.selectable_value(&mut self.mode, mode, label); });
+                    }
+                });
+            }
 
             if self.mode == EditMode::Terrain {
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width());
-                    ui.label("关卡预设:");
+                    ui.label(tr("关卡预设:"));
                     ui.vertical_centered_justified(|ui| {
                         for (i, preset) in self.presets.clone().iter().enumerate() {
-                            ui.push_id(i, |ui| { if ui.button(format!("加载: {}", preset.name)).clicked() { self.apply_preset(ctx, preset); } });
+                            ui.push_id(i, |ui| { if ui.button(trf!("加载: {}", preset.name)).clicked() { self.request_discard(ctx, DiscardAction::LoadPreset(i)); } });
                         }
                     });
                 });
@@ -565,64 +1611,90 @@ impl eframe::App for MapEditor {
                 
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width());
-                    ui.label("地形编辑层级:");
+                    ui.label(tr("地形编辑层级:"));
                     ui.horizontal(|ui| {
-                        ui.radio_value(&mut self.current_edit_layer_type, BuildingType::Floor, "地面");
-                        ui.radio_value(&mut self.current_edit_layer_type, BuildingType::Wall, "墙壁");
-                        ui.radio_value(&mut self.current_edit_layer_type, BuildingType::Ceiling, "吊顶");
+                        ui.radio_value(&mut self.current_edit_layer_type, BuildingType::Floor, tr("地面"));
+                        ui.radio_value(&mut self.current_edit_layer_type, BuildingType::Wall, tr("墙壁"));
+                        ui.radio_value(&mut self.current_edit_layer_type, BuildingType::Ceiling, tr("吊顶"));
                     });
                     ui.separator();
+                    self.show_layer_visibility(ui);
+                    ui.separator();
 
-                    ui.label("地形笔刷:");
-                    let brushes = [(-1, "障碍"), (0, "平地"), (1, "高台1"), (2, "高台2"), (3, "高台3")];
-                    for (val, label) in brushes.iter() {
+                    ui.label(tr("地形笔刷:"));
+                    for t in &self.terrain_types {
                         ui.horizontal(|ui| {
-                            ui.radio_value(&mut self.current_brush, *val, *label);
+                            ui.radio_value(&mut self.current_brush, t.id, t.name.as_str());
                             let (rect, _) = ui.allocate_exact_size(Vec2::new(12.0, 12.0), Sense::hover());
-                            ui.painter().rect_filled(rect, 2.0, get_layer_color(*val));
+                            ui.painter().rect_filled(rect, 2.0, get_layer_color(&self.terrain_types, t.id));
                         });
                     }
-                    ui.add(egui::Slider::new(&mut self.brush_radius, 0..=10).text("笔刷半径"));
+                    let prev_tool = self.terrain_tool;
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(tr("工具:"));
+                        ui.radio_value(&mut self.terrain_tool, TerrainTool::Brush, tr("笔刷"));
+                        ui.radio_value(&mut self.terrain_tool, TerrainTool::Rect, tr("矩形填充"));
+                        ui.radio_value(&mut self.terrain_tool, TerrainTool::Line, tr("直线"));
+                        ui.radio_value(&mut self.terrain_tool, TerrainTool::Noise, tr("噪声")).on_hover_text(tr("只涂笔刷范围内噪声较高的格子，适合铺出自然形状的障碍区"));
+                        ui.radio_value(&mut self.terrain_tool, TerrainTool::Scatter, tr("散布")).on_hover_text(tr("按密度随机涂笔刷范围内的格子"));
+                        ui.radio_value(&mut self.terrain_tool, TerrainTool::Smooth, tr("平滑")).on_hover_text(tr("清除笔刷范围内的孤立杂点，拉直两种地形之间的锯齿边缘，多抹几下更平整"));
+                    });
+                    if self.terrain_tool != prev_tool { self.terrain_shape_origin = None; }
+                    ui.add_enabled(self.terrain_tool != TerrainTool::Rect, egui::Slider::new(&mut self.brush_radius, 0..=10).text(tr("笔刷半径")));
+                    self.show_procedural_brush_settings(ui);
                 });
 
                 ui.add_space(10.0);
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width());
-                    ui.label("网格和镜头设置:");
+                    ui.label(tr("网格和镜头设置:"));
                     ui.horizontal(|ui| { 
-                        ui.label("网格宽:"); ui.add(egui::DragValue::new(&mut self.grid_width).speed(0.1)); 
-                        ui.label("网格高:"); ui.add(egui::DragValue::new(&mut self.grid_height).speed(0.1)); 
+                        ui.label(tr("网格宽:")); ui.add(egui::DragValue::new(&mut self.grid_width).speed(0.1)); 
+                        ui.label(tr("网格高:")); ui.add(egui::DragValue::new(&mut self.grid_height).speed(0.1)); 
                     });
                     ui.horizontal(|ui| {
-                        ui.label("偏移 X:"); ui.add(egui::DragValue::new(&mut self.offset_x).speed(1.0));
-                        ui.label("偏移 Y:"); ui.add(egui::DragValue::new(&mut self.offset_y).speed(1.0));
+                        ui.label(tr("偏移 X:")); ui.add(egui::DragValue::new(&mut self.offset_x).speed(1.0));
+                        ui.label(tr("偏移 Y:")); ui.add(egui::DragValue::new(&mut self.offset_y).speed(1.0));
                     });
+                    ui.checkbox(&mut self.show_align_handles, tr("在画布上显示对齐手柄")).on_hover_text(tr("拖动左上角平移网格，拖动右边/下边/右下角缩放格子；按住 Ctrl 微调"));
+                    self.show_calibration(ui);
+                    self.show_grid_detection(ui);
                     ui.horizontal(|ui| {
-                        ui.label("底图高度:"); ui.add(egui::DragValue::new(&mut self.map_bottom).speed(1.0));
-                        ui.label("底图宽度:"); ui.add(egui::DragValue::new(&mut self.map_right).speed(1.0));
+                        ui.label(tr("底图高度:")); ui.add(egui::DragValue::new(&mut self.map_bottom).speed(1.0));
+                        ui.label(tr("底图宽度:")); ui.add(egui::DragValue::new(&mut self.map_right).speed(1.0));
                     });
                     ui.horizontal(|ui| {
-                        ui.label("网格行列:");
-                        if ui.add(egui::DragValue::new(&mut self.grid_rows)).changed() { self.resize_grids(); }
-                        if ui.add(egui::DragValue::new(&mut self.grid_cols)).changed() { self.resize_grids(); }
+                        ui.label(tr("网格行列:"));
+                        let (mut rows, mut cols) = (self.grid_rows, self.grid_cols);
+                        let changed = ui.add(egui::DragValue::new(&mut rows)).changed() | ui.add(egui::DragValue::new(&mut cols)).changed();
+                        if changed { self.request_grid_size(rows, cols); }
+                        if ui.button(tr("调整尺寸…")).on_hover_text(tr("选择锚点扩展或裁剪网格，建筑与事件随之平移")).clicked() { self.open_resize_dialog(); }
+                        if ui.button(tr("✂ 裁剪到内容")).on_hover_text(tr("把网格收缩到非障碍格子、建筑、路线与技能目标的外接矩形，减小导出文件")).clicked() { self.trim_to_content(); }
                     });
+                    self.show_map_transforms(ui);
+                    ui.separator();
+                    self.show_time_model_settings(ui);
                     ui.separator();
                     ui.horizontal(|ui| {
-                        ui.label("镜头速度上:"); ui.add(egui::DragValue::new(&mut self.camera_speed_up).speed(0.1));
-                        ui.label("镜头速度下:"); ui.add(egui::DragValue::new(&mut self.camera_speed_down).speed(0.1));
+                        ui.label(tr("镜头速度上:")); ui.add(egui::DragValue::new(&mut self.camera_speed_up).speed(0.1));
+                        ui.label(tr("镜头速度下:")); ui.add(egui::DragValue::new(&mut self.camera_speed_down).speed(0.1));
                     });
                     ui.horizontal(|ui| {
-                        ui.label("镜头速度左:"); ui.add(egui::DragValue::new(&mut self.camera_speed_left).speed(0.1));
-                        ui.label("镜头速度右:"); ui.add(egui::DragValue::new(&mut self.camera_speed_right).speed(0.1));
+                        ui.label(tr("镜头速度左:")); ui.add(egui::DragValue::new(&mut self.camera_speed_left).speed(0.1));
+                        ui.label(tr("镜头速度右:")); ui.add(egui::DragValue::new(&mut self.camera_speed_right).speed(0.1));
                     });
-                    ui.vertical_centered_justified(|ui| { if ui.button("加载自定义地图底图").clicked() { self.pick_and_load_image(ctx); } });
+                    self.show_viewport_presets(ui);
+                    self.show_camera_keyframes(ui);
+                    ui.vertical_centered_justified(|ui| { if ui.button(tr("加载自定义地图底图")).clicked() { self.pick_and_load_image(ctx); } });
+                    self.show_reference_images(ui, ctx);
+                    ui.vertical_centered_justified(|ui| { if ui.button(tr("从颜色图导入地形…")).on_hover_text(tr("读取按颜色标注地形的图片，填充当前层的地面")).clicked() { self.open_terrain_image_import(); } });
                     ui.separator();
-                    ui.label("观察框安全区域 (多个矩形):");
+                    ui.label(tr("观察框安全区域 (多个矩形):"));
                     ui.horizontal(|ui| {
-                        if ui.button("添加区域").clicked() {
+                        if ui.button(tr("添加区域")).clicked() {
                             self.viewport_safe_areas.push(Rect::from_min_max(Pos2::ZERO, Pos2::ZERO));
                         }
-                        if ui.button("清空区域").clicked() {
+                        if ui.button(tr("清空区域")).clicked() {
                             self.viewport_safe_areas.clear();
                         }
                     });
@@ -631,7 +1703,7 @@ impl eframe::App for MapEditor {
                     egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
                         for i in 0..self.viewport_safe_areas.len() {
                             ui.horizontal(|ui| {
-                                ui.label(format!("区域{}:", i));
+                                ui.label(trf!("区域{}:", i));
                                 ui.label("X1:"); ui.add(egui::DragValue::new(&mut self.viewport_safe_areas[i].min.x).speed(1.0));
                                 ui.label("Y1:"); ui.add(egui::DragValue::new(&mut self.viewport_safe_areas[i].min.y).speed(1.0));
                                 ui.label("X2:"); ui.add(egui::DragValue::new(&mut self.viewport_safe_areas[i].max.x).speed(1.0));
@@ -649,138 +1721,156 @@ impl eframe::App for MapEditor {
 
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width());
-                    ui.label("数据存取:");
+                    ui.label(tr("数据存取:"));
                     ui.vertical_centered_justified(|ui| {
-                        ui.label("地图名称:");
+                        ui.label(tr("地图名称:"));
                         ui.text_edit_singleline(&mut self.map_filename);
+                        egui::ComboBox::from_label(tr("导出格式")).selected_text(tr(self.export_format.label())).show_ui(ui, |ui| {
+                            for format in DataFormat::ALL { ui.selectable_value(&mut self.export_format, format, tr(format.label())); }
+                        });
                         ui.separator();
                         
-                        if ui.button("导出全部数据").clicked() {
-                            self.export_terrain();
-                            self.export_buildings();
-                            let map_name = self.map_filename.split('.').next().unwrap_or("地图");
-                            let export_dir = PathBuf::from("output").join(map_name);
-                            let _ = fs::create_dir_all(&export_dir);
-                            let out = export_dir.join(format!("{}防御塔列表.json", map_name));
-                            if let Ok(json) = serde_json::to_string_pretty(&self.building_configs) { let _ = fs::write(out, json); }
-                        }
-                        if ui.button("导入地形文件").clicked() { self.import_terrain(); }
-                        if ui.button("导入策略文件").clicked() { self.import_buildings(); }
-                        if ui.button("导入防御塔列表").clicked() { self.import_building_configs(ctx); }
+                        if ui.button(tr("导出全部数据")).clicked() { self.open_export_dialog(); }
+                        if ui.button(tr("🔁 往返自检")).on_hover_text(tr("按每种格式导出当前工程再重新导入，列出丢失或变化的字段")).clicked() { self.run_roundtrip_check(); }
+                        if ui.button(tr("导入地形文件")).clicked() { self.request_discard(ctx, DiscardAction::ImportTerrain); }
+                        if ui.button(tr("导入旧版地形…")).on_hover_text(tr("转换只有 elevation_grid / grid_pixel_size 的旧文件，列出补出的默认值并立即另存为当前格式")).clicked() { self.request_discard(ctx, DiscardAction::ImportLegacyTerrain); }
+                        if ui.button(tr("导入策略文件…")).on_hover_text(tr("替换当前策略，或与当前策略合并")).clicked() { self.import_buildings(); }
+                        if ui.button(tr("部分导入…")).on_hover_text(tr("只从文件中取建筑、升级、拆除或准备动作，其余保持不变")).clicked() { self.open_partial_import(); }
+                        if ui.button(tr("导入防御塔列表")).clicked() { self.request_discard(ctx, DiscardAction::ImportBuildingConfigs); }
+                        if ui.button(tr("导出操作序列 (CSV)")).clicked() { self.export_operations_csv(); }
+                        if ui.button(tr("导出操作序列 (operations)")).on_hover_text(tr("建造、升级、拆除等按执行顺序合并成一个列表，每条带 type 字段")).clicked() { self.export_operations(); }
+                        if ui.button(tr("按波次拆分导出操作 (wave_XX)")).on_hover_text(tr("每波一个文件，只含这一波的操作，写到 waves 目录")).clicked() { self.export_wave_operations(); }
+                        if ui.add_enabled(!self.time_model.wave_seconds.is_empty(), egui::Button::new(tr("导出绝对时间序列"))).on_hover_text(tr("每个操作带从关卡开始算起的秒数，按每波时长换算")).on_disabled_hover_text(tr("请先在时间划分中填写每波时长")).clicked() { self.export_timeline(); }
+                        if ui.button(tr("导出当前波次图片 (PNG)")).clicked() { self.export_snapshot(); }
+                        ui.separator();
+                        if ui.button(tr("保存工程 (.minke)")).clicked() { self.save_project(); }
+                        if ui.button(tr("打开工程")).clicked() { self.request_discard(ctx, DiscardAction::OpenProject); }
+                        ui.separator();
+                        ui.label(trf!("资源根目录: {}", self.asset_root.display())).on_hover_text(tr("防御塔图标、预设及配置文件的相对路径以此为准"));
+                        if ui.button(tr("更改资源根目录")).clicked() { self.pick_asset_root(ctx); }
+                        if ui.button(tr("重新加载配置")).clicked() { self.reload_asset_configs(ctx); }
+                        ui.checkbox(&mut self.auto_reload_configs, tr("配置文件改动时自动重新加载")).on_hover_text(tr("重新加载会用资源目录中的防御塔配置替换当前工程中的配置"));
+                        ui.separator();
+                        self.show_http_api_settings(ui, ctx);
+                        self.show_live_sync_settings(ui, ctx);
                     });
                 });
 
             } else if self.mode == EditMode::Building {
+                self.show_wave_settings(ui);
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width());
-                    ui.label("波次设置:");
                     ui.horizontal(|ui| {
-                        ui.label("当前波次:");
-                        ui.add(egui::DragValue::new(&mut self.current_wave_num).clamp_range(1..=100));
-                        ui.checkbox(&mut self.current_is_late, "后期");
+                        ui.label(tr("放置吸附:"));
+                        ui.radio_value(&mut self.placement_snap, PlacementSnap::Cell, tr("整格"));
+                        ui.radio_value(&mut self.placement_snap, PlacementSnap::HalfCell, tr("半格"));
+                        ui.radio_value(&mut self.placement_snap, PlacementSnap::Pixel, tr("像素")).on_hover_text(tr("按底图像素放置；占用与碰撞仍按建筑所在的整格计算"));
                     });
-                });
-                ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.label("选择建筑物:");
+                    ui.label(tr("选择建筑物:"));
+                    ui.horizontal(|ui| {
+                        ui.label(tr("筛选:"));
+                        ui.add(egui::TextEdit::singleline(&mut self.building_filter).hint_text(tr("名称 / 分类 / 标签")).desired_width(ui.available_width() - 30.0));
+                        if !self.building_filter.is_empty() && ui.small_button("×").clicked() { self.building_filter.clear(); }
+                    });
+                    // 分类按配置中首次出现的顺序排列，未分类的排在最后
+                    let mut categories: Vec<&str> = Vec::new();
+                    for t in &self.building_templates {
+                        if !t.category.is_empty() && !categories.contains(&t.category.as_str()) { categories.push(&t.category); }
+                    }
+                    let grouped = !categories.is_empty();
+                    categories.push("");
+                    let filtering = !self.building_filter.trim().is_empty();
+                    let mut row = |ui: &mut egui::Ui, i: usize, t: &BuildingTemplate| {
+                        ui.horizontal(|ui| {
+                            ui.set_min_width(ui.available_width());
+                            let type_label = match t.b_type {
+                                BuildingType::Floor => tr("[地]"),
+                                BuildingType::Wall => tr("[墙]"),
+                                BuildingType::Ceiling => tr("[顶]"),
+                            };
+                            let radio = ui.radio_value(&mut self.selected_building_idx, i, format!("{} {}", type_label, t.name));
+                            if radio.clicked() { self.active_stamp = None; }
+                            if !t.tags.is_empty() { radio.on_hover_text(trf!("标签: {}", t.tags.join(", "))); }
+                            
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.add_space(5.0);
+                                let (rect, _) = ui.allocate_exact_size(Vec2::new(18.0, 18.0), Sense::hover());
+                                if let Some(icon) = &t.icon { ui.painter().image(icon.id(), rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE); }
+                                else { ui.painter().rect_filled(rect, 2.0, t.color); }
+                            });
+                        });
+                    };
                     egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
                         ui.vertical_centered_justified(|ui| {
-                            for (i, t) in self.building_templates.iter().enumerate() {
-                                ui.horizontal(|ui| {
-                                    ui.set_min_width(ui.available_width());
-                                    let type_label = match t.b_type {
-                                        BuildingType::Floor => "[地]",
-                                        BuildingType::Wall => "[墙]",
-                                        BuildingType::Ceiling => "[顶]",
-                                    };
-                                    ui.radio_value(&mut self.selected_building_idx, i, format!("{} {}", type_label, t.name));
-                                    
-                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                        ui.add_space(5.0);
-                                        let (rect, _) = ui.allocate_exact_size(Vec2::new(18.0, 18.0), Sense::hover());
-                                        if let Some(icon) = &t.icon { ui.painter().image(icon.id(), rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE); }
-                                        else { ui.painter().rect_filled(rect, 2.0, t.color); }
-                                    });
+                            let mut shown = 0;
+                            for category in &categories {
+                                let items: Vec<(usize, &BuildingTemplate)> = self.building_templates.iter().enumerate().filter(|(_, t)| t.category == *category && t.matches_filter(&self.building_filter)).collect();
+                                if items.is_empty() { continue; }
+                                shown += items.len();
+                                if !grouped {
+                                    for (i, t) in items { row(ui, i, t); }
+                                    continue;
+                                }
+                                let title = format!("{} ({})", if category.is_empty() { tr("未分类") } else { category }, items.len());
+                                // 筛选时展开所有包含结果的分类
+                                egui::CollapsingHeader::new(title).id_source(("building_category", *category)).default_open(true).open(filtering.then_some(true)).show(ui, |ui| {
+                                    for (i, t) in items { row(ui, i, t); }
                                 });
                             }
+                            if shown == 0 { ui.weak(tr("没有匹配的建筑")); }
                         });
                     });
                 });
+                self.show_stamp_library(ui);
+            } else if self.mode == EditMode::Select {
+                self.show_wave_settings(ui);
+                self.show_selection_ops(ui);
             } else if self.mode == EditMode::Upgrade {
-                ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.label("添加全局升级:");
-                    ui.vertical_centered_justified(|ui| {
-                        egui::ComboBox::from_label("目标塔")
-                            .selected_text(&self.building_templates[self.selected_upgrade_target_idx].name)
-                            .show_ui(ui, |ui| {
-                                for (i, t) in self.building_templates.iter().enumerate() {
-                                    ui.selectable_value(&mut self.selected_upgrade_target_idx, i, &t.name);
-                                }
-                            });
-                        if ui.button("[+] 添加升级指令").clicked() {
-                            self.upgrade_events.push(UpgradeEvent { 
-                                building_name: self.building_templates[self.selected_upgrade_target_idx].name.clone(), 
-                                wave_num: self.current_wave_num, 
-                                is_late: self.current_is_late 
-                            });
-                        }
-                    });
-                });
-                ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.label("已配置的升级序列:");
-                    let mut delete_idx = None;
-                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
-                        if self.upgrade_events.is_empty() { ui.label("暂无升级记录"); }
-                        for (i, ev) in self.upgrade_events.iter().enumerate() {
-                            ui.horizontal(|ui| {
-                                if ui.button("[X]").clicked() { delete_idx = Some(i); }
-                                ui.label(format!("W{}{}: 升级 {}", ev.wave_num, if ev.is_late{"L"} else {""}, ev.building_name));
-                            });
-                        }
-                    });
-                    if let Some(idx) = delete_idx { self.upgrade_events.remove(idx); }
-                });
+                self.show_upgrade_panel(ui);
             } else if self.mode == EditMode::Demolish { 
-                 ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.label("拆除任务预览:");
-                    let mut delete_idx = None;
-                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
-                        if self.demolish_events.is_empty() { ui.label("暂无拆除记录"); }
-                        for (i, ev) in self.demolish_events.iter().enumerate() {
-                            ui.horizontal(|ui| {
-                                if ui.button("[X]").clicked() { delete_idx = Some(i); }
-                                ui.label(format!("W{}{}: 拆除 {}", ev.wave_num, if ev.is_late{"L"} else {""}, ev.name));
-                            });
-                        }
-                    });
-                    if let Some(idx) = delete_idx { self.demolish_events.remove(idx); }
-                });
+                self.show_demolish_panel(ui);
+                self.show_bulk_demolish(ui);
+            } else if self.mode == EditMode::Sell {
+                self.show_sell_panel(ui);
+            } else if self.mode == EditMode::Relocate {
+                self.show_relocate_panel(ui);
+            } else if self.mode == EditMode::Ability {
+                self.show_ability_panel(ui);
             } else if self.mode == EditMode::BuildingConfig {
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width());
-                    ui.label("编辑建筑:");
+                    ui.label(tr("编辑建筑:"));
                     
                     if let Some(idx) = self.editing_building_idx {
+                        let (mut reload_icon, mut browse_icon) = (false, false);
+                        let preview = self.building_config_icons.get(idx).cloned().flatten();
                         let config = &mut self.building_configs[idx];
                         
-                        ui.label("名称:");
+                        ui.label(tr("名称:"));
                         ui.text_edit_singleline(&mut config.name);
+
+                        ui.label(tr("分类:"));
+                        ui.add(egui::TextEdit::singleline(&mut config.category).hint_text(tr("例如：输出、控制、辅助")));
+                        ui.label(tr("标签:"));
+                        // 编辑中的原始文本暂存在 egui 内存里，否则输入逗号后会被立即规整掉
+                        let tags_id = ui.id().with(("building_tags", idx));
+                        let mut tags_text = ui.data_mut(|d| d.get_temp::<String>(tags_id)).unwrap_or_else(|| config.tags.join(", "));
+                        let tags_edit = ui.add(egui::TextEdit::singleline(&mut tags_text).hint_text(tr("逗号分隔，例如：减速, 对空")));
+                        if tags_edit.changed() { config.tags = parse_tags(&tags_text); ui.data_mut(|d| d.insert_temp(tags_id, tags_text)); }
+                        if tags_edit.lost_focus() { ui.data_mut(|d| d.remove::<String>(tags_id)); }
                         
                         ui.separator();
                         
-                        ui.label("类型:");
+                        ui.label(tr("类型:"));
                         ui.horizontal(|ui| {
-                            ui.radio_value(&mut config.b_type, BuildingType::Floor, "地面");
-                            ui.radio_value(&mut config.b_type, BuildingType::Wall, "墙壁");
-                            ui.radio_value(&mut config.b_type, BuildingType::Ceiling, "吊顶");
+                            ui.radio_value(&mut config.b_type, BuildingType::Floor, tr("地面"));
+                            ui.radio_value(&mut config.b_type, BuildingType::Wall, tr("墙壁"));
+                            ui.radio_value(&mut config.b_type, BuildingType::Ceiling, tr("吊顶"));
                         });
                         
                         ui.separator();
                         
-                        ui.label("网格位置 (列, 行):");
+                        ui.label(tr("网格位置 (列, 行):"));
                         ui.horizontal(|ui| {
                             ui.add(egui::DragValue::new(&mut config.grid_index[0]).clamp_range(0..=4));
                             ui.label(",");
@@ -789,22 +1879,70 @@ impl eframe::App for MapEditor {
                         
                         ui.separator();
                         
-                        ui.label("尺寸:");
+                        ui.label(tr("尺寸:"));
                         ui.horizontal(|ui| {
-                            ui.label("宽:");
+                            ui.label(tr("宽:"));
                             ui.add(egui::DragValue::new(&mut config.width).clamp_range(1..=10));
-                            ui.label("高:");
+                            ui.label(tr("高:"));
                             ui.add(egui::DragValue::new(&mut config.height).clamp_range(1..=10));
                         });
                         
                         ui.separator();
                         
-                        ui.label("费用:");
+                        ui.label(tr("费用:"));
                         ui.add(egui::DragValue::new(&mut config.cost).clamp_range(0..=10000));
+                        ui.horizontal(|ui| {
+                            let mut has_upgrade_cost = config.upgrade_cost.is_some();
+                            if ui.checkbox(&mut has_upgrade_cost, tr("升级费用:")).changed() { config.upgrade_cost = has_upgrade_cost.then_some(config.cost); }
+                            match &mut config.upgrade_cost {
+                                Some(cost) => { ui.add(egui::DragValue::new(cost).clamp_range(0..=10000)); }
+                                None => { ui.label(tr("同建造费用")); }
+                            }
+                        });
+
+                        // 升级等级：配置后升级次数以此为上限，每级可单独设置费用、射程与图标
+                        ui.label(tr("升级等级:"));
+                        let mut remove_level = None;
+                        for (i, level) in config.upgrades.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("×").clicked() { remove_level = Some(i); }
+                                ui.label(format!("Lv{}", i + 1));
+                                ui.add(egui::DragValue::new(&mut level.cost).clamp_range(0..=10000).prefix("$"));
+                                let mut has_range = level.range.is_some();
+                                if ui.checkbox(&mut has_range, tr("射程")).changed() { level.range = has_range.then_some(config.range.unwrap_or(3.0)); }
+                                if let Some(range) = &mut level.range { ui.add(egui::DragValue::new(range).clamp_range(0.5..=50.0).speed(0.1)); }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label(tr("图标:"));
+                                let mut path = level.icon_path.clone().unwrap_or_default();
+                                let edit = ui.add(egui::TextEdit::singleline(&mut path).hint_text(tr("沿用上一级")));
+                                if edit.changed() { level.icon_path = Some(path.trim().to_string()).filter(|p| !p.is_empty()); }
+                                reload_icon |= edit.lost_focus();
+                            });
+                        }
+                        if let Some(i) = remove_level { config.upgrades.remove(i); }
+                        ui.horizontal(|ui| {
+                            if ui.button(tr("+ 添加等级")).clicked() {
+                                let cost = config.upgrades.last().map_or(config.upgrade_cost.unwrap_or(config.cost), |l| l.cost);
+                                config.upgrades.push(UpgradeLevel { cost, range: None, icon_path: None });
+                            }
+                            if config.upgrades.is_empty() { ui.weak(tr("未配置：不限升级次数")); }
+                            else { ui.weak(trf!("最多升级 {} 次，累计 {}", config.upgrades.len(), config.upgrades.iter().map(|l| l.cost).sum::<i32>())); }
+                        });
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            let mut has_range = config.range.is_some();
+                            if ui.checkbox(&mut has_range, tr("射程 (格):")).changed() { config.range = has_range.then_some(3.0); }
+                            if let Some(range) = &mut config.range {
+                                ui.add(egui::DragValue::new(range).clamp_range(0.5..=50.0).speed(0.1));
+                            }
+                        });
                         
                         ui.separator();
                         
-                        ui.label("颜色 (RGBA):");
+                        ui.label(tr("颜色 (RGBA):"));
                         ui.horizontal(|ui| {
                             ui.label("R:");
                             ui.add(egui::DragValue::new(&mut config.color[0]).clamp_range(0..=255).speed(1.0));
@@ -820,198 +1958,223 @@ impl eframe::App for MapEditor {
                         
                         ui.separator();
                         
-                        ui.label("图标路径:");
-                        ui.text_edit_singleline(&mut config.icon_path);
+                        ui.label(tr("图标路径:"));
+                        let path_edit = ui.text_edit_singleline(&mut config.icon_path);
+                        // 输入框失去焦点（含回车）且路径与已加载的图标不一致时重新加载
+                        let full_path = resolve_asset(&self.asset_root, &config.icon_path).to_string_lossy().into_owned();
+                        reload_icon |= path_edit.lost_focus() && preview.as_ref().map(|t| t.name()) != Some(full_path);
+                        ui.horizontal(|ui| {
+                            browse_icon = ui.button(tr("浏览…")).clicked();
+                            reload_icon |= ui.button(tr("🔄 重新加载")).on_hover_text(tr("图标文件在外部修改后重新读取")).clicked();
+                        });
+                        ui.horizontal(|ui| {
+                            let (rect, _) = ui.allocate_exact_size(Vec2::splat(64.0), Sense::hover());
+                            match &preview {
+                                Some(tex) => {
+                                    // 按比例缩放到预览框内，保持图标原始宽高比
+                                    let size = tex.size_vec2();
+                                    let fit = Rect::from_center_size(rect.center(), size * (rect.width() / size.x.max(size.y)));
+                                    ui.painter().rect_filled(rect, 4.0, Color32::from_gray(40));
+                                    ui.painter().image(tex.id(), fit, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE);
+                                }
+                                None => {
+                                    ui.painter().rect_filled(rect, 4.0, Color32::from_rgba_unmultiplied(config.color[0], config.color[1], config.color[2], config.color[3]));
+                                    ui.painter().text(rect.center(), Align2::CENTER_CENTER, "?", FontId::proportional(20.0), Color32::WHITE);
+                                }
+                            }
+                            ui.vertical(|ui| {
+                                match &preview {
+                                    Some(tex) => { ui.label(trf!("{} × {} 像素", tex.size()[0], tex.size()[1])); }
+                                    None => { ui.colored_label(Color32::from_rgb(255, 160, 0), tr("未加载图标，以纯色显示")); }
+                                }
+                            });
+                        });
                         
                         ui.separator();
                         
-                        if ui.button("完成编辑").clicked() {
+                        if ui.button(tr("完成编辑")).clicked() {
                             self.editing_building_idx = None;
                         }
+                        if browse_icon { self.browse_config_icon(ui.ctx(), idx); } else if reload_icon { self.reload_config_icon(ui.ctx(), idx); }
                     } else {
-                        ui.label("点击右侧建筑卡片进行编辑");
+                        ui.label(tr("点击右侧建筑卡片进行编辑"));
                     }
                 });
+            } else if self.mode == EditMode::Route {
+                self.show_route_panel(ui);
             } else if self.mode == EditMode::PrepActions {
-                ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.label("准备动作序列:");
-                    ui.label("在地图加载前执行的键盘操作序列");
-                    ui.separator();
-                    
-                    ui.horizontal(|ui| {
-                        if ui.button("添加 Log").clicked() {
-                            self.prep_actions.push(PrepAction::Log { msg: String::new() });
-                        }
-                        if ui.button("添加 KeyDown").clicked() {
-                            self.prep_actions.push(PrepAction::KeyDown { key: String::new() });
-                        }
-                        if ui.button("添加 KeyUp").clicked() {
-                            self.prep_actions.push(PrepAction::KeyUp { key: String::new() });
-                        }
-                    });
-                    ui.horizontal(|ui| {
-                        if ui.button("添加 Wait").clicked() {
-                            self.prep_actions.push(PrepAction::Wait { ms: 100 });
-                        }
-                        if ui.button("添加 KeyUpAll").clicked() {
-                            self.prep_actions.push(PrepAction::KeyUpAll);
-                        }
-                    });
-                });
-                
-                ui.separator();
-                
-                ui.group(|ui| {
-                    ui.set_min_width(ui.available_width());
-                    ui.label("动作列表:");
-                    
-                    let mut delete_idx = None;
-                    let mut move_up_idx = None;
-                    let mut move_down_idx = None;
-                    let actions_count = self.prep_actions.len();
-                    
-                    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
-                        if self.prep_actions.is_empty() {
-                            ui.label("暂无准备动作");
-                        }
-                        for i in 0..actions_count {
-                            ui.horizontal(|ui| {
-                                ui.label(format!("{}.", i + 1));
-                                
-                                match &mut self.prep_actions[i] {
-                                    PrepAction::Log { msg } => {
-                                        ui.label("Log:");
-                                        ui.text_edit_singleline(msg);
-                                    }
-                                    PrepAction::KeyDown { key } => {
-                                        ui.label("KeyDown:");
-                                        ui.add(egui::TextEdit::singleline(key).desired_width(40.0));
-                                    }
-                                    PrepAction::KeyUp { key } => {
-                                        ui.label("KeyUp:");
-                                        ui.add(egui::TextEdit::singleline(key).desired_width(40.0));
-                                    }
-                                    PrepAction::Wait { ms } => {
-                                        ui.label("Wait:");
-                                        ui.add(egui::DragValue::new(ms).speed(10.0));
-                                        ui.label("ms");
-                                    }
-                                    PrepAction::KeyUpAll => {
-                                        ui.label("KeyUpAll");
-                                    }
-                                }
-                                
-                                if ui.small_button("↑").clicked() && i > 0 {
-                                    move_up_idx = Some(i);
-                                }
-                                if ui.small_button("↓").clicked() && i < actions_count - 1 {
-                                    move_down_idx = Some(i);
-                                }
-                                if ui.small_button("×").clicked() {
-                                    delete_idx = Some(i);
-                                }
-                            });
-                        }
-                    });
-                    
-                    if let Some(idx) = delete_idx {
-                        self.prep_actions.remove(idx);
-                    }
-                    if let Some(idx) = move_up_idx {
-                        self.prep_actions.swap(idx, idx - 1);
-                    }
-                    if let Some(idx) = move_down_idx {
-                        self.prep_actions.swap(idx, idx + 1);
-                    }
-                });
+                self.show_prep_panel(ui);
             }
         });
 
         egui::SidePanel::right("help").resizable(false).default_width(280.0).show(ctx, |ui| {
                 ui.style_mut().spacing.item_spacing.y = 8.0;
-                ui.vertical_centered_justified(|ui| { ui.heading("帮助"); });
+                ui.vertical_centered_justified(|ui| { ui.heading(tr("帮助")); });
                 ui.separator();
 
                 match self.mode {
                 EditMode::Terrain => {
-                    ui.label("【地形模式】");
-                    ui.label("• 关卡预设：快速加载预设地图配置");
-                    ui.label("• 地形编辑层级：选择地面/墙壁/吊顶");
-                    ui.label("• 地形笔刷：绘制不同类型的地形");
-                    ui.label("  - 障碍：不可通行区域");
-                    ui.label("  - 平地/高台：可通行区域");
-                    ui.label("• 网格和镜头设置：");
-                    ui.label("  - 调整网格大小和偏移");
-                    ui.label("  - 设置镜头移动速度");
-                    ui.label("  - 配置观察框安全区域");
-                    ui.label("• 数据存取：导出/导入地图数据");
+                    ui.label(tr("【地形模式】"));
+                    ui.label(tr("• 关卡预设：快速加载预设地图配置"));
+                    ui.label(tr("• 地形编辑层级：选择地面/墙壁/吊顶"));
+                    ui.label(tr("• 图层显示：勾选显示主层级/子层，调节地形不透明度以看清底图"));
+                    ui.label(tr("• 地形笔刷：绘制不同类型的地形"));
+                    for t in &self.terrain_types {
+                        ui.horizontal(|ui| {
+                            let (rect, _) = ui.allocate_exact_size(Vec2::new(12.0, 12.0), Sense::hover());
+                            ui.painter().rect_filled(rect, 2.0, get_layer_color(&self.terrain_types, t.id));
+                            ui.label(format!("{} ({})：{}", t.name, t.id, if t.buildable { tr("可建造") } else { tr("不可建造") }));
+                        });
+                    }
+                    ui.label(tr("• 网格和镜头设置："));
+                    ui.label(tr("  - 调整网格大小和偏移"));
+                    ui.label(tr("  - 设置镜头移动速度"));
+                    ui.label(tr("  - 配置观察框安全区域"));
+                    ui.label(tr("  - 保存命名的观察框预设，建造/拆除事件会引用所在的预设"));
+                    ui.label(tr("  - 录制各波次的镜头关键帧"));
+                    ui.label(tr("• 数据存取：导出/导入地图数据"));
+                    ui.separator();
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 左键：绘制地形"));
+                    ui.label(tr("• 右键：擦除地形"));
+                    ui.label(tr("• 矩形填充：拖动框选区域后松开填充"));
+                    ui.label(tr("• 直线：依次点击折线顶点，右键/Esc 结束"));
+                    ui.label(tr("• 滚轮：缩放地图"));
+                    ui.label(tr("• 中键拖动：平移地图"));
+                    ui.label(tr("• WASD/方向键：移动观察框"));
+                }
+                EditMode::Route => {
+                    ui.label(tr("【路线模式】"));
+                    ui.label(tr("• 敌人路线：出生点 → 路径点 → 出口"));
+                    ui.label(tr("• 每条路线各一个出生点和出口"));
+                    ui.label(tr("• 路线随地形数据一起导出"));
+                    ui.label(tr("• 寻路预览：按地面层 A* 寻路，障碍不可通行"));
                     ui.separator();
-                    ui.label("【操作说明】");
-                    ui.label("• 左键：绘制地形");
-                    ui.label("• 右键：擦除地形");
-                    ui.label("• 滚轮：缩放地图");
-                    ui.label("• 中键拖动：平移地图");
-                    ui.label("• WASD/方向键：移动观察框");
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 左键点击：放置选中类型的点"));
+                    ui.label(tr("• 拖动已有的点：移动位置"));
+                    ui.label(tr("• 右键点击点：删除"));
                 }
                 EditMode::Building => {
-                    ui.label("【布局模式】");
-                    ui.label("• 波次设置：设置当前编辑波次");
-                    ui.label("• 选择建筑物：选择要放置的塔");
+                    ui.label(tr("【布局模式】"));
+                    ui.label(tr("• 波次设置：设置当前编辑波次"));
+                    ui.label(tr("• 选择建筑物：选择要放置的塔"));
+                    ui.label(tr("• 回放策略：按设定速度自动推进波次"));
+                    ui.label(tr("• 启用收入配置后：幽灵框橙色表示资金不足"));
                     ui.separator();
-                    ui.label("【操作说明】");
-                    ui.label("• 左键：放置建筑物");
-                    ui.label("• 右键：删除建筑物");
-                    ui.label("• 滚轮：缩放地图");
-                    ui.label("• 中键拖动：平移地图");
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 左键：放置建筑物"));
+                    ui.label(tr("• 右键：删除建筑物"));
+                    ui.label(tr("• 滚轮：缩放地图"));
+                    ui.label(tr("• 中键拖动：平移地图"));
+                }
+                EditMode::Select => {
+                    ui.label(tr("【选择模式】"));
+                    ui.label(tr("• 选中建筑：查看信息并批量操作"));
+                    ui.label(tr("  - 设为当前波次 / 标记拆除 / 删除"));
+                    ui.label(tr("  - 配置了射程的塔显示覆盖范围"));
+                    ui.separator();
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 左键点击：选中建筑（Shift 多选）"));
+                    ui.label(tr("• 空白处拖动：框选建筑"));
+                    ui.label(tr("• 拖动选中建筑：整组移动（保留 UID 与拆除事件）"));
+                    ui.label(tr("• Ctrl+C / Ctrl+V：复制选中建筑并粘贴到光标处"));
+                    ui.label(tr("• 滚轮：缩放地图"));
+                    ui.label(tr("• 中键拖动：平移地图"));
                 }
                 EditMode::Upgrade => {
-                    ui.label("【升级模式】");
-                    ui.label("• 添加全局升级：配置塔的升级时机");
-                    ui.label("• 已配置的升级序列：查看/删除升级");
+                    ui.label(tr("【升级模式】"));
+                    ui.label(tr("• 添加全局升级：配置塔的升级时机"));
+                    ui.label(tr("• 单体升级：点击画布上的建筑，只升级这一座"));
+                    ui.label(tr("• 已配置的升级序列：查看/删除升级"));
                     ui.separator();
-                    ui.label("【操作说明】");
-                    ui.label("• 选择目标塔和波次");
-                    ui.label("• 点击[+]添加升级指令");
-                    ui.label("• 点击[X]删除升级");
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 选择目标塔和波次"));
+                    ui.label(tr("• 左键点击建筑：只升级该建筑；右键：恢复全局升级"));
+                    ui.label(tr("• 点击[+]添加升级指令"));
+                    ui.label(tr("• 点击[X]删除升级"));
+                    ui.label(tr("• 点击✏原地修改波次与目标"));
+                    ui.label(tr("• 勾选多条后可批量平移波次、切换前期/后期或删除"));
                 }
                 EditMode::Demolish => {
-                    ui.label("【拆除模式】");
-                    ui.label("• 拆除任务预览：查看已配置的拆除");
+                    ui.label(tr("【拆除模式】"));
+                    ui.label(tr("• 拆除任务预览：查看已配置的拆除"));
+                    ui.separator();
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 在地图上左键点击塔添加拆除任务"));
+                    ui.label(tr("• 在空白处拖出矩形，框内的塔一次全部标记拆除"));
+                    ui.label(tr("• 按模板批量拆除：一次拆除当前存在的全部同名塔"));
+                    ui.label(tr("• 点击[X]删除拆除"));
+                    ui.label(tr("• 点击✏原地修改波次与目标"));
+                    ui.label(tr("• 勾选多条后可批量平移波次、切换前期/后期或删除"));
+                }
+                EditMode::Sell => {
+                    ui.label(tr("【出售模式】"));
+                    ui.label(tr("• 出售任务预览：查看已配置的出售"));
+                    ui.label(tr("• 出售按经济配置返还部分建造费用"));
+                    ui.separator();
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 在地图上左键点击塔"));
+                    ui.label(tr("• 添加出售任务"));
+                    ui.label(tr("• 点击[X]删除出售"));
+                }
+                EditMode::Relocate => {
+                    ui.label(tr("【迁移模式】"));
+                    ui.label(tr("• 迁移任务预览：查看已配置的迁移"));
+                    ui.label(tr("• 迁移后建筑保留 UID，之后的波次显示在新位置"));
+                    ui.separator();
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 左键点击塔选中，再左键点击目标位置"));
+                    ui.label(tr("• 右键/Esc：取消选中"));
+                    ui.label(tr("• 点击[X]删除迁移"));
+                }
+                EditMode::Ability => {
+                    ui.label(tr("【技能模式】"));
+                    ui.label(tr("• 技能名称由外部程序解释，如 airstrike"));
+                    ui.label(tr("• 技能序列预览：查看已配置的技能释放"));
                     ui.separator();
-                    ui.label("【操作说明】");
-                    ui.label("• 在地图上右键点击塔");
-                    ui.label("• 添加拆除任务");
-                    ui.label("• 点击[X]删除拆除");
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 指定目标：在地图上左键点击目标格子"));
+                    ui.label(tr("• 无目标：点击[+]添加到当前波次"));
+                    ui.label(tr("• 点击[X]删除技能"));
                 }
                 EditMode::BuildingConfig => {
-                    ui.label("【建筑配置模式】");
-                    ui.label("• 管理建筑物的属性配置");
-                    ui.label("• 设置名称、类型、颜色等");
-                    ui.label("• 导出/导入配置列表");
+                    ui.label(tr("【建筑配置模式】"));
+                    ui.label(tr("• 管理建筑物的属性配置"));
+                    ui.label(tr("• 设置名称、类型、颜色等"));
+                    ui.label(tr("• 射程：悬停或选中已放置的塔时显示覆盖范围"));
+                    ui.label(tr("• 导出/导入配置列表"));
                     ui.separator();
-                    ui.label("【操作说明】");
-                    ui.label("• 左侧：建筑列表");
-                    ui.label("• 右侧：编辑建筑信息");
-                    ui.label("• 点击卡片编辑建筑");
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 左侧：建筑列表"));
+                    ui.label(tr("• 右侧：编辑建筑信息"));
+                    ui.label(tr("• 点击卡片编辑建筑"));
                 }
                 EditMode::PrepActions => {
-                    ui.label("【准备动作模式】");
-                    ui.label("• 配置地图加载前的键盘操作");
-                    ui.label("• Log: 输出日志信息");
-                    ui.label("• KeyDown: 按下按键");
-                    ui.label("• KeyUp: 释放按键");
-                    ui.label("• Wait: 等待指定毫秒");
-                    ui.label("• KeyUpAll: 释放所有按键");
+                    ui.label(tr("【准备动作模式】"));
+                    ui.label(tr("• 配置地图加载前的键盘/鼠标操作"));
+                    ui.label(tr("• Log: 输出日志信息"));
+                    ui.label(tr("• KeyDown: 按下按键"));
+                    ui.label(tr("• KeyUp: 释放按键"));
+                    ui.label(tr("• Wait: 等待指定毫秒"));
+                    ui.label(tr("• KeyUpAll: 释放所有按键"));
+                    ui.label(tr("• MouseMove: 移动鼠标到窗口内坐标"));
+                    ui.label(tr("• MouseClick: 在当前位置单击"));
+                    ui.label(tr("• MouseDrag: 按住从起点拖到终点"));
+                    ui.label(tr("• Scroll: 滚动滚轮（正数向上）"));
                     ui.separator();
-                    ui.label("【操作说明】");
-                    ui.label("• 点击按钮添加动作");
-                    ui.label("• 使用↑↓调整顺序");
-                    ui.label("• 点击×删除动作");
+                    ui.label(tr("【操作说明】"));
+                    ui.label(tr("• 点击按钮添加动作"));
+                    ui.label(tr("• 使用↑↓调整顺序"));
+                    ui.label(tr("• 点击×删除动作"));
+                    ui.label(tr("• 录制：在编辑器窗口中按键，按下/松开与间隔会追加到序列末尾"));
+                    ui.label(tr("• 试运行：倒计时内切换到游戏窗口，序列会真实发送键鼠输入"));
                 }
             }
         });
 
+        egui::TopBottomPanel::top("strategy_tabs").show(ctx, |ui| self.show_strategy_tabs(ui));
+
         egui::CentralPanel::default().show(ctx, |ui| {
             if self.mode == EditMode::BuildingConfig {
                 self.show_building_config_ui(ui);
@@ -1020,9 +2183,12 @@ impl eframe::App for MapEditor {
 
             let input = ui.input(|i| i.clone());
             let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
-            let panel_rect = response.rect; 
+            let panel_rect = response.rect;
+            self.canvas_rect = panel_rect;
+            let over_minimap = self.handle_minimap_input(&input, response.dragged(), panel_rect);
+            let canvas_hovered = response.hovered() && !over_minimap;
             if input.pointer.button_down(egui::PointerButton::Middle) { self.pan += input.pointer.delta(); }
-            if response.hovered() {
+            if canvas_hovered {
                 let scroll = input.raw_scroll_delta.y;
                 if scroll != 0.0 {
                     let old = self.zoom; self.zoom = (self.zoom * (1.0 + scroll * 0.001)).clamp(0.1, 10.0);
@@ -1040,17 +2206,24 @@ impl eframe::App for MapEditor {
                 
                 // 计算新的位置
                 let mut new_pos = self.viewport_pos;
-                if input.key_down(egui::Key::W) || input.key_down(egui::Key::ArrowUp) {
+                let mut keys = String::new();
+                // 有选中建筑时方向键用于微移建筑
+                let arrows = !self.arrow_keys_nudge();
+                if input.key_down(egui::Key::W) || (arrows && input.key_down(egui::Key::ArrowUp)) {
                     new_pos.y -= self.camera_speed_up * dt;
+                    keys.push('w');
                 }
-                if input.key_down(egui::Key::S) || input.key_down(egui::Key::ArrowDown) {
+                if input.key_down(egui::Key::S) || (arrows && input.key_down(egui::Key::ArrowDown)) {
                     new_pos.y += self.camera_speed_down * dt;
+                    keys.push('s');
                 }
-                if input.key_down(egui::Key::A) || input.key_down(egui::Key::ArrowLeft) {
+                if input.key_down(egui::Key::A) || (arrows && input.key_down(egui::Key::ArrowLeft)) {
                     new_pos.x -= self.camera_speed_left * dt;
+                    keys.push('a');
                 }
-                if input.key_down(egui::Key::D) || input.key_down(egui::Key::ArrowRight) {
+                if input.key_down(egui::Key::D) || (arrows && input.key_down(egui::Key::ArrowRight)) {
                     new_pos.x += self.camera_speed_right * dt;
+                    keys.push('d');
                 }
                 
                 // 检查新位置是否在任何安全区域内
@@ -1060,6 +2233,8 @@ impl eframe::App for MapEditor {
                 });
                 
                 // 如果有效，则更新位置
+                if !is_valid || new_pos == self.viewport_pos { keys.clear(); }
+                self.record_camera_frame(keys, dt);
                 if is_valid {
                     self.viewport_pos = new_pos;
                 }
@@ -1069,188 +2244,395 @@ impl eframe::App for MapEditor {
             let z_grid_width = self.grid_width * self.zoom;
             let z_grid_height = self.grid_height * self.zoom;
 
+            painter.rect_filled(panel_rect, 0.0, self.canvas_background);
             if let Some(tex) = &self.texture {
                 painter.image(tex.id(), Rect::from_min_size(panel_rect.min + self.pan, tex.size_vec2() * self.zoom), Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE);
             }
+            self.draw_reference_images(&painter, panel_rect.min + self.pan);
+
+            if self.route_preview_major_z != self.current_major_z { self.route_preview = None; self.route_preview_major_z = self.current_major_z; }
+            self.refresh_route_preview();
+            let aligning = self.mode == EditMode::Terrain && self.handle_alignment_input(&input, panel_rect, canvas_hovered);
 
             let layer = self.layers_data.get(&self.current_major_z).unwrap();
 
-            let draw_layer = |grid: &Vec<Vec<i8>>, layer_type: BuildingType, is_active: bool| {
-                for r in 0..self.grid_rows {
-                    for c in 0..self.grid_cols {
-                        let val = grid[r][c];
+            // 只遍历与画布相交的行列，所有格子合并为一个网格批量提交
+            let (visible_cols, visible_rows) = visible_cell_range(panel_rect, origin, Vec2::new(z_grid_width, z_grid_height), self.grid_cols, self.grid_rows);
+            let draw_layer = |grid: &Grid, layer_type: BuildingType, is_active: bool| {
+                let mut mesh = egui::Mesh::default();
+                for r in visible_rows.clone() {
+                    for c in visible_cols.clone() {
+                        let val = grid.get(r, c);
                         if val < -1 { continue; } 
 
                         let rect = Rect::from_min_size(origin + Vec2::new(c as f32 * z_grid_width, r as f32 * z_grid_height), Vec2::new(z_grid_width, z_grid_height)).shrink(0.5);
+                        let mut color = get_layer_color(&self.terrain_types, val); 
                         
-                        if panel_rect.intersects(rect) { 
-                            let mut color = get_layer_color(val); 
-                            
-                            match layer_type {
-                                BuildingType::Floor => {}, 
-                                BuildingType::Wall => { color = Color32::from_rgba_unmultiplied(color.r(), (color.g() as f32 * 0.5) as u8, color.b(), 220); }, 
-                                BuildingType::Ceiling => { color = Color32::from_rgba_unmultiplied(color.r(), color.g(), (color.b() as f32 * 0.5) as u8, 220); }, 
-                            }
-
-                            if !is_active {
-                                color = color.linear_multiply(0.2);
-                            }
+                        match layer_type {
+                            BuildingType::Floor => {}, 
+                            BuildingType::Wall => { color = Color32::from_rgba_unmultiplied(color.r(), (color.g() as f32 * 0.5) as u8, color.b(), 220); }, 
+                            BuildingType::Ceiling => { color = Color32::from_rgba_unmultiplied(color.r(), color.g(), (color.b() as f32 * 0.5) as u8, 220); }, 
+                        }
 
-                            if is_active && self.mode == EditMode::Terrain {
-                                painter.rect_filled(rect, 0.0, color);
-                            } else {
-                                if is_active { painter.rect_filled(rect, 0.0, color); }
-                                else { painter.rect_stroke(rect.shrink(1.0), 0.0, Stroke::new(1.0, color)); }
-                            }
+                        if !is_active {
+                            color = color.linear_multiply(0.2);
                         }
+                        color = color.linear_multiply(self.terrain_opacity);
+
+                        if is_active { mesh.add_colored_rect(rect, color); }
+                        else { add_rect_outline(&mut mesh, rect.shrink(1.0), 1.0, color); }
                     }
                 }
+                painter.add(mesh);
             };
 
-            for &l_type in &[BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling] {
-                if l_type != self.current_edit_layer_type {
-                    draw_layer(layer.get_grid(l_type), l_type, false);
+            // 其它可见的主层级只画轮廓，便于与当前层对照
+            let mut other_zs: Vec<i32> = self.layers_data.keys().copied().filter(|z| *z != self.current_major_z && !self.hidden_major_z.contains(z)).collect();
+            other_zs.sort();
+            for z in other_zs {
+                for &l_type in &[BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling] {
+                    if !self.hidden_layer_types.contains(&l_type) { draw_layer(self.layers_data[&z].get_grid(l_type), l_type, false); }
+                }
+            }
+            if !self.hidden_major_z.contains(&self.current_major_z) {
+                for &l_type in &[BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling] {
+                    if l_type != self.current_edit_layer_type && !self.hidden_layer_types.contains(&l_type) {
+                        draw_layer(layer.get_grid(l_type), l_type, false);
+                    }
+                }
+                if !self.hidden_layer_types.contains(&self.current_edit_layer_type) {
+                    draw_layer(layer.get_grid(self.current_edit_layer_type), self.current_edit_layer_type, true);
                 }
             }
-            draw_layer(layer.get_grid(self.current_edit_layer_type), self.current_edit_layer_type, true);
 
             let t_current = get_time_value(self.current_wave_num, self.current_is_late);
-            let highlight_target_name = if self.mode == EditMode::Upgrade {
-                Some(self.building_templates[self.selected_upgrade_target_idx].name.clone())
-            } else { None };
-
-            for b in &self.placed_buildings {
+            // 按建造先后绘制，后建的盖在上面
+            let mut draw_order: Vec<&PlacedBuilding> = self.placed_buildings.iter().collect();
+            draw_order.sort_by_key(|b| (time_order(b.wave_num, b.is_late, b.offset_seconds), b.order.unwrap_or(u32::MAX)));
+            for b in draw_order {
+                if !self.building_in_wave_filter(b) { continue; }
                 let t_create = get_time_value(b.wave_num, b.is_late);
                 let t_demolish = self.get_building_demolish_time(b.uid);
-                let alpha_mult = if t_current >= t_demolish { 0.05 } else if t_current < t_create { 0.3 } else { 1.0 };
-                let rect = Rect::from_min_size(origin + Vec2::new(b.grid_x as f32 * z_grid_width, b.grid_y as f32 * z_grid_height), Vec2::new(b.width as f32 * z_grid_width, b.height as f32 * z_grid_height));
+                let Some(alpha_mult) = self.building_alpha(t_create, t_demolish, t_current) else { continue; };
+                let dashed = self.building_dashed(t_create, t_current);
+                // 迁移过的建筑画在当前时间所在的位置，非整格放置的再加上小数偏移
+                let (bx, by) = self.building_pos_at(b, t_current);
+                let (fx, fy) = (bx as f32 + b.offset[0], by as f32 + b.offset[1]);
+                let rect = Rect::from_min_size(origin + Vec2::new(fx * z_grid_width, fy * z_grid_height), Vec2::new(b.width as f32 * z_grid_width, b.height as f32 * z_grid_height));
+                if !panel_rect.intersects(rect) { continue; }
                 
                 let temp = self.building_templates.iter().find(|t| t.name == b.template_name);
-                if let Some(t) = temp {
+                if dashed {
+                    draw_dashed_outline(&painter, rect, b.color);
+                } else if let Some(t) = temp {
                     let tint = Color32::from_white_alpha((255.0 * alpha_mult) as u8);
-                    if let Some(icon) = &t.icon { painter.image(icon.id(), rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), tint); }
+                    if let Some(icon) = t.icon_at_tier(self.upgrade_tier_at(&b.template_name, Some(b.uid), t_current)) { painter.image(icon.id(), rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), tint); }
                     else { painter.rect_filled(rect, 4.0, Color32::from_rgba_unmultiplied(b.color.r(), b.color.g(), b.color.b(), (b.color.a() as f32 * alpha_mult) as u8)); }
                 }
                 
                 if alpha_mult > 0.1 {
                     let stroke_alpha = (180.0 * alpha_mult) as u8;
-                    painter.rect_stroke(rect, 1.5, Stroke::new(1.5, Color32::from_black_alpha(stroke_alpha)));
+                    if !dashed { painter.rect_stroke(rect, 1.5, Stroke::new(1.5, Color32::from_black_alpha(stroke_alpha))); }
                     painter.text(
     rect.min + Vec2::new(2.0, 2.0), 
     Align2::LEFT_TOP, 
-    format!("W{}{}", b.wave_num, if b.is_late { "L" } else { "" }), 
+    wave_label(b.wave_num, b.is_late), 
     FontId::proportional(18.0 * self.zoom.max(1.0)), 
     Color32::BLACK // 改成红色
 );
                 }
 
-                if let Some(target) = &highlight_target_name {
-                    if &b.template_name == target && alpha_mult > 0.5 {
-                        painter.rect_stroke(rect.expand(2.0), 0.0, Stroke::new(2.5, Color32::GREEN));
-                    }
+                if self.selected_uids.contains(&b.uid) {
+                    painter.rect_stroke(rect.expand(2.0), 0.0, Stroke::new(2.5, Color32::LIGHT_BLUE));
+                }
+
+                if self.mode == EditMode::Upgrade && alpha_mult > 0.5 {
+                    if self.is_upgrade_highlighted(b) { painter.rect_stroke(rect.expand(2.0), 0.0, Stroke::new(2.5, Color32::GREEN)); }
+                    let tier = self.upgrade_tier_at(&b.template_name, Some(b.uid), t_current);
+                    if tier > 0 { painter.text(rect.left_bottom() + Vec2::new(2.0, -2.0), Align2::LEFT_BOTTOM, format!("Lv{}", tier), FontId::proportional(13.0), Color32::from_rgb(0, 120, 0)); }
                 }
 
                 if t_demolish != i32::MAX && alpha_mult > 0.1 {
                     painter.line_segment([rect.min, rect.max], Stroke::new(2.0, Color32::from_rgba_unmultiplied(255, 0, 0, (200.0 * alpha_mult) as u8)));
                     painter.line_segment([rect.left_bottom(), rect.right_top()], Stroke::new(2.0, Color32::from_rgba_unmultiplied(255, 0, 0, (200.0 * alpha_mult) as u8)));
+                    self.draw_sell_marker(&painter, rect, b.uid, alpha_mult);
+                }
+                if b.comment.is_some() && alpha_mult > 0.1 {
+                    painter.text(rect.right_top() + Vec2::new(-2.0, 2.0), Align2::RIGHT_TOP, "💬", FontId::proportional(14.0), Color32::from_white_alpha((255.0 * alpha_mult) as u8));
+                }
+            }
+            self.draw_heatmap(&painter, panel_rect, origin, Vec2::new(z_grid_width, z_grid_height));
+            self.draw_consistency(&painter, panel_rect, origin, Vec2::new(z_grid_width, z_grid_height), input.pointer.hover_pos());
+            self.draw_relocations(&painter, origin, Vec2::new(z_grid_width, z_grid_height));
+            self.draw_abilities(&painter, origin, Vec2::new(z_grid_width, z_grid_height));
+            self.draw_plugin_overlays(&painter, origin, Vec2::new(z_grid_width, z_grid_height));
+
+            self.draw_routes(&painter, origin, Vec2::new(z_grid_width, z_grid_height));
+
+            if let Some((x, y, w, h)) = self.finding_highlight {
+                let rect = Rect::from_min_size(origin + Vec2::new(x as f32 * z_grid_width, y as f32 * z_grid_height), Vec2::new(w as f32 * z_grid_width, h as f32 * z_grid_height));
+                painter.rect_stroke(rect.expand(3.0), 0.0, Stroke::new(3.0, Color32::YELLOW));
+            }
+
+            // 选中或悬停的建筑显示射程覆盖
+            if self.mode != EditMode::Terrain {
+                let mut range_targets: Vec<usize> = self.placed_buildings.iter().enumerate().filter(|(_, b)| self.selected_uids.contains(&b.uid)).map(|(i, _)| i).collect();
+                if let Some(pos) = response.hover_pos() {
+                    let rel = pos - origin;
+                    if let Some(i) = self.active_building_at(Pos2::new(rel.x / z_grid_width, rel.y / z_grid_height)) {
+                        if !range_targets.contains(&i) { range_targets.push(i); }
+                    }
+                }
+                for i in range_targets {
+                    let b = &self.placed_buildings[i];
+                    if let Some(range) = self.building_range(b) {
+                        self.draw_building_range(&painter, origin, Vec2::new(z_grid_width, z_grid_height), b, range);
+                    }
                 }
             }
 
-            self.hover_info = "无".to_string(); 
+            self.hover_info = tr("无").to_string();
+            self.hovered_terrain = None; 
 
             // 🔥 核心修改：输入隔离与交互逻辑
-            // 只有当鼠标悬停在中央画布区域时，才处理地图交互
-            if response.hovered() {
+            // 只有当鼠标悬停在中央画布区域（且不在小地图上）时，才处理地图交互
+            if canvas_hovered {
                 if let Some(pos) = input.pointer.hover_pos() {
                     let rel = pos - origin; 
                     let (cx, ry) = ((rel.x / z_grid_width).floor() as i32, (rel.y / z_grid_height).floor() as i32);
+                    // 光标所在的小数格坐标，建筑命中按绘制位置（含小数偏移）判断
+                    let at = Pos2::new(rel.x / z_grid_width, rel.y / z_grid_height);
                     
                     if cx >= 0 && ry >= 0 && (cx as usize) < self.grid_cols && (ry as usize) < self.grid_rows {
                         let current_grid = layer.get_grid(self.current_edit_layer_type);
-                        let terrain_h = current_grid[ry as usize][cx as usize];
+                        let terrain_h = current_grid.get(ry as usize, cx as usize);
+                        self.hovered_terrain = Some(terrain_h);
                         
                         let px_x = cx as f32 * self.grid_width;
                         let px_y = ry as f32 * self.grid_height;
                         
-                        self.hover_info = format!("Grid: ({}, {})\nPixel: ({:.1}, {:.1})\n层级: {:?}\nID: {}", cx, ry, px_x, px_y, self.current_edit_layer_type, terrain_h);
+                        self.hover_info = trf!("Grid: ({}, {})\nPixel: ({}, {})\n层级: {}\nID: {}", cx, ry, format!("{:.1}", px_x), format!("{:.1}", px_y), format!("{:?}", self.current_edit_layer_type), terrain_h);
 
-                        let hovered_buildings: Vec<&PlacedBuilding> = self.placed_buildings.iter().filter(|b| {
-                            cx >= b.grid_x as i32 && cx < (b.grid_x + b.width) as i32 && 
-                            ry >= b.grid_y as i32 && ry < (b.grid_y + b.height) as i32 &&
-                            t_current >= get_time_value(b.wave_num, b.is_late) && t_current < self.get_building_demolish_time(b.uid)
-                        }).collect();
+                        let hovered_buildings: Vec<&PlacedBuilding> = self.placed_buildings.iter().filter(|b| self.building_covers_at(b, at, t_current)).collect();
 
-                        if !hovered_buildings.is_empty() {
-                            self.hover_info += "\n\n[建筑]:";
+                        if !hovered_buildings.is_empty() && self.show_building_tooltips {
+                            self.hover_info += &trf!("\n\n[建筑]: {} 座（详见光标提示）", hovered_buildings.len());
+                            let uids: Vec<usize> = hovered_buildings.iter().map(|b| b.uid).collect();
+                            if !input.pointer.any_down() { self.show_building_tooltip(ui.ctx(), &uids, t_current); }
+                        } else if !hovered_buildings.is_empty() {
+                            self.hover_info += tr("\n\n[建筑]:");
                             for b in hovered_buildings {
                                 let type_str = match b.b_type {
-                                    BuildingType::Floor => "地", BuildingType::Wall => "墙", BuildingType::Ceiling => "顶",
+                                    BuildingType::Floor => tr("地"), BuildingType::Wall => tr("墙"), BuildingType::Ceiling => tr("顶"),
                                 };
                                 self.hover_info += &format!("\n- {} ({})", b.template_name, type_str);
+                                if let Some(c) = &b.comment { self.hover_info += &format!("\n  💬 {}", c); }
                             }
                         }
                     } else {
-                        self.hover_info = "光标越界".to_string();
+                        self.hover_info = tr("光标越界").to_string();
                     }
                     
                     // 仅当 Hovered 时处理编辑逻辑
-                    if self.mode == EditMode::Terrain {
+                    if self.mode == EditMode::Terrain && !aligning && !self.handle_calibration_input(&response, pos, panel_rect.min + self.pan) {
                         let (c, r) = (cx, ry);
-                        if r >= 0 && c >= 0 && (r as usize) < self.grid_rows && (c as usize) < self.grid_cols {
-                            if input.pointer.button_down(egui::PointerButton::Primary) || input.pointer.button_down(egui::PointerButton::Secondary) {
-                                let layer_data = self.layers_data.get_mut(&self.current_major_z).unwrap();
-                                let grid = layer_data.get_grid_mut(self.current_edit_layer_type);
-                                
-                                let val = if input.pointer.button_down(egui::PointerButton::Primary) { self.current_brush } else { -1 };
-                                for dr in (r-self.brush_radius)..=(r+self.brush_radius) {
-                                    for dc in (c-self.brush_radius)..=(c+self.brush_radius) {
-                                        if dr >= 0 && dc >= 0 && (dr as usize) < self.grid_rows && (dc as usize) < self.grid_cols { grid[dr as usize][dc as usize] = val; }
+                        self.draw_brush_footprint(&painter, origin, Vec2::new(z_grid_width, z_grid_height), c, r);
+                        match self.terrain_tool {
+                            TerrainTool::Brush | TerrainTool::Noise | TerrainTool::Scatter => {
+                                if r >= 0 && c >= 0 && (r as usize) < self.grid_rows && (c as usize) < self.grid_cols {
+                                    if input.pointer.button_down(egui::PointerButton::Primary) || input.pointer.button_down(egui::PointerButton::Secondary) {
+                                        let val = if input.pointer.button_down(egui::PointerButton::Primary) { self.current_brush } else { -1 };
+                                        if self.terrain_tool == TerrainTool::Brush { self.paint_terrain_square(c, r, self.brush_radius, val); } else { self.paint_procedural(c, r, val); }
+                                    }
+                                }
+                            }
+                            TerrainTool::Smooth => {
+                                if self.cell_in_grid((c, r)) && input.pointer.button_down(egui::PointerButton::Primary) { self.smooth_terrain(c, r); }
+                            }
+                            TerrainTool::Rect => {
+                                // 左键按下开始、松开填充当前笔刷；右键填充障碍
+                                if input.pointer.button_pressed(egui::PointerButton::Primary) {
+                                    self.terrain_shape_origin = Some((c, r));
+                                    self.terrain_shape_value = self.current_brush;
+                                } else if input.pointer.button_pressed(egui::PointerButton::Secondary) {
+                                    self.terrain_shape_origin = Some((c, r));
+                                    self.terrain_shape_value = -1;
+                                }
+                                if let Some((c0, r0)) = self.terrain_shape_origin {
+                                    let preview = Rect::from_two_pos(
+                                        origin + Vec2::new(c0.min(c) as f32 * z_grid_width, r0.min(r) as f32 * z_grid_height),
+                                        origin + Vec2::new((c0.max(c) + 1) as f32 * z_grid_width, (r0.max(r) + 1) as f32 * z_grid_height),
+                                    );
+                                    painter.rect_filled(preview, 0.0, get_layer_color(&self.terrain_types, self.terrain_shape_value));
+                                    painter.rect_stroke(preview, 0.0, Stroke::new(1.5, Color32::WHITE));
+                                    if input.pointer.any_released() {
+                                        self.fill_terrain_rect((c0, r0), (c, r), self.terrain_shape_value);
+                                        self.terrain_shape_origin = None;
+                                    }
+                                }
+                            }
+                            TerrainTool::Line => {
+                                // 左键依次点击折线顶点，右键或 Esc 结束折线
+                                if response.clicked_by(egui::PointerButton::Primary) {
+                                    let start = self.terrain_shape_origin.unwrap_or((c, r));
+                                    for (lc, lr) in bresenham_line(start, (c, r)) {
+                                        self.paint_terrain_square(lc, lr, self.brush_radius, self.current_brush);
                                     }
+                                    self.terrain_shape_origin = Some((c, r));
+                                } else if response.clicked_by(egui::PointerButton::Secondary) || input.key_pressed(egui::Key::Escape) {
+                                    self.terrain_shape_origin = None;
                                 }
                             }
                         }
+                    } else if self.current_wave_locked() && self.mode_adds_to_current_wave() {
+                        painter.text(pos + Vec2::new(16.0, 16.0), Align2::LEFT_TOP, trf!("🔒 第 {} 波已锁定", self.current_wave_num), FontId::proportional(14.0), Color32::from_rgb(255, 200, 0));
+                    } else if self.mode == EditMode::Building && self.active_stamp.is_some() {
+                        self.handle_stamp_placement(&painter, &response, &input, origin, Vec2::new(z_grid_width, z_grid_height), rel);
                     } else if self.mode == EditMode::Building {
                         let t = &self.building_templates[self.selected_building_idx];
-                        let c = ((rel.x / z_grid_width) - (t.width as f32 / 2.0)).round() as i32;
-                        let r = ((rel.y / z_grid_height) - (t.height as f32 / 2.0)).round() as i32;
-                        let ghost_rect = Rect::from_min_size(origin + Vec2::new(c as f32 * z_grid_width, r as f32 * z_grid_height), Vec2::new(t.width as f32 * z_grid_width, t.height as f32 * z_grid_height));
+                        // 按吸附精度取整后拆成所在整格与小数偏移
+                        let (step_x, step_y) = match self.placement_snap {
+                            PlacementSnap::Cell => (1.0, 1.0),
+                            PlacementSnap::HalfCell => (0.5, 0.5),
+                            PlacementSnap::Pixel => (1.0 / self.grid_width, 1.0 / self.grid_height),
+                        };
+                        let fx = (((rel.x / z_grid_width) - (t.width as f32 / 2.0)) / step_x).round() * step_x;
+                        let fy = (((rel.y / z_grid_height) - (t.height as f32 / 2.0)) / step_y).round() * step_y;
+                        let (c, r) = (fx.floor() as i32, fy.floor() as i32);
+                        let offset = [fx - fx.floor(), fy - fy.floor()];
+                        let ghost_rect = Rect::from_min_size(origin + Vec2::new(fx * z_grid_width, fy * z_grid_height), Vec2::new(t.width as f32 * z_grid_width, t.height as f32 * z_grid_height));
                         
                         let is_valid = r >= 0 && c >= 0 && self.can_place_building(r as usize, c as usize, t.width, t.height, t.b_type);
+                        // 预算只做软提示：超出时幽灵框变橙色，仍允许放置
+                        let cost = build_cost(&self.building_configs, &t.name);
+                        let money = self.money_remaining();
+                        let over_budget = money.is_some_and(|m| m < cost);
                         
-                        painter.rect_stroke(ghost_rect, 0.0, Stroke::new(2.5, if is_valid { Color32::GREEN } else { Color32::RED }));
+                        let ghost_color = if !is_valid { Color32::RED } else if over_budget { Color32::from_rgb(255, 140, 0) } else { Color32::GREEN };
+                        painter.rect_stroke(ghost_rect, 0.0, Stroke::new(2.5, ghost_color));
+                        if let (true, Some(m)) = (is_valid && over_budget, money) {
+                            painter.text(ghost_rect.center_bottom() + Vec2::new(0.0, 4.0), Align2::CENTER_TOP, trf!("资金不足: {} / {}", m, cost), FontId::proportional(14.0), ghost_color);
+                        }
                         if response.clicked_by(egui::PointerButton::Primary) && is_valid {
                             self.placed_buildings.push(PlacedBuilding { 
                                 uid: self.next_uid, 
                                 template_name: t.name.clone(), 
                                 b_type: t.b_type, 
                                 grid_x: c as usize, grid_y: r as usize, width: t.width, height: t.height, 
-                                color: t.color, wave_num: self.current_wave_num, is_late: self.current_is_late 
+                                color: t.color, wave_num: self.current_wave_num, is_late: self.current_is_late, viewport: self.current_viewport_preset(), comment: None, offset, offset_seconds: None, order: None,
                             });
                             self.next_uid += 1;
-                        } else if response.clicked_by(egui::PointerButton::Secondary) {
-                            let (px, py) = (cx, ry);
-                            // 1. 先从地图上移除被点击的建筑
-                            self.placed_buildings.retain(|b| !(px >= b.grid_x as i32 && px < (b.grid_x + b.width) as i32 && py >= b.grid_y as i32 && py < (b.grid_y + b.height) as i32));
-                            
-                            // 2. 然后清理无效的拆除计划（只保留那些 UID 依然存在于 placed_buildings 中的事件）
-                            self.demolish_events.retain(|e| self.placed_buildings.iter().any(|b| b.uid == e.uid));
+                        } else if response.secondary_clicked() {
+                            self.context_menu_uid = self.active_building_at(at).map(|i| self.placed_buildings[i].uid);
                         }
-                    } else if self.mode == EditMode::Demolish {
-                        let (px, py) = (cx, ry);
-                        let target = self.placed_buildings.iter().find(|b| {
-                            px >= b.grid_x as i32 && px < (b.grid_x + b.width) as i32 && py >= b.grid_y as i32 && py < (b.grid_y + b.height) as i32 &&
-                            t_current >= get_time_value(b.wave_num, b.is_late) && t_current < self.get_building_demolish_time(b.uid)
-                        });
-                        if let Some(b) = target {
-                            let r = Rect::from_min_size(origin + Vec2::new(b.grid_x as f32 * z_grid_width, b.grid_y as f32 * z_grid_height), Vec2::new(b.width as f32 * z_grid_width, b.height as f32 * z_grid_height));
-                            painter.rect_stroke(r, 0.0, Stroke::new(3.0, Color32::YELLOW));
-                            if response.clicked_by(egui::PointerButton::Primary) && !self.demolish_events.iter().any(|e| e.uid == b.uid) {
-                                self.demolish_events.push(DemolishEvent { uid: b.uid, name: b.template_name.clone(), grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, wave_num: self.current_wave_num, is_late: self.current_is_late });
+                    } else if self.mode == EditMode::Select {
+                        let shift = input.modifiers.shift;
+                        if response.clicked_by(egui::PointerButton::Primary) {
+                            let hit = self.active_building_at(at).map(|i| self.placed_buildings[i].uid);
+                            match (hit, shift) {
+                                (Some(uid), true) => {
+                                    if let Some(i) = self.selected_uids.iter().position(|u| *u == uid) { self.selected_uids.remove(i); } else { self.selected_uids.push(uid); }
+                                }
+                                (Some(uid), false) => { self.selected_uids = vec![uid]; }
+                                (None, false) => { self.selected_uids.clear(); }
+                                (None, true) => {}
+                            }
+                        }
+                        if !ctx.wants_keyboard_input() {
+                            for event in &input.events {
+                                match event {
+                                    egui::Event::Copy => {
+                                        if let Some(text) = self.copy_selection() { ctx.output_mut(|o| o.copied_text = text); }
+                                    }
+                                    egui::Event::Paste(text) => {
+                                        let group = serde_json::from_str::<BuildingClipboard>(text).map(|c| c.minke_buildings).unwrap_or_else(|_| self.building_clipboard.clone());
+                                        self.paste_buildings(&group, cx, ry);
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                        if response.drag_started_by(egui::PointerButton::Primary) {
+                            // 以按下时的位置判断抓取的建筑，避免拖动阈值造成的偏移
+                            let press_pos = input.pointer.press_origin().unwrap_or(pos);
+                            let press = press_pos - origin;
+                            let (px, py) = ((press.x / z_grid_width).floor() as i32, (press.y / z_grid_height).floor() as i32);
+                            if let Some(i) = self.active_building_at(Pos2::new(press.x / z_grid_width, press.y / z_grid_height)) {
+                                let b = &self.placed_buildings[i];
+                                let (bx, by) = self.building_pos_at(b, t_current);
+                                if !self.selected_uids.contains(&b.uid) { self.selected_uids = vec![b.uid]; }
+                                self.building_drag = Some(BuildingDrag { uid: b.uid, grab_dx: px - bx as i32, grab_dy: py - by as i32 });
+                            } else {
+                                // 空白处拖动：框选
+                                self.selection_box_origin = Some(press_pos);
+                            }
+                        }
+                        if response.double_clicked() {
+                            if let Some(i) = self.active_building_at(at) { self.open_building_edit(self.placed_buildings[i].uid); }
+                        }
+                        if response.secondary_clicked() { self.context_menu_uid = self.active_building_at(at).map(|i| self.placed_buildings[i].uid); }
+                        if let Some(drag) = self.building_drag {
+                            if let Some(anchor) = self.placed_buildings.iter().find(|b| b.uid == drag.uid) {
+                                // 整条时间线一起平移，偏移量按当前时间的位置计算
+                                let (ax, ay) = self.building_pos_at(anchor, t_current);
+                                let (dx, dy) = (cx - drag.grab_dx - ax as i32, ry - drag.grab_dy - ay as i32);
+                                let uids = self.selected_uids.clone();
+                                let is_valid = self.can_move_buildings(&uids, dx, dy);
+                                for b in self.placed_buildings.iter().filter(|b| uids.contains(&b.uid)) {
+                                    let (bx, by) = self.building_pos_at(b, t_current);
+                                    let ghost_rect = Rect::from_min_size(origin + Vec2::new((bx as i32 + dx) as f32 * z_grid_width, (by as i32 + dy) as f32 * z_grid_height), Vec2::new(b.width as f32 * z_grid_width, b.height as f32 * z_grid_height));
+                                    painter.rect_stroke(ghost_rect, 0.0, Stroke::new(2.5, if is_valid { Color32::GREEN } else { Color32::RED }));
+                                }
+                                if response.drag_released_by(egui::PointerButton::Primary) {
+                                    if is_valid { self.move_buildings(&uids, dx, dy); }
+                                    self.building_drag = None;
+                                }
+                            }
+                        }
+                        if let Some(start) = self.selection_box_origin {
+                            let sel_rect = Rect::from_two_pos(start, pos);
+                            painter.rect_filled(sel_rect, 0.0, Color32::from_rgba_unmultiplied(100, 180, 255, 40));
+                            painter.rect_stroke(sel_rect, 0.0, Stroke::new(1.0, Color32::LIGHT_BLUE));
+                            if response.drag_released_by(egui::PointerButton::Primary) {
+                                if !shift { self.selected_uids.clear(); }
+                                for uid in self.active_buildings_in_box(sel_rect, origin, Vec2::new(z_grid_width, z_grid_height)) {
+                                    if !self.selected_uids.contains(&uid) { self.selected_uids.push(uid); }
+                                }
+                                self.selection_box_origin = None;
                             }
                         }
+                    } else if self.mode == EditMode::Route {
+                        self.handle_route_input(&response, &input, cx, ry);
+                    } else if self.mode == EditMode::Demolish {
+                        self.handle_demolish_input(&response, &input, &painter, origin, Vec2::new(z_grid_width, z_grid_height), pos);
+                    } else if self.mode == EditMode::Sell {
+                        self.handle_sell_input(&response, &painter, origin, Vec2::new(z_grid_width, z_grid_height), at);
+                    } else if self.mode == EditMode::Relocate {
+                        self.handle_relocate_input(&response, &painter, origin, Vec2::new(z_grid_width, z_grid_height), at);
+                    } else if self.mode == EditMode::Upgrade {
+                        self.handle_upgrade_input(&response, &painter, origin, Vec2::new(z_grid_width, z_grid_height), at);
+                    } else if self.mode == EditMode::Ability {
+                        self.handle_ability_input(&response, &painter, origin, Vec2::new(z_grid_width, z_grid_height), cx, ry);
                     }
                 }
             }
+            if self.relocate_pick.is_some() && self.mode != EditMode::Relocate { self.relocate_pick = None; }
+            if self.upgrade_target_uid.is_some() && self.mode != EditMode::Upgrade { self.upgrade_target_uid = None; }
+            // 选择与布局模式下右键建筑弹出菜单
+            self.show_building_context_menu(&response);
+
+            if self.terrain_shape_origin.is_some() && (self.mode != EditMode::Terrain || (self.terrain_tool == TerrainTool::Rect && !input.pointer.any_down())) {
+                self.terrain_shape_origin = None;
+            }
+            if self.route_drag.is_some() && (self.mode != EditMode::Route || !input.pointer.primary_down()) {
+                self.route_drag = None;
+            }
+            // 拖动在画布外松开或切换了模式时取消移动
+            if (self.building_drag.is_some() || self.selection_box_origin.is_some()) && (self.mode != EditMode::Select || !input.pointer.primary_down()) {
+                self.building_drag = None;
+                self.selection_box_origin = None;
+            }
+            if self.demolish_box_origin.is_some() && (self.mode != EditMode::Demolish || !input.pointer.primary_down()) { self.demolish_box_origin = None; }
 
             // 绘制观察框
             if let Some(tex) = &self.texture {
@@ -1277,12 +2659,21 @@ impl eframe::App for MapEditor {
                     );
                     painter.rect_stroke(area_rect, 2.0, Stroke::new(2.0, Color32::from_rgba_unmultiplied(255, 255, 0, 150)));
                 }
+                self.draw_camera_path(&painter, map_origin);
             }
+            self.draw_calibration(&painter, panel_rect.min + self.pan);
+            self.draw_grid_detection(&painter, panel_rect.min + self.pan);
+            if self.mode == EditMode::Terrain { self.draw_align_handles(&painter, panel_rect, input.pointer.hover_pos()); }
+
+            self.draw_rulers(&painter, panel_rect, origin, Vec2::new(z_grid_width, z_grid_height), input.pointer.hover_pos());
+            self.draw_minimap(&painter, panel_rect);
+
+            self.draw_wave_note_banner(&painter, panel_rect);
 
             // 🔥 悬浮信息栏绘制：独立在地图上方 (最后绘制以确保最上层)
-            if !self.hover_info.is_empty() && self.hover_info != "无" {
+            if !self.hover_info.is_empty() && self.hover_info != tr("无") {
                 // 在左上角绘制
-                let info_pos = panel_rect.min + Vec2::new(10.0, 10.0);
+                let info_pos = panel_rect.min + Vec2::splat(10.0 + self.ruler_inset());
                 let galley = painter.layout_no_wrap(self.hover_info.clone(), FontId::new(14.0, FontFamily::Monospace), Color32::WHITE);
                 
                 let bg_rect = Rect::from_min_size(info_pos, galley.size() + Vec2::new(10.0, 10.0));
@@ -1290,5 +2681,40 @@ impl eframe::App for MapEditor {
                 painter.galley(info_pos + Vec2::new(5.0, 5.0), galley, Color32::WHITE);
             }
         });
+
+        self.notifications.show(ctx);
+        self.show_economy_window(ctx);
+        self.show_script_window(ctx);
+        self.show_validation_window(ctx);
+        self.show_comment_editor(ctx);
+        self.show_building_edit(ctx);
+        self.show_renumber_dialog(ctx);
+        self.show_wave_shift_dialog(ctx);
+        self.show_orphan_cleanup_dialog(ctx);
+        self.show_strategy_import_dialog(ctx);
+        self.show_partial_import_dialog(ctx);
+        self.show_legacy_import_dialog(ctx);
+        self.show_roundtrip_dialog(ctx);
+        self.show_unsaved_prompt(ctx);
+        self.show_export_dialog(ctx);
+        self.show_resize_dialog(ctx);
+        self.show_terrain_image_import(ctx);
+        self.show_terrain_legend(ctx);
+        self.show_build_order(ctx);
+        self.draw_drop_hint(ctx);
+
+        let frame_end_snapshot = self.capture_snapshot();
+        let changed = frame_end_snapshot != frame_start_snapshot;
+        if changed { self.route_preview = None; }
+        // 本帧有升级或拆除事件被删除（脚本、清理、批量操作等）时，之后的下标都已错位
+        if frame_end_snapshot.strategy.upgrade_events.len() < frame_start_snapshot.strategy.upgrade_events.len() || frame_end_snapshot.strategy.demolish_events.len() < frame_start_snapshot.strategy.demolish_events.len() {
+            self.checked_events.clear();
+            self.editing_event = None;
+        }
+        self.update_dirty_state(ctx, &frame_end_snapshot, changed || restored);
+        let gesture_active = ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input() || self.camera_recording.is_some();
+        self.record_history(frame_start_snapshot, &frame_end_snapshot, gesture_active);
+        self.update_live_sync(ctx, changed || restored, gesture_active);
+        if self.history_request.is_some() || self.tab_request.is_some() { ctx.request_repaint(); }
     }
 }
\ No newline at end of file
@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// 🔥 资源路径解析：配置里的相对路径一律相对于资源根目录（默认 maps/），
+// 工程文件里的路径相对于工程文件所在目录，整个目录拷到别的机器上也能打开
+
+pub const ASSET_ROOT_ENV: &str = "MINKE_ASSET_ROOT";
+pub const BUILDING_CONFIGS_FILE: &str = "buildings_config.json";
+pub const PRESETS_FILE: &str = "map_presets.json";
+pub const TERRAIN_TYPES_FILE: &str = "terrain_types.json";
+pub const STAMPS_FILE: &str = "stamps.json";
+pub const CONSISTENCY_RULES_FILE: &str = "consistency_rules.json";
+pub const VIEW_OPTIONS_FILE: &str = "view_options.json";
+
+// 旧配置里的路径都带 maps/ 前缀（相对于工作目录），解析时去掉再拼到资源根目录上
+const LEGACY_PREFIX: &str = "maps/";
+
+// 优先级：环境变量 > 工作目录下的 maps/ > 可执行文件旁的 maps/
+pub fn default_asset_root() -> PathBuf {
+    if let Ok(root) = env::var(ASSET_ROOT_ENV) { return PathBuf::from(root); }
+    let local = PathBuf::from("maps");
+    if local.is_dir() { return local; }
+    env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join("maps"))).filter(|p| p.is_dir()).unwrap_or(local)
+}
+
+pub fn resolve_asset(root: &Path, p: &str) -> PathBuf {
+    let p = p.replace('\\', "/");
+    if Path::new(&p).is_absolute() { return PathBuf::from(p); }
+    root.join(p.strip_prefix(LEGACY_PREFIX).unwrap_or(&p))
+}
+
+pub fn resolve_relative_to(base_dir: &Path, p: &str) -> PathBuf {
+    let p = p.replace('\\', "/");
+    if Path::new(&p).is_absolute() { PathBuf::from(p) } else { base_dir.join(p) }
+}
+
+// 尽量写成相对于 base_dir 的路径（统一用 / 分隔）；不在同一盘符等无法相对化时保留绝对路径
+pub fn portable_path(base_dir: &Path, target: &Path) -> String {
+    let slashed = |p: &Path| p.to_string_lossy().replace('\\', "/");
+    let (Ok(base), Ok(target_abs)) = (base_dir.canonicalize(), target.canonicalize()) else { return slashed(target); };
+    let base: Vec<Component> = base.components().collect();
+    let target_parts: Vec<Component> = target_abs.components().collect();
+    let common = base.iter().zip(&target_parts).take_while(|(a, b)| a == b).count();
+    if common == 0 { return slashed(&target_abs); }
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() { relative.push(".."); }
+    for part in &target_parts[common..] { relative.push(part); }
+    slashed(&relative)
+}
+
+// 轮询配置文件与图标的修改时间，开启自动重新加载后外部编辑保存即由编辑器重新加载
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct ConfigWatcher {
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+    last_check: Option<Instant>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ConfigWatcher {
+    // 记录当前文件状态作为基准（加载配置后调用）
+    pub fn track(&mut self, paths: Vec<PathBuf>) {
+        self.stamps = paths.into_iter().map(|p| { let t = modified(&p); (p, t) }).collect();
+        self.last_check = Some(Instant::now());
+    }
+
+    // 距上次检查不足 WATCH_INTERVAL 时直接返回 false
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.is_some_and(|t| t.elapsed() < WATCH_INTERVAL) { return false; }
+        self.last_check = Some(Instant::now());
+        self.stamps.iter().any(|(p, t)| modified(p) != *t)
+    }
+}
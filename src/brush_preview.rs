@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+use eframe::egui::{Color32, Painter, Pos2, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::models::TerrainTool;
+use crate::utils::bresenham_line;
+
+// 🔥 笔刷范围预览：落笔前在光标处勾出这一笔实际会改动的格子（已裁剪到网格内），大半径时不会误涂到旁边
+// 只描整块区域的外轮廓；噪声、散布笔刷按种子逐格判断，轮廓与实际涂上的格子完全一致
+
+const OUTLINE: Color32 = Color32::WHITE;
+const SHADOW: Color32 = Color32::from_black_alpha(160);
+
+impl MapEditor {
+    // 当前工具在 (c, r) 落笔时会改动的格子 (列, 行)；矩形工具拖动时另有预览，这里只给出起点格子
+    fn brush_footprint(&self, c: i32, r: i32) -> HashSet<(i32, i32)> {
+        let radius = self.brush_radius;
+        let centers = match (self.terrain_tool, self.terrain_shape_origin) {
+            (TerrainTool::Rect, Some(_)) => return HashSet::new(),
+            (TerrainTool::Rect, None) => return [(c, r)].into_iter().filter(|&cell| self.cell_in_grid(cell)).collect(),
+            (TerrainTool::Line, Some(last)) => bresenham_line(last, (c, r)),
+            // 笔刷类工具只在光标位于网格内时落笔
+            (TerrainTool::Brush | TerrainTool::Noise | TerrainTool::Scatter | TerrainTool::Smooth, _) if !self.cell_in_grid((c, r)) => return HashSet::new(),
+            _ => vec![(c, r)],
+        };
+        centers.into_iter()
+            .flat_map(|(cx, cy)| (cy - radius..=cy + radius).flat_map(move |y| (cx - radius..=cx + radius).map(move |x| (x, y))))
+            .filter(|&cell| self.cell_in_grid(cell) && self.procedural_brush.selects(self.terrain_tool, cell.0, cell.1))
+            .collect()
+    }
+
+    pub(crate) fn cell_in_grid(&self, (c, r): (i32, i32)) -> bool {
+        c >= 0 && r >= 0 && (c as usize) < self.grid_cols && (r as usize) < self.grid_rows
+    }
+
+    pub(crate) fn draw_brush_footprint(&self, painter: &Painter, origin: Pos2, cell: Vec2, c: i32, r: i32) {
+        let cells = self.brush_footprint(c, r);
+        let at = |x: i32, y: i32| origin + Vec2::new(x as f32 * cell.x, y as f32 * cell.y);
+        let mut edges = Vec::new();
+        for &(x, y) in &cells {
+            if !cells.contains(&(x, y - 1)) { edges.push([at(x, y), at(x + 1, y)]); }
+            if !cells.contains(&(x, y + 1)) { edges.push([at(x, y + 1), at(x + 1, y + 1)]); }
+            if !cells.contains(&(x - 1, y)) { edges.push([at(x, y), at(x, y + 1)]); }
+            if !cells.contains(&(x + 1, y)) { edges.push([at(x + 1, y), at(x + 1, y + 1)]); }
+        }
+        // 先画深色描边，在浅色底图上也看得清
+        for (width, color) in [(3.0, SHADOW), (1.2, OUTLINE)] {
+            for edge in &edges { painter.line_segment(*edge, Stroke::new(width, color)); }
+        }
+    }
+}
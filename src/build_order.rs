@@ -0,0 +1,85 @@
+use eframe::egui::{self, Stroke};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::operations::{flatten_operations, Operation, OperationAction};
+use crate::utils::{event_time_label, time_order};
+
+// 🔥 建造顺序列表：按执行顺序列出全部操作（与导出的操作序列一致），同一时刻内的操作都可以拖动调整先后。
+// 松手后该时刻内的操作依次写入各自的 order 字段（0, 1, 2…），随策略文件保存
+
+// 拖动中的行号，单独包一层以免与其它拖放的载荷混淆
+struct DraggedRow(usize);
+
+fn operation_label(op: &Operation) -> String {
+    let uid = op.uid.unwrap_or_default();
+    match op.action {
+        OperationAction::Build => trf!("建造 {} (UID {})", op.building_name, uid),
+        OperationAction::Upgrade if op.uid.is_some() => trf!("升级 {} (UID {})", op.building_name, uid),
+        OperationAction::Upgrade => trf!("升级 {}", op.building_name),
+        OperationAction::Demolish => trf!("拆除 {} (UID {})", op.building_name, uid),
+        OperationAction::Sell => trf!("出售 {} (UID {})", op.building_name, uid),
+        OperationAction::Relocate => trf!("迁移 {} (UID {})", op.building_name, uid),
+        OperationAction::Ability => trf!("技能 {}", op.building_name),
+    }
+}
+
+fn slot(op: &Operation) -> (i32, i64) {
+    time_order(op.wave_num, op.is_late, op.offset_seconds)
+}
+
+impl MapEditor {
+    // 把 from 行移到 to 行的位置（两行须在同一时刻），再按新的先后给该时刻内的操作重新编号
+    fn reorder_operations(&mut self, ops: &[Operation], from: usize, to: usize) {
+        let mut rows: Vec<usize> = (0..ops.len()).filter(|&i| slot(&ops[i]) == slot(&ops[to])).collect();
+        let (Some(a), Some(b)) = (rows.iter().position(|&i| i == from), rows.iter().position(|&i| i == to)) else { return; };
+        let row = rows.remove(a);
+        rows.insert(b, row);
+        for (rank, &i) in rows.iter().enumerate() {
+            let order = Some(rank as u32);
+            match ops[i].action {
+                OperationAction::Build => self.placed_buildings[ops[i].index].order = order,
+                OperationAction::Upgrade => self.upgrade_events[ops[i].index].order = order,
+                OperationAction::Demolish => self.demolish_events[ops[i].index].order = order,
+                OperationAction::Sell => self.sell_events[ops[i].index].order = order,
+                OperationAction::Relocate => self.relocate_events[ops[i].index].order = order,
+                OperationAction::Ability => self.ability_events[ops[i].index].order = order,
+            }
+        }
+    }
+
+    pub(crate) fn show_build_order(&mut self, ctx: &egui::Context) {
+        if !self.show_build_order { return; }
+        let mut open = true;
+        // 导出的建筑列表与 placed_buildings 顺序一致，index 可直接对应回去
+        let ops = flatten_operations(&self.build_buildings_export());
+        let mut moved = None;
+        egui::Window::new(tr("建造顺序")).open(&mut open).default_size([360.0, 420.0]).show(ctx, |ui| {
+            ui.weak(tr("拖动 ☰ 调整同一时刻内操作的先后"));
+            ui.separator();
+            if ops.is_empty() { ui.weak(tr("暂无操作")); }
+            egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                for (i, op) in ops.iter().enumerate() {
+                    if i == 0 || slot(&ops[i - 1]) != slot(op) { ui.strong(event_time_label(op.wave_num, op.is_late, op.offset_seconds)); }
+                    // 锁定波次中的操作不能调整先后
+                    if self.wave_locked(op.wave_num) {
+                        ui.horizontal(|ui| { ui.add_space(18.0); ui.weak(operation_label(op)); });
+                        continue;
+                    }
+                    let response = ui.dnd_drag_source(egui::Id::new(("build_order", i)), DraggedRow(i), |ui| {
+                        ui.horizontal(|ui| { ui.label("☰"); ui.label(operation_label(op)); });
+                    }).response;
+                    // 只能放到同一时刻的可排序行上；向下拖放在目标之后，向上拖放在目标之前
+                    let target = |from: usize| (from != i && slot(&ops[from]) == slot(op)).then_some(from);
+                    if let Some(from) = response.dnd_hover_payload::<DraggedRow>().and_then(|row| target(row.0)) {
+                        let y = if from < i { response.rect.bottom() } else { response.rect.top() };
+                        ui.painter().hline(response.rect.x_range(), y, Stroke::new(2.0, ui.visuals().selection.stroke.color));
+                    }
+                    if let Some(from) = response.dnd_release_payload::<DraggedRow>().and_then(|row| target(row.0)) { moved = Some((from, i)); }
+                }
+            });
+        });
+        if let Some((from, to)) = moved { self.reorder_operations(&ops, from, to); }
+        if !open { self.show_build_order = false; }
+    }
+}
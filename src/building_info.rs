@@ -0,0 +1,180 @@
+use eframe::egui::{self, Color32, RichText, Sense, Vec2};
+
+use crate::app::MapEditor;
+use crate::economy::{build_cost, cumulative_upgrade_cost, upgrade_levels};
+use crate::events::{footprint_segments, offset_seconds_editor, wave_editor};
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::{event_time_label, get_time_value, time_order, wave_from_time_value, wave_label};
+
+// 🔥 建筑信息：悬停在建筑上时在光标旁显示模板、UID、占地、建造/移除时间、相关升级与费用；
+// 选择模式下双击建筑打开属性窗口，修改建造时间、层类型或更换模板，应用前按地形与重叠校验整条时间线
+
+// 属性窗口中尚未应用的修改
+pub(crate) struct BuildingEdit {
+    uid: usize,
+    template: String,
+    b_type: BuildingType,
+    wave_num: i32,
+    is_late: bool,
+    offset_seconds: Option<f32>,
+}
+
+fn type_label(b_type: BuildingType) -> &'static str {
+    match b_type { BuildingType::Floor => tr("地"), BuildingType::Wall => tr("墙"), BuildingType::Ceiling => tr("顶") }
+}
+
+impl MapEditor {
+    pub(crate) fn show_building_tooltip(&self, ctx: &egui::Context, uids: &[usize], t: i32) {
+        let levels = upgrade_levels(&self.upgrade_events);
+        egui::show_tooltip_at_pointer(ctx, egui::Id::new("building_tooltip"), |ui| {
+            for (i, b) in self.placed_buildings.iter().filter(|b| uids.contains(&b.uid)).enumerate() {
+                if i > 0 { ui.separator(); }
+                self.building_tooltip_contents(ui, b, t, &levels);
+            }
+        });
+    }
+
+    fn building_tooltip_contents(&self, ui: &mut egui::Ui, b: &PlacedBuilding, t: i32, levels: &[usize]) {
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(Vec2::splat(12.0), Sense::hover());
+            ui.painter().rect_filled(rect, 2.0, b.color.to_opaque());
+            ui.label(RichText::new(&b.template_name).strong());
+            ui.weak(format!("UID {} · {}", b.uid, type_label(b.b_type)));
+        });
+
+        let (x, y) = self.building_pos_at(b, t);
+        let moved = (x, y) != (b.grid_x, b.grid_y);
+        ui.label(trf!("占地: {}×{}，位于 ({}, {}){}", b.width, b.height, x, y, if moved { tr("（已迁移）") } else { "" }));
+        ui.label(trf!("建造: {}", event_time_label(b.wave_num, b.is_late, b.offset_seconds)));
+        let removal = self.demolish_events.iter().filter(|e| e.uid == b.uid).map(|e| (time_order(e.wave_num, e.is_late, e.offset_seconds), tr("拆除"), event_time_label(e.wave_num, e.is_late, e.offset_seconds)))
+            .chain(self.sell_events.iter().filter(|e| e.uid == b.uid).map(|e| (time_order(e.wave_num, e.is_late, None), tr("出售"), wave_label(e.wave_num, e.is_late))))
+            .min_by_key(|(t, _, _)| *t);
+        match removal {
+            Some((_, kind, wave)) => ui.label(trf!("移除: {}于 {}", kind, wave)),
+            None => ui.label(tr("移除: 未安排")),
+        };
+        let relocations = self.relocate_events.iter().filter(|e| e.uid == b.uid).count();
+        if relocations > 0 { ui.label(trf!("迁移: {} 次", relocations)); }
+
+        let mut upgrades: Vec<((i32, i64), String)> = self.upgrade_events.iter().zip(levels)
+            .filter(|(u, _)| u.building_name == b.template_name && u.uid.is_none_or(|uid| uid == b.uid))
+            .map(|(u, level)| (time_order(u.wave_num, u.is_late, u.offset_seconds), format!("{} → Lv{}{}", event_time_label(u.wave_num, u.is_late, u.offset_seconds), level, if u.uid.is_some() { tr("（单独）") } else { "" })))
+            .collect();
+        upgrades.sort_by_key(|(t, _)| *t);
+        let tier = self.upgrade_tier_at(&b.template_name, Some(b.uid), t);
+        if upgrades.is_empty() {
+            ui.label(tr("升级: 无"));
+        } else {
+            ui.label(trf!("升级: 当前 Lv{}，共 {} 次", tier, upgrades.len()));
+            for (_, text) in upgrades { ui.label(format!("  {}", text)); }
+        }
+
+        let (build, upgraded) = (build_cost(&self.building_configs, &b.template_name), cumulative_upgrade_cost(&self.building_configs, &b.template_name, tier));
+        if build != 0 || upgraded != 0 { ui.label(trf!("费用: 建造 {} + 升级 {} = {}", build, upgraded, build + upgraded)); }
+        if let Some(c) = &b.comment { ui.colored_label(Color32::LIGHT_YELLOW, format!("💬 {}", c)); }
+    }
+}
+
+impl MapEditor {
+    pub(crate) fn open_building_edit(&mut self, uid: usize) {
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return; };
+        self.building_edit = Some(BuildingEdit { uid, template: b.template_name.clone(), b_type: b.b_type, wave_num: b.wave_num, is_late: b.is_late, offset_seconds: b.offset_seconds });
+    }
+
+    // 修改后的尺寸：更换模板时取新模板的尺寸，否则保持原尺寸
+    fn building_edit_size(&self, b: &PlacedBuilding, edit: &BuildingEdit) -> (usize, usize) {
+        if edit.template == b.template_name { return (b.width, b.height); }
+        self.building_templates.iter().find(|t| t.name == edit.template).map_or((b.width, b.height), |t| (t.width, t.height))
+    }
+
+    // 修改后不合法的原因；合法时返回 None
+    fn building_edit_error(&self, edit: &BuildingEdit) -> Option<String> {
+        let b = self.placed_buildings.iter().find(|b| b.uid == edit.uid)?;
+        if self.wave_locked(b.wave_num) { return Some(trf!("第 {} 波已锁定", b.wave_num)); }
+        if self.wave_locked(edit.wave_num) { return Some(trf!("第 {} 波已锁定", edit.wave_num)); }
+        let t_create = get_time_value(edit.wave_num, edit.is_late);
+        let t_removed = self.get_building_demolish_time(b.uid);
+        if t_create >= t_removed { return Some(tr("建造时间必须早于拆除或出售时间").to_string()); }
+        if let Some(r) = self.relocate_events.iter().find(|r| r.uid == b.uid && get_time_value(r.wave_num, r.is_late) <= t_create) {
+            return Some(trf!("建造时间必须早于 {} 的迁移", wave_label(r.wave_num, r.is_late)));
+        }
+        if let Some(u) = self.upgrade_events.iter().find(|u| u.uid == Some(b.uid) && get_time_value(u.wave_num, u.is_late) < t_create) {
+            return Some(trf!("建造时间不能晚于 {} 的升级", wave_label(u.wave_num, u.is_late)));
+        }
+        let (w, h) = self.building_edit_size(b, edit);
+        footprint_segments(b.uid, (b.grid_x, b.grid_y), t_create, t_removed, &self.relocate_events).into_iter()
+            .find(|&(from, until, x, y)| !self.can_place_building_during(y, x, w, h, edit.b_type, from, until, &[b.uid]))
+            .map(|(from, _, x, y)| trf!("{} 起在 ({}, {}) 与地形或其他建筑冲突", { let (w, late) = wave_from_time_value(from); wave_label(w, late) }, x, y))
+    }
+
+    // 「设为当前波次」：先把选中的建筑一起改到当前时间，再按属性窗口的规则逐座校验（选中的建筑之间也互相检查），有问题时恢复原值并提示
+    pub(crate) fn move_buildings_to_current_wave(&mut self, uids: &[usize]) {
+        let (wave_num, is_late) = (self.current_wave_num, self.current_is_late);
+        let before = self.placed_buildings.clone();
+        for b in self.placed_buildings.iter_mut().filter(|b| uids.contains(&b.uid)) { (b.wave_num, b.is_late) = (wave_num, is_late); }
+        let error = self.placed_buildings.iter().filter(|b| uids.contains(&b.uid)).find_map(|b| {
+            let edit = BuildingEdit { uid: b.uid, template: b.template_name.clone(), b_type: b.b_type, wave_num, is_late, offset_seconds: b.offset_seconds };
+            self.building_edit_error(&edit).map(|e| format!("{} (UID {}): {}", b.template_name, b.uid, e))
+        });
+        if let Some(e) = error {
+            self.placed_buildings = before;
+            self.notifications.warn(trf!("无法设为当前波次：{}", e));
+        }
+    }
+
+    // 写回建筑，并同步拆除 / 出售 / 迁移事件中记录的名称与尺寸；单独升级跟随建筑改到新模板
+    fn apply_building_edit(&mut self, edit: &BuildingEdit) {
+        let Some(i) = self.placed_buildings.iter().position(|b| b.uid == edit.uid) else { return; };
+        let (width, height) = self.building_edit_size(&self.placed_buildings[i], edit);
+        let color = self.building_templates.iter().find(|t| t.name == edit.template).map_or(self.placed_buildings[i].color, |t| t.color);
+        let b = &mut self.placed_buildings[i];
+        let old_name = std::mem::replace(&mut b.template_name, edit.template.clone());
+        (b.b_type, b.wave_num, b.is_late, b.offset_seconds, b.width, b.height) = (edit.b_type, edit.wave_num, edit.is_late, edit.offset_seconds, width, height);
+        if old_name != edit.template { b.color = color; }
+        let uid = edit.uid;
+        for e in self.demolish_events.iter_mut().filter(|e| e.uid == uid) { (e.name, e.width, e.height) = (edit.template.clone(), width, height); }
+        for e in self.sell_events.iter_mut().filter(|e| e.uid == uid) { (e.name, e.width, e.height) = (edit.template.clone(), width, height); }
+        for e in self.relocate_events.iter_mut().filter(|e| e.uid == uid) { (e.name, e.width, e.height) = (edit.template.clone(), width, height); }
+        for e in self.upgrade_events.iter_mut().filter(|e| e.uid == Some(uid)) { e.building_name = edit.template.clone(); }
+    }
+
+    pub(crate) fn show_building_edit(&mut self, ctx: &egui::Context) {
+        let Some(mut edit) = self.building_edit.take() else { return; };
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == edit.uid) else { return; };
+        let title = trf!("{} (UID {}) 的属性", b.template_name, b.uid);
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        egui::Window::new(tr("建筑属性")).id(egui::Id::new("building_edit")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(title);
+            ui.horizontal(|ui| { wave_editor(ui, &mut edit.wave_num, &mut edit.is_late); offset_seconds_editor(ui, &mut edit.offset_seconds); });
+            ui.horizontal(|ui| {
+                ui.label(tr("模板:"));
+                egui::ComboBox::from_id_source("building_edit_template").selected_text(edit.template.clone()).show_ui(ui, |ui| {
+                    for t in &self.building_templates {
+                        if ui.selectable_label(edit.template == t.name, format!("{} ({}×{})", t.name, t.width, t.height)).clicked() {
+                            edit.template = t.name.clone();
+                            edit.b_type = t.b_type;
+                        }
+                    }
+                });
+            });
+            ui.horizontal(|ui| {
+                ui.label(tr("层类型:"));
+                for b_type in [BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling] {
+                    ui.selectable_value(&mut edit.b_type, b_type, type_label(b_type));
+                }
+            });
+            let error = self.building_edit_error(&edit);
+            if let Some(e) = &error { ui.colored_label(Color32::RED, e); }
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(error.is_none(), egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if apply {
+            self.apply_building_edit(&edit);
+        } else if open && !cancel {
+            self.building_edit = Some(edit);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use eframe::egui::{self, Key, Modifiers};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::{BuildingExport, EditMode};
+use crate::utils::get_time_value;
+
+// 🔥 建筑右键菜单与删除键：选择与布局模式下右键建筑弹出菜单（属性、备注、标记拆除、复制一份、删除），不再右键即删。
+// 右键的建筑在选中范围内时，标记拆除、复制与删除作用于全部选中的建筑；选择模式下 Delete / Backspace 删除选中的建筑
+
+impl MapEditor {
+    // 菜单作用的建筑：右键的建筑已被选中时为全部选中的建筑，否则只有它自己
+    fn context_menu_targets(&self, uid: usize) -> Vec<usize> {
+        if self.selected_uids.contains(&uid) { self.selected_uids.clone() } else { vec![uid] }
+    }
+
+    pub(crate) fn handle_delete_keys(&mut self, ctx: &egui::Context) {
+        if self.mode != EditMode::Select || self.selected_uids.is_empty() || ctx.wants_keyboard_input() { return; }
+        if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Delete) | i.consume_key(Modifiers::NONE, Key::Backspace)) {
+            let uids = self.selected_uids.clone();
+            self.delete_buildings(&uids);
+        }
+    }
+
+    // 在整组当前位置的右侧复制一份（放不下时依次尝试下方、左侧、上方），使用当前波次与新的 UID，副本成为新的选中
+    fn duplicate_buildings(&mut self, uids: &[usize]) {
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let placed: Vec<(BuildingExport, usize, usize)> = self.placed_buildings.iter().filter(|b| uids.contains(&b.uid)).map(|b| {
+            let (x, y) = self.building_pos_at(b, t_current);
+            let export = BuildingExport {
+                uid: b.uid, name: b.template_name.clone(), b_type: b.b_type, grid_x: x, grid_y: y, width: b.width, height: b.height,
+                wave_num: b.wave_num, is_late: b.is_late, viewport: b.viewport.clone(), comment: b.comment.clone(), offset: b.offset, offset_seconds: None, order: None,
+            };
+            (export, x + b.width, y + b.height)
+        }).collect();
+        let (Some(min_x), Some(min_y)) = (placed.iter().map(|(b, ..)| b.grid_x).min(), placed.iter().map(|(b, ..)| b.grid_y).min()) else { return; };
+        let (w, h) = (placed.iter().map(|p| p.1).max().unwrap_or(min_x) - min_x, placed.iter().map(|p| p.2).max().unwrap_or(min_y) - min_y);
+        let group: Vec<BuildingExport> = placed.into_iter().map(|(b, ..)| BuildingExport { grid_x: b.grid_x - min_x, grid_y: b.grid_y - min_y, ..b }).collect();
+        let (x, y, w, h) = (min_x as i32, min_y as i32, w as i32, h as i32);
+        if ![(x + w, y), (x, y + h), (x - w, y), (x, y - h)].into_iter().any(|(cx, cy)| self.paste_buildings(&group, cx, cy)) {
+            self.notifications.warn(tr("周围没有放得下副本的位置"));
+        }
+    }
+
+    pub(crate) fn show_building_context_menu(&mut self, response: &egui::Response) {
+        let Some(uid) = self.context_menu_uid else { return; };
+        if !matches!(self.mode, EditMode::Select | EditMode::Building) || !self.placed_buildings.iter().any(|b| b.uid == uid) { return; }
+        response.context_menu(|ui| {
+            let targets = self.context_menu_targets(uid);
+            if targets.len() > 1 { ui.weak(trf!("作用于选中的 {} 座建筑", targets.len())); }
+            if ui.button(tr("属性…")).clicked() {
+                self.open_building_edit(uid);
+                ui.close_menu();
+            }
+            if ui.button(tr("编辑备注…")).clicked() {
+                self.open_comment_editor(uid);
+                ui.close_menu();
+            }
+            ui.separator();
+            if ui.add_enabled(!self.current_wave_locked(), egui::Button::new(tr("在当前波次标记拆除"))).clicked() {
+                for uid in &targets { self.add_demolish_event(*uid); }
+                ui.close_menu();
+            }
+            if ui.button(tr("复制一份")).clicked() {
+                self.duplicate_buildings(&targets);
+                ui.close_menu();
+            }
+            if ui.button(tr("删除")).clicked() {
+                self.delete_buildings(&targets);
+                ui.close_menu();
+            }
+        });
+    }
+}
@@ -0,0 +1,66 @@
+use eframe::egui::{self, Color32, Painter, Rect, Shape, Stroke};
+
+use crate::app::MapEditor;
+use crate::assets::{resolve_asset, VIEW_OPTIONS_FILE};
+use crate::i18n::tr;
+use crate::models::{BuildingVisibility, FutureStyle};
+use crate::storage::{read_json, write_data};
+
+// 🔥 建筑显示方式：尚未建造的建筑可以半透明显示、只画虚线轮廓或完全隐藏，已拆除 / 出售的建筑（残影）可以调淡或隐藏。
+// 这是用户自己的偏好而不是策略数据，保存在资源根目录的 view_options.json 中、所有地图共用，不进撤销历史
+
+impl MapEditor {
+    pub(crate) fn load_building_visibility(&mut self) {
+        let path = resolve_asset(&self.asset_root, VIEW_OPTIONS_FILE);
+        if !path.exists() { self.building_visibility = BuildingVisibility::default(); return; }
+        match read_json::<BuildingVisibility>(path) {
+            Ok(visibility) => self.building_visibility = visibility,
+            Err(e) => self.notifications.warn(e),
+        }
+    }
+
+    fn save_building_visibility(&mut self) {
+        if let Err(e) = write_data(resolve_asset(&self.asset_root, VIEW_OPTIONS_FILE), &self.building_visibility) { self.notifications.error(e); }
+    }
+
+    // 建筑在 t_current 的不透明度，不画时返回 None；回放时新出现 / 被拆除的建筑在当前时间格内渐变
+    pub(crate) fn building_alpha(&self, t_create: i32, t_demolish: i32, t_current: i32) -> Option<f32> {
+        let v = &self.building_visibility;
+        let future = if v.future == FutureStyle::Hidden { 0.0 } else { v.future_alpha };
+        let ruin = if v.hide_demolished { 0.0 } else { v.demolished_alpha };
+        let mut alpha = if t_current >= t_demolish { ruin } else if t_current < t_create { future } else { 1.0 };
+        if self.playback_active {
+            let progress = self.playback_progress.min(1.0);
+            if t_create == t_current { alpha = future + (1.0 - future) * progress; }
+            if t_demolish == t_current { alpha = 1.0 - (1.0 - ruin) * progress; }
+        }
+        (alpha > 0.0).then_some(alpha)
+    }
+
+    pub(crate) fn building_dashed(&self, t_create: i32, t_current: i32) -> bool {
+        t_current < t_create && self.building_visibility.future == FutureStyle::Dashed
+    }
+
+    pub(crate) fn show_building_visibility(&mut self, ui: &mut egui::Ui) {
+        let v = &mut self.building_visibility;
+        // 拖动滑块时每帧都会变化，松手后再写文件
+        let slider_done = |r: egui::Response| r.drag_released() || (r.changed() && !r.dragged());
+        let mut save = false;
+        ui.label(tr("未建造的建筑:"));
+        ui.horizontal(|ui| {
+            save |= ui.radio_value(&mut v.future, FutureStyle::Faded, tr("半透明")).changed();
+            save |= ui.radio_value(&mut v.future, FutureStyle::Dashed, tr("虚线轮廓")).changed();
+            save |= ui.radio_value(&mut v.future, FutureStyle::Hidden, tr("隐藏")).changed();
+        });
+        save |= slider_done(ui.add_enabled(v.future != FutureStyle::Hidden, egui::Slider::new(&mut v.future_alpha, 0.05..=1.0).text(tr("不透明度"))));
+        save |= ui.checkbox(&mut v.hide_demolished, tr("隐藏已拆除 / 出售的建筑")).changed();
+        save |= slider_done(ui.add_enabled(!v.hide_demolished, egui::Slider::new(&mut v.demolished_alpha, 0.01..=1.0).text(tr("残影不透明度"))));
+        if ui.small_button(tr("恢复默认")).clicked() { *v = BuildingVisibility::default(); save = true; }
+        if save { self.save_building_visibility(); }
+    }
+}
+
+pub(crate) fn draw_dashed_outline(painter: &Painter, rect: Rect, color: Color32) {
+    let points = [rect.left_top(), rect.right_top(), rect.right_bottom(), rect.left_bottom(), rect.left_top()];
+    painter.extend(Shape::dashed_line(&points, Stroke::new(2.0, Color32::from_rgb(color.r(), color.g(), color.b())), 6.0, 4.0));
+}
@@ -0,0 +1,162 @@
+use eframe::egui::{self, Align2, Color32, FontId, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+
+// 🔥 网格与底图对齐
+// 两点校准：在底图上点选两个已知的网格交点并填写它们的网格坐标，自动求出格子宽高与网格偏移；
+// 两点不同行也不同列时分别求宽高，同行或同列时按正方形格子计算
+// 对齐手柄：在画布上直接拖动网格的左上角（平移偏移）、右边/下边/右下角（缩放格子宽高），拖动时按住 Ctrl 微调
+
+const MARKER_COLORS: [Color32; 2] = [Color32::from_rgb(255, 80, 200), Color32::from_rgb(80, 220, 255)];
+const HANDLE_SIZE: f32 = 12.0;
+const FINE_FACTOR: f32 = 0.1;
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub(crate) enum AlignHandle { Move, Right, Bottom, Corner }
+
+#[derive(Default)]
+pub(crate) struct Calibration {
+    // 两个点在底图上的像素坐标
+    points: [Option<Pos2>; 2],
+    // 两个点对应的网格交点：第 col 列、第 row 行格子的左上角
+    cells: [[i32; 2]; 2],
+    // 下一次在画布上点击设置的点
+    picking: Option<usize>,
+}
+
+// 解出的 (格宽, 格高, 偏移 X, 偏移 Y)；两点重合或无法确定尺寸时返回 None
+fn solve(p: [Pos2; 2], cells: [[i32; 2]; 2]) -> Option<(f32, f32, f32, f32)> {
+    let (dc, dr) = ((cells[1][0] - cells[0][0]) as f32, (cells[1][1] - cells[0][1]) as f32);
+    let w = (dc != 0.0).then(|| (p[1].x - p[0].x) / dc);
+    let h = (dr != 0.0).then(|| (p[1].y - p[0].y) / dr);
+    let (w, h) = match (w, h) { (Some(w), Some(h)) => (w, h), (Some(w), None) => (w, w), (None, Some(h)) => (h, h), (None, None) => return None };
+    if w <= 0.0 || h <= 0.0 || !w.is_finite() || !h.is_finite() { return None; }
+    Some((w, h, p[0].x - cells[0][0] as f32 * w, p[0].y - cells[0][1] as f32 * h))
+}
+
+impl MapEditor {
+    fn calibration_result(&self) -> Option<(f32, f32, f32, f32)> {
+        let cal = self.calibration.as_ref()?;
+        solve([cal.points[0]?, cal.points[1]?], cal.cells)
+    }
+
+    pub(crate) fn show_calibration(&mut self, ui: &mut egui::Ui) {
+        let Some(cal) = self.calibration.as_mut() else {
+            ui.vertical_centered_justified(|ui| {
+                if ui.button(tr("📐 两点校准…")).on_hover_text(tr("在底图上点选两个已知的网格交点，自动求出格子尺寸与偏移")).clicked() {
+                    self.calibration = Some(Calibration { cells: [[0, 0], [10, 10]], picking: Some(0), ..Default::default() });
+                }
+            });
+            return;
+        };
+        ui.label(tr("两点校准：点击「拾取」后在画布上单击网格交点，再填写该交点的网格坐标（列, 行）"));
+        for (i, color) in MARKER_COLORS.into_iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.colored_label(color, trf!("点 {}", i + 1));
+                match cal.points[i] {
+                    Some(p) => ui.label(format!("({:.1}, {:.1})", p.x, p.y)),
+                    None => ui.weak(tr("未设置")),
+                };
+                if ui.selectable_label(cal.picking == Some(i), tr("拾取")).clicked() { cal.picking = if cal.picking == Some(i) { None } else { Some(i) }; }
+                ui.label(tr("列:")); ui.add(egui::DragValue::new(&mut cal.cells[i][0]));
+                ui.label(tr("行:")); ui.add(egui::DragValue::new(&mut cal.cells[i][1]));
+            });
+        }
+        let result = self.calibration_result();
+        match result {
+            Some((w, h, x, y)) => { ui.label(trf!("格宽 {}，格高 {}，偏移 ({}, {})", format!("{:.2}", w), format!("{:.2}", h), format!("{:.1}", x), format!("{:.1}", y))); }
+            None => { ui.weak(tr("需要两个位置不同的点，且网格坐标至少有一个方向不同")); }
+        }
+        ui.horizontal(|ui| {
+            if ui.add_enabled(result.is_some(), egui::Button::new(tr("应用"))).clicked() {
+                if let Some((w, h, x, y)) = result {
+                    (self.grid_width, self.grid_height, self.offset_x, self.offset_y) = (w, h, x, y);
+                    self.calibration = None;
+                    self.notifications.info(tr("已按两点校准更新网格尺寸与偏移"));
+                }
+            }
+            if ui.button(tr("取消")).clicked() { self.calibration = None; }
+        });
+    }
+
+    // 校准拾取中时接管画布左键，返回 true 表示本帧的点击已被处理
+    pub(crate) fn handle_calibration_input(&mut self, response: &egui::Response, pointer: Pos2, map_origin: Pos2) -> bool {
+        let zoom = self.zoom;
+        let Some(cal) = self.calibration.as_mut() else { return false; };
+        let Some(i) = cal.picking else { return false; };
+        if response.clicked_by(egui::PointerButton::Primary) {
+            cal.points[i] = Some(((pointer - map_origin) / zoom).to_pos2());
+            cal.picking = (0..2).find(|&j| cal.points[j].is_none());
+        }
+        true
+    }
+
+    pub(crate) fn draw_calibration(&self, painter: &Painter, map_origin: Pos2) {
+        let Some(cal) = &self.calibration else { return; };
+        for (i, p) in cal.points.iter().enumerate() {
+            let Some(p) = p else { continue; };
+            let screen = map_origin + p.to_vec2() * self.zoom;
+            let stroke = Stroke::new(2.0, MARKER_COLORS[i]);
+            painter.line_segment([screen - Vec2::new(10.0, 0.0), screen + Vec2::new(10.0, 0.0)], stroke);
+            painter.line_segment([screen - Vec2::new(0.0, 10.0), screen + Vec2::new(0.0, 10.0)], stroke);
+            painter.circle_stroke(screen, 5.0, stroke);
+            painter.text(screen + Vec2::new(8.0, -8.0), Align2::LEFT_BOTTOM, format!("{} ({}, {})", i + 1, cal.cells[i][0], cal.cells[i][1]), FontId::proportional(13.0), MARKER_COLORS[i]);
+        }
+    }
+}
+
+impl MapEditor {
+    // 各手柄的屏幕位置：左上角平移，右边中点/下边中点/右下角缩放
+    fn align_handle_rects(&self, panel_rect: Rect) -> [(AlignHandle, Rect); 4] {
+        let min = panel_rect.min + self.pan + Vec2::new(self.offset_x, self.offset_y) * self.zoom;
+        let size = Vec2::new(self.grid_cols as f32 * self.grid_width, self.grid_rows as f32 * self.grid_height) * self.zoom;
+        let grid = Rect::from_min_size(min, size);
+        let handle = |p: Pos2| Rect::from_center_size(p, Vec2::splat(HANDLE_SIZE));
+        [
+            (AlignHandle::Move, handle(grid.left_top())),
+            (AlignHandle::Right, handle(grid.right_center())),
+            (AlignHandle::Bottom, handle(grid.center_bottom())),
+            (AlignHandle::Corner, handle(grid.right_bottom())),
+        ]
+    }
+
+    // 对齐手柄开启时处理拖动；返回 true 表示指针在手柄上或正在拖动，画布本帧不做其他编辑
+    pub(crate) fn handle_alignment_input(&mut self, input: &egui::InputState, panel_rect: Rect, canvas_hovered: bool) -> bool {
+        if !self.show_align_handles { self.align_drag = None; return false; }
+        let hover = input.pointer.hover_pos();
+        let over = hover.and_then(|p| self.align_handle_rects(panel_rect).into_iter().find(|(_, r)| r.contains(p))).map(|(h, _)| h);
+        if input.pointer.primary_pressed() && canvas_hovered { self.align_drag = over; }
+        if !input.pointer.primary_down() { self.align_drag = None; }
+        let Some(handle) = self.align_drag else { return canvas_hovered && over.is_some(); };
+        let factor = if input.modifiers.ctrl { FINE_FACTOR } else { 1.0 };
+        let delta = input.pointer.delta() / self.zoom * factor;
+        let (cols, rows) = (self.grid_cols.max(1) as f32, self.grid_rows.max(1) as f32);
+        match handle {
+            AlignHandle::Move => { self.offset_x += delta.x; self.offset_y += delta.y; }
+            AlignHandle::Right => self.grid_width = (self.grid_width + delta.x / cols).max(1.0),
+            AlignHandle::Bottom => self.grid_height = (self.grid_height + delta.y / rows).max(1.0),
+            AlignHandle::Corner => {
+                self.grid_width = (self.grid_width + delta.x / cols).max(1.0);
+                self.grid_height = (self.grid_height + delta.y / rows).max(1.0);
+            }
+        }
+        true
+    }
+
+    pub(crate) fn draw_align_handles(&self, painter: &Painter, panel_rect: Rect, hover: Option<Pos2>) {
+        if !self.show_align_handles { return; }
+        let rects = self.align_handle_rects(panel_rect);
+        let grid = Rect::from_two_pos(rects[0].1.center(), rects[3].1.center());
+        painter.rect_stroke(grid, 0.0, Stroke::new(1.5, Color32::from_rgb(255, 160, 0)));
+        for (handle, rect) in rects {
+            let active = self.align_drag == Some(handle) || (self.align_drag.is_none() && hover.is_some_and(|p| rect.contains(p)));
+            painter.rect_filled(rect, 2.0, if active { Color32::WHITE } else { Color32::from_rgb(255, 160, 0) });
+            painter.rect_stroke(rect, 2.0, Stroke::new(1.0, Color32::BLACK));
+        }
+        if self.align_drag.is_some() {
+            let text = trf!("偏移 ({}, {})  格子 {}×{}", format!("{:.1}", self.offset_x), format!("{:.1}", self.offset_y), format!("{:.2}", self.grid_width), format!("{:.2}", self.grid_height));
+            painter.text(rects[3].1.right_bottom() + Vec2::new(4.0, 4.0), Align2::LEFT_TOP, text, FontId::monospace(12.0), Color32::WHITE);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use eframe::egui::{self, Color32, Painter, Pos2, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::wave_label;
+
+// 🔥 镜头关键帧录制：按 WASD 移动观察框时，在方向键组合变化的时刻记下位置与时间
+// 外部程序在对应波次按住 keys 中的方向键直到下一帧，即可复现相同的镜头移动
+
+// 观察框与预设位置的距离小于该值（底图像素）时视为处于该预设
+const PRESET_EPSILON: f32 = 0.5;
+
+// 录制中的一段轨迹，波次在开始录制时确定
+pub(crate) struct CameraRecording {
+    wave_num: i32,
+    is_late: bool,
+    elapsed_ms: f64,
+    keys: Option<String>,
+}
+
+impl MapEditor {
+    // 当前波次已有轨迹时，重新录制会替换它
+    pub(crate) fn start_camera_recording(&mut self) {
+        let (wave_num, is_late) = (self.current_wave_num, self.current_is_late);
+        self.camera_keyframes.retain(|k| k.wave_num != wave_num || k.is_late != is_late);
+        self.camera_recording = Some(CameraRecording { wave_num, is_late, elapsed_ms: 0.0, keys: None });
+    }
+
+    pub(crate) fn stop_camera_recording(&mut self) {
+        let Some(rec) = self.camera_recording.take() else { return; };
+        if rec.keys.as_deref().is_some_and(|k| !k.is_empty()) {
+            self.push_camera_keyframe(&rec, String::new());
+        }
+        let count = self.camera_keyframes.iter().filter(|k| k.wave_num == rec.wave_num && k.is_late == rec.is_late).count();
+        self.notifications.info(trf!("已录制 {} 的镜头轨迹，共 {} 个关键帧", wave_label(rec.wave_num, rec.is_late), count));
+    }
+
+    fn push_camera_keyframe(&mut self, rec: &CameraRecording, keys: String) {
+        self.camera_keyframes.push(CameraKeyframe {
+            wave_num: rec.wave_num, is_late: rec.is_late, t_ms: rec.elapsed_ms.round() as u64,
+            x: self.viewport_pos.x, y: self.viewport_pos.y, keys,
+        });
+    }
+
+    // 观察框本帧移动之前调用；keys 为实际生效的方向键（被安全区域挡住时为空）
+    pub(crate) fn record_camera_frame(&mut self, keys: String, dt: f32) {
+        let Some(mut rec) = self.camera_recording.take() else { return; };
+        if rec.keys.as_ref() != Some(&keys) {
+            self.push_camera_keyframe(&rec, keys.clone());
+            rec.keys = Some(keys);
+        }
+        rec.elapsed_ms += dt as f64 * 1000.0;
+        self.camera_recording = Some(rec);
+    }
+
+    pub(crate) fn show_camera_keyframes(&mut self, ui: &mut egui::Ui) {
+        ui.label(tr("镜头关键帧:"));
+        ui.horizontal(|ui| {
+            if let Some(rec) = &self.camera_recording {
+                let label = trf!("● 录制中 {} ({}s)", wave_label(rec.wave_num, rec.is_late), format!("{:.1}", rec.elapsed_ms / 1000.0));
+                if ui.button(tr("⏹ 停止录制")).clicked() { self.stop_camera_recording(); }
+                ui.colored_label(Color32::RED, label);
+                ui.ctx().request_repaint();
+            } else {
+                let hint = if self.texture.is_some() { tr("录制当前波次的镜头轨迹：用 WASD/方向键移动观察框") } else { tr("需要先加载底图") };
+                if ui.add_enabled(self.texture.is_some(), egui::Button::new(tr("⏺ 录制镜头"))).on_hover_text(hint).on_disabled_hover_text(hint).clicked() {
+                    self.start_camera_recording();
+                }
+                if ui.add_enabled(!self.camera_keyframes.is_empty(), egui::Button::new(tr("清空关键帧"))).clicked() { self.camera_keyframes.clear(); }
+            }
+        });
+
+        let mut remove_idx = None;
+        let mut jump_to = None;
+        egui::ScrollArea::vertical().id_source("camera_keyframes").max_height(150.0).show(ui, |ui| {
+            for (i, k) in self.camera_keyframes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let keys = if k.keys.is_empty() { "-" } else { k.keys.as_str() };
+                    let text = format!("{} {:>6}ms ({:.0}, {:.0}) {}", wave_label(k.wave_num, k.is_late), k.t_ms, k.x, k.y, keys);
+                    if ui.selectable_label(false, egui::RichText::new(text).monospace()).on_hover_text(tr("点击把观察框移到此处")).clicked() { jump_to = Some(Vec2::new(k.x, k.y)); }
+                    if ui.small_button("×").clicked() { remove_idx = Some(i); }
+                });
+            }
+        });
+        if let Some(pos) = jump_to { self.viewport_pos = pos; }
+        if let Some(i) = remove_idx { self.camera_keyframes.remove(i); }
+    }
+
+    // 观察框当前所在的预设；新建的建造/拆除/出售/迁移事件记录它
+    pub(crate) fn current_viewport_preset(&self) -> Option<String> {
+        self.viewport_presets.iter()
+            .find(|p| (p.x - self.viewport_pos.x).abs() < PRESET_EPSILON && (p.y - self.viewport_pos.y).abs() < PRESET_EPSILON)
+            .map(|p| p.name.clone())
+    }
+
+    pub(crate) fn show_viewport_presets(&mut self, ui: &mut egui::Ui) {
+        ui.label(tr("观察框预设:"));
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.viewport_preset_name).hint_text(tr("名称")).desired_width(120.0));
+            let name = self.viewport_preset_name.trim().to_string();
+            let exists = self.viewport_presets.iter().any(|p| p.name == name);
+            let label = if exists { tr("覆盖为当前位置") } else { tr("保存当前位置") };
+            if ui.add_enabled(!name.is_empty(), egui::Button::new(label)).clicked() {
+                let (x, y) = (self.viewport_pos.x, self.viewport_pos.y);
+                match self.viewport_presets.iter_mut().find(|p| p.name == name) {
+                    Some(p) => { p.x = x; p.y = y; }
+                    None => self.viewport_presets.push(ViewportPreset { name, x, y }),
+                }
+                self.viewport_preset_name.clear();
+            }
+        });
+
+        let current = self.current_viewport_preset();
+        let mut remove_idx = None;
+        let mut jump_to = None;
+        for (i, p) in self.viewport_presets.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let used = self.placed_buildings.iter().filter(|b| b.viewport.as_ref() == Some(&p.name)).count()
+                    + self.demolish_events.iter().filter(|d| d.viewport.as_ref() == Some(&p.name)).count()
+                    + self.sell_events.iter().filter(|s| s.viewport.as_ref() == Some(&p.name)).count()
+                    + self.relocate_events.iter().filter(|r| r.viewport.as_ref() == Some(&p.name)).count();
+                let text = format!("{} ({:.0}, {:.0})", p.name, p.x, p.y);
+                if ui.selectable_label(current.as_ref() == Some(&p.name), text).on_hover_text(trf!("点击把观察框移到此处；被 {} 个事件引用", used)).clicked() {
+                    jump_to = Some(Vec2::new(p.x, p.y));
+                }
+                if ui.small_button("×").clicked() { remove_idx = Some(i); }
+            });
+        }
+        if let Some(pos) = jump_to { self.viewport_pos = pos; }
+        if let Some(i) = remove_idx { self.viewport_presets.remove(i); }
+        match &current {
+            Some(name) => ui.label(trf!("新建的建造/拆除/出售/迁移事件将引用预设「{}」", name)),
+            None => ui.weak(tr("观察框不在任何预设上，新事件不引用预设")),
+        };
+    }
+
+    // 在画布上画出当前波次的镜头轨迹（观察框中心的连线）
+    pub(crate) fn draw_camera_path(&self, painter: &Painter, map_origin: Pos2) {
+        let half = Vec2::new(self.viewport_width, self.viewport_height) / 2.0;
+        let points: Vec<Pos2> = self.camera_keyframes.iter()
+            .filter(|k| k.wave_num == self.current_wave_num && k.is_late == self.current_is_late)
+            .map(|k| map_origin + (Vec2::new(k.x, k.y) + half) * self.zoom)
+            .collect();
+        let color = Color32::from_rgb(0, 220, 255);
+        painter.add(egui::Shape::line(points.clone(), Stroke::new(2.0, color)));
+        for p in points { painter.circle_filled(p, 4.0, color); }
+    }
+}
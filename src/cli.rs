@@ -0,0 +1,362 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::*;
+use crate::storage::*;
+use crate::validation::*;
+use crate::migration::*;
+use crate::assets::*;
+use crate::plugins::{registry, PluginData};
+use crate::generator::{generate_project, GenerateOptions};
+use crate::time_model::set_time_model;
+use crate::legacy::{convert_legacy_terrain, converted_path, is_legacy_terrain};
+use crate::roundtrip::check_project_roundtrip;
+use crate::operations::{operations_by_wave, operations_export, timed_operations};
+
+// 🔥 无界面命令行模式：不启动 eframe，供构建流水线校验/转换/导出数据
+
+const USAGE: &str = "用法:
+  MAP --cli validate <目录|文件>...                   校验地形/策略/防御塔列表/工程文件
+  MAP --cli convert <旧文件.json> --to v2 [--out <路径>]  升级为当前数据格式
+  MAP --cli export <工程.minke> [--out <输出根目录>] [--format json|yaml|toml|msgpack] [--split-waves]  从工程文件导出游戏端数据
+  MAP --cli watch <工程.minke> [--out <输出根目录>] [--format json|yaml|toml|msgpack] [--split-waves]   监视工程文件，每次保存后重新导出（Ctrl+C 退出）
+  MAP --cli roundtrip <工程.minke>...                 往返自检：按每种格式导出后重新导入，列出丢失或变化的字段
+  MAP --cli generate <输出.minke> [--size <列>x<行>] [--buildings N] [--events N] [--waves N] [--seed N]  生成随机地形与策略，用于压力测试
+
+数据文件按扩展名识别格式：.json / .yaml / .yml / .toml / .msgpack
+--split-waves 另外把操作序列按波次拆成 <地图名>/waves/wave_03.json 等，每波一个文件";
+
+const COMMANDS: &[&str] = &["validate", "convert", "export", "watch", "roundtrip", "generate"];
+
+pub fn is_cli_invocation(args: &[String]) -> bool {
+    args.first().is_some_and(|a| a == "--cli" || COMMANDS.contains(&a.as_str()))
+}
+
+// 返回进程退出码：0 成功，1 数据有错误，2 用法错误
+pub fn run(args: &[String]) -> i32 {
+    attach_parent_console();
+    let args: Vec<String> = args.iter().skip_while(|a| *a == "--cli").cloned().collect();
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("validate") => cmd_validate(&args[1..]),
+        Some("convert") => cmd_convert(&args[1..]),
+        Some("export") => cmd_export(&args[1..]),
+        Some("watch") => cmd_watch(&args[1..]),
+        Some("roundtrip") => cmd_roundtrip(&args[1..]),
+        Some("generate") => cmd_generate(&args[1..]),
+        _ => { println!("{}", USAGE); return 2; }
+    };
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => { eprintln!("[ERROR] {}", e); eprintln!("{}", USAGE); 2 }
+    }
+}
+
+// windows_subsystem = "windows" 下没有控制台，命令行模式需要挂到父进程的控制台上
+#[cfg(windows)]
+fn attach_parent_console() {
+    extern "system" { fn AttachConsole(process_id: u32) -> i32; }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS); }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+// 取出 `--name value` 形式的参数与不带取值的开关，剩余为位置参数
+struct ParsedArgs {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl ParsedArgs {
+    fn parse(args: &[String], names: &[&str], flags: &[&str]) -> Result<Self, String> {
+        let mut parsed = ParsedArgs { positional: Vec::new(), options: Vec::new(), flags: Vec::new() };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if flags.contains(&arg.as_str()) {
+                parsed.flags.push(arg.clone());
+            } else if names.contains(&arg.as_str()) {
+                let value = iter.next().ok_or_else(|| format!("参数 {} 缺少取值", arg))?;
+                parsed.options.push((arg.clone(), value.clone()));
+            } else if arg.starts_with("--") {
+                return Err(format!("未知参数 {}", arg));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+}
+
+pub enum DataFile {
+    Terrain(MapTerrainExport),
+    Strategy(MapBuildingsExport),
+    BuildingConfigs(Vec<BuildingConfig>),
+    TerrainTypes(Vec<TerrainType>),
+    Project(Box<ProjectFile>),
+}
+
+// 根据顶层结构判断文件类型，迁移到当前格式版本后再按具体类型解析
+pub fn load_data_file(path: &Path) -> Result<(DataFile, MigrationReport), String> {
+    let value: serde_json::Value = read_data(path)?;
+    let unversioned = |data| (data, MigrationReport::default());
+    if let Some(items) = value.as_array() {
+        if items.first().is_some_and(|i| i.get("id").is_some()) {
+            return read_data(path).map(DataFile::TerrainTypes).map(unversioned);
+        }
+        return read_data(path).map(DataFile::BuildingConfigs).map(unversioned);
+    }
+    let has = |key: &str| value.get(key).is_some();
+    if has("terrain") && has("strategy") {
+        read_migrated(path, migrate_project).map(|(p, r)| (DataFile::Project(Box::new(p)), r))
+    } else if has("layers") {
+        read_migrated(path, migrate_terrain).map(|(t, r)| (DataFile::Terrain(t), r))
+    } else if has("buildings") {
+        read_migrated(path, migrate_strategy).map(|(s, r)| (DataFile::Strategy(s), r))
+    } else {
+        Err(format!("无法识别 {} 的数据类型", path.display()))
+    }
+}
+
+fn collect_files(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for p in paths {
+        let path = PathBuf::from(p);
+        if path.is_dir() {
+            let entries = fs::read_dir(&path).map_err(|e| format!("读取目录 {} 失败: {}", path.display(), e))?;
+            let mut found: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|f| f.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| DATA_EXTENSIONS.contains(&ext) || ext == PROJECT_EXTENSION))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn print_findings(path: &Path, findings: &[Finding]) {
+    if findings.is_empty() { println!("[OK]    {}", path.display()); }
+    for f in findings {
+        let tag = match f.severity { Severity::Error => "[ERROR]", Severity::Warning => "[WARN] " };
+        println!("{} {}: {}", tag, path.display(), f.message);
+    }
+}
+
+fn cmd_validate(args: &[String]) -> Result<bool, String> {
+    let paths = ParsedArgs::parse(args, &[], &[])?.positional;
+    if paths.is_empty() { return Err("validate 需要至少一个目录或文件".into()); }
+
+    let mut ok = true;
+    let mut loaded = Vec::new();
+    for path in collect_files(&paths)? {
+        match load_data_file(&path) {
+            Ok((data, report)) => {
+                if !report.is_empty() { println!("[INFO]  {}: {}", path.display(), report.summary()); }
+                loaded.push((path, data));
+            }
+            Err(e) => { println!("[ERROR] {}", e); ok = false; }
+        }
+    }
+
+    // 同一批文件中的地形尺寸与防御塔列表用于策略的交叉检查
+    let grid_size = loaded.iter().find_map(|(_, d)| if let DataFile::Terrain(t) = d { Some(t.grid_size()) } else { None });
+    let configs = loaded.iter().find_map(|(_, d)| if let DataFile::BuildingConfigs(c) = d { Some(c.clone()) } else { None });
+    let viewport_presets = loaded.iter().find_map(|(_, d)| if let DataFile::Terrain(t) = d { Some(t.meta.viewport_presets.clone()) } else { None });
+    // 地形值按同批的调色板检查，没有则使用资源根目录下的 terrain_types.json 或内置调色板
+    let terrain_types = match loaded.iter().find_map(|(_, d)| if let DataFile::TerrainTypes(t) = d { Some(t.clone()) } else { None }) {
+        Some(types) => types,
+        None => load_terrain_types(resolve_asset(&default_asset_root(), TERRAIN_TYPES_FILE)).unwrap_or_else(|e| { println!("[WARN]  {}，使用内置地形类型", e); default_terrain_types() }),
+    };
+
+    // 先后比较按同批地形文件中的时间模型进行
+    if let Some(model) = loaded.iter().find_map(|(_, d)| if let DataFile::Terrain(t) = d { Some(&t.meta.time_model) } else { None }) { set_time_model(model); }
+    let plugins = registry();
+    for (path, data) in &loaded {
+        let findings = match data {
+            DataFile::Terrain(t) => validate_terrain(t, &terrain_types),
+            DataFile::BuildingConfigs(c) => validate_building_configs(c),
+            DataFile::TerrainTypes(t) => validate_terrain_types(t),
+            DataFile::Strategy(s) => {
+                let mut findings = validate_strategy(s, grid_size, configs.as_deref());
+                if let Some(presets) = &viewport_presets { findings.extend(validate_viewport_refs(s, presets)); }
+                findings
+            }
+            DataFile::Project(p) => {
+                set_time_model(&p.terrain.meta.time_model);
+                let mut findings = validate_terrain(&p.terrain, &terrain_types);
+                findings.extend(validate_building_configs(&p.building_configs));
+                findings.extend(validate_strategy(&p.strategy, Some(p.terrain.grid_size()), Some(&p.building_configs)));
+                findings.extend(validate_viewport_refs(&p.strategy, &p.terrain.meta.viewport_presets));
+                findings.extend(plugins.validate(&PluginData { terrain: &p.terrain, strategy: &p.strategy, building_configs: &p.building_configs }));
+                findings
+            }
+        };
+        print_findings(path, &findings);
+        if has_errors(&findings) { ok = false; }
+    }
+    if loaded.is_empty() && ok { println!("没有找到可校验的文件"); }
+    Ok(ok)
+}
+
+fn cmd_convert(args: &[String]) -> Result<bool, String> {
+    let parsed = ParsedArgs::parse(args, &["--to", "--out"], &[])?;
+    let [input] = parsed.positional.as_slice() else { return Err("convert 需要且仅需要一个输入文件".into()); };
+    let current = format!("v{}", FORMAT_VERSION);
+    match parsed.option("--to") {
+        None => {}
+        Some(v) if v == current => {}
+        Some(other) => return Err(format!("不支持的目标格式 {}（当前仅支持 {}）", other, current)),
+    }
+    let input = PathBuf::from(input);
+    let out = parsed.option("--out").map_or_else(|| converted_path(&input), PathBuf::from);
+    // 旧版单网格地形走专门的转换，逐项列出转换内容与补出的默认值
+    let value: serde_json::Value = read_data(&input)?;
+    if is_legacy_terrain(&value) {
+        let (terrain, report) = convert_legacy_terrain(value, &input.file_stem().unwrap_or_default().to_string_lossy())?;
+        for change in &report.converted { println!("[CONV]  {}", change); }
+        for default in &report.defaults { println!("[DEFLT] {}", default); }
+        write_data(&out, &terrain)?;
+        println!("[OK]    {} -> {}", input.display(), out.display());
+        return Ok(true);
+    }
+    let (data, report) = load_data_file(&input)?;
+    for change in &report.changes { println!("[MIGR]  {}", change); }
+    let result = match data {
+        DataFile::Terrain(mut t) => { t.normalize(); write_data(&out, &t) }
+        DataFile::Strategy(s) => write_data(&out, &s),
+        DataFile::BuildingConfigs(c) => write_data(&out, &c),
+        DataFile::TerrainTypes(t) => write_data(&out, &t),
+        DataFile::Project(mut p) => { p.terrain.normalize(); write_data(&out, &p) }
+    };
+    result?;
+    println!("[OK]    {} -> {}", input.display(), out.display());
+    Ok(true)
+}
+
+// export 与 watch 共用的参数：工程文件、输出根目录、导出格式与是否按波次拆分操作序列
+fn parse_export_args(args: &[String], command: &str) -> Result<(PathBuf, String, DataFormat, bool), String> {
+    let parsed = ParsedArgs::parse(args, &["--out", "--format"], &["--split-waves"])?;
+    let [input] = parsed.positional.as_slice() else { return Err(format!("{} 需要且仅需要一个工程文件", command)); };
+    let format = match parsed.option("--format") {
+        None => DataFormat::Json,
+        Some(name) => *DataFormat::ALL.iter().find(|f| f.extension() == name).ok_or_else(|| format!("不支持的导出格式 {}", name))?,
+    };
+    Ok((PathBuf::from(input), parsed.option("--out").unwrap_or("output").to_string(), format, parsed.flag("--split-waves")))
+}
+
+fn export_project(input: &Path, out: &str, format: DataFormat, split_waves: bool) -> Result<(), String> {
+    let (DataFile::Project(mut project), _) = load_data_file(input)? else {
+        return Err(format!("{} 不是工程文件", input.display()));
+    };
+    project.terrain.normalize();
+    set_time_model(&project.terrain.meta.time_model);
+    let map_name = project.map_filename.split('.').next().filter(|n| !n.is_empty()).unwrap_or(&project.project_name).to_string();
+    let paths = export_paths(out, &map_name, format);
+    write_data(&paths.terrain, &project.terrain)?;
+    write_data(&paths.strategy, &project.strategy)?;
+    write_data(&paths.building_configs, &project.building_configs)?;
+    write_data(&paths.operations, &operations_export(&project.strategy))?;
+    for p in [&paths.terrain, &paths.strategy, &paths.building_configs, &paths.operations] { println!("[OK]    {}", p.display()); }
+    // 配置了每波时长时一并导出绝对时间序列
+    if let Some(timeline) = timed_operations(&project.strategy, &project.terrain.meta.time_model) {
+        write_data(&paths.timeline, &timeline)?;
+        println!("[OK]    {}", paths.timeline.display());
+    }
+    if split_waves {
+        for p in write_wave_files(&paths.waves, &operations_by_wave(&project.strategy), format)? { println!("[OK]    {}", p.display()); }
+    }
+    for result in registry().export_all(out, &map_name, &PluginData { terrain: &project.terrain, strategy: &project.strategy, building_configs: &project.building_configs }) {
+        println!("[OK]    {}", result?.display());
+    }
+    Ok(())
+}
+
+fn cmd_export(args: &[String]) -> Result<bool, String> {
+    let (input, out, format, split_waves) = parse_export_args(args, "export")?;
+    export_project(&input, &out, format, split_waves)?;
+    Ok(true)
+}
+
+// 启动时先导出一次，之后每隔 WATCH_INTERVAL 检查工程文件的修改时间，变化后重新导出；
+// 导出失败（例如编辑器正在写入）只打印错误，等下一次保存再试
+fn cmd_watch(args: &[String]) -> Result<bool, String> {
+    let (input, out, format, split_waves) = parse_export_args(args, "watch")?;
+    if !input.is_file() { return Err(format!("找不到工程文件 {}", input.display())); }
+    let mut watcher = ConfigWatcher::default();
+    watcher.track(vec![input.clone()]);
+    println!("[WATCH] {} -> {}（Ctrl+C 退出）", input.display(), out);
+    if let Err(e) = export_project(&input, &out, format, split_waves) { println!("[ERROR] {}", e); }
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        if !watcher.poll() { continue; }
+        watcher.track(vec![input.clone()]);
+        println!("[WATCH] {} 已修改，重新导出", input.display());
+        if let Err(e) = export_project(&input, &out, format, split_waves) { println!("[ERROR] {}", e); }
+    }
+}
+
+// 有任何格式的往返结果与导出前不同即返回 false（退出码 1）
+fn cmd_roundtrip(args: &[String]) -> Result<bool, String> {
+    let paths = ParsedArgs::parse(args, &[], &[])?.positional;
+    if paths.is_empty() { return Err("roundtrip 需要至少一个工程文件".into()); }
+    let mut ok = true;
+    for path in paths.iter().map(PathBuf::from) {
+        // 读取失败或不是工程文件时只记为失败，继续检查其余文件
+        let (mut project, report) = match load_data_file(&path) {
+            Ok((DataFile::Project(project), report)) => (project, report),
+            Ok(_) => { println!("[FAIL]  {} 不是工程文件", path.display()); ok = false; continue; }
+            Err(e) => { println!("[FAIL]  {}", e); ok = false; continue; }
+        };
+        for change in &report.changes { println!("[MIGR]  {}: {}", path.display(), change); }
+        // 与编辑器打开工程时一样先补齐网格，避免把迁移本身当作差异
+        project.terrain.normalize();
+        for result in check_project_roundtrip(&project).0 {
+            if result.problems.is_empty() { println!("[OK]    {} ({})", path.display(), result.format.label()); continue; }
+            ok = false;
+            for p in &result.problems { println!("[LOSS]  {} ({}): {}", path.display(), result.format.label(), p); }
+        }
+    }
+    Ok(ok)
+}
+
+// 生成的工程会立即按 validate 的规则自检，正常情况下不应有错误
+fn cmd_generate(args: &[String]) -> Result<bool, String> {
+    let parsed = ParsedArgs::parse(args, &["--size", "--buildings", "--events", "--waves", "--seed"], &[])?;
+    let [out] = parsed.positional.as_slice() else { return Err("generate 需要且仅需要一个输出文件".into()); };
+    let number = |name: &str, default: u64| -> Result<u64, String> {
+        parsed.option(name).map_or(Ok(default), |v| v.parse().map_err(|_| format!("参数 {} 的取值 {} 不是非负整数", name, v)))
+    };
+    let mut opts = GenerateOptions::default();
+    if let Some(size) = parsed.option("--size") {
+        let (cols, rows) = size.split_once(['x', 'X']).and_then(|(c, r)| Some((c.parse().ok()?, r.parse().ok()?))).ok_or_else(|| format!("无效的尺寸 {}（应为 <列>x<行>，如 64x48）", size))?;
+        (opts.cols, opts.rows) = (cols, rows);
+    }
+    opts.buildings = number("--buildings", opts.buildings as u64)? as usize;
+    opts.events = number("--events", opts.events as u64)? as usize;
+    opts.waves = number("--waves", opts.waves as u64)?.min(i32::MAX as u64 / 2 - 1) as i32;
+    opts.seed = number("--seed", opts.seed)?;
+    if opts.rows == 0 || opts.cols == 0 || opts.waves == 0 { return Err("尺寸与波数必须大于 0".into()); }
+
+    let project = generate_project(&opts);
+    let out = PathBuf::from(out);
+    write_data(&out, &project)?;
+    let s = &project.strategy;
+    let events = s.upgrades.len() + s.demolishes.len() + s.sells.len() + s.relocations.len() + s.abilities.len();
+    println!("[OK]    {}: {}x{}，{} 座建筑（请求 {}），{} 个事件，种子 {}", out.display(), opts.cols, opts.rows, s.buildings.len(), opts.buildings, events, opts.seed);
+    let mut findings = validate_terrain(&project.terrain, &default_terrain_types());
+    findings.extend(validate_strategy(s, Some(project.terrain.grid_size()), Some(&project.building_configs)));
+    print_findings(&out, &findings);
+    Ok(!has_errors(&findings))
+}
@@ -0,0 +1,72 @@
+use eframe::egui::{Color32, FontId, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::{BuildingType, ConsistencyRule, Grid};
+
+// 🔥 子层一致性检查：按 consistency_rules.json 中的规则（默认：墙壁下方的地面不能是障碍，吊顶下方要有地面或墙壁支撑）
+// 找出当前主层级中地面 / 墙壁 / 吊顶三张网格互相矛盾的格子，在画布上标出；光标停在格子上时列出违反的规则
+
+const CELL_FILL: Color32 = Color32::from_rgba_premultiplied(120, 0, 120, 90);
+const CELL_STROKE: Color32 = Color32::from_rgb(255, 0, 255);
+
+impl MapEditor {
+    fn cell_buildable(&self, grid: &Grid, r: usize, c: usize) -> bool {
+        let v = grid.get(r, c);
+        self.terrain_types.iter().any(|t| t.id == v && t.buildable)
+    }
+
+    // 每个违反规则的格子 (行, 列, 违反的规则下标)；规则涉及的子层尚未绘制（网格为空）时跳过该规则
+    pub(crate) fn consistency_violations(&self) -> Vec<(usize, usize, Vec<usize>)> {
+        let Some(layer) = self.layers_data.get(&self.current_major_z) else { return Vec::new(); };
+        let rules: Vec<(usize, &ConsistencyRule, &Grid, Vec<&Grid>)> = self.consistency_rules.iter().enumerate().filter_map(|(i, rule)| {
+            let requires: Vec<&Grid> = rule.requires.iter().map(|&t| layer.get_grid(t)).collect();
+            let subject = layer.get_grid(rule.layer);
+            (!subject.is_empty() && !requires.is_empty() && requires.iter().all(|g| !g.is_empty())).then_some((i, rule, subject, requires))
+        }).collect();
+        let grids = [BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling].map(|t| layer.get_grid(t)).into_iter().filter(|g| !g.is_empty());
+        let (rows, cols) = grids.fold((self.grid_rows, self.grid_cols), |(r, c), g| (r.min(g.rows()), c.min(g.cols())));
+        let mut cells = Vec::new();
+        for r in 0..rows {
+            for c in 0..cols {
+                let broken: Vec<usize> = rules.iter().filter(|(_, rule, subject, requires)| {
+                    let present = if rule.ids.is_empty() { self.cell_buildable(subject, r, c) } else { rule.ids.contains(&subject.get(r, c)) };
+                    present && !requires.iter().any(|g| self.cell_buildable(g, r, c))
+                }).map(|(i, ..)| *i).collect();
+                if !broken.is_empty() { cells.push((r, c, broken)); }
+            }
+        }
+        cells
+    }
+
+    pub(crate) fn draw_consistency(&self, painter: &Painter, panel_rect: Rect, origin: Pos2, cell: Vec2, hover: Option<Pos2>) {
+        if !self.show_consistency { return; }
+        let cells = self.consistency_violations();
+        let mut counts = vec![0usize; self.consistency_rules.len()];
+        let mut hovered = None;
+        for (r, c, broken) in &cells {
+            for &i in broken { counts[i] += 1; }
+            let rect = Rect::from_min_size(origin + Vec2::new(*c as f32 * cell.x, *r as f32 * cell.y), cell);
+            if !panel_rect.intersects(rect) { continue; }
+            painter.rect_filled(rect, 0.0, CELL_FILL);
+            painter.rect_stroke(rect.shrink(0.5), 0.0, Stroke::new(1.0, CELL_STROKE));
+            if hover.is_some_and(|p| rect.contains(p)) { hovered = Some(broken); }
+        }
+        if let (Some(broken), Some(pos)) = (hovered, hover) {
+            let text = broken.iter().map(|&i| format!("⚠ {}", self.consistency_rules[i].name)).collect::<Vec<_>>().join("\n");
+            let galley = painter.layout_no_wrap(text, FontId::proportional(13.0), Color32::WHITE);
+            let text_pos = pos + Vec2::new(16.0, -galley.size().y - 8.0);
+            painter.rect_filled(Rect::from_min_size(text_pos, galley.size()).expand(4.0), 3.0, Color32::from_black_alpha(200));
+            painter.galley(text_pos, galley, Color32::WHITE);
+        }
+
+        // 图例：左下角（热力图图例之上）按规则列出问题格数
+        let inset = self.ruler_inset();
+        let mut lines = vec![if cells.is_empty() { tr("子层一致：没有问题格子").to_string() } else { trf!("子层一致性问题 {} 格", cells.len()) }];
+        lines.extend(self.consistency_rules.iter().zip(&counts).filter(|(_, &n)| n > 0).map(|(rule, n)| format!("  {} · {}", rule.name, n)));
+        let galley = painter.layout_no_wrap(lines.join("\n"), FontId::proportional(13.0), Color32::WHITE);
+        let text_pos = Pos2::new(panel_rect.min.x + 10.0 + inset, panel_rect.max.y - 60.0 - galley.size().y);
+        painter.rect_filled(Rect::from_min_size(text_pos, galley.size()).expand(3.0), 3.0, Color32::from_black_alpha(170));
+        painter.galley(text_pos, galley, Color32::WHITE);
+    }
+}
@@ -0,0 +1,84 @@
+use eframe::egui::{self, Color32, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::utils::{get_time_value, wave_label};
+
+// 🔥 拆除模式的批量操作：左键点击建筑在当前波次添加拆除事件；在空白处按住左键拖出矩形，
+// 松手时把框内当前存在、尚未计划拆除或出售的建筑一次全部标记拆除，不必逐座点击。
+// 面板中还可以按模板一次拆除当前存在的全部同名建筑（例如第 10 波拆掉所有早期箭塔），逐座生成拆除事件
+
+const BOX_FILL: Color32 = Color32::from_rgba_premultiplied(120, 30, 30, 50);
+const BOX_STROKE: Color32 = Color32::from_rgb(255, 90, 90);
+
+impl MapEditor {
+    // 当前时间存在、且当前位置与画布上矩形 rect 相交的建筑 uid
+    pub(crate) fn active_buildings_in_box(&self, rect: Rect, origin: Pos2, cell: Vec2) -> Vec<usize> {
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        self.placed_buildings.iter().filter(|b| {
+            let intersects = self.building_screen_rect(b, t_current, origin, cell).intersects(rect);
+            intersects && t_current >= get_time_value(b.wave_num, b.is_late) && t_current < self.get_building_demolish_time(b.uid)
+        }).map(|b| b.uid).collect()
+    }
+
+    // 当前时间存在、尚未计划拆除或出售的建筑
+    fn demolishable(&self, uid: usize) -> bool {
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let planned = self.demolish_events.iter().any(|e| e.uid == uid) || self.sell_events.iter().any(|e| e.uid == uid);
+        !planned && self.placed_buildings.iter().any(|b| b.uid == uid && t_current >= get_time_value(b.wave_num, b.is_late) && t_current < self.get_building_demolish_time(uid))
+    }
+
+    pub(crate) fn show_bulk_demolish(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("按模板批量拆除:"));
+            let mut names: Vec<&str> = self.placed_buildings.iter().filter(|b| self.demolishable(b.uid)).map(|b| b.template_name.as_str()).collect();
+            names.sort_unstable();
+            names.dedup();
+            if names.is_empty() { ui.weak(tr("当前时间没有可拆除的建筑")); return; }
+            if !names.contains(&self.bulk_demolish_template.as_str()) { self.bulk_demolish_template = names[0].to_string(); }
+            egui::ComboBox::from_id_source("bulk_demolish_template").selected_text(&self.bulk_demolish_template).show_ui(ui, |ui| {
+                for name in &names { ui.selectable_value(&mut self.bulk_demolish_template, name.to_string(), *name); }
+            });
+            let uids: Vec<usize> = self.placed_buildings.iter().filter(|b| b.template_name == self.bulk_demolish_template && self.demolishable(b.uid)).map(|b| b.uid).collect();
+            let label = trf!("在 {} 拆除全部 {} 座", wave_label(self.current_wave_num, self.current_is_late), uids.len());
+            let button = ui.add_enabled(!self.current_wave_locked(), egui::Button::new(label)).on_disabled_hover_text(trf!("第 {} 波已锁定", self.current_wave_num));
+            if button.on_hover_text(tr("为当前存在、尚未计划拆除或出售的每一座同名建筑添加拆除事件")).clicked() {
+                for uid in uids { self.add_demolish_event(uid); }
+            }
+        });
+    }
+
+    fn highlight_building(&self, painter: &Painter, origin: Pos2, cell: Vec2, uid: usize) {
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return; };
+        painter.rect_stroke(self.building_screen_rect(b, t_current, origin, cell), 0.0, Stroke::new(3.0, Color32::YELLOW));
+    }
+
+    pub(crate) fn handle_demolish_input(&mut self, response: &egui::Response, input: &egui::InputState, painter: &Painter, origin: Pos2, cell: Vec2, pos: Pos2) {
+        let at = ((pos - origin) / cell).to_pos2();
+        if let Some(start) = self.demolish_box_origin {
+            let rect = Rect::from_two_pos(start, pos);
+            painter.rect_filled(rect, 0.0, BOX_FILL);
+            painter.rect_stroke(rect, 0.0, Stroke::new(1.0, BOX_STROKE));
+            // 已计划在之后拆除或出售的建筑不再重复标记
+            let targets: Vec<usize> = self.active_buildings_in_box(rect, origin, cell).into_iter().filter(|uid| self.demolishable(*uid)).collect();
+            for &uid in &targets { self.highlight_building(painter, origin, cell, uid); }
+            if response.drag_released_by(egui::PointerButton::Primary) {
+                for uid in targets { self.add_demolish_event(uid); }
+                self.demolish_box_origin = None;
+            }
+            return;
+        }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let target = self.placed_buildings.iter().find(|b| self.building_covers_at(b, at, t_current)).map(|b| b.uid);
+        if let Some(uid) = target {
+            self.highlight_building(painter, origin, cell, uid);
+            if response.clicked_by(egui::PointerButton::Primary) { self.add_demolish_event(uid); }
+        }
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            // 以按下时的位置为起点，避免拖动阈值造成的偏移
+            self.demolish_box_origin = Some(input.pointer.press_origin().unwrap_or(pos));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+use eframe::egui::{self, ViewportCommand};
+
+use crate::app::MapEditor;
+use crate::history::{EditSnapshot, SharedSnapshot};
+use crate::i18n::{tr, trf};
+use crate::models::{MapBuildingsExport, FORMAT_VERSION};
+
+// 🔥 未保存修改：记录最近一次保存工程、导出全部数据、打开工程或加载预设时的编辑快照，当前数据与之不同即为有未保存的修改，
+// 标题栏加 *。加载预设、导入文件、打开工程（含拖入的数据文件）或关闭窗口前若有未保存的修改，先弹窗确认。
+// 地形等共用数据与每个标签页的策略分别记录保存时的状态；导入只覆盖当前标签页，关闭窗口时检查所有标签页
+
+pub(crate) const APP_TITLE: &str = "MINKE Editor";
+
+// 等待确认的会覆盖当前数据的操作
+pub(crate) enum DiscardAction {
+    LoadPreset(usize),
+    ImportTerrain,
+    ImportLegacyTerrain,
+    ReplaceStrategy(PathBuf, Box<MapBuildingsExport>),
+    ImportBuildingConfigs,
+    OpenProject,
+    DroppedFiles(Vec<PathBuf>),
+    Close,
+}
+
+// 标签页保存时的策略；地图名与格式版本在导出时按当前地图重新填写，比较时忽略
+pub(crate) fn saved_form(data: &MapBuildingsExport) -> MapBuildingsExport {
+    MapBuildingsExport { format_version: FORMAT_VERSION, map_name: String::new(), ..data.clone() }
+}
+
+#[derive(Default)]
+pub(crate) struct DirtyState {
+    saved: Option<SharedSnapshot>,
+    // 共用数据或当前标签页的策略有未保存的修改
+    pub(crate) dirty: bool,
+    // 其他标签页有未保存的修改
+    other_tabs_dirty: bool,
+    title: String,
+    pending: Option<DiscardAction>,
+    // 用户已确认关闭，不再拦截关闭请求
+    closing: bool,
+}
+
+impl MapEditor {
+    // 共用数据与当前标签页的策略已保存到磁盘（或刚从磁盘加载）
+    pub(crate) fn mark_saved(&mut self) {
+        self.dirty_state.saved = Some(self.capture_snapshot().shared);
+        let saved = saved_form(&self.build_buildings_export());
+        if let Some(tab) = self.strategy_tabs.get_mut(self.active_tab) { tab.saved = Some(saved); }
+        self.dirty_state.dirty = false;
+    }
+
+    // 其他标签页中有未保存修改的名称
+    pub(crate) fn unsaved_tabs(&self) -> Vec<String> {
+        self.strategy_tabs.iter().filter(|t| t.data.as_ref().is_some_and(|data| t.saved.as_ref() != Some(&saved_form(data)))).map(|t| t.name.clone()).collect()
+    }
+
+    // 每帧结束时调用，只在数据变化（含切换标签页）后与保存时的状态比较；撤销回到保存时的状态后不再算作未保存
+    pub(crate) fn update_dirty_state(&mut self, ctx: &egui::Context, current: &EditSnapshot, changed: bool) {
+        if changed {
+            let active_saved = self.strategy_tabs.get(self.active_tab).and_then(|t| t.saved.as_ref());
+            self.dirty_state.dirty = self.dirty_state.saved.as_ref() != Some(&current.shared) || active_saved != Some(&saved_form(&self.build_buildings_export()));
+            self.dirty_state.other_tabs_dirty = !self.unsaved_tabs().is_empty();
+        }
+        let title = if self.dirty_state.dirty || self.dirty_state.other_tabs_dirty { format!("* {} - {}", self.export_map_name(), APP_TITLE) } else { format!("{} - {}", self.export_map_name(), APP_TITLE) };
+        if title != self.dirty_state.title {
+            ctx.send_viewport_cmd(ViewportCommand::Title(title.clone()));
+            self.dirty_state.title = title;
+        }
+        if ctx.input(|i| i.viewport().close_requested()) && !self.dirty_state.closing && (self.dirty_state.dirty || self.dirty_state.other_tabs_dirty) {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            self.dirty_state.pending = Some(DiscardAction::Close);
+        }
+    }
+
+    // 当前标签页或共用数据有未保存的修改时先确认，否则立即执行（这些操作不影响其他标签页）
+    pub(crate) fn request_discard(&mut self, ctx: &egui::Context, action: DiscardAction) {
+        if self.dirty_state.dirty { self.dirty_state.pending = Some(action); } else { self.run_discard_action(ctx, action); }
+    }
+
+    fn run_discard_action(&mut self, ctx: &egui::Context, action: DiscardAction) {
+        match action {
+            DiscardAction::LoadPreset(i) => {
+                let Some(preset) = self.presets.get(i).cloned() else { return; };
+                self.apply_preset(ctx, &preset);
+            }
+            DiscardAction::ImportTerrain => self.import_terrain(),
+            DiscardAction::ImportLegacyTerrain => self.import_legacy_terrain(),
+            DiscardAction::ReplaceStrategy(path, data) => self.replace_strategy(path, *data),
+            DiscardAction::ImportBuildingConfigs => self.import_building_configs(ctx),
+            DiscardAction::OpenProject => self.open_project(ctx),
+            DiscardAction::DroppedFiles(paths) => self.import_dropped_files(ctx, paths),
+            DiscardAction::Close => {
+                self.dirty_state.closing = true;
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+            }
+        }
+    }
+
+    pub(crate) fn show_unsaved_prompt(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.dirty_state.pending.take() else { return; };
+        let (mut save, mut discard, mut cancel) = (false, false, false);
+        let question = match &action {
+            DiscardAction::Close => match self.unsaved_tabs() {
+                tabs if tabs.is_empty() => tr("关闭窗口前是否保存？").to_string(),
+                tabs => trf!("关闭窗口前是否保存？标签页 {} 也有未保存的修改，保存工程只保存当前标签页，请先切换过去保存", tabs.join("、")),
+            },
+            DiscardAction::LoadPreset(i) => trf!("加载预设 {} 会覆盖当前数据，是否先保存？", self.presets.get(*i).map_or("", |p| p.name.as_str())),
+            _ => tr("导入会覆盖当前数据，是否先保存？").to_string(),
+        };
+        egui::Window::new(tr("有未保存的修改")).id(egui::Id::new("unsaved_prompt")).collapsible(false).resizable(false).anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0]).show(ctx, |ui| {
+            ui.label(question);
+            ui.horizontal(|ui| {
+                save = ui.button(tr("保存工程…")).clicked();
+                discard = ui.button(tr("不保存")).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if save {
+            // 保存对话框被取消或写入失败时仍有未保存的修改，不继续；关闭时其他标签页仍未保存则继续询问
+            self.save_project();
+            let others = matches!(action, DiscardAction::Close) && !self.unsaved_tabs().is_empty();
+            if !self.dirty_state.dirty && !others { self.run_discard_action(ctx, action); } else if !self.dirty_state.dirty { self.dirty_state.pending = Some(action); }
+        } else if discard {
+            self.run_discard_action(ctx, action);
+        } else if !cancel {
+            self.dirty_state.pending = Some(action);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use crate::models::*;
+use crate::utils::{get_time_value, time_order};
+
+// 按波次统计建造/升级支出、出售返还与收入，不依赖 UI
+
+pub struct WaveLedger {
+    pub wave_num: i32,
+    pub build_cost: i32,
+    pub upgrade_cost: i32,
+    pub income: i32,
+    pub sell_refund: i32,
+    pub cumulative_spend: i32,
+    // 初始资金 + 截至本波的累计收入与出售返还
+    pub cumulative_budget: i32,
+}
+
+impl WaveLedger {
+    pub fn balance(&self) -> i32 { self.cumulative_budget - self.cumulative_spend }
+    pub fn overspent(&self) -> bool { self.balance() < 0 }
+}
+
+fn find_config<'a>(configs: &'a [BuildingConfig], name: &str) -> Option<&'a BuildingConfig> {
+    configs.iter().find(|c| c.name == name)
+}
+
+pub fn build_cost(configs: &[BuildingConfig], name: &str) -> i32 {
+    find_config(configs, name).map_or(0, |c| c.cost)
+}
+
+// 模板的第 level 次升级（从 1 开始）的费用；超出配置的等级时按 upgrade_cost 计
+pub fn upgrade_cost(configs: &[BuildingConfig], name: &str, level: usize) -> i32 {
+    find_config(configs, name).map_or(0, |c| level.checked_sub(1).and_then(|i| c.upgrades.get(i)).map_or(c.upgrade_cost.unwrap_or(c.cost), |l| l.cost))
+}
+
+// 每条升级事件升到第几级：按时间先后（同一时间段内再按秒数偏移），同一时刻按列表顺序。
+// 全局升级按模板计数；指定建筑的升级在模板已有的全局升级之上再按该建筑计数
+pub fn upgrade_levels(upgrades: &[UpgradeEvent]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..upgrades.len()).collect();
+    order.sort_by_key(|&i| time_order(upgrades[i].wave_num, upgrades[i].is_late, upgrades[i].offset_seconds));
+    let mut global: HashMap<&str, usize> = HashMap::new();
+    let mut own: HashMap<usize, usize> = HashMap::new();
+    let mut levels = vec![0; upgrades.len()];
+    for i in order {
+        let u = &upgrades[i];
+        match u.uid {
+            Some(uid) => {
+                let count = own.entry(uid).or_default();
+                *count += 1;
+                levels[i] = global.get(u.building_name.as_str()).copied().unwrap_or(0) + *count;
+            }
+            None => {
+                let count = global.entry(&u.building_name).or_default();
+                *count += 1;
+                levels[i] = *count;
+            }
+        }
+    }
+    levels
+}
+
+// 模板截至 tier 级的累计升级费用
+pub fn cumulative_upgrade_cost(configs: &[BuildingConfig], name: &str, tier: usize) -> i32 {
+    (1..=tier).map(|level| upgrade_cost(configs, name, level)).sum()
+}
+
+// 收入在该波开始（前期）时到账
+fn income_until(economy: &EconomyConfig, t: i32) -> i32 {
+    economy.wave_income.iter().filter(|w| get_time_value(w.wave_num, false) <= t).map(|w| w.amount).sum()
+}
+
+pub fn sell_refund(configs: &[BuildingConfig], economy: &EconomyConfig, name: &str) -> i32 {
+    build_cost(configs, name) * economy.sell_refund_percent / 100
+}
+
+fn refund_until(strategy: &MapBuildingsExport, configs: &[BuildingConfig], economy: &EconomyConfig, t: i32) -> i32 {
+    strategy.sells.iter().filter(|s| get_time_value(s.wave_num, s.is_late) <= t).map(|s| sell_refund(configs, economy, &s.name)).sum()
+}
+
+fn spend_until(strategy: &MapBuildingsExport, configs: &[BuildingConfig], t: i32) -> i32 {
+    let builds: i32 = strategy.buildings.iter().filter(|b| get_time_value(b.wave_num, b.is_late) <= t).map(|b| build_cost(configs, &b.name)).sum();
+    let upgrades: i32 = strategy.upgrades.iter().zip(upgrade_levels(&strategy.upgrades)).filter(|(u, _)| get_time_value(u.wave_num, u.is_late) <= t).map(|(u, level)| upgrade_cost(configs, &u.building_name, level)).sum();
+    builds + upgrades
+}
+
+// 时间值 t 上所有操作执行完后剩余的资金
+pub fn money_at(strategy: &MapBuildingsExport, configs: &[BuildingConfig], economy: &EconomyConfig, t: i32) -> i32 {
+    economy.initial_money + income_until(economy, t) + refund_until(strategy, configs, economy, t) - spend_until(strategy, configs, t)
+}
+
+pub fn wave_ledger(strategy: &MapBuildingsExport, configs: &[BuildingConfig], economy: &EconomyConfig) -> Vec<WaveLedger> {
+    let waves = strategy.buildings.iter().map(|b| b.wave_num)
+        .chain(strategy.upgrades.iter().map(|u| u.wave_num))
+        .chain(strategy.sells.iter().map(|s| s.wave_num))
+        .chain(economy.wave_income.iter().map(|w| w.wave_num));
+    let (Some(first), Some(last)) = (waves.clone().min(), waves.max()) else { return Vec::new(); };
+    let levels = upgrade_levels(&strategy.upgrades);
+
+    (first..=last).map(|wave_num| {
+        let end = get_time_value(wave_num, true);
+        WaveLedger {
+            wave_num,
+            build_cost: strategy.buildings.iter().filter(|b| b.wave_num == wave_num).map(|b| build_cost(configs, &b.name)).sum(),
+            upgrade_cost: strategy.upgrades.iter().zip(&levels).filter(|(u, _)| u.wave_num == wave_num).map(|(u, &level)| upgrade_cost(configs, &u.building_name, level)).sum(),
+            income: economy.wave_income.iter().filter(|w| w.wave_num == wave_num).map(|w| w.amount).sum(),
+            sell_refund: strategy.sells.iter().filter(|s| s.wave_num == wave_num).map(|s| sell_refund(configs, economy, &s.name)).sum(),
+            cumulative_spend: spend_until(strategy, configs, end),
+            cumulative_budget: economy.initial_money + income_until(economy, end) + refund_until(strategy, configs, economy, end),
+        }
+    }).collect()
+}
@@ -0,0 +1,460 @@
+use eframe::egui::{self, Align2, Color32, FontId, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::{event_time_label, get_time_value, wave_label};
+use crate::time_model::with_time_model;
+
+// 🔥 出售 / 迁移事件：出售与拆除一样让建筑从该时间起消失（另按经济配置返还费用），
+// 迁移让建筑在该时间移到新位置，uid 不变；建筑在任一时间的位置由其迁移事件决定
+// 技能事件与建筑无关，只记录名称、时间与可选的目标格子
+
+// 建筑各时间段 [t_from, t_until) 所在的左上角格子，按迁移事件切分；GUI 与校验共用
+pub fn footprint_segments(uid: usize, start: (usize, usize), t_create: i32, t_removed: i32, relocations: &[RelocateEvent]) -> Vec<(i32, i32, usize, usize)> {
+    let mut moves: Vec<&RelocateEvent> = relocations.iter().filter(|r| r.uid == uid).collect();
+    moves.sort_by_key(|r| get_time_value(r.wave_num, r.is_late));
+    let mut segments = Vec::new();
+    let (mut from, mut pos) = (t_create, start);
+    for r in moves {
+        let t = get_time_value(r.wave_num, r.is_late);
+        if t >= t_removed { break; }
+        if t > from { segments.push((from, t, pos.0, pos.1)); from = t; }
+        pos = (r.to_x, r.to_y);
+    }
+    if from < t_removed { segments.push((from, t_removed, pos.0, pos.1)); }
+    segments
+}
+
+// 事件列表中正在原地编辑的条目（列表下标）
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub(crate) enum EventRef { Upgrade(usize), Demolish(usize) }
+
+// 对事件列表中勾选的多条事件一次执行
+#[derive(Clone, Copy)]
+pub(crate) enum BulkAction { Shift(i32), ToggleLate, Delete }
+
+// 事件编辑行共用的波次控件，返回是否有改动
+pub(crate) fn wave_editor(ui: &mut egui::Ui, wave_num: &mut i32, is_late: &mut bool) -> bool {
+    ui.label(tr("波次:"));
+    let wave = ui.add(egui::DragValue::new(wave_num).clamp_range(1..=100)).changed();
+    let late = with_time_model(TimeModel::has_late_phase) && ui.checkbox(is_late, tr("后期")).changed();
+    late || wave
+}
+
+// 可选的秒数偏移：勾选后填写相对所在时间段开始的秒数
+pub(crate) fn offset_seconds_editor(ui: &mut egui::Ui, offset_seconds: &mut Option<f32>) -> bool {
+    let mut enabled = offset_seconds.is_some();
+    let mut changed = false;
+    if ui.checkbox(&mut enabled, tr("延后")).on_hover_text(tr("在所在时间段开始后再等待的秒数，只影响同一时间段内操作的先后")).changed() {
+        *offset_seconds = enabled.then_some(0.0);
+        changed = true;
+    }
+    if let Some(seconds) = offset_seconds { changed |= ui.add(egui::DragValue::new(seconds).speed(0.5).clamp_range(0.0..=3600.0).suffix(tr(" 秒"))).changed(); }
+    changed
+}
+
+// 时间值 t 时建筑所在的左上角格子（t 之前最后一次迁移的目标，没有则为建造位置）
+pub fn position_at(uid: usize, start: (usize, usize), t: i32, relocations: &[RelocateEvent]) -> (usize, usize) {
+    relocations.iter()
+        .filter(|r| r.uid == uid && get_time_value(r.wave_num, r.is_late) <= t)
+        .max_by_key(|r| get_time_value(r.wave_num, r.is_late))
+        .map_or(start, |r| (r.to_x, r.to_y))
+}
+
+impl MapEditor {
+    pub(crate) fn building_pos_at(&self, b: &PlacedBuilding, t: i32) -> (usize, usize) {
+        position_at(b.uid, (b.grid_x, b.grid_y), t, &self.relocate_events)
+    }
+
+    pub(crate) fn building_segments(&self, b: &PlacedBuilding) -> Vec<(i32, i32, usize, usize)> {
+        footprint_segments(b.uid, (b.grid_x, b.grid_y), get_time_value(b.wave_num, b.is_late), self.get_building_demolish_time(b.uid), &self.relocate_events)
+    }
+
+    // 时间值 t 时建筑占据的矩形（单位：格），含小数偏移，与画布上绘制的位置一致
+    pub(crate) fn building_rect_at(&self, b: &PlacedBuilding, t: i32) -> Rect {
+        let (x, y) = self.building_pos_at(b, t);
+        Rect::from_min_size(Pos2::new(x as f32 + b.offset[0], y as f32 + b.offset[1]), Vec2::new(b.width as f32, b.height as f32))
+    }
+
+    // 同上，换算到画布坐标
+    pub(crate) fn building_screen_rect(&self, b: &PlacedBuilding, t: i32, origin: Pos2, cell: Vec2) -> Rect {
+        let rect = self.building_rect_at(b, t);
+        Rect::from_min_size(origin + rect.min.to_vec2() * cell, rect.size() * cell)
+    }
+
+    // 时间值 t 时建筑存在且覆盖点 at（单位：格，可带小数）
+    pub(crate) fn building_covers_at(&self, b: &PlacedBuilding, at: Pos2, t: i32) -> bool {
+        if t < get_time_value(b.wave_num, b.is_late) || t >= self.get_building_demolish_time(b.uid) { return false; }
+        self.building_rect_at(b, t).contains(at)
+    }
+
+    // 删除建筑后清理引用它们的拆除 / 出售 / 迁移事件
+    pub(crate) fn prune_orphan_events(&mut self) {
+        let exists = |uid: usize| self.placed_buildings.iter().any(|b| b.uid == uid);
+        self.demolish_events.retain(|e| exists(e.uid));
+        self.sell_events.retain(|e| exists(e.uid));
+        self.relocate_events.retain(|e| exists(e.uid));
+        self.upgrade_events.retain(|e| e.uid.is_none_or(exists));
+        if self.upgrade_target_uid.is_some_and(|uid| !exists(uid)) { self.upgrade_target_uid = None; }
+    }
+
+    // 已有拆除或出售事件、或当前时间尚未建成的建筑不能出售；返回是否添加了出售事件
+    pub(crate) fn add_sell_event(&mut self, uid: usize) -> bool {
+        if self.current_wave_locked() { return false; }
+        if self.demolish_events.iter().any(|e| e.uid == uid) || self.sell_events.iter().any(|e| e.uid == uid) { return false; }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        if !self.placed_buildings.iter().any(|b| b.uid == uid && get_time_value(b.wave_num, b.is_late) < t_current) { return false; }
+        if self.refuse_locked(self.relocations_locked_from(uid, t_current)) { return false; }
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return false; };
+        let (grid_x, grid_y) = self.building_pos_at(b, t_current);
+        self.sell_events.push(SellEvent { uid, name: b.template_name.clone(), grid_x, grid_y, width: b.width, height: b.height, wave_num: self.current_wave_num, is_late: self.current_is_late, viewport: self.current_viewport_preset(), order: None });
+        // 出售之后的迁移不再有意义
+        self.relocate_events.retain(|r| r.uid != uid || get_time_value(r.wave_num, r.is_late) < t_current);
+        true
+    }
+
+    // 在当前时间把建筑迁移到 (to_x, to_y)，直到它的下一次迁移或被移除；目标不可放置时返回 false
+    pub(crate) fn can_relocate(&self, uid: usize, to_x: usize, to_y: usize) -> bool {
+        if self.current_wave_locked() { return false; }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return false; };
+        if t_current <= get_time_value(b.wave_num, b.is_late) || t_current >= self.get_building_demolish_time(uid) { return false; }
+        let t_until = self.relocate_events.iter()
+            .filter(|r| r.uid == uid)
+            .map(|r| get_time_value(r.wave_num, r.is_late))
+            .filter(|t| *t > t_current)
+            .min()
+            .unwrap_or_else(|| self.get_building_demolish_time(uid));
+        self.building_pos_at(b, t_current) != (to_x, to_y) && self.can_place_building_during(to_y, to_x, b.width, b.height, b.b_type, t_current, t_until, &[uid])
+    }
+
+    pub(crate) fn add_relocate_event(&mut self, uid: usize, to_x: usize, to_y: usize) -> bool {
+        if !self.can_relocate(uid, to_x, to_y) { return false; }
+        let (wave_num, is_late) = (self.current_wave_num, self.current_is_late);
+        let t_current = get_time_value(wave_num, is_late);
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == uid) else { return false; };
+        // 同一时间的迁移只保留一条，起点取本次迁移之前的位置
+        let (from_x, from_y) = self.building_pos_at(b, t_current - 1);
+        let event = RelocateEvent { uid, name: b.template_name.clone(), from_x, from_y, to_x, to_y, width: b.width, height: b.height, wave_num, is_late, viewport: self.current_viewport_preset(), order: None };
+        self.relocate_events.retain(|r| r.uid != uid || r.wave_num != wave_num || r.is_late != is_late);
+        self.relocate_events.push(event);
+        true
+    }
+
+    pub(crate) fn show_demolish_panel(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("拆除任务预览:"));
+            let mut delete_idx = None;
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                if self.demolish_events.is_empty() { ui.label(tr("暂无拆除记录")); }
+                for i in 0..self.demolish_events.len() {
+                    if self.editing_event == Some(EventRef::Demolish(i)) { self.edit_demolish_row(ui, i); continue; }
+                    if !self.wave_filter.contains(self.demolish_events[i].wave_num) { continue; }
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(!self.wave_locked(self.demolish_events[i].wave_num), |ui| {
+                            self.event_checkbox(ui, EventRef::Demolish(i));
+                            if ui.button("[X]").clicked() { delete_idx = Some(i); }
+                            if ui.small_button("✏").on_hover_text(tr("编辑波次与目标")).clicked() { self.editing_event = Some(EventRef::Demolish(i)); }
+                        });
+                        let ev = &self.demolish_events[i];
+                        ui.label(trf!("{}: 拆除 {}", event_time_label(ev.wave_num, ev.is_late, ev.offset_seconds), ev.name));
+                    });
+                }
+                self.show_filtered_count(ui, self.demolish_events.iter().map(|e| e.wave_num));
+            });
+            if let Some(idx) = delete_idx { self.demolish_events.remove(idx); self.editing_event = None; self.checked_events.clear(); }
+            self.show_bulk_event_bar(ui, true);
+        });
+    }
+
+    pub(crate) fn event_checkbox(&mut self, ui: &mut egui::Ui, event: EventRef) {
+        let mut checked = self.checked_events.contains(&event);
+        if ui.checkbox(&mut checked, "").changed() {
+            if checked { self.checked_events.insert(event); } else { self.checked_events.remove(&event); }
+        }
+    }
+
+    fn event_wave(&self, demolish: bool, i: usize) -> i32 {
+        if demolish { self.demolish_events[i].wave_num } else { self.upgrade_events[i].wave_num }
+    }
+
+    // 勾选的升级（demolish 为 false）或拆除事件的列表下标，升序；被波次范围隐藏的与锁定波次中的事件不参与批量操作
+    fn checked_indices(&self, demolish: bool) -> Vec<usize> {
+        let len = if demolish { self.demolish_events.len() } else { self.upgrade_events.len() };
+        let mut indices: Vec<usize> = self.checked_events.iter().filter_map(|e| match (e, demolish) {
+            (EventRef::Demolish(i), true) | (EventRef::Upgrade(i), false) => Some(*i),
+            _ => None,
+        }).filter(|&i| i < len && self.wave_filter.contains(self.event_wave(demolish, i)) && !self.wave_locked(self.event_wave(demolish, i))).collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    fn apply_bulk_action(&mut self, demolish: bool, action: BulkAction) {
+        let checked = self.checked_indices(demolish);
+        // 不能平移进锁定的波次
+        if let BulkAction::Shift(delta) = action {
+            if self.refuse_locked(checked.iter().any(|&i| self.wave_locked((self.event_wave(demolish, i) + delta).max(1)))) { return; }
+        }
+        if let BulkAction::Delete = action {
+            let mut i = 0;
+            let mut keep = || { i += 1; !checked.contains(&(i - 1)) };
+            if demolish { self.demolish_events.retain(|_| keep()); } else { self.upgrade_events.retain(|_| keep()); }
+            self.checked_events.clear();
+            self.editing_event = None;
+            return;
+        }
+        let times: Vec<(&mut i32, &mut bool)> = if demolish {
+            self.demolish_events.iter_mut().enumerate().filter(|(i, _)| checked.contains(i)).map(|(_, e)| (&mut e.wave_num, &mut e.is_late)).collect()
+        } else {
+            self.upgrade_events.iter_mut().enumerate().filter(|(i, _)| checked.contains(i)).map(|(_, e)| (&mut e.wave_num, &mut e.is_late)).collect()
+        };
+        for (wave_num, is_late) in times {
+            match action {
+                BulkAction::Shift(delta) => *wave_num = (*wave_num + delta).max(1),
+                BulkAction::ToggleLate => *is_late = !*is_late,
+                BulkAction::Delete => {}
+            }
+        }
+        // 拆除时间变了，目标当时的位置也可能不同
+        if demolish { for i in checked { self.sync_demolish_event(i); } }
+    }
+
+    // 事件列表下方的批量操作栏：全选 / 清除勾选，以及对勾选事件平移波次、切换前期/后期、删除
+    pub(crate) fn show_bulk_event_bar(&mut self, ui: &mut egui::Ui, demolish: bool) {
+        let len = if demolish { self.demolish_events.len() } else { self.upgrade_events.len() };
+        if len == 0 { return; }
+        let event = |i: usize| if demolish { EventRef::Demolish(i) } else { EventRef::Upgrade(i) };
+        // 全选只选中波次范围内（列表中可见）的事件
+        let visible: Vec<usize> = (0..len).filter(|&i| self.wave_filter.contains(self.event_wave(demolish, i)) && !self.wave_locked(self.event_wave(demolish, i))).collect();
+        let count = self.checked_indices(demolish).len();
+        let mut action = None;
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.small_button(tr("全选")).clicked() { self.checked_events.extend(visible.iter().map(|&i| event(i))); }
+            if count > 0 && ui.small_button(tr("清除勾选")).clicked() { for i in 0..len { self.checked_events.remove(&event(i)); } }
+            ui.label(trf!("已勾选 {} 条", count));
+        });
+        if count == 0 { return; }
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.bulk_wave_shift).clamp_range(-50..=50).prefix("±"));
+            if ui.button(tr("平移波次")).clicked() && self.bulk_wave_shift != 0 { action = Some(BulkAction::Shift(self.bulk_wave_shift)); }
+            if with_time_model(TimeModel::has_late_phase) && ui.button(tr("切换前期/后期")).clicked() { action = Some(BulkAction::ToggleLate); }
+            if ui.button(tr("删除")).clicked() { action = Some(BulkAction::Delete); }
+        });
+        if let Some(action) = action { self.apply_bulk_action(demolish, action); }
+    }
+
+    // 原地编辑拆除事件：改动波次或目标后按新的建筑与时间重新计算名称、尺寸和位置
+    fn edit_demolish_row(&mut self, ui: &mut egui::Ui, i: usize) {
+        let Some(ev) = self.demolish_events.get(i) else { self.editing_event = None; return; };
+        let (uid, before) = (ev.uid, (ev.wave_num, ev.is_late));
+        // 可选目标：尚未被拆除或出售的建筑，以及当前目标本身
+        let candidates: Vec<(usize, String)> = self.placed_buildings.iter()
+            .filter(|b| b.uid == uid || (!self.demolish_events.iter().any(|e| e.uid == b.uid) && !self.sell_events.iter().any(|e| e.uid == b.uid)))
+            .map(|b| (b.uid, trf!("{} (UID {})", b.template_name, b.uid))).collect();
+        let mut changed = false;
+        ui.group(|ui| {
+            let ev = &mut self.demolish_events[i];
+            ui.horizontal(|ui| { changed |= wave_editor(ui, &mut ev.wave_num, &mut ev.is_late); offset_seconds_editor(ui, &mut ev.offset_seconds); });
+            ui.horizontal(|ui| {
+                ui.label(tr("目标:"));
+                let selected = candidates.iter().find(|(u, _)| *u == ev.uid).map_or_else(|| trf!("{} (UID {})", ev.name, ev.uid), |(_, label)| label.clone());
+                egui::ComboBox::from_id_source(("demolish_edit_target", i)).selected_text(selected).show_ui(ui, |ui| {
+                    for (u, label) in &candidates { changed |= ui.selectable_value(&mut ev.uid, *u, label).changed(); }
+                });
+            });
+            if ui.button(tr("完成")).clicked() { self.editing_event = None; }
+        });
+        // 不能改到锁定的波次
+        let wave = self.demolish_events[i].wave_num;
+        if wave != before.0 && self.refuse_locked(self.wave_locked(wave)) { (self.demolish_events[i].wave_num, self.demolish_events[i].is_late) = before; }
+        if changed { self.sync_demolish_event(i); }
+        let ev = &self.demolish_events[i];
+        if let Some(b) = self.placed_buildings.iter().find(|b| b.uid == ev.uid) {
+            if get_time_value(ev.wave_num, ev.is_late) <= get_time_value(b.wave_num, b.is_late) {
+                ui.colored_label(Color32::RED, trf!("拆除时间不晚于建造时间 {}", wave_label(b.wave_num, b.is_late)));
+            }
+        }
+    }
+
+    fn sync_demolish_event(&mut self, i: usize) {
+        let ev = &self.demolish_events[i];
+        let t = get_time_value(ev.wave_num, ev.is_late);
+        let Some(b) = self.placed_buildings.iter().find(|b| b.uid == ev.uid) else { return; };
+        let ((grid_x, grid_y), name, width, height) = (self.building_pos_at(b, t), b.template_name.clone(), b.width, b.height);
+        let ev = &mut self.demolish_events[i];
+        (ev.grid_x, ev.grid_y, ev.name, ev.width, ev.height) = (grid_x, grid_y, name, width, height);
+    }
+
+    pub(crate) fn show_sell_panel(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("出售任务预览:"));
+            let mut delete_idx = None;
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                if self.sell_events.is_empty() { ui.label(tr("暂无出售记录")); }
+                for (i, ev) in self.sell_events.iter().enumerate().filter(|(_, e)| self.wave_filter.contains(e.wave_num)) {
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.wave_locked(ev.wave_num), egui::Button::new("[X]")).clicked() { delete_idx = Some(i); }
+                        ui.label(trf!("{}: 出售 {}", wave_label(ev.wave_num, ev.is_late), ev.name));
+                    });
+                }
+                self.show_filtered_count(ui, self.sell_events.iter().map(|e| e.wave_num));
+            });
+            if let Some(idx) = delete_idx { self.sell_events.remove(idx); }
+        });
+    }
+
+    pub(crate) fn show_relocate_panel(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            match self.relocate_pick.and_then(|uid| self.placed_buildings.iter().find(|b| b.uid == uid)) {
+                Some(b) => ui.colored_label(Color32::from_rgb(255, 0, 255), trf!("正在迁移 {} (UID {})，左键放置，右键/Esc 取消", b.template_name, b.uid)),
+                None => ui.label(tr("左键点击当前波次存在的建筑开始迁移")),
+            };
+            ui.separator();
+            ui.label(tr("迁移任务预览:"));
+            let mut delete_idx = None;
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                if self.relocate_events.is_empty() { ui.label(tr("暂无迁移记录")); }
+                for (i, ev) in self.relocate_events.iter().enumerate().filter(|(_, e)| self.wave_filter.contains(e.wave_num)) {
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.wave_locked(ev.wave_num), egui::Button::new("[X]")).clicked() { delete_idx = Some(i); }
+                        ui.label(trf!("{}: 迁移 {} ({}, {}) → ({}, {})", wave_label(ev.wave_num, ev.is_late), ev.name, ev.from_x, ev.from_y, ev.to_x, ev.to_y));
+                    });
+                }
+                self.show_filtered_count(ui, self.relocate_events.iter().map(|e| e.wave_num));
+            });
+            if let Some(idx) = delete_idx { self.relocate_events.remove(idx); }
+        });
+    }
+
+    // 出售模式：左键点击当前时间存在的建筑添加出售事件
+    pub(crate) fn handle_sell_input(&mut self, response: &egui::Response, painter: &Painter, origin: Pos2, cell: Vec2, at: Pos2) {
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let Some(b) = self.placed_buildings.iter().find(|b| self.building_covers_at(b, at, t_current)) else { return; };
+        painter.rect_stroke(self.building_screen_rect(b, t_current, origin, cell), 0.0, Stroke::new(3.0, Color32::GOLD));
+        if response.clicked_by(egui::PointerButton::Primary) {
+            let uid = b.uid;
+            self.add_sell_event(uid);
+        }
+    }
+
+    // 迁移模式：先点击选中建筑，再点击目标格子（建筑左上角）放置
+    pub(crate) fn handle_relocate_input(&mut self, response: &egui::Response, painter: &Painter, origin: Pos2, cell: Vec2, at: Pos2) {
+        let (cx, cy) = (at.x.floor() as i32, at.y.floor() as i32);
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        if response.clicked_by(egui::PointerButton::Secondary) || response.ctx.input(|i| i.key_pressed(egui::Key::Escape)) { self.relocate_pick = None; return; }
+        let picked = self.relocate_pick.and_then(|uid| self.placed_buildings.iter().find(|b| b.uid == uid));
+        match picked {
+            Some(b) => {
+                let (uid, w, h) = (b.uid, b.width, b.height);
+                let valid = cx >= 0 && cy >= 0 && self.can_relocate(uid, cx as usize, cy as usize);
+                let ghost = Rect::from_min_size(origin + Vec2::new(cx as f32 * cell.x, cy as f32 * cell.y), Vec2::new(w as f32 * cell.x, h as f32 * cell.y));
+                painter.rect_stroke(ghost, 0.0, Stroke::new(2.5, if valid { Color32::GREEN } else { Color32::RED }));
+                if response.clicked_by(egui::PointerButton::Primary) && valid && self.add_relocate_event(uid, cx as usize, cy as usize) {
+                    self.relocate_pick = None;
+                }
+            }
+            None => {
+                let Some(b) = self.placed_buildings.iter().find(|b| self.building_covers_at(b, at, t_current)) else { return; };
+                painter.rect_stroke(self.building_screen_rect(b, t_current, origin, cell), 0.0, Stroke::new(3.0, Color32::from_rgb(255, 0, 255)));
+                if response.clicked_by(egui::PointerButton::Primary) { self.relocate_pick = Some(b.uid); }
+            }
+        }
+    }
+
+    // 当前波次发生的迁移：起点画虚框并用箭头指向新位置
+    pub(crate) fn draw_relocations(&self, painter: &Painter, origin: Pos2, cell: Vec2) {
+        let color = Color32::from_rgb(255, 0, 255);
+        for r in self.relocate_events.iter().filter(|r| r.wave_num == self.current_wave_num && r.is_late == self.current_is_late && self.wave_filter.contains(r.wave_num)) {
+            let rect_at = |x: usize, y: usize| Rect::from_min_size(origin + Vec2::new(x as f32 * cell.x, y as f32 * cell.y), Vec2::new(r.width as f32 * cell.x, r.height as f32 * cell.y));
+            let (from, to) = (rect_at(r.from_x, r.from_y), rect_at(r.to_x, r.to_y));
+            painter.add(egui::Shape::dashed_line(&[from.left_top(), from.right_top(), from.right_bottom(), from.left_bottom(), from.left_top()], Stroke::new(1.5, color), 6.0, 4.0));
+            painter.arrow(from.center(), to.center() - from.center(), Stroke::new(2.5, color));
+        }
+    }
+
+    // 出售的建筑在红叉上再标注 $
+    pub(crate) fn draw_sell_marker(&self, painter: &Painter, rect: Rect, uid: usize, alpha_mult: f32) {
+        if !self.sell_events.iter().any(|e| e.uid == uid) { return; }
+        let color = Color32::from_rgba_unmultiplied(255, 200, 0, (230.0 * alpha_mult) as u8);
+        painter.text(rect.center(), Align2::CENTER_CENTER, "$", FontId::proportional((rect.height() * 0.6).max(10.0)), color);
+    }
+
+    pub(crate) fn show_ability_panel(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("添加技能释放:"));
+            ui.horizontal(|ui| {
+                ui.label(tr("名称:"));
+                ui.add(egui::TextEdit::singleline(&mut self.ability_name).hint_text("airstrike").desired_width(140.0));
+            });
+            // 已用过的名称可一键选中，避免拼写不一致
+            let mut known: Vec<&str> = self.ability_events.iter().map(|e| e.name.as_str()).collect();
+            known.sort_unstable();
+            known.dedup();
+            if !known.is_empty() {
+                let mut picked = None;
+                ui.horizontal_wrapped(|ui| {
+                    for name in known { if ui.selectable_label(self.ability_name == name, name).clicked() { picked = Some(name.to_string()); } }
+                });
+                if let Some(name) = picked { self.ability_name = name; }
+            }
+            ui.checkbox(&mut self.ability_targeted, tr("指定目标格子"));
+            let name = self.ability_name.trim().to_string();
+            if self.ability_targeted {
+                ui.label(if name.is_empty() { tr("请先填写技能名称") } else { tr("在地图上左键点击目标格子添加到当前波次") });
+            } else if ui.add_enabled(!name.is_empty() && !self.current_wave_locked(), egui::Button::new(tr("[+] 添加到当前波次"))).clicked() {
+                self.ability_events.push(AbilityEvent { name, wave_num: self.current_wave_num, is_late: self.current_is_late, target: None, order: None });
+            }
+        });
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("技能序列预览:"));
+            let mut delete_idx = None;
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                if self.ability_events.is_empty() { ui.label(tr("暂无技能记录")); }
+                for (i, ev) in self.ability_events.iter().enumerate().filter(|(_, e)| self.wave_filter.contains(e.wave_num)) {
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!self.wave_locked(ev.wave_num), egui::Button::new("[X]")).clicked() { delete_idx = Some(i); }
+                        let wave = wave_label(ev.wave_num, ev.is_late);
+                        match ev.target {
+                            Some((x, y)) => ui.label(trf!("{}: 技能 {} → ({}, {})", wave, ev.name, x, y)),
+                            None => ui.label(trf!("{}: 技能 {}", wave, ev.name)),
+                        };
+                    });
+                }
+                self.show_filtered_count(ui, self.ability_events.iter().map(|e| e.wave_num));
+            });
+            if let Some(idx) = delete_idx { self.ability_events.remove(idx); }
+        });
+    }
+
+    // 技能模式：指定目标时左键点击格子添加技能事件
+    pub(crate) fn handle_ability_input(&mut self, response: &egui::Response, painter: &Painter, origin: Pos2, cell: Vec2, cx: i32, cy: i32) {
+        let name = self.ability_name.trim();
+        if !self.ability_targeted || name.is_empty() || cx < 0 || cy < 0 || cx as usize >= self.grid_cols || cy as usize >= self.grid_rows { return; }
+        let rect = Rect::from_min_size(origin + Vec2::new(cx as f32 * cell.x, cy as f32 * cell.y), cell);
+        painter.circle_stroke(rect.center(), cell.x.min(cell.y) * 0.6, Stroke::new(2.0, Color32::from_rgb(255, 80, 0)));
+        if response.clicked_by(egui::PointerButton::Primary) {
+            let event = AbilityEvent { name: name.to_string(), wave_num: self.current_wave_num, is_late: self.current_is_late, target: Some((cx as usize, cy as usize)), order: None };
+            self.ability_events.push(event);
+        }
+    }
+
+    // 当前波次释放的有目标技能：目标格子画圈并标注名称
+    pub(crate) fn draw_abilities(&self, painter: &Painter, origin: Pos2, cell: Vec2) {
+        let color = Color32::from_rgb(255, 80, 0);
+        let radius = (cell.x.min(cell.y) * 0.45).max(4.0);
+        for ev in self.ability_events.iter().filter(|e| e.wave_num == self.current_wave_num && e.is_late == self.current_is_late && self.wave_filter.contains(e.wave_num)) {
+            let Some((x, y)) = ev.target else { continue; };
+            let center = origin + Vec2::new((x as f32 + 0.5) * cell.x, (y as f32 + 0.5) * cell.y);
+            painter.circle_filled(center, radius, color.linear_multiply(0.35));
+            painter.circle_stroke(center, radius, Stroke::new(2.0, color));
+            painter.text(center + Vec2::new(0.0, -radius - 2.0), Align2::CENTER_BOTTOM, format!("✦ {}", ev.name), FontId::proportional(14.0), color);
+        }
+    }
+}
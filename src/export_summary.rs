@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use eframe::egui::{self, Color32};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::operations::{operations_export, timed_operations};
+use crate::storage::write_data;
+
+// 🔥 导出全部数据的确认与结果：先列出将要写入的每个文件以及是否已存在（会被覆盖），确认后逐个写入，
+// 在同一窗口中显示每个文件成功或失败的原因，不再只靠一闪而过的通知
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportItem { Terrain, Strategy, BuildingConfigs, Operations, Timeline, Plugin(usize) }
+
+struct PlannedFile {
+    item: ExportItem,
+    label: String,
+    path: PathBuf,
+    exists: bool,
+    // 导出后为写入结果
+    result: Option<Result<(), String>>,
+}
+
+pub(crate) struct ExportDialog {
+    files: Vec<PlannedFile>,
+}
+
+impl MapEditor {
+    pub(crate) fn open_export_dialog(&mut self) {
+        let paths = self.export_paths();
+        let mut planned = vec![
+            (ExportItem::Terrain, tr("地形").to_string(), paths.terrain),
+            (ExportItem::Strategy, tr("策略").to_string(), paths.strategy),
+            (ExportItem::BuildingConfigs, tr("防御塔列表").to_string(), paths.building_configs),
+            (ExportItem::Operations, tr("操作序列").to_string(), paths.operations),
+        ];
+        if !self.time_model.wave_seconds.is_empty() { planned.push((ExportItem::Timeline, tr("绝对时间序列").to_string(), paths.timeline)); }
+        planned.extend(self.plugins.export_paths("output", &self.export_map_name()).into_iter().enumerate().map(|(i, (name, path))| (ExportItem::Plugin(i), trf!("插件: {}", name), path)));
+        let files = planned.into_iter().map(|(item, label, path)| PlannedFile { item, label, exists: path.exists(), path, result: None }).collect();
+        self.export_dialog = Some(ExportDialog { files });
+    }
+
+    fn write_export_item(&self, item: ExportItem, path: &Path) -> Result<(), String> {
+        match item {
+            ExportItem::Terrain => write_data(path, &self.build_terrain_export()),
+            ExportItem::Strategy => write_data(path, &self.build_buildings_export()),
+            ExportItem::BuildingConfigs => write_data(path, &self.building_configs),
+            ExportItem::Operations => write_data(path, &operations_export(&self.build_buildings_export())),
+            ExportItem::Timeline => match timed_operations(&self.build_buildings_export(), &self.time_model) {
+                Some(timeline) => write_data(path, &timeline),
+                None => Err(tr("请先在时间划分中填写每波时长").to_string()),
+            },
+            ExportItem::Plugin(i) => self.export_plugin(i, path),
+        }
+    }
+
+    fn run_export(&mut self, dialog: &mut ExportDialog) {
+        for file in &mut dialog.files { file.result = Some(self.write_export_item(file.item, &file.path)); }
+        let failed = dialog.files.iter().filter(|f| matches!(f.result, Some(Err(_)))).count();
+        // 地形、策略与防御塔列表都写成功才算已保存
+        let saved = dialog.files.iter().filter(|f| matches!(f.item, ExportItem::Terrain | ExportItem::Strategy | ExportItem::BuildingConfigs)).all(|f| matches!(f.result, Some(Ok(()))));
+        if saved { self.mark_saved(); }
+        if failed == 0 { self.notifications.info(trf!("已导出 {} 个文件", dialog.files.len())); } else { self.notifications.error(trf!("导出完成: {} 个成功，{} 个失败", dialog.files.len() - failed, failed)); }
+    }
+
+    pub(crate) fn show_export_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.export_dialog.take() else { return; };
+        let (mut open, mut export, mut close) = (true, false, false);
+        let done = dialog.files.iter().any(|f| f.result.is_some());
+        egui::Window::new(tr("导出全部数据")).id(egui::Id::new("export_all")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            egui::Grid::new("export_files").num_columns(3).striped(true).show(ui, |ui| {
+                for file in &dialog.files {
+                    ui.label(&file.label);
+                    ui.label(file.path.display().to_string());
+                    match &file.result {
+                        None if file.exists => { ui.colored_label(Color32::YELLOW, tr("⚠ 将覆盖")); }
+                        None => { ui.label(tr("新建")); }
+                        Some(Ok(())) => { ui.colored_label(Color32::GREEN, tr("✔ 已写入")); }
+                        Some(Err(e)) => { ui.colored_label(Color32::RED, tr("✖ 失败")).on_hover_text(e); }
+                    }
+                    ui.end_row();
+                }
+            });
+            for e in dialog.files.iter().filter_map(|f| f.result.as_ref()?.as_ref().err()) { ui.colored_label(Color32::RED, e); }
+            ui.horizontal(|ui| {
+                if done {
+                    close = ui.button(tr("关闭")).clicked();
+                } else {
+                    let overwrite = dialog.files.iter().filter(|f| f.exists).count();
+                    export = ui.button(if overwrite > 0 { trf!("覆盖 {} 个文件并导出", overwrite) } else { tr("导出").to_string() }).clicked();
+                    close = ui.button(tr("取消")).clicked();
+                }
+            });
+        });
+        if export { self.run_export(&mut dialog); }
+        if open && !close { self.export_dialog = Some(dialog); }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use fastrand::Rng;
+
+use crate::models::*;
+use crate::utils::{get_time_value, wave_from_time_value};
+
+// 🔥 随机布局生成：开发用的压力测试数据，生成合法的随机地形与遵守放置规则的随机策略（N 座建筑 + M 个事件），
+// 用来测试导出、画布渲染性能与下游程序。同一个种子总是生成相同的结果
+// 规则与编辑器一致：建筑只放在同一高度的可建造地形上，同类建筑在同一时间不重叠；拆除 / 出售晚于建造且每座最多一次，
+// 迁移发生在建筑存在期间且目标位置空闲，单独升级在建筑存在期间
+
+pub struct GenerateOptions {
+    pub rows: usize,
+    pub cols: usize,
+    pub buildings: usize,
+    pub events: usize,
+    pub waves: i32,
+    pub seed: u64,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self { rows: 64, cols: 64, buildings: 200, events: 100, waves: 20, seed: 1 }
+    }
+}
+
+// 放置一座建筑的尝试次数，失败则跳过
+const PLACE_ATTEMPTS: usize = 50;
+const ABILITY_NAMES: [&str; 3] = ["airstrike", "freeze", "heal"];
+
+fn config(name: &str, b_type: BuildingType, size: (usize, usize), color: [u8; 4], cost: i32, range: Option<f32>) -> BuildingConfig {
+    BuildingConfig {
+        name: name.to_string(), b_type, grid_index: [0, 0], width: size.0, height: size.1, color, icon_path: String::new(), cost,
+        upgrade_cost: Some(cost / 2), range, category: String::new(), tags: Vec::new(), upgrades: Vec::new(),
+    }
+}
+
+fn generated_configs() -> Vec<BuildingConfig> {
+    vec![
+        config("随机墙", BuildingType::Wall, (1, 1), [160, 160, 160, 255], 20, None),
+        config("随机箭塔", BuildingType::Floor, (2, 2), [220, 120, 40, 255], 100, Some(4.5)),
+        config("随机炮台", BuildingType::Floor, (3, 2), [200, 60, 60, 255], 180, Some(6.0)),
+        config("随机陷阱", BuildingType::Floor, (1, 1), [120, 200, 80, 255], 40, None),
+        config("随机吊灯", BuildingType::Ceiling, (2, 1), [240, 220, 80, 255], 60, Some(3.0)),
+    ]
+}
+
+// 在平地上叠加随机矩形的障碍与各级高台
+fn random_grid(rng: &mut Rng, rows: usize, cols: usize, types: &[TerrainType]) -> Grid {
+    let mut grid = Grid::new(rows, cols, 0);
+    for _ in 0..(rows * cols / 64).max(1) {
+        let (h, w) = (rng.usize(1..=(rows / 6).max(1)), rng.usize(1..=(cols / 6).max(1)));
+        let (r, c) = (rng.usize(0..rows), rng.usize(0..cols));
+        grid.fill(r..r + h, c..c + w, types[rng.usize(0..types.len())].id);
+    }
+    grid
+}
+
+// 建筑 uid → 存在期间各段的 (开始, 结束, x, y)
+type Occupancy = HashMap<usize, Vec<(i32, i32, usize, usize)>>;
+
+struct Generator<'a> {
+    rng: Rng,
+    opts: &'a GenerateOptions,
+    layer: LayerData,
+    types: &'a [TerrainType],
+    strategy: MapBuildingsExport,
+    occupancy: Occupancy,
+}
+
+impl Generator<'_> {
+    fn max_time(&self) -> i32 { get_time_value(self.opts.waves, true) }
+
+    fn terrain_ok(&self, x: usize, y: usize, w: usize, h: usize, b_type: BuildingType) -> bool {
+        if x + w > self.opts.cols || y + h > self.opts.rows { return false; }
+        let grid = self.layer.get_grid(b_type);
+        let base = grid.get(y, x);
+        self.types.iter().any(|t| t.id == base && t.buildable) && (y..y + h).all(|r| (x..x + w).all(|c| grid.get(r, c) == base))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn is_free(&self, x: usize, y: usize, w: usize, h: usize, b_type: BuildingType, t_from: i32, t_until: i32, ignore: usize) -> bool {
+        self.strategy.buildings.iter().filter(|b| b.b_type == b_type && b.uid != ignore).all(|b| {
+            self.occupancy[&b.uid].iter().all(|&(s, e, bx, by)| !(x < bx + b.width && x + w > bx && y < by + b.height && y + h > by && t_from < e && t_until > s))
+        })
+    }
+
+    fn removal_time(&self, uid: usize) -> i32 {
+        self.occupancy[&uid].last().map_or(i32::MAX, |s| s.1)
+    }
+
+    fn place_building(&mut self, uid: usize, configs: &[BuildingConfig]) {
+        let c = &configs[self.rng.usize(0..configs.len())];
+        let t = self.rng.i32(get_time_value(1, false)..=self.max_time());
+        for _ in 0..PLACE_ATTEMPTS {
+            let (x, y) = (self.rng.usize(0..self.opts.cols), self.rng.usize(0..self.opts.rows));
+            if !self.terrain_ok(x, y, c.width, c.height, c.b_type) || !self.is_free(x, y, c.width, c.height, c.b_type, t, i32::MAX, usize::MAX) { continue; }
+            let (wave_num, is_late) = wave_from_time_value(t);
+            self.strategy.buildings.push(BuildingExport {
+                uid, name: c.name.clone(), b_type: c.b_type, grid_x: x, grid_y: y, width: c.width, height: c.height,
+                wave_num, is_late, viewport: None, comment: None, offset: [0.0, 0.0], offset_seconds: None, order: None,
+            });
+            self.occupancy.insert(uid, vec![(t, i32::MAX, x, y)]);
+            return;
+        }
+    }
+
+    // 拆除或出售一座尚未安排移除的建筑
+    fn remove_building(&mut self, sell: bool) {
+        let candidates: Vec<usize> = (0..self.strategy.buildings.len())
+            .filter(|&i| { let b = &self.strategy.buildings[i]; self.removal_time(b.uid) == i32::MAX && get_time_value(b.wave_num, b.is_late) < self.max_time() })
+            .collect();
+        if candidates.is_empty() { return; }
+        let b = self.strategy.buildings[candidates[self.rng.usize(0..candidates.len())]].clone();
+        let t = self.rng.i32(get_time_value(b.wave_num, b.is_late) + 1..=self.max_time());
+        if let Some(last) = self.occupancy.get_mut(&b.uid).and_then(|s| s.last_mut()) { last.1 = t; }
+        let (wave_num, is_late) = wave_from_time_value(t);
+        if sell {
+            self.strategy.sells.push(SellEvent { uid: b.uid, name: b.name, grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, wave_num, is_late, viewport: None, order: None });
+        } else {
+            self.strategy.demolishes.push(DemolishEvent { uid: b.uid, name: b.name, grid_x: b.grid_x, grid_y: b.grid_y, width: b.width, height: b.height, wave_num, is_late, viewport: None, offset_seconds: None, order: None });
+        }
+    }
+
+    // 在最后一段位置的存在期间内迁移到一个空闲位置
+    fn relocate_building(&mut self) {
+        let b = self.strategy.buildings[self.rng.usize(0..self.strategy.buildings.len())].clone();
+        let &(start, end, from_x, from_y) = self.occupancy[&b.uid].last().expect("每座建筑至少有一段");
+        let end = end.min(self.max_time() + 1);
+        if end - start < 2 { return; }
+        let t = self.rng.i32(start + 1..end);
+        for _ in 0..PLACE_ATTEMPTS {
+            let (x, y) = (self.rng.usize(0..self.opts.cols), self.rng.usize(0..self.opts.rows));
+            if (x, y) == (from_x, from_y) || !self.terrain_ok(x, y, b.width, b.height, b.b_type) || !self.is_free(x, y, b.width, b.height, b.b_type, t, self.removal_time(b.uid), b.uid) { continue; }
+            let segments = self.occupancy.get_mut(&b.uid).expect("已放置的建筑");
+            let last = segments.last_mut().expect("每座建筑至少有一段");
+            let removed = last.1;
+            last.1 = t;
+            segments.push((t, removed, x, y));
+            let (wave_num, is_late) = wave_from_time_value(t);
+            self.strategy.relocations.push(RelocateEvent { uid: b.uid, name: b.name, from_x, from_y, to_x: x, to_y: y, width: b.width, height: b.height, wave_num, is_late, viewport: None, order: None });
+            return;
+        }
+    }
+
+    fn upgrade_building(&mut self) {
+        let b = self.strategy.buildings[self.rng.usize(0..self.strategy.buildings.len())].clone();
+        let (built, removed) = (get_time_value(b.wave_num, b.is_late), self.removal_time(b.uid).min(self.max_time() + 1));
+        if removed <= built { return; }
+        let (wave_num, is_late) = wave_from_time_value(self.rng.i32(built..removed));
+        self.strategy.upgrades.push(UpgradeEvent { building_name: b.name, wave_num, is_late, uid: Some(b.uid), offset_seconds: None, order: None });
+    }
+
+    fn cast_ability(&mut self) {
+        let (wave_num, is_late) = wave_from_time_value(self.rng.i32(get_time_value(1, false)..=self.max_time()));
+        let target = self.rng.bool().then(|| (self.rng.usize(0..self.opts.cols), self.rng.usize(0..self.opts.rows)));
+        self.strategy.abilities.push(AbilityEvent { name: ABILITY_NAMES[self.rng.usize(0..ABILITY_NAMES.len())].to_string(), wave_num, is_late, target, order: None });
+    }
+}
+
+// 生成一个完整的工程（rows、cols、waves 须不小于 1）：随机地形、一条随机路线、随机建筑与事件，以及所用的建筑配置
+pub fn generate_project(opts: &GenerateOptions) -> ProjectFile {
+    let mut rng = Rng::with_seed(opts.seed);
+    let types = default_terrain_types();
+    let (rows, cols) = (opts.rows, opts.cols);
+    let layer = LayerData {
+        major_z: 0,
+        name: "Default Layer".into(),
+        floor_grid: random_grid(&mut rng, rows, cols, &types),
+        wall_grid: random_grid(&mut rng, rows, cols, &types),
+        ceiling_grid: random_grid(&mut rng, rows, cols, &types),
+        elevation_grid: None,
+    };
+    let map_name = format!("random_{}", opts.seed);
+    let configs = generated_configs();
+    let mut gen = Generator { rng, opts, layer, types: &types, strategy: MapBuildingsExport { format_version: FORMAT_VERSION, map_name: map_name.clone(), ..Default::default() }, occupancy: HashMap::new() };
+
+    for uid in 1000..1000 + opts.buildings { gen.place_building(uid, &configs); }
+    // 先安排拆除与出售，迁移与升级再落在剩余的存在期间内
+    let kinds: Vec<u32> = (0..opts.events).map(|_| gen.rng.u32(0..5)).collect();
+    if !gen.strategy.buildings.is_empty() {
+        for kind in kinds.iter().filter(|k| **k < 2) { gen.remove_building(*kind == 1); }
+        for _ in kinds.iter().filter(|k| **k == 2) { gen.relocate_building(); }
+        for _ in kinds.iter().filter(|k| **k == 3) { gen.upgrade_building(); }
+    }
+    for _ in kinds.iter().filter(|k| **k == 4) { gen.cast_ability(); }
+
+    let mut strategy = gen.strategy;
+    strategy.relocations.sort_by_key(|r| (r.uid, get_time_value(r.wave_num, r.is_late)));
+    let point = |kind, rng: &mut Rng| RoutePoint { kind, grid_x: rng.usize(0..cols), grid_y: rng.usize(0..rows) };
+    let mut route = EnemyRoute { name: "随机路线".into(), points: vec![point(RoutePointKind::Spawn, &mut gen.rng)] };
+    for _ in 0..3 { route.points.push(point(RoutePointKind::Waypoint, &mut gen.rng)); }
+    route.points.push(point(RoutePointKind::Exit, &mut gen.rng));
+
+    let meta = MapMeta {
+        grid_pixel_width: 32.0, grid_pixel_height: 32.0, offset_x: 0.0, offset_y: 0.0, bottom: rows as f32 * 32.0, right: cols as f32 * 32.0,
+        camera_speed_up: 0.0, camera_speed_down: 0.0, camera_speed_left: 0.0, camera_speed_right: 0.0,
+        viewport_safe_areas: Vec::new(), prep_actions: Vec::new(), camera_keyframes: Vec::new(), viewport_presets: Vec::new(), time_model: TimeModel::default(),
+    };
+    ProjectFile {
+        format_version: FORMAT_VERSION,
+        project_name: map_name.clone(),
+        base_image_path: None,
+        preset_name: None,
+        map_filename: String::new(),
+        terrain: MapTerrainExport { format_version: FORMAT_VERSION, map_name, meta, layers: vec![gen.layer], routes: vec![route] },
+        strategy,
+        building_configs: configs,
+        reference_images: Vec::new(),
+    }
+}
@@ -0,0 +1,137 @@
+use eframe::egui::{self, Color32, Painter, Pos2, Stroke, Vec2};
+use image::GrayImage;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::storage::read_image;
+
+// 🔥 自动检测网格：分析底图中网格线的周期，给出格宽、格高与偏移的建议值，作为新地图校准的起点（之后再用对齐手柄或两点校准微调）
+// 每个方向先求相邻像素的亮度差、沿另一方向累加得到边缘强度曲线，减去局部均值只留下细线；
+// 自相关最强的周期即格子尺寸（取接近最大值的最小周期，避免选到 2 倍、3 倍），再对每条网格线的位置做直线拟合得到亚像素的尺寸与偏移
+
+const MIN_PERIOD: usize = 6;
+const SMOOTH_RADIUS: usize = 4;
+// 自相关不低于最大值的这一比例时视为同样可信，取其中最小的周期
+const HARMONIC_RATIO: f32 = 0.85;
+// 低于此可信度时提示结果可能不准
+const LOW_SCORE: f32 = 0.3;
+const PREVIEW_COLOR: Color32 = Color32::from_rgb(0, 230, 255);
+
+#[derive(Clone, Copy)]
+pub(crate) struct GridDetection {
+    width: f32,
+    height: f32,
+    offset_x: f32,
+    offset_y: f32,
+    // 0~1，两个方向中较弱的自相关
+    score: f32,
+}
+
+struct AxisFit {
+    period: f32,
+    offset: f32,
+    score: f32,
+}
+
+// 竖线（vertical = true）或横线的边缘强度曲线，下标 i 表示第 i-1 与第 i 个像素之间
+fn edge_profile(img: &GrayImage, vertical: bool) -> Vec<f32> {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let raw = img.as_raw();
+    let (len, span) = if vertical { (w, h) } else { (h, w) };
+    let px = |i: usize, j: usize| raw[if vertical { j * w + i } else { i * w + j }] as f32;
+    let mut profile = vec![0.0; len];
+    for (i, v) in profile.iter_mut().enumerate().skip(1) {
+        *v = (0..span).map(|j| (px(i, j) - px(i - 1, j)).abs()).sum::<f32>() / span as f32;
+    }
+    (0..len).map(|i| {
+        let (a, b) = (i.saturating_sub(SMOOTH_RADIUS), (i + SMOOTH_RADIUS + 1).min(len));
+        (profile[i] - profile[a..b].iter().sum::<f32>() / (b - a) as f32).max(0.0)
+    }).collect()
+}
+
+// 求一个方向的周期与第一条网格线的位置；曲线没有明显周期时返回 None
+fn fit_axis(profile: &[f32]) -> Option<AxisFit> {
+    let n = profile.len();
+    let max_period = n / 3;
+    if max_period <= MIN_PERIOD { return None; }
+    let mean = profile.iter().sum::<f32>() / n as f32;
+    let centered: Vec<f32> = profile.iter().map(|v| v - mean).collect();
+    let energy: f32 = centered.iter().map(|v| v * v).sum();
+    if energy <= f32::EPSILON { return None; }
+    let corr: Vec<f32> = (0..=max_period + 1).map(|p| {
+        if p < MIN_PERIOD - 1 { return 0.0; }
+        (0..n - p).map(|i| centered[i] * centered[i + p]).sum::<f32>() / energy * n as f32 / (n - p) as f32
+    }).collect();
+    let best = corr[MIN_PERIOD..=max_period].iter().copied().fold(f32::MIN, f32::max);
+    if best <= 0.0 { return None; }
+    let period = (MIN_PERIOD..=max_period).find(|&p| corr[p] >= best * HARMONIC_RATIO && corr[p] >= corr[p - 1] && corr[p] >= corr[p + 1])?;
+    let phase = (0..period).max_by(|&a, &b| {
+        let comb = |s: usize| (s..n).step_by(period).map(|i| profile[i]).sum::<f32>();
+        comb(a).total_cmp(&comb(b))
+    })?;
+    // 在每条预计的网格线附近找峰值，按强度加权取质心
+    let reach = (period / 4).max(1);
+    let lines: Vec<(f32, f32)> = (0..).map(|k| phase + k * period).take_while(|&c| c < n).enumerate().filter_map(|(k, c)| {
+        let (a, b) = (c.saturating_sub(reach), (c + reach + 1).min(n));
+        let peak = (a..b).max_by(|&x, &y| profile[x].total_cmp(&profile[y]))?;
+        let (a, b) = (peak.saturating_sub(1), (peak + 2).min(n));
+        let weight: f32 = profile[a..b].iter().sum();
+        (weight > 0.0).then(|| (k as f32, (a..b).map(|i| i as f32 * profile[i]).sum::<f32>() / weight))
+    }).collect();
+    if lines.len() < 2 { return Some(AxisFit { period: period as f32, offset: phase as f32, score: best.min(1.0) }); }
+    let count = lines.len() as f32;
+    let (mk, mp) = (lines.iter().map(|l| l.0).sum::<f32>() / count, lines.iter().map(|l| l.1).sum::<f32>() / count);
+    let slope = lines.iter().map(|(k, p)| (k - mk) * (p - mp)).sum::<f32>() / lines.iter().map(|(k, _)| (k - mk).powi(2)).sum::<f32>();
+    Some(AxisFit { period: slope, offset: mp - slope * mk, score: best.min(1.0) })
+}
+
+// 两个方向都找不到周期时返回 None；只找到一个方向时按正方形格子处理
+fn detect_grid(img: &GrayImage) -> Option<GridDetection> {
+    let (x, y) = (fit_axis(&edge_profile(img, true)), fit_axis(&edge_profile(img, false)));
+    let (width, height) = match (&x, &y) { (Some(x), Some(y)) => (x.period, y.period), (Some(x), None) => (x.period, x.period), (None, Some(y)) => (y.period, y.period), (None, None) => return None };
+    let score = [&x, &y].iter().map(|f| f.as_ref().map_or(0.0, |f| f.score)).fold(1.0, f32::min);
+    Some(GridDetection { width, height, offset_x: x.map_or(0.0, |f| f.offset), offset_y: y.map_or(0.0, |f| f.offset), score })
+}
+
+impl MapEditor {
+    fn run_grid_detection(&mut self) {
+        let Some(path) = self.base_image_path.clone() else { return; };
+        match read_image(&path) {
+            Ok(img) => match detect_grid(&img.to_luma8()) {
+                Some(found) => self.grid_detection = Some(found),
+                None => self.notifications.error(tr("未能在底图中找到明显的网格线")),
+            },
+            Err(e) => self.notifications.error(e),
+        }
+    }
+
+    pub(crate) fn show_grid_detection(&mut self, ui: &mut egui::Ui) {
+        let Some(found) = self.grid_detection else {
+            ui.vertical_centered_justified(|ui| {
+                if ui.add_enabled(self.base_image_path.is_some(), egui::Button::new(tr("🔍 自动检测网格"))).on_hover_text(tr("分析底图中网格线的周期，给出格子尺寸与偏移的建议值")).clicked() { self.run_grid_detection(); }
+            });
+            return;
+        };
+        ui.label(trf!("检测结果：格宽 {}，格高 {}，偏移 ({}, {})", format!("{:.2}", found.width), format!("{:.2}", found.height), format!("{:.1}", found.offset_x), format!("{:.1}", found.offset_y)));
+        let confidence = trf!("可信度 {}%", (found.score * 100.0).round());
+        if found.score < LOW_SCORE { ui.colored_label(Color32::YELLOW, trf!("{}，网格线不明显，结果可能不准确", confidence)); } else { ui.weak(confidence); }
+        ui.horizontal(|ui| {
+            if ui.button(tr("应用")).clicked() {
+                (self.grid_width, self.grid_height, self.offset_x, self.offset_y) = (found.width, found.height, found.offset_x, found.offset_y);
+                self.grid_detection = None;
+                self.notifications.info(tr("已按检测结果更新网格尺寸与偏移"));
+            }
+            if ui.button(tr("取消")).clicked() { self.grid_detection = None; }
+        });
+    }
+
+    // 检测结果未应用时在画布上用虚线预览建议的网格
+    pub(crate) fn draw_grid_detection(&self, painter: &Painter, map_origin: Pos2) {
+        let Some(found) = &self.grid_detection else { return; };
+        let (cols, rows) = (self.grid_cols, self.grid_rows);
+        let at = |c: usize, r: usize| map_origin + Vec2::new(found.offset_x + c as f32 * found.width, found.offset_y + r as f32 * found.height) * self.zoom;
+        let stroke = Stroke::new(1.0, PREVIEW_COLOR);
+        for c in 0..=cols { painter.extend(egui::Shape::dashed_line(&[at(c, 0), at(c, rows)], stroke, 6.0, 4.0)); }
+        for r in 0..=rows { painter.extend(egui::Shape::dashed_line(&[at(0, r), at(cols, r)], stroke, 6.0, 4.0)); }
+    }
+}
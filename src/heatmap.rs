@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+use eframe::egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::trf;
+use crate::models::BuildingType;
+
+// 🔥 占用热力图：统计每个格子在整个策略中先后被多少座建筑占用（迁移过的建筑在经过的每个位置各计一次），
+// 用于发现过度拥挤的位置和从未使用过的可建造空地
+
+// 计数从少到多：蓝 → 黄 → 红
+fn heat_color(t: f32) -> Color32 {
+    let lerp = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    let (from, to, t) = if t < 0.5 { ([40, 120, 255], [255, 230, 0], t * 2.0) } else { ([255, 230, 0], [255, 30, 0], t * 2.0 - 1.0) };
+    Color32::from_rgba_unmultiplied(lerp(from[0], to[0], t), lerp(from[1], to[1], t), lerp(from[2], to[2], t), 150)
+}
+
+impl MapEditor {
+    // 行优先的计数表，长度为 grid_rows * grid_cols
+    pub(crate) fn occupancy_counts(&self) -> Vec<u32> {
+        let (rows, cols) = (self.grid_rows, self.grid_cols);
+        let mut counts = vec![0u32; rows * cols];
+        for b in &self.placed_buildings {
+            let mut cells = HashSet::new();
+            for (_, _, x, y) in self.building_segments(b) {
+                for r in y..(y + b.height).min(rows) {
+                    for c in x..(x + b.width).min(cols) { cells.insert(r * cols + c); }
+                }
+            }
+            for i in cells { counts[i] += 1; }
+        }
+        counts
+    }
+
+    pub(crate) fn draw_heatmap(&self, painter: &Painter, panel_rect: Rect, origin: Pos2, cell: Vec2) {
+        if !self.show_heatmap { return; }
+        let counts = self.occupancy_counts();
+        let max = counts.iter().copied().max().unwrap_or(0).max(1);
+        let floor = self.layers_data.get(&self.current_major_z).map(|l| l.get_grid(BuildingType::Floor));
+        let buildable = |r: usize, c: usize| floor.filter(|g| !g.is_empty()).is_some_and(|g| {
+            let v = g.get(r, c);
+            self.terrain_types.iter().any(|t| t.id == v && t.buildable)
+        });
+        let show_numbers = cell.x.min(cell.y) >= 16.0;
+        let mut idle = 0;
+        for r in 0..self.grid_rows {
+            for c in 0..self.grid_cols {
+                let count = counts[r * self.grid_cols + c];
+                if count == 0 && !buildable(r, c) { continue; }
+                if count == 0 { idle += 1; }
+                let rect = Rect::from_min_size(origin + Vec2::new(c as f32 * cell.x, r as f32 * cell.y), cell);
+                if !panel_rect.intersects(rect) { continue; }
+                // 从未使用的可建造格压暗，其余按占用次数着色
+                if count == 0 { painter.rect_filled(rect, 0.0, Color32::from_black_alpha(110)); continue; }
+                painter.rect_filled(rect, 0.0, heat_color((count - 1) as f32 / (max - 1).max(1) as f32));
+                if show_numbers { painter.text(rect.center(), Align2::CENTER_CENTER, count.to_string(), FontId::monospace(11.0), Color32::BLACK); }
+            }
+        }
+
+        // 图例：左下角的色带与统计
+        let inset = self.ruler_inset();
+        let bar = Rect::from_min_size(Pos2::new(panel_rect.min.x + 10.0 + inset, panel_rect.max.y - 40.0), Vec2::new(120.0, 10.0));
+        let steps = 24;
+        let label = trf!("1 → {} 座建筑 · 空置可建格 {}", max, idle);
+        let galley = painter.layout_no_wrap(label, FontId::proportional(13.0), Color32::WHITE);
+        let text_pos = bar.left_bottom() + Vec2::new(0.0, 4.0);
+        painter.rect_filled(Rect::from_min_size(text_pos, galley.size()).expand(3.0).union(bar.expand(3.0)), 3.0, Color32::from_black_alpha(170));
+        for i in 0..steps {
+            let x0 = bar.min.x + bar.width() * i as f32 / steps as f32;
+            painter.rect_filled(Rect::from_min_max(Pos2::new(x0, bar.min.y), Pos2::new(x0 + bar.width() / steps as f32 + 0.5, bar.max.y)), 0.0, heat_color(i as f32 / (steps - 1) as f32).to_opaque());
+        }
+        painter.galley(text_pos, galley, Color32::WHITE);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use eframe::egui::{Rect, TextureHandle};
+
+use crate::models::*;
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+// 可撤销的编辑状态快照（只包含会被导出的数据，不含视图状态）
+// 地形等各标签页共用的部分有一份共用的撤销历史，策略部分的撤销历史随标签页保存，
+// 这样在一个标签页撤销策略时不会把之后在其他标签页做的地形修改一起撤掉
+
+// 各标签页共用的数据
+#[derive(Clone, PartialEq)]
+pub struct SharedSnapshot {
+    pub grid_rows: usize,
+    pub grid_cols: usize,
+    pub grid_width: f32,
+    pub grid_height: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub layers_data: HashMap<i32, LayerData>,
+    pub building_configs: Vec<BuildingConfig>,
+    pub building_config_icons: Vec<Option<TextureHandle>>,
+    pub prep_actions: Vec<PrepAction>,
+    pub camera_keyframes: Vec<CameraKeyframe>,
+    pub viewport_presets: Vec<ViewportPreset>,
+    pub viewport_safe_areas: Vec<Rect>,
+    pub enemy_routes: Vec<EnemyRoute>,
+}
+
+// 当前标签页的策略
+#[derive(Clone, PartialEq)]
+pub struct StrategySnapshot {
+    pub placed_buildings: Vec<PlacedBuilding>,
+    pub next_uid: usize,
+    pub upgrade_events: Vec<UpgradeEvent>,
+    pub demolish_events: Vec<DemolishEvent>,
+    pub sell_events: Vec<SellEvent>,
+    pub relocate_events: Vec<RelocateEvent>,
+    pub ability_events: Vec<AbilityEvent>,
+    pub wave_notes: HashMap<i32, String>,
+    pub locked_waves: BTreeSet<i32>,
+    pub economy: Option<EconomyConfig>,
+}
+
+// 共用撤销历史中的一条：整图变换、调整尺寸这类同时改动所有标签页策略的操作，另外记下各标签页（按标签页 id）变换前的策略，撤销时一起恢复
+pub struct SharedEntry {
+    pub snapshot: SharedSnapshot,
+    pub strategies: Vec<(usize, MapBuildingsExport)>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct EditSnapshot {
+    pub shared: SharedSnapshot,
+    pub strategy: StrategySnapshot,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum HistoryRequest { Undo, Redo }
+
+// 每条历史带一个全局递增的序号：撤销时在共用历史与当前标签页的历史中取序号最大的一条，
+// 同一帧同时改了两部分时两边序号相同，一起撤销
+pub struct History<T> {
+    undo_stack: VecDeque<(u64, T)>,
+    redo_stack: Vec<(u64, T)>,
+    pub max_depth: usize,
+    // 一次连续操作（按住鼠标拖动、文本输入）只记录一条历史
+    in_gesture: bool,
+}
+
+impl<T> History<T> {
+    pub fn new(max_depth: usize) -> Self {
+        Self { undo_stack: VecDeque::new(), redo_stack: Vec::new(), max_depth, in_gesture: false }
+    }
+
+    // 每帧结束时调用：若本帧数据发生变化且不处于同一次连续操作中，则把帧开始时的状态以 seq 压栈，返回是否压栈
+    pub fn observe(&mut self, seq: u64, before: T, changed: bool, gesture_active: bool) -> bool {
+        let pushed = changed && !self.in_gesture;
+        if pushed {
+            self.undo_stack.push_back((seq, before));
+            self.redo_stack.clear();
+            self.trim();
+            self.in_gesture = true;
+        }
+        if !gesture_active { self.in_gesture = false; }
+        pushed
+    }
+
+    pub fn undo_seq(&self) -> Option<u64> { self.undo_stack.back().map(|(seq, _)| *seq) }
+    pub fn redo_seq(&self) -> Option<u64> { self.redo_stack.last().map(|(seq, _)| *seq) }
+
+    // current 按要恢复的那一条生成当前状态，存入反向的栈
+    pub fn undo(&mut self, current: impl FnOnce(&T) -> T) -> Option<T> {
+        let (seq, prev) = self.undo_stack.pop_back()?;
+        self.redo_stack.push((seq, current(&prev)));
+        self.in_gesture = false;
+        Some(prev)
+    }
+
+    pub fn redo(&mut self, current: impl FnOnce(&T) -> T) -> Option<T> {
+        let (seq, next) = self.redo_stack.pop()?;
+        self.undo_stack.push_back((seq, current(&next)));
+        self.trim();
+        self.in_gesture = false;
+        Some(next)
+    }
+
+    // 不论是否处于连续操作中都单独记录一条
+    pub fn push(&mut self, seq: u64, before: T) {
+        self.undo_stack.push_back((seq, before));
+        self.redo_stack.clear();
+        self.trim();
+    }
+
+    // 另一份历史记录了新的编辑，这里的重做不再有效
+    pub fn clear_redo(&mut self) { self.redo_stack.clear(); }
+
+    pub fn can_undo(&self) -> bool { !self.undo_stack.is_empty() }
+    pub fn can_redo(&self) -> bool { !self.redo_stack.is_empty() }
+
+    pub fn trim(&mut self) {
+        while self.undo_stack.len() > self.max_depth { self.undo_stack.pop_front(); }
+    }
+}
@@ -0,0 +1,238 @@
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use eframe::egui;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::get_time_value;
+
+// 🔥 本地 HTTP 接口：供外部自动化脚本与执行端实时查询、修改编辑器中的当前策略
+// 只监听 127.0.0.1，并且只接受 Host 为本机地址、不带外部网页 Origin 的请求（防止网页跨站提交与 DNS 重绑定）；请求在后台线程接收，转交界面线程在下一帧处理（与手动编辑一样可撤销），处理结果再回传给后台线程响应
+//   GET  /api/state                 当前地图、时间与网格尺寸
+//   GET  /api/strategy              与导出文件相同的完整策略
+//   GET  /api/buildings[?active]    建筑列表；带 active 时只返回当前时间存在的建筑
+//   PUT  /api/wave                  {"wave_num": 3, "is_late": false} 设置当前时间
+//   POST /api/events                {"type": "demolish", "uid": 1001, "wave_num": 5} 添加事件，省略时间时取当前时间
+
+pub(crate) const DEFAULT_PORT: u16 = 8765;
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+// 请求体上限，事件请求远小于此
+const MAX_BODY: u64 = 64 * 1024;
+
+struct ApiCall {
+    method: Method,
+    url: String,
+    body: String,
+    reply: Sender<(u16, String)>,
+}
+
+pub(crate) struct HttpApi {
+    server: Arc<Server>,
+    calls: Receiver<ApiCall>,
+    pub(crate) addr: String,
+}
+
+fn error_json(message: impl Into<String>) -> String {
+    json!({ "error": message.into() }).to_string()
+}
+
+fn local_addrs(port: u16) -> [String; 2] {
+    [format!("127.0.0.1:{}", port), format!("localhost:{}", port)]
+}
+
+// Host 头是本机地址：经 DNS 重绑定指向 127.0.0.1 的外部域名会带上自己的域名
+pub(crate) fn is_local_host(host: &str, port: u16) -> bool {
+    local_addrs(port).iter().any(|a| host.eq_ignore_ascii_case(a))
+}
+
+// 浏览器中的网页发起请求时带 Origin；脚本与执行端一般不带
+pub(crate) fn is_local_origin(origin: &str, port: u16) -> bool {
+    local_addrs(port).iter().any(|a| origin.eq_ignore_ascii_case(&format!("http://{}", a)))
+}
+
+fn is_local_request(request: &Request, port: u16) -> bool {
+    let header = |name: &'static str| request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str());
+    header("Host").is_some_and(|h| is_local_host(h, port)) && header("Origin").is_none_or(|o| is_local_origin(o, port))
+}
+
+impl HttpApi {
+    pub(crate) fn start(port: u16, ctx: egui::Context) -> Result<Self, String> {
+        let addr = format!("127.0.0.1:{}", port);
+        let server = Arc::new(Server::http(&addr).map_err(|e| format!("无法监听 {}: {}", addr, e))?);
+        let (sender, calls) = mpsc::channel();
+        let worker = Arc::clone(&server);
+        thread::spawn(move || {
+            for mut request in worker.incoming_requests() {
+                let mut body = String::new();
+                // 外部请求不读请求体
+                let read = if is_local_request(&request, port) { Some(request.as_reader().take(MAX_BODY + 1).read_to_string(&mut body)) } else { None };
+                let (status, content) = match read {
+                    None => (403, error_json("只接受本机发起的请求")),
+                    Some(Err(e)) => (400, error_json(format!("读取请求失败: {}", e))),
+                    Some(Ok(n)) if n as u64 > MAX_BODY => (413, error_json(format!("请求体超过 {} 字节", MAX_BODY))),
+                    Some(Ok(_)) => {
+                        let (reply, response) = mpsc::channel();
+                        if sender.send(ApiCall { method: request.method().clone(), url: request.url().to_string(), body, reply }).is_err() { break; }
+                        ctx.request_repaint();
+                        response.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(|_| (503, error_json("编辑器未响应")))
+                    }
+                };
+                let header = Header::from_bytes("Content-Type", "application/json; charset=utf-8").expect("静态响应头");
+                let _ = request.respond(Response::from_string(content).with_status_code(status).with_header(header));
+            }
+        });
+        Ok(Self { server, calls, addr })
+    }
+}
+
+// 关闭接口时让后台线程的 incoming_requests 结束
+impl Drop for HttpApi {
+    fn drop(&mut self) { self.server.unblock(); }
+}
+
+#[derive(Deserialize)]
+struct WaveRequest {
+    wave_num: i32,
+    #[serde(default)]
+    is_late: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventRequest {
+    Demolish { uid: usize },
+    Sell { uid: usize },
+    Relocate { uid: usize, to_x: usize, to_y: usize },
+    Upgrade { building_name: String, #[serde(default)] uid: Option<usize> },
+    Ability { name: String, #[serde(default)] target: Option<(usize, usize)> },
+}
+
+#[derive(Deserialize)]
+struct TimedEvent {
+    #[serde(flatten)]
+    event: EventRequest,
+    wave_num: Option<i32>,
+    is_late: Option<bool>,
+}
+
+impl MapEditor {
+    // 每帧处理后台线程转来的请求
+    pub(crate) fn poll_http_api(&mut self) {
+        let Some(api) = &self.http_api else { return; };
+        let calls: Vec<ApiCall> = api.calls.try_iter().collect();
+        for call in calls {
+            let path = call.url.split('?').next().unwrap_or_default();
+            let query = call.url.split_once('?').map_or("", |(_, q)| q);
+            let result = self.handle_api_call(&call.method, path, query, &call.body);
+            let _ = call.reply.send(match result {
+                Ok((status, value)) => (status, value.to_string()),
+                Err((status, message)) => (status, error_json(message)),
+            });
+        }
+    }
+
+    fn handle_api_call(&mut self, method: &Method, path: &str, query: &str, body: &str) -> Result<(u16, Value), (u16, String)> {
+        match (method, path) {
+            (Method::Get, "/api/state") => Ok((200, json!({
+                "map_name": self.export_map_name(),
+                "wave_num": self.current_wave_num,
+                "is_late": self.current_is_late,
+                "rows": self.grid_rows,
+                "cols": self.grid_cols,
+                "buildings": self.placed_buildings.len(),
+            }))),
+            (Method::Get, "/api/strategy") => serde_json::to_value(self.build_buildings_export()).map(|v| (200, v)).map_err(|e| (500, e.to_string())),
+            (Method::Get, "/api/buildings") => {
+                let t = get_time_value(self.current_wave_num, self.current_is_late);
+                let active_only = query.split('&').any(|p| p == "active" || p == "active=true" || p == "active=1");
+                let buildings: Vec<BuildingExport> = self.build_buildings_export().buildings.into_iter()
+                    .filter(|b| !active_only || (get_time_value(b.wave_num, b.is_late) <= t && t < self.get_building_demolish_time(b.uid)))
+                    .collect();
+                serde_json::to_value(buildings).map(|v| (200, v)).map_err(|e| (500, e.to_string()))
+            }
+            (Method::Put, "/api/wave") => {
+                let req: WaveRequest = serde_json::from_str(body).map_err(|e| (400, e.to_string()))?;
+                if req.wave_num < 1 { return Err((400, "wave_num 必须不小于 1".into())); }
+                (self.current_wave_num, self.current_is_late) = (req.wave_num, req.is_late);
+                Ok((200, json!({ "wave_num": req.wave_num, "is_late": req.is_late })))
+            }
+            (Method::Post, "/api/events") => {
+                let req: TimedEvent = serde_json::from_str(body).map_err(|e| (400, e.to_string()))?;
+                let (wave_num, is_late) = (req.wave_num.unwrap_or(self.current_wave_num), req.is_late.unwrap_or(self.current_is_late));
+                if wave_num < 1 { return Err((400, "wave_num 必须不小于 1".into())); }
+                self.add_api_event(req.event, wave_num, is_late).map_err(|e| (409, e))?;
+                Ok((201, json!({ "wave_num": wave_num, "is_late": is_late })))
+            }
+            (_, "/api/state" | "/api/strategy" | "/api/buildings" | "/api/wave" | "/api/events") => Err((405, format!("{} 不支持 {}", path, method))),
+            _ => Err((404, format!("未知的接口 {}", path))),
+        }
+    }
+
+    // 复用界面上添加事件的逻辑：临时切到事件时间再调用，之后恢复当前时间
+    fn add_api_event(&mut self, event: EventRequest, wave_num: i32, is_late: bool) -> Result<(), String> {
+        // 界面上锁定波次的按钮不可用，这里同样拒绝，而不是静默忽略
+        if self.wave_locked(wave_num) { return Err(format!("第 {} 波已锁定", wave_num)); }
+        let exists = |uid: usize| self.placed_buildings.iter().any(|b| b.uid == uid);
+        match &event {
+            EventRequest::Demolish { uid } | EventRequest::Sell { uid } | EventRequest::Relocate { uid, .. } if !exists(*uid) => return Err(format!("找不到建筑 UID {}", uid)),
+            EventRequest::Demolish { uid } | EventRequest::Sell { uid } if self.get_building_demolish_time(*uid) != i32::MAX => return Err(format!("建筑 UID {} 已安排拆除或出售", uid)),
+            EventRequest::Upgrade { building_name, .. } if !self.building_templates.iter().any(|t| &t.name == building_name) => return Err(format!("未知的建筑模板 {}", building_name)),
+            EventRequest::Upgrade { building_name, uid: Some(uid) } if !self.placed_buildings.iter().any(|b| b.uid == *uid && &b.template_name == building_name) => return Err(format!("找不到模板为 {} 的建筑 UID {}", building_name, uid)),
+            EventRequest::Ability { name, .. } if name.trim().is_empty() => return Err("技能名称不能为空".into()),
+            EventRequest::Ability { target: Some((x, y)), .. } if *x >= self.grid_cols || *y >= self.grid_rows => return Err(format!("目标格子 ({}, {}) 超出网格", x, y)),
+            _ => {}
+        }
+        // 拆除与出售只能在建成之后
+        if let EventRequest::Demolish { uid } | EventRequest::Sell { uid } = &event {
+            if let Some(b) = self.placed_buildings.iter().find(|b| b.uid == *uid) {
+                if get_time_value(wave_num, is_late) <= get_time_value(b.wave_num, b.is_late) { return Err(format!("建筑 UID {} 在该时间尚未建成", b.uid)); }
+            }
+        }
+        // 与界面上一样：单体升级时建筑必须已建成且尚未移除，已达最高等级的不能再升级
+        if let EventRequest::Upgrade { building_name, uid } = &event {
+            let t = get_time_value(wave_num, is_late);
+            if let Some(b) = uid.and_then(|uid| self.placed_buildings.iter().find(|b| b.uid == uid)) {
+                if t < get_time_value(b.wave_num, b.is_late) || t >= self.get_building_demolish_time(b.uid) { return Err(format!("建筑 UID {} 在该时间不存在", b.uid)); }
+            }
+            let max_tier = self.building_configs.iter().find(|c| &c.name == building_name).map_or(0, |c| c.upgrades.len());
+            if max_tier > 0 && self.planned_upgrades(building_name, *uid) >= max_tier { return Err(format!("{} 已规划 {} 次升级，达到最高等级", building_name, max_tier)); }
+        }
+        // 以上检查通过后，拆除与出售只会因之后的迁移位于锁定波次而失败
+        let failure = if matches!(event, EventRequest::Relocate { .. }) { "该时间无法迁移到目标位置" } else { "之后的迁移位于已锁定的波次" };
+        let saved = (self.current_wave_num, self.current_is_late);
+        (self.current_wave_num, self.current_is_late) = (wave_num, is_late);
+        let added = match event {
+            EventRequest::Demolish { uid } => self.add_demolish_event(uid),
+            EventRequest::Sell { uid } => self.add_sell_event(uid),
+            EventRequest::Relocate { uid, to_x, to_y } => self.add_relocate_event(uid, to_x, to_y),
+            EventRequest::Upgrade { building_name, uid } => { self.upgrade_events.push(UpgradeEvent { building_name, wave_num, is_late, uid, offset_seconds: None, order: None }); true }
+            EventRequest::Ability { name, target } => { self.ability_events.push(AbilityEvent { name: name.trim().to_string(), wave_num, is_late, target, order: None }); true }
+        };
+        (self.current_wave_num, self.current_is_late) = saved;
+        if added { Ok(()) } else { Err(failure.into()) }
+    }
+
+    pub(crate) fn show_http_api_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let mut enabled = self.http_api.is_some();
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut enabled, tr("HTTP 接口")).on_hover_text(tr("供外部脚本与执行端通过 http://127.0.0.1 实时读写当前策略")).changed() {
+                self.http_api = None;
+                if enabled {
+                    match HttpApi::start(self.http_api_port, ctx.clone()) {
+                        Ok(api) => { self.notifications.info(trf!("HTTP 接口已启动: http://{}", api.addr)); self.http_api = Some(api); }
+                        Err(e) => self.notifications.error(e),
+                    }
+                }
+            }
+            ui.add_enabled(self.http_api.is_none(), egui::DragValue::new(&mut self.http_api_port).clamp_range(1024..=65535).prefix(tr("端口 ")));
+        });
+        if let Some(api) = &self.http_api { ui.weak(trf!("监听 http://{}/api/", api.addr)); }
+    }
+}
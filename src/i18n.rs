@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+use serde::Deserialize;
+
+use crate::storage::read_json;
+
+// 🔥 界面多语言：以中文原文为键查 lang/<语言代码>.json，缺失的条目直接显示中文原文
+// 只翻译界面文字；数据文件内容与校验/读写错误信息（GUI 与命令行共用）保持中文
+
+pub const LANG_DIR: &str = "lang";
+pub const LANG_ENV: &str = "MINKE_LANG";
+// 源码中的文字即为该语言，没有对应的翻译表文件时直接显示原文
+pub const SOURCE_LANGUAGE: &str = "zh-CN";
+
+#[derive(Deserialize)]
+struct LanguageFile {
+    name: String,
+    #[serde(default)]
+    strings: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+}
+
+// 切换语言时泄漏旧表换取 &'static str，切换次数有限，可以接受
+static TABLE: RwLock<Option<&'static HashMap<String, String>>> = RwLock::new(None);
+
+pub fn tr(key: &'static str) -> &'static str {
+    let table = *TABLE.read().unwrap_or_else(|e| e.into_inner());
+    table.and_then(|t| t.get(key)).map_or(key, String::as_str)
+}
+
+// 依次用参数替换翻译后模板中的 {}，供 trf! 使用
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut parts = template.split("{}");
+    out.push_str(parts.next().unwrap_or_default());
+    for (i, part) in parts.enumerate() {
+        match args.get(i) { Some(arg) => out.push_str(&arg.to_string()), None => out.push_str("{}") }
+        out.push_str(part);
+    }
+    out
+}
+
+// 翻译版 format!：模板只支持 {}，需要格式说明符的参数请先自行格式化
+macro_rules! trf {
+    ($key:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::fill($crate::i18n::tr($key), &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
+pub(crate) use trf;
+
+fn read_language(path: &Path) -> Result<LanguageFile, String> {
+    read_json(path)
+}
+
+// lang/ 下的所有 *.json，源语言即使没有文件也始终可选
+pub fn available_languages(dir: impl AsRef<Path>) -> Vec<Language> {
+    let mut languages = vec![Language { code: SOURCE_LANGUAGE.into(), name: "简体中文".into() }];
+    let Ok(entries) = fs::read_dir(dir) else { return languages; };
+    let mut found: Vec<Language> = entries.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| {
+            let code = p.file_stem()?.to_string_lossy().into_owned();
+            read_language(&p).ok().map(|f| Language { code, name: f.name })
+        })
+        .collect();
+    found.sort_by(|a, b| a.code.cmp(&b.code));
+    languages.retain(|l| !found.iter().any(|f| f.code == l.code));
+    languages.splice(0..0, found);
+    languages
+}
+
+pub fn set_language(dir: impl AsRef<Path>, code: &str) -> Result<(), String> {
+    let path = dir.as_ref().join(format!("{}.json", code));
+    let table = if code == SOURCE_LANGUAGE && !path.exists() { None } else {
+        Some(&*Box::leak(Box::new(read_language(&path)?.strings)))
+    };
+    *TABLE.write().unwrap_or_else(|e| e.into_inner()) = table;
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+use eframe::egui::{self, Color32};
+use rfd::FileDialog;
+use serde_json::{json, Map, Value};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::{MapTerrainExport, FORMAT_VERSION};
+use crate::storage::{read_data, write_data, DataFormat, DATA_EXTENSIONS};
+
+// 🔥 旧版单网格地形导入：main.rs 时代的文件只有一张 elevation_grid（有的甚至没有 layers，网格直接放在顶层）和单一的 grid_pixel_size，
+// 缺少的 meta 字段过去在读取时被悄悄补上默认值。这里逐项转换，把转换了什么、哪些值是补出来的分别列出，并立即以当前格式另存一份
+
+#[derive(Default)]
+pub struct LegacyReport {
+    pub converted: Vec<String>,
+    // 文件中没有、按默认值补出来的字段，需要人工核对
+    pub defaults: Vec<String>,
+}
+
+// 没有 format_version，且带有 elevation_grid 或 grid_pixel_size 的地形文件
+pub fn is_legacy_terrain(value: &Value) -> bool {
+    if value.get("format_version").is_some() { return false; }
+    let layer_grid = value.get("layers").and_then(Value::as_array).is_some_and(|layers| layers.iter().any(|l| l.get("elevation_grid").is_some()));
+    value.get("elevation_grid").is_some() || layer_grid || value.pointer("/meta/grid_pixel_size").is_some()
+}
+
+// 网格行长度不一时按最长的一行补 -1（障碍）
+fn pad_rows(grid: &mut Value) -> Option<(usize, usize)> {
+    let rows = grid.as_array_mut()?;
+    let cols = rows.iter().filter_map(Value::as_array).map(Vec::len).max().unwrap_or(0);
+    let mut padded = false;
+    for row in rows.iter_mut().filter_map(Value::as_array_mut) {
+        if row.len() < cols { row.resize(cols, json!(-1)); padded = true; }
+    }
+    padded.then_some((rows.len(), cols))
+}
+
+fn insert_default(obj: &mut Map<String, Value>, key: &str, value: Value, path: &str, defaults: &mut Vec<String>) {
+    if obj.contains_key(key) { return; }
+    defaults.push(format!("{}{} = {}", path, key, value));
+    obj.insert(key.into(), value);
+}
+
+pub fn convert_legacy_terrain(mut value: Value, fallback_name: &str) -> Result<(MapTerrainExport, LegacyReport), String> {
+    let mut report = LegacyReport::default();
+    let obj = value.as_object_mut().ok_or("不是地形文件：顶层不是对象")?;
+    if !obj.contains_key("layers") {
+        let grid = obj.remove("elevation_grid").ok_or("不是地形文件：既没有 layers 也没有 elevation_grid")?;
+        obj.insert("layers".into(), json!([{ "major_z": 0, "elevation_grid": grid }]));
+        report.converted.push("顶层的 elevation_grid 转为第 0 层".into());
+    }
+    insert_default(obj, "map_name", json!(fallback_name), "", &mut report.defaults);
+
+    let meta = obj.entry("meta").or_insert_with(|| json!({}));
+    let meta = meta.as_object_mut().ok_or("meta 不是对象")?;
+    match meta.remove("grid_pixel_size") {
+        Some(size) => {
+            for key in ["grid_pixel_width", "grid_pixel_height"] {
+                if !meta.contains_key(key) { meta.insert(key.into(), size.clone()); }
+            }
+            report.converted.push(format!("meta.grid_pixel_size = {} 拆分为 grid_pixel_width / grid_pixel_height", size));
+        }
+        None => for key in ["grid_pixel_width", "grid_pixel_height"] { insert_default(meta, key, json!(32.0), "meta.", &mut report.defaults); },
+    }
+    for key in ["offset_x", "offset_y", "bottom", "right"] { insert_default(meta, key, json!(0.0), "meta.", &mut report.defaults); }
+    for key in ["camera_speed_up", "camera_speed_down", "camera_speed_left", "camera_speed_right"] { insert_default(meta, key, json!(1.0), "meta.", &mut report.defaults); }
+
+    let layers = obj.get_mut("layers").and_then(Value::as_array_mut).ok_or("layers 不是数组")?;
+    for (i, layer) in layers.iter_mut().enumerate() {
+        let layer = layer.as_object_mut().ok_or_else(|| format!("layers[{}] 不是对象", i))?;
+        insert_default(layer, "major_z", json!(i), &format!("layers[{}].", i), &mut report.defaults);
+        let z = layer["major_z"].clone();
+        insert_default(layer, "name", json!(format!("Major_Layer_{}", z)), &format!("layers[{}].", i), &mut report.defaults);
+        if let Some(grid) = layer.remove("elevation_grid") {
+            if layer.get("floor_grid").and_then(Value::as_array).is_none_or(|g| g.is_empty()) {
+                layer.insert("floor_grid".into(), grid);
+                report.converted.push(format!("层 {}: elevation_grid 转为 floor_grid", z));
+            } else {
+                report.converted.push(format!("层 {}: 已有 floor_grid，忽略 elevation_grid", z));
+            }
+        }
+        if let Some((rows, cols)) = layer.get_mut("floor_grid").and_then(pad_rows) {
+            report.defaults.push(format!("层 {}: 地面网格各行长度不一，按 {}x{} 用障碍 (-1) 补齐", z, cols, rows));
+        }
+        for key in ["wall_grid", "ceiling_grid"] {
+            if layer.get(key).and_then(Value::as_array).is_none_or(|g| g.is_empty()) { report.defaults.push(format!("层 {}: {} 缺失，填充为空 (-1)", z, key)); }
+        }
+    }
+    obj.insert("format_version".into(), json!(FORMAT_VERSION));
+    let mut terrain: MapTerrainExport = serde_json::from_value(value).map_err(|e| format!("转换后仍无法解析: {}", e))?;
+    // 墙壁 / 吊顶按地面网格的尺寸补齐
+    terrain.normalize();
+    Ok((terrain, report))
+}
+
+// 另存的路径：与原文件同目录，文件名加 _v<当前版本>
+pub fn converted_path(input: &Path) -> PathBuf {
+    input.with_file_name(format!("{}_v{}.{}", input.file_stem().unwrap_or_default().to_string_lossy(), FORMAT_VERSION, DataFormat::from_path(input).extension()))
+}
+
+pub(crate) struct LegacyImportDialog {
+    source: PathBuf,
+    saved: Result<PathBuf, String>,
+    report: LegacyReport,
+}
+
+impl MapEditor {
+    pub(crate) fn import_legacy_terrain(&mut self) {
+        let Some(path) = FileDialog::new().set_directory("maps").add_filter(tr("旧版地形文件"), DATA_EXTENSIONS).pick_file() else { return; };
+        let value: Value = match read_data(&path) {
+            Ok(value) => value,
+            Err(e) => { self.notifications.error(e); return; }
+        };
+        if !is_legacy_terrain(&value) { self.notifications.warn(trf!("{} 不是旧版地形文件，按普通地形导入即可", path.display())); return; }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let (terrain, report) = match convert_legacy_terrain(value, &stem) {
+            Ok(converted) => converted,
+            Err(e) => { self.notifications.error(format!("{}: {}", path.display(), e)); return; }
+        };
+        let out = converted_path(&path);
+        let saved = write_data(&out, &terrain).map(|()| out);
+        self.apply_terrain_data(terrain);
+        self.legacy_import_dialog = Some(LegacyImportDialog { source: path, saved, report });
+    }
+
+    pub(crate) fn show_legacy_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.legacy_import_dialog.take() else { return; };
+        let (mut open, mut close) = (true, false);
+        egui::Window::new(tr("旧版地形转换报告")).id(egui::Id::new("legacy_import")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(trf!("已导入 {}", dialog.source.display()));
+            match &dialog.saved {
+                Ok(path) => { ui.colored_label(Color32::GREEN, trf!("✔ 已按当前格式另存为 {}", path.display())); }
+                Err(e) => { ui.colored_label(Color32::RED, trf!("✖ 另存失败: {}", e)); }
+            }
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                ui.strong(trf!("转换 ({})", dialog.report.converted.len()));
+                for line in &dialog.report.converted { ui.label(format!("• {}", line)); }
+                ui.strong(trf!("补出的默认值 ({})", dialog.report.defaults.len()));
+                if dialog.report.defaults.is_empty() { ui.weak(tr("无")); }
+                for line in &dialog.report.defaults { ui.colored_label(Color32::YELLOW, format!("• {}", line)); }
+            });
+            if !dialog.report.defaults.is_empty() { ui.weak(tr("默认值不一定符合原地图，请在地形模式中核对格子尺寸、偏移与镜头速度")); }
+            close = ui.button(tr("关闭")).clicked();
+        });
+        if open && !close { self.legacy_import_dialog = Some(dialog); }
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::ErrorKind;
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use eframe::egui::{self, Color32};
+use serde::Serialize;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+use crate::app::MapEditor;
+use crate::http_api::{is_local_host, is_local_origin};
+use crate::i18n::{tr, trf};
+use crate::models::*;
+
+// 🔥 实时同步：编辑器作为 WebSocket 服务端（只监听 127.0.0.1），执行端连上后每次会影响导出数据的修改都立即推送过去，
+// 省去「导出 → 复制 → 重新加载」的循环。推送内容与三个导出文件相同，打包成一条 JSON 文本消息；
+// 新连接会先收到当前的完整数据。拖动等连续操作期间按 PUSH_INTERVAL 节流，松手后再推送最终结果。
+// 握手时只接受 Host 为本机地址、不带外部网页 Origin 的连接；推送由单独的发送线程完成，界面线程不做阻塞写入；
+// 执行端发来的帧（pong、Close）由发送线程每轮读掉，收到 Close 后回应并断开
+
+pub(crate) const DEFAULT_PORT: u16 = 8766;
+const PUSH_INTERVAL: Duration = Duration::from_millis(250);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+// 没有修改时定期发送 ping，及时发现已断开的连接
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct SyncMessage<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    terrain: &'a MapTerrainExport,
+    strategy: &'a MapBuildingsExport,
+    building_configs: &'a [BuildingConfig],
+}
+
+type Clients = Arc<Mutex<Vec<WebSocket<TcpStream>>>>;
+
+enum Outgoing { Update(String), Ping }
+
+pub(crate) struct LiveSync {
+    addr: String,
+    clients: Clients,
+    connected: Arc<AtomicUsize>,
+    outgoing: Sender<Outgoing>,
+    stopped: Arc<AtomicBool>,
+    dirty: bool,
+    last_push: Option<Instant>,
+}
+
+fn send_to(ws: &mut WebSocket<TcpStream>, payload: &str) -> bool {
+    ws.send(Message::Text(payload.to_string())).is_ok()
+}
+
+// 拒绝其他网页（Origin）或经 DNS 重绑定（Host）发起的连接，否则任何网站都能订阅工程数据；返回类型由 tungstenite 规定
+#[allow(clippy::result_large_err)]
+fn check_origin(port: u16) -> impl FnOnce(&Request, Response) -> Result<Response, ErrorResponse> {
+    move |request, response| {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+        if header("Host").is_some_and(|h| is_local_host(h, port)) && header("Origin").is_none_or(|o| is_local_origin(o, port)) { return Ok(response); }
+        let mut reject = ErrorResponse::new(Some("只接受本机发起的连接".into()));
+        *reject.status_mut() = StatusCode::FORBIDDEN;
+        Err(reject)
+    }
+}
+
+// 读掉执行端发来的所有帧，收到 Close 或连接出错时返回 false；读取期间临时切换为非阻塞，发送仍按写入超时阻塞
+fn drain_incoming(ws: &mut WebSocket<TcpStream>) -> bool {
+    if ws.get_mut().set_nonblocking(true).is_err() { return false; }
+    let open = loop {
+        match ws.read() {
+            Ok(Message::Close(_)) => break false,
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => break true,
+            Err(_) => break false,
+        }
+    };
+    if ws.get_mut().set_nonblocking(false).is_err() { return false; }
+    // 把自动排队的 Close 回应发出去
+    if !open { let _ = ws.flush(); }
+    open
+}
+
+// 发送线程：积压的推送只发最新的一条；发送期间不持有连接列表的锁，新连接照常加入。发送失败（对方已断开或写入超时）的连接直接移除
+fn run_sender(outgoing: Receiver<Outgoing>, clients: Clients, latest: Arc<Mutex<Option<String>>>, connected: Arc<AtomicUsize>) {
+    while let Ok(first) = outgoing.recv() {
+        let message = match outgoing.try_iter().fold(first, |acc, next| if matches!(next, Outgoing::Ping) { acc } else { next }) {
+            Outgoing::Update(payload) => { *latest.lock().unwrap() = Some(payload.clone()); Message::Text(payload) }
+            Outgoing::Ping => Message::Ping(Vec::new()),
+        };
+        let mut sending = mem::take(&mut *clients.lock().unwrap());
+        sending.retain_mut(|ws| drain_incoming(ws) && ws.send(message.clone()).is_ok());
+        let mut clients = clients.lock().unwrap();
+        clients.extend(sending);
+        connected.store(clients.len(), Ordering::Relaxed);
+    }
+}
+
+impl LiveSync {
+    pub(crate) fn start(port: u16, ctx: egui::Context) -> Result<Self, String> {
+        let addr = format!("127.0.0.1:{}", port);
+        let listener = TcpListener::bind(&addr).map_err(|e| format!("无法监听 {}: {}", addr, e))?;
+        let (outgoing, receiver) = mpsc::channel();
+        let sync = LiveSync { addr, clients: Clients::default(), connected: Arc::default(), outgoing, stopped: Arc::default(), dirty: true, last_push: None };
+        // 最近一次推送的内容，新连接握手后立即发送
+        let latest: Arc<Mutex<Option<String>>> = Arc::default();
+        let (clients, connected, stopped) = (Arc::clone(&sync.clients), Arc::clone(&sync.connected), Arc::clone(&sync.stopped));
+        {
+            let (clients, latest, connected) = (Arc::clone(&clients), Arc::clone(&latest), Arc::clone(&connected));
+            thread::spawn(move || run_sender(receiver, clients, latest, connected));
+        }
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::Relaxed) { break; }
+                let Ok(stream) = stream else { continue; };
+                let (clients, latest, connected, ctx) = (Arc::clone(&clients), Arc::clone(&latest), Arc::clone(&connected), ctx.clone());
+                // 握手可能阻塞，每个连接单独处理
+                thread::spawn(move || {
+                    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                    let Ok(mut ws) = tungstenite::accept_hdr(stream, check_origin(port)) else { return; };
+                    // 持有连接列表的锁读取并发送最新内容：发送线程要等加入后才能取走列表，之后的推送一定会发给这个连接
+                    let mut clients = clients.lock().unwrap();
+                    let initial = latest.lock().unwrap().clone();
+                    if initial.is_some_and(|payload| !send_to(&mut ws, &payload)) { return; }
+                    clients.push(ws);
+                    connected.store(clients.len(), Ordering::Relaxed);
+                    ctx.request_repaint();
+                });
+            }
+        });
+        Ok(sync)
+    }
+
+    pub(crate) fn client_count(&self) -> usize { self.connected.load(Ordering::Relaxed) }
+
+    fn heartbeat(&mut self) {
+        let _ = self.outgoing.send(Outgoing::Ping);
+        self.last_push = Some(Instant::now());
+    }
+
+    fn broadcast(&mut self, payload: String) {
+        let _ = self.outgoing.send(Outgoing::Update(payload));
+        self.last_push = Some(Instant::now());
+        self.dirty = false;
+    }
+}
+
+// 让阻塞在 accept 上的线程醒来退出，并关闭已有连接
+impl Drop for LiveSync {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        let _ = TcpStream::connect(&self.addr);
+        for ws in self.clients.lock().unwrap().iter_mut() { let _ = ws.close(None); let _ = ws.flush(); }
+    }
+}
+
+impl MapEditor {
+    // 每帧末尾调用：changed 表示本帧导出数据有变化（含撤销 / 重做、切换策略标签）
+    pub(crate) fn update_live_sync(&mut self, ctx: &egui::Context, changed: bool, gesture_active: bool) {
+        let Some(sync) = &mut self.live_sync else { return; };
+        sync.dirty |= changed;
+        if !sync.dirty {
+            if sync.last_push.is_none_or(|t| t.elapsed() >= HEARTBEAT_INTERVAL) { sync.heartbeat(); }
+            ctx.request_repaint_after(HEARTBEAT_INTERVAL);
+            return;
+        }
+        if gesture_active && sync.last_push.is_some_and(|t| t.elapsed() < PUSH_INTERVAL) {
+            ctx.request_repaint_after(PUSH_INTERVAL);
+            return;
+        }
+        let (terrain, strategy) = (self.build_terrain_export(), self.build_buildings_export());
+        let message = SyncMessage { kind: "update", terrain: &terrain, strategy: &strategy, building_configs: &self.building_configs };
+        match serde_json::to_string(&message) {
+            Ok(payload) => { if let Some(sync) = &mut self.live_sync { sync.broadcast(payload); } }
+            Err(e) => self.notifications.error(e.to_string()),
+        }
+    }
+
+    pub(crate) fn show_live_sync_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let mut enabled = self.live_sync.is_some();
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut enabled, tr("实时同步")).on_hover_text(tr("执行端通过 WebSocket 连接后，每次修改都会立即推送最新的导出数据")).changed() {
+                self.live_sync = None;
+                if enabled {
+                    match LiveSync::start(self.live_sync_port, ctx.clone()) {
+                        Ok(sync) => { self.notifications.info(trf!("实时同步已启动: ws://{}", sync.addr)); self.live_sync = Some(sync); }
+                        Err(e) => self.notifications.error(e),
+                    }
+                }
+            }
+            ui.add_enabled(self.live_sync.is_none(), egui::DragValue::new(&mut self.live_sync_port).clamp_range(1024..=65535).prefix(tr("端口 ")));
+        });
+    }
+
+    // 顶部工具栏的连接状态：未开启时不显示
+    pub(crate) fn show_live_sync_status(&self, ui: &mut egui::Ui) {
+        let Some(sync) = &self.live_sync else { return; };
+        let (color, text) = match sync.client_count() {
+            0 => (Color32::from_rgb(230, 180, 0), tr("● 等待连接").to_string()),
+            n => (Color32::from_rgb(80, 200, 80), trf!("● 已连接 {}", n)),
+        };
+        ui.colored_label(color, text).on_hover_text(trf!("实时同步 ws://{}", sync.addr));
+    }
+}
@@ -2,6 +2,64 @@
 
 mod models;
 mod utils;
+mod history;
+mod notify;
+mod storage;
+mod assets;
+mod i18n;
+mod migration;
+mod validation;
+mod economy;
+mod operations;
+mod cli;
+mod pathfinding;
+mod routes;
+mod minimap;
+mod rulers;
+mod snapshot;
+mod prep;
+mod prep_run;
+mod camera;
+mod events;
+mod notes;
+mod heatmap;
+mod tabs;
+mod upgrades;
+mod wave_filter;
+mod transform;
+mod terrain_image;
+mod calibration;
+mod references;
+mod building_info;
+mod renumber;
+mod scripting;
+mod plugins;
+mod http_api;
+mod live_sync;
+mod grid_detect;
+mod stamps;
+mod generator;
+mod procedural;
+mod brush_preview;
+mod terrain_smooth;
+mod terrain_legend;
+mod time_model;
+mod build_order;
+mod nudge;
+mod building_menu;
+mod demolish;
+mod wave_shift;
+mod wave_lock;
+mod building_visibility;
+mod consistency;
+mod orphans;
+mod dirty;
+mod export_summary;
+mod strategy_import;
+mod partial_import;
+mod selection_export;
+mod legacy;
+mod roundtrip;
 mod app;
 
 use app::MapEditor;
@@ -9,6 +67,11 @@ use eframe::egui;
 use std::fs;
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cli::is_cli_invocation(&args) {
+        std::process::exit(cli::run(&args));
+    }
+
     println!("--- MINKE Strategy Editor Starting ---");
 
     let options = eframe::NativeOptions { 
@@ -18,7 +81,7 @@ fn main() -> eframe::Result<()> {
         ..Default::default() 
     };
     
-    eframe::run_native("MINKE Editor", options, Box::new(|cc| {
+    eframe::run_native(dirty::APP_TITLE, options, Box::new(|cc| {
         println!("[System] Graphics initialized.");
         
         let mut f = egui::FontDefinitions::default();
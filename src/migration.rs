@@ -0,0 +1,108 @@
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::models::FORMAT_VERSION;
+use crate::storage::{from_value_strict, read_data};
+
+// 🔥 数据格式迁移：在反序列化为具体类型之前，按版本逐步升级原始数据并记录改动
+// 版本约定：没有 format_version 字段的文件视为 v1（elevation_grid / grid_pixel_size 时代）
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub changes: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    pub fn summary(&self) -> String {
+        format!("已从 v{} 升级到 v{}: {}", self.from_version, FORMAT_VERSION, self.changes.join("；"))
+    }
+}
+
+type Step = fn(&mut Value, &mut Vec<String>);
+
+// (起始版本, 升级到下一版本的步骤)
+const TERRAIN_STEPS: &[(u32, Step)] = &[(1, terrain_v1_to_v2)];
+const STRATEGY_STEPS: &[(u32, Step)] = &[(1, strategy_v1_to_v2)];
+
+fn run_steps(value: &mut Value, steps: &[(u32, Step)]) -> MigrationReport {
+    let from_version = value.get("format_version").and_then(Value::as_u64).map_or(1, |v| v as u32);
+    let mut report = MigrationReport { from_version, changes: Vec::new() };
+    if from_version > FORMAT_VERSION {
+        report.changes.push(format!("文件版本 v{} 高于当前支持的 v{}，按当前格式读取", from_version, FORMAT_VERSION));
+        return report;
+    }
+    for (version, step) in steps {
+        if *version >= from_version { step(value, &mut report.changes); }
+    }
+    if let Some(obj) = value.as_object_mut() { obj.insert("format_version".into(), json!(FORMAT_VERSION)); }
+    report
+}
+
+pub fn migrate_terrain(value: &mut Value) -> MigrationReport { run_steps(value, TERRAIN_STEPS) }
+pub fn migrate_strategy(value: &mut Value) -> MigrationReport { run_steps(value, STRATEGY_STEPS) }
+
+pub fn migrate_project(value: &mut Value) -> MigrationReport {
+    let mut report = run_steps(value, &[]);
+    if let Some(terrain) = value.get_mut("terrain") { report.changes.extend(migrate_terrain(terrain).changes.into_iter().map(|c| format!("地形: {}", c))); }
+    if let Some(strategy) = value.get_mut("strategy") { report.changes.extend(migrate_strategy(strategy).changes.into_iter().map(|c| format!("策略: {}", c))); }
+    report
+}
+
+fn is_empty_grid(v: Option<&Value>) -> bool {
+    v.and_then(Value::as_array).is_none_or(|a| a.is_empty())
+}
+
+fn terrain_v1_to_v2(value: &mut Value, changes: &mut Vec<String>) {
+    if let Some(meta) = value.get_mut("meta").and_then(Value::as_object_mut) {
+        if let Some(size) = meta.remove("grid_pixel_size") {
+            for key in ["grid_pixel_width", "grid_pixel_height"] {
+                if !meta.contains_key(key) { meta.insert(key.into(), size.clone()); }
+            }
+            changes.push("meta.grid_pixel_size 拆分为 grid_pixel_width / grid_pixel_height".into());
+        }
+    }
+    let Some(layers) = value.get_mut("layers").and_then(Value::as_array_mut) else { return; };
+    for layer in layers.iter_mut().filter_map(Value::as_object_mut) {
+        let z = layer.get("major_z").cloned().unwrap_or(json!(0));
+        if let Some(old) = layer.remove("elevation_grid") {
+            if is_empty_grid(layer.get("floor_grid")) && !old.is_null() {
+                layer.insert("floor_grid".into(), old);
+                changes.push(format!("层 {}: elevation_grid 迁移为 floor_grid", z));
+            }
+        }
+        let (rows, cols) = match layer.get("floor_grid").and_then(Value::as_array) {
+            Some(grid) if !grid.is_empty() => (grid.len(), grid.first().and_then(Value::as_array).map_or(0, |r| r.len())),
+            _ => continue,
+        };
+        for key in ["wall_grid", "ceiling_grid"] {
+            if is_empty_grid(layer.get(key)) {
+                layer.insert(key.into(), json!(vec![vec![-1i8; cols]; rows]));
+                changes.push(format!("层 {}: 补齐空的 {}", z, key));
+            }
+        }
+    }
+}
+
+fn strategy_v1_to_v2(value: &mut Value, changes: &mut Vec<String>) {
+    let Some(obj) = value.as_object_mut() else { return; };
+    for key in ["upgrades", "demolishes"] {
+        if !obj.contains_key(key) {
+            obj.insert(key.into(), json!([]));
+            changes.push(format!("补充缺失的 {} 字段", key));
+        }
+    }
+}
+
+// 读取任意格式的数据文件，迁移到当前版本后再解析为具体类型
+pub fn read_migrated<T: DeserializeOwned>(path: impl AsRef<Path>, migrate: fn(&mut Value) -> MigrationReport) -> Result<(T, MigrationReport), String> {
+    let path = path.as_ref();
+    let mut value: Value = read_data(path)?;
+    let report = migrate(&mut value);
+    // 错误带迁移后数据中的字段路径；不回退去严格解析原文件，否则旧版本文件会把已迁移的旧字段报成未知字段
+    let data = from_value_strict(value).map_err(|e| format!("解析 {} 失败: {}", path.display(), e))?;
+    Ok((data, report))
+}
@@ -0,0 +1,91 @@
+use eframe::egui::{self, Color32, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::utils::{get_layer_color, get_time_value};
+
+// 🔥 小地图：画布右上角显示底图 + 当前层地面 + 当前时间存在的建筑 + 画布可见范围，点击/拖动重新定位 pan
+
+const MINIMAP_MAX_SIZE: f32 = 200.0;
+const MINIMAP_MARGIN: f32 = 10.0;
+
+impl MapEditor {
+    // 小地图覆盖的范围（底图像素坐标）：底图与网格的并集
+    fn minimap_world_rect(&self) -> Rect {
+        let grid = Rect::from_min_size(Pos2::new(self.offset_x, self.offset_y), Vec2::new(self.grid_cols as f32 * self.grid_width, self.grid_rows as f32 * self.grid_height));
+        match &self.texture {
+            Some(tex) => grid.union(Rect::from_min_size(Pos2::ZERO, tex.size_vec2())),
+            None => grid,
+        }
+    }
+
+    pub(crate) fn minimap_rect(&self, panel_rect: Rect) -> Option<Rect> {
+        if !self.show_minimap { return None; }
+        let world = self.minimap_world_rect();
+        if world.width() <= 0.0 || world.height() <= 0.0 { return None; }
+        let scale = MINIMAP_MAX_SIZE / world.width().max(world.height());
+        let size = world.size() * scale;
+        Some(Rect::from_min_size(Pos2::new(panel_rect.max.x - size.x - MINIMAP_MARGIN, panel_rect.min.y + MINIMAP_MARGIN + self.ruler_inset()), size))
+    }
+
+    // 在画布交互之前调用；返回 true 表示指针归小地图处理，画布本帧不响应
+    pub(crate) fn handle_minimap_input(&mut self, input: &egui::InputState, canvas_dragged: bool, panel_rect: Rect) -> bool {
+        let Some(rect) = self.minimap_rect(panel_rect) else { self.minimap_drag = false; return false; };
+        let hover = input.pointer.hover_pos();
+        let over = hover.is_some_and(|p| rect.contains(p));
+        if input.pointer.primary_pressed() && over && !canvas_dragged { self.minimap_drag = true; }
+        if !input.pointer.primary_down() && !input.pointer.primary_released() { self.minimap_drag = false; }
+        if self.minimap_drag {
+            if let Some(pos) = hover {
+                let world = self.minimap_world_rect();
+                let target = world.min + (pos.clamp(rect.min, rect.max) - rect.min) * (world.width() / rect.width());
+                self.pan = panel_rect.size() / 2.0 - target.to_vec2() * self.zoom;
+            }
+        }
+        self.minimap_drag || (over && !canvas_dragged)
+    }
+
+    pub(crate) fn draw_minimap(&self, painter: &Painter, panel_rect: Rect) {
+        let Some(rect) = self.minimap_rect(panel_rect) else { return; };
+        let world = self.minimap_world_rect();
+        let scale = rect.width() / world.width();
+        let to_mini = |p: Pos2| rect.min + (p - world.min) * scale;
+
+        painter.rect_filled(rect.expand(2.0), 2.0, Color32::from_black_alpha(200));
+        if let Some(tex) = &self.texture {
+            painter.image(tex.id(), Rect::from_min_max(to_mini(Pos2::ZERO), to_mini(tex.size_vec2().to_pos2())), Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::from_white_alpha(160));
+        }
+
+        let cell = Vec2::new(self.grid_width, self.grid_height) * scale;
+        let cell_rect = |c: usize, r: usize, w: usize, h: usize| {
+            let min = to_mini(Pos2::new(self.offset_x + c as f32 * self.grid_width, self.offset_y + r as f32 * self.grid_height));
+            Rect::from_min_size(min, Vec2::new(w as f32 * cell.x, h as f32 * cell.y))
+        };
+        // 格子小于一个像素时按步长抽样，大地图下小地图的开销与其像素数相当
+        if let Some(layer) = self.layers_data.get(&self.current_major_z) {
+            let stride = (1.0 / cell.x.min(cell.y)).ceil().max(1.0) as usize;
+            let mut mesh = egui::Mesh::default();
+            let grid = &layer.floor_grid;
+            for r in (0..grid.rows()).step_by(stride) {
+                for c in (0..grid.cols()).step_by(stride) {
+                    let val = grid.get(r, c);
+                    if val < -1 { continue; }
+                    let rect = cell_rect(c, r, stride.min(grid.cols() - c), stride.min(grid.rows() - r));
+                    mesh.add_colored_rect(rect, get_layer_color(&self.terrain_types, val).linear_multiply(0.8));
+                }
+            }
+            painter.add(mesh);
+        }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        for b in &self.placed_buildings {
+            if t_current < get_time_value(b.wave_num, b.is_late) || t_current >= self.get_building_demolish_time(b.uid) { continue; }
+            let color = if self.selected_uids.contains(&b.uid) { Color32::LIGHT_BLUE } else { b.color.to_opaque() };
+            let (x, y) = self.building_pos_at(b, t_current);
+            painter.rect_filled(cell_rect(x, y, b.width, b.height), 0.0, color);
+        }
+
+        // 画布当前可见的范围：屏幕坐标 = panel_rect.min + pan + 底图坐标 * zoom
+        let visible = Rect::from_min_size(((-self.pan) / self.zoom).to_pos2(), panel_rect.size() / self.zoom);
+        painter.rect_stroke(Rect::from_min_max(to_mini(visible.min), to_mini(visible.max)).intersect(rect), 0.0, Stroke::new(1.5, Color32::WHITE));
+        painter.rect_stroke(rect.expand(2.0), 2.0, Stroke::new(if self.minimap_drag { 2.0 } else { 1.0 }, Color32::GRAY));
+    }
+}
@@ -1,7 +1,10 @@
-use serde::{Deserialize, Serialize, Deserializer};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize, Deserializer, Serializer};
 use eframe::egui::{Color32, TextureHandle, Rect, Pos2};
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct SafeArea {
     pub min_x: f32,
     pub min_y: f32,
@@ -29,8 +32,32 @@ impl From<SafeArea> for Rect {
     }
 }
 
+// 镜头关键帧：每个波次一段轨迹，t_ms 为距该段开始的毫秒数，(x, y) 为观察框左上角的底图坐标
+// keys 为从该帧起按住的方向键（w/a/s/d 的组合，空字符串表示停止移动），直到下一帧
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[serde(tag = "type")]
+pub struct CameraKeyframe {
+    pub wave_num: i32,
+    #[serde(default)]
+    pub is_late: bool,
+    pub t_ms: u64,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub keys: String,
+}
+
+// 命名的观察框位置（观察框左上角的底图坐标）；建造/拆除事件通过 viewport 字段引用，
+// 表示事件的屏幕坐标在镜头位于该预设时有效
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ViewportPreset {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+// 内部标记的枚举会先缓冲再解析，严格模式看不到其中的未知字段，只能在这里拒绝
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", deny_unknown_fields)]
 pub enum PrepAction {
     #[serde(rename = "Log")]
     Log { msg: String },
@@ -42,6 +69,27 @@ pub enum PrepAction {
     Wait { ms: u64 },
     #[serde(rename = "KeyUpAll")]
     KeyUpAll,
+    #[serde(rename = "MouseMove")]
+    MouseMove { x: i32, y: i32 },
+    #[serde(rename = "MouseClick")]
+    MouseClick { button: MouseButton },
+    // 在 from 按下、duration_ms 内移动到 to 后松开
+    #[serde(rename = "MouseDrag")]
+    MouseDrag { button: MouseButton, from_x: i32, from_y: i32, to_x: i32, to_y: i32, duration_ms: u64 },
+    // 滚轮格数，正数向上
+    #[serde(rename = "Scroll")]
+    Scroll { delta: i32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MouseButton { Left, Right, Middle }
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+    pub fn label(self) -> &'static str {
+        match self { MouseButton::Left => "Left", MouseButton::Right => "Right", MouseButton::Middle => "Middle" }
+    }
 }
 
 impl Default for PrepAction {
@@ -50,7 +98,7 @@ impl Default for PrepAction {
     }
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, PartialEq)]
 pub struct MapMeta {
     pub grid_pixel_width: f32,
     pub grid_pixel_height: f32,
@@ -72,6 +120,65 @@ pub struct MapMeta {
     pub viewport_safe_areas: Vec<SafeArea>,
     #[serde(default)]
     pub prep_actions: Vec<PrepAction>,
+    #[serde(default)]
+    pub camera_keyframes: Vec<CameraKeyframe>,
+    #[serde(default)]
+    pub viewport_presets: Vec<ViewportPreset>,
+    #[serde(skip_serializing_if = "TimeModel::is_default")]
+    pub time_model: TimeModel,
+}
+
+// 关卡的时间划分：事件按 (波次, 前期/后期) 记录，比较先后时换算成时间值，见 get_time_value
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct TimeModel {
+    // 每波的时间段数：2 为前期 + 后期；1 表示不区分，后期事件与同波前期视为同一时刻。其他值按最接近的 1 或 2 处理，校验时报错
+    pub slots_per_wave: i32,
+    // 后期开始于本波开始后的秒数，用于显示（如 W3+30s）与换算绝对时间；未设置时显示为 W3L
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub late_offset_seconds: Option<f32>,
+    // 每波时长（秒），下标 0 为第 1 波，超出表长的波次沿用最后一项；用于导出绝对时间序列，为空时不能导出
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub wave_seconds: Vec<f32>,
+}
+
+impl Default for TimeModel {
+    fn default() -> Self { Self { slots_per_wave: 2, late_offset_seconds: None, wave_seconds: Vec::new() } }
+}
+
+impl TimeModel {
+    pub fn is_default(&self) -> bool { *self == Self::default() }
+
+    pub fn has_late_phase(&self) -> bool { self.slots_per_wave >= 2 }
+
+    pub fn time_value(&self, wave: i32, late: bool) -> i32 {
+        if self.has_late_phase() { wave * 2 + if late { 1 } else { 0 } } else { wave }
+    }
+
+    pub fn wave_at(&self, t: i32) -> (i32, bool) {
+        if self.has_late_phase() { (t.div_euclid(2), t.rem_euclid(2) == 1) } else { (t, false) }
+    }
+
+    fn wave_duration(&self, wave: i32) -> Option<f32> {
+        let i = (wave.max(1) - 1) as usize;
+        self.wave_seconds.get(i).or(self.wave_seconds.last()).copied()
+    }
+
+    // 从关卡开始算起的秒数：之前各波的时长之和，后期再加上后期开始的秒数（未设置时取本波时长的一半），最后加上事件自身的秒数偏移
+    pub fn absolute_seconds(&self, wave: i32, late: bool, offset_seconds: Option<f32>) -> Option<f32> {
+        if self.wave_seconds.is_empty() { return None; }
+        let wave_start = (1..wave).map(|w| self.wave_duration(w)).sum::<Option<f32>>()?;
+        let late_start = if late && self.has_late_phase() { self.late_offset_seconds.or(self.wave_duration(wave).map(|d| d / 2.0))? } else { 0.0 };
+        Some(wave_start + late_start + offset_seconds.unwrap_or(0.0))
+    }
+
+    pub fn label(&self, wave: i32, late: bool) -> String {
+        match (late && self.has_late_phase(), self.late_offset_seconds) {
+            (false, _) => format!("W{}", wave),
+            (true, None) => format!("W{}L", wave),
+            (true, Some(s)) => format!("W{}+{}s", wave, s),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -90,6 +197,9 @@ struct MapMetaLegacy {
     camera_speed_right: Option<f32>,
     viewport_safe_areas: Option<Vec<SafeArea>>,
     prep_actions: Option<Vec<PrepAction>>,
+    camera_keyframes: Option<Vec<CameraKeyframe>>,
+    viewport_presets: Option<Vec<ViewportPreset>>,
+    time_model: Option<TimeModel>,
 }
 
 impl Default for MapMetaLegacy {
@@ -108,6 +218,9 @@ impl Default for MapMetaLegacy {
             camera_speed_right: None,
             viewport_safe_areas: None,
             prep_actions: None,
+            camera_keyframes: None,
+            viewport_presets: None,
+            time_model: None,
         }
     }
 }
@@ -148,6 +261,9 @@ impl<'de> Deserialize<'de> for MapMeta {
             camera_speed_right: legacy.camera_speed_right.unwrap_or(1.0),
             viewport_safe_areas: legacy.viewport_safe_areas.unwrap_or_default(),
             prep_actions: legacy.prep_actions.unwrap_or_default(),
+            camera_keyframes: legacy.camera_keyframes.unwrap_or_default(),
+            viewport_presets: legacy.viewport_presets.unwrap_or_default(),
+            time_model: legacy.time_model.unwrap_or_default(),
         })
     }
 }
@@ -160,31 +276,246 @@ pub enum BuildingType {
 }
 
 fn default_building_type() -> BuildingType { BuildingType::Floor }
-fn default_grid() -> Vec<Vec<i8>> { Vec::new() }
 
-#[derive(Serialize, Deserialize, Clone)]
+// 🔥 分块网格：按 GRID_CHUNK × GRID_CHUNK 分块存储，整块同值时只存一个值
+// 分块以 Arc 共享，克隆（撤销快照）只复制指针，写入时才复制被改动的块
+// 序列化格式仍为按行排列的二维数组 [[i8]]
+pub const GRID_CHUNK: usize = 32;
+const CHUNK_AREA: usize = GRID_CHUNK * GRID_CHUNK;
+
+#[derive(Clone, Debug)]
+enum Chunk {
+    Uniform(i8),
+    Dense(Arc<Vec<i8>>),
+}
+
+impl Chunk {
+    fn get(&self, i: usize) -> i8 {
+        match self { Chunk::Uniform(v) => *v, Chunk::Dense(cells) => cells[i] }
+    }
+
+    fn set(&mut self, i: usize, val: i8) {
+        match self {
+            Chunk::Uniform(v) if *v == val => {}
+            Chunk::Uniform(v) => {
+                let mut cells = vec![*v; CHUNK_AREA];
+                cells[i] = val;
+                *self = Chunk::Dense(Arc::new(cells));
+            }
+            Chunk::Dense(cells) => Arc::make_mut(cells)[i] = val,
+        }
+    }
+}
+
+// 网格边缘的分块也按完整尺寸存储，超出网格的部分不参与读写与比较
+#[derive(Clone, Debug, Default)]
+pub struct Grid {
+    rows: usize,
+    cols: usize,
+    chunks: Vec<Chunk>,
+    // 读入时第一个与最宽行长度不同的行 (行号, 列数)，供校验报告；不参与比较
+    ragged: Option<(usize, usize)>,
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize, fill: i8) -> Self {
+        let count = rows.div_ceil(GRID_CHUNK) * cols.div_ceil(GRID_CHUNK);
+        Grid { rows, cols, chunks: vec![Chunk::Uniform(fill); count], ragged: None }
+    }
+
+    // 列数取最宽的一行，较短的行末尾补 -1（障碍），不一致的行记入 ragged 由校验报告
+    pub fn from_rows(rows: &[Vec<i8>]) -> Self {
+        let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut grid = Grid::new(rows.len(), cols, -1);
+        grid.ragged = rows.iter().enumerate().find(|(_, row)| row.len() != cols).map(|(r, row)| (r, row.len()));
+        for (r, row) in rows.iter().enumerate() {
+            for (c, &val) in row.iter().enumerate() { grid.set(r, c, val); }
+        }
+        grid
+    }
+
+    pub fn ragged(&self) -> Option<(usize, usize)> { self.ragged }
+
+    pub fn rows(&self) -> usize { self.rows }
+    pub fn cols(&self) -> usize { self.cols }
+    pub fn is_empty(&self) -> bool { self.rows == 0 || self.cols == 0 }
+
+    fn locate(&self, r: usize, c: usize) -> (usize, usize) {
+        assert!(r < self.rows && c < self.cols, "网格坐标 ({}, {}) 超出 {}x{}", r, c, self.rows, self.cols);
+        ((r / GRID_CHUNK) * self.cols.div_ceil(GRID_CHUNK) + c / GRID_CHUNK, (r % GRID_CHUNK) * GRID_CHUNK + c % GRID_CHUNK)
+    }
+
+    // 与 Vec<Vec<i8>> 的 grid[r][c] 相同的行列顺序，越界时 panic
+    pub fn get(&self, r: usize, c: usize) -> i8 {
+        let (chunk, i) = self.locate(r, c);
+        self.chunks[chunk].get(i)
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, val: i8) {
+        let (chunk, i) = self.locate(r, c);
+        self.chunks[chunk].set(i, val);
+    }
+
+    // 填充矩形区域（超出网格的部分自动裁剪），完整覆盖的分块直接变为同值块
+    pub fn fill(&mut self, rows: Range<usize>, cols: Range<usize>, val: i8) {
+        let rows = rows.start.min(self.rows)..rows.end.min(self.rows);
+        let cols = cols.start.min(self.cols)..cols.end.min(self.cols);
+        for i in 0..self.chunks.len() {
+            let (chunk_rows, chunk_cols) = self.chunk_span(i);
+            let (r0, c0) = (chunk_rows.start, chunk_cols.start);
+            let r_span = chunk_rows.start.max(rows.start)..chunk_rows.end.min(rows.end);
+            let c_span = chunk_cols.start.max(cols.start)..chunk_cols.end.min(cols.end);
+            if r_span.is_empty() || c_span.is_empty() { continue; }
+            let chunk = &mut self.chunks[i];
+            if r_span == chunk_rows && c_span == chunk_cols { *chunk = Chunk::Uniform(val); continue; }
+            for r in r_span {
+                for c in c_span.clone() { chunk.set((r - r0) * GRID_CHUNK + (c - c0), val); }
+            }
+        }
+    }
+
+    // 调整尺寸，保留重叠部分，新增部分填 fill
+    pub fn resize(&mut self, rows: usize, cols: usize, fill: i8) {
+        if rows == self.rows && cols == self.cols { return; }
+        let mut resized = Grid::new(rows, cols, fill);
+        for r in 0..rows.min(self.rows) {
+            for c in 0..cols.min(self.cols) { resized.set(r, c, self.get(r, c)); }
+        }
+        *self = resized;
+    }
+
+    // 值不等于 empty 的格子的外接矩形 (行范围, 列范围)，整块为 empty 的分块直接跳过
+    pub fn content_bounds(&self, empty: i8) -> Option<(Range<usize>, Range<usize>)> {
+        let mut bounds: Option<(Range<usize>, Range<usize>)> = None;
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            if matches!(chunk, Chunk::Uniform(v) if *v == empty) { continue; }
+            let (rows, cols) = self.chunk_span(i);
+            for r in rows {
+                for c in cols.clone() {
+                    if self.get(r, c) == empty { continue; }
+                    bounds = Some(match bounds {
+                        Some((br, bc)) => (br.start.min(r)..br.end.max(r + 1), bc.start.min(c)..bc.end.max(c + 1)),
+                        None => (r..r + 1, c..c + 1),
+                    });
+                }
+            }
+        }
+        bounds
+    }
+
+    // 调整尺寸并把原内容平移 (dr, dc) 格：移出网格的部分丢弃，空出的部分填 fill
+    pub fn resized_with_shift(&self, rows: usize, cols: usize, dr: isize, dc: isize, fill: i8) -> Grid {
+        let mut resized = Grid::new(rows, cols, fill);
+        for r in 0..self.rows {
+            let Some(nr) = r.checked_add_signed(dr).filter(|&nr| nr < rows) else { continue; };
+            for c in 0..self.cols {
+                let Some(nc) = c.checked_add_signed(dc).filter(|&nc| nc < cols) else { continue; };
+                resized.set(nr, nc, self.get(r, c));
+            }
+        }
+        resized
+    }
+
+    // 水平翻转时列逆序，垂直翻转时行逆序
+    pub fn flipped(&self, horizontal: bool) -> Grid {
+        let mut flipped = Grid::new(self.rows, self.cols, -1);
+        for r in 0..self.rows {
+            for c in 0..self.cols {
+                let (sr, sc) = if horizontal { (r, self.cols - 1 - c) } else { (self.rows - 1 - r, c) };
+                flipped.set(r, c, self.get(sr, sc));
+            }
+        }
+        flipped
+    }
+
+    // 旋转 90°，行列数互换：顺时针时原 (r, c) 落到 (c, rows - 1 - r)
+    pub fn rotated(&self, clockwise: bool) -> Grid {
+        let mut rotated = Grid::new(self.cols, self.rows, -1);
+        for r in 0..self.cols {
+            for c in 0..self.rows {
+                let (sr, sc) = if clockwise { (self.rows - 1 - c, r) } else { (c, self.cols - 1 - r) };
+                rotated.set(r, c, self.get(sr, sc));
+            }
+        }
+        rotated
+    }
+
+    pub fn row(&self, r: usize) -> impl Iterator<Item = i8> + '_ {
+        (0..self.cols).map(move |c| self.get(r, c))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = i8> + '_ {
+        (0..self.rows).flat_map(move |r| self.row(r))
+    }
+
+    // 分块 i 覆盖的行列范围（已裁剪到网格内）
+    fn chunk_span(&self, i: usize) -> (Range<usize>, Range<usize>) {
+        let chunk_cols = self.cols.div_ceil(GRID_CHUNK);
+        let (r0, c0) = ((i / chunk_cols) * GRID_CHUNK, (i % chunk_cols) * GRID_CHUNK);
+        (r0..(r0 + GRID_CHUNK).min(self.rows), c0..(c0 + GRID_CHUNK).min(self.cols))
+    }
+}
+
+// 共享同一份数据的分块直接判等，撤销快照的逐帧比较因此只需检查被改动过的块
+impl PartialEq for Grid {
+    fn eq(&self, other: &Self) -> bool {
+        if self.rows != other.rows || self.cols != other.cols { return false; }
+        self.chunks.iter().zip(&other.chunks).enumerate().all(|(i, pair)| match pair {
+            (Chunk::Uniform(a), Chunk::Uniform(b)) => a == b,
+            (Chunk::Dense(a), Chunk::Dense(b)) if Arc::ptr_eq(a, b) => true,
+            (a, b) => {
+                let (rows, cols) = self.chunk_span(i);
+                let (r0, c0) = (rows.start, cols.start);
+                rows.flat_map(|r| cols.clone().map(move |c| (r - r0) * GRID_CHUNK + (c - c0))).all(|j| a.get(j) == b.get(j))
+            }
+        })
+    }
+}
+
+struct GridRow<'a>(&'a Grid, usize);
+
+impl Serialize for GridRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.row(self.1))
+    }
+}
+
+impl Serialize for Grid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((0..self.rows).map(|r| GridRow(self, r)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Grid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Vec::<Vec<i8>>::deserialize(deserializer)?;
+        Ok(Grid::from_rows(&rows))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct LayerData {
     pub major_z: i32,
     pub name: String,
     
-    #[serde(default = "default_grid")]
-    pub floor_grid: Vec<Vec<i8>>,
+    #[serde(default)]
+    pub floor_grid: Grid,
     
-    #[serde(default = "default_grid")]
-    pub wall_grid: Vec<Vec<i8>>,
+    #[serde(default)]
+    pub wall_grid: Grid,
     
-    #[serde(default = "default_grid")]
-    pub ceiling_grid: Vec<Vec<i8>>,
+    #[serde(default)]
+    pub ceiling_grid: Grid,
 
     // 🔥 新增：兼容旧版本 JSON 的字段
     // 标记为 Option 且跳过序列化（只读不存）
     #[serde(default, skip_serializing)]
-    pub elevation_grid: Option<Vec<Vec<i8>>>,
+    pub elevation_grid: Option<Grid>,
 }
 
 impl LayerData {
     // 辅助函数：根据类型获取只读网格
-    pub fn get_grid(&self, b_type: BuildingType) -> &Vec<Vec<i8>> {
+    pub fn get_grid(&self, b_type: BuildingType) -> &Grid {
         match b_type {
             BuildingType::Floor => &self.floor_grid,
             BuildingType::Wall => &self.wall_grid,
@@ -193,7 +524,7 @@ impl LayerData {
     }
 
     // 辅助函数：根据类型获取可变网格
-    pub fn get_grid_mut(&mut self, b_type: BuildingType) -> &mut Vec<Vec<i8>> {
+    pub fn get_grid_mut(&mut self, b_type: BuildingType) -> &mut Grid {
         match b_type {
             BuildingType::Floor => &mut self.floor_grid,
             BuildingType::Wall => &mut self.wall_grid,
@@ -214,7 +545,7 @@ impl LayerData {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct BuildingExport {
     pub uid: usize,
     pub name: String,
@@ -226,16 +557,45 @@ pub struct BuildingExport {
     pub height: usize,
     pub wave_num: i32,
     pub is_late: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewport: Option<String>,
+    // 给队友看的说明，例如为什么放在这里
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    // 非整格放置时相对 (grid_x, grid_y) 的小数偏移（单位：格，0 ≤ 偏移 < 1）
+    #[serde(default, skip_serializing_if = "is_zero_offset")]
+    pub offset: [f32; 2],
+    // 相对所在时间段开始（前期即波次开始）的秒数，只决定同一时间段内操作的先后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset_seconds: Option<f32>,
+    // 同一时刻内的执行先后，在建造顺序列表中拖动设置；未设置的排在已设置的之后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+fn is_zero_offset(offset: &[f32; 2]) -> bool { *offset == [0.0, 0.0] }
+
+// 布局模式下的放置吸附：整格（默认）、半格或底图像素级。占用与碰撞检测仍按建筑所在的整格计算
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum PlacementSnap { #[default] Cell, HalfCell, Pixel }
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct UpgradeEvent {
     pub building_name: String, 
     pub wave_num: i32,
     pub is_late: bool,
+    // 只升级这一座建筑；未指定时升级该模板的所有建筑
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<usize>,
+    // 相对所在时间段开始（前期即波次开始）的秒数，只决定同一时间段内操作的先后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset_seconds: Option<f32>,
+    // 同一时刻内的执行先后，在建造顺序列表中拖动设置；未设置的排在已设置的之后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct DemolishEvent {
     pub uid: usize,          
     pub name: String,
@@ -245,26 +605,314 @@ pub struct DemolishEvent {
     pub height: usize,
     pub wave_num: i32,
     pub is_late: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewport: Option<String>,
+    // 相对所在时间段开始（前期即波次开始）的秒数，只决定同一时间段内操作的先后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset_seconds: Option<f32>,
+    // 同一时刻内的执行先后，在建造顺序列表中拖动设置；未设置的排在已设置的之后
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+// 出售：与拆除一样移除建筑，并按经济配置返还部分建造费用
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SellEvent {
+    pub uid: usize,
+    pub name: String,
+    pub grid_x: usize,
+    pub grid_y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub wave_num: i32,
+    pub is_late: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewport: Option<String>,
+    // 同一时刻内的执行先后，见 DemolishEvent::order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+}
+
+// 迁移：建筑在该时间从 from 移到 to（左上角格子），uid 不变
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct RelocateEvent {
+    pub uid: usize,
+    pub name: String,
+    pub from_x: usize,
+    pub from_y: usize,
+    pub to_x: usize,
+    pub to_y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub wave_num: i32,
+    pub is_late: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewport: Option<String>,
+    // 同一时刻内的执行先后，见 DemolishEvent::order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+}
+
+// 技能释放：名称由外部程序解释（如 "airstrike"），target 为目标格子 (grid_x, grid_y)，无目标的技能省略
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct AbilityEvent {
+    pub name: String,
+    pub wave_num: i32,
+    pub is_late: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<(usize, usize)>,
+    // 同一时刻内的执行先后，见 DemolishEvent::order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct MapTerrainExport {
+    #[serde(default)]
+    pub format_version: u32,
     pub map_name: String,
     pub meta: MapMeta,
     pub layers: Vec<LayerData>,
+    #[serde(default)]
+    pub routes: Vec<EnemyRoute>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum RoutePointKind { Spawn, Waypoint, Exit }
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct RoutePoint {
+    pub kind: RoutePointKind,
+    pub grid_x: usize,
+    pub grid_y: usize,
+}
+
+// 敌人路线：出生点 → 路径点 → 出口，按顺序连成折线
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EnemyRoute {
+    pub name: String,
+    pub points: Vec<RoutePoint>,
+}
+
+impl EnemyRoute {
+    // 出生点始终在首位、出口始终在末位，各只保留一个；路径点插在出口之前
+    pub fn insert_point(&mut self, kind: RoutePointKind, grid_x: usize, grid_y: usize) {
+        let point = RoutePoint { kind, grid_x, grid_y };
+        match kind {
+            RoutePointKind::Spawn => {
+                self.points.retain(|p| p.kind != RoutePointKind::Spawn);
+                self.points.insert(0, point);
+            }
+            RoutePointKind::Exit => {
+                self.points.retain(|p| p.kind != RoutePointKind::Exit);
+                self.points.push(point);
+            }
+            RoutePointKind::Waypoint => {
+                let idx = if self.points.last().is_some_and(|p| p.kind == RoutePointKind::Exit) { self.points.len() - 1 } else { self.points.len() };
+                self.points.insert(idx, point);
+            }
+        }
+    }
+
+    pub fn point_at(&self, grid_x: usize, grid_y: usize) -> Option<usize> {
+        self.points.iter().rposition(|p| p.grid_x == grid_x && p.grid_y == grid_y)
+    }
+}
+
+impl MapTerrainExport {
+    // 网格尺寸以第一个非空地面网格为准
+    pub fn grid_size(&self) -> (usize, usize) {
+        self.layers.iter().find(|l| !l.floor_grid.is_empty()).map(|l| (l.floor_grid.rows(), l.floor_grid.cols())).unwrap_or((0, 0))
+    }
+
+    // 迁移旧字段，并把空的墙壁/吊顶网格补齐为与地面相同的尺寸
+    pub fn normalize(&mut self) {
+        self.format_version = FORMAT_VERSION;
+        for layer in &mut self.layers { layer.normalize(); }
+        let (rows, cols) = self.grid_size();
+        for layer in &mut self.layers {
+            for b_type in [BuildingType::Wall, BuildingType::Ceiling] {
+                let grid = layer.get_grid_mut(b_type);
+                if grid.is_empty() { *grid = Grid::new(rows, cols, -1); }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct MapBuildingsExport {
+    #[serde(default)]
+    pub format_version: u32,
     pub map_name: String,
     pub buildings: Vec<BuildingExport>,
     #[serde(default)]
     pub upgrades: Vec<UpgradeEvent>,
     #[serde(default)]
     pub demolishes: Vec<DemolishEvent>, 
+    #[serde(default)]
+    pub sells: Vec<SellEvent>,
+    #[serde(default)]
+    pub relocations: Vec<RelocateEvent>,
+    #[serde(default)]
+    pub abilities: Vec<AbilityEvent>,
+    // 每波备注（前期/后期共用），键为波次
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "serialize_wave_notes", deserialize_with = "deserialize_wave_notes")]
+    pub wave_notes: HashMap<i32, String>,
+    // 锁定的波次：这些波次的建筑与事件在编辑器中不能修改或删除
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub locked_waves: BTreeSet<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub economy: Option<EconomyConfig>,
+}
+
+// TOML 的键只能是字符串，波次统一按字符串写出，并按波次排序保证输出稳定
+fn serialize_wave_notes<S: Serializer>(notes: &HashMap<i32, String>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut sorted: Vec<(&i32, &String)> = notes.iter().collect();
+    sorted.sort_by_key(|(wave, _)| **wave);
+    serializer.collect_map(sorted.into_iter().map(|(wave, note)| (wave.to_string(), note)))
+}
+
+fn deserialize_wave_notes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<i32, String>, D::Error> {
+    HashMap::<String, String>::deserialize(deserializer)?.into_iter()
+        .map(|(wave, note)| wave.trim().parse().map(|w| (w, note)).map_err(|_| serde::de::Error::custom(format!("无效的波次 \"{}\"", wave))))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct WaveIncome {
+    pub wave_num: i32,
+    pub amount: i32,
+}
+
+// 经济配置：初始资金 + 每波开始时获得的收入
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EconomyConfig {
+    #[serde(default)]
+    pub initial_money: i32,
+    #[serde(default)]
+    pub wave_income: Vec<WaveIncome>,
+    // 出售时返还建造费用的百分比
+    #[serde(default = "default_sell_refund_percent")]
+    pub sell_refund_percent: i32,
+}
+
+fn default_sell_refund_percent() -> i32 { 50 }
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self { initial_money: 0, wave_income: Vec::new(), sell_refund_percent: default_sell_refund_percent() }
+    }
+}
+
+// 复制到系统剪贴板的建筑组，坐标相对于组的左上角；支持在不同编辑器实例之间粘贴
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BuildingClipboard {
+    pub minke_buildings: Vec<BuildingExport>,
+}
+
+// 图章中的一座建筑：坐标相对于组的左上角，time_offset 为相对组内最早建筑的建造时间（固定按每波前期 + 后期两段计，不随关卡的时间模型变化，见 stamps::stamp_time）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StampBuilding {
+    pub name: String,
+    #[serde(default = "default_building_type")]
+    pub b_type: BuildingType,
+    pub dx: usize,
+    pub dy: usize,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub time_offset: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero_offset")]
+    pub offset: [f32; 2],
+}
+
+// 图章库中保存的建筑组，所有地图共用
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Stamp {
+    pub name: String,
+    pub buildings: Vec<StampBuilding>,
+}
+
+// 尚未建造的建筑在画布上的画法
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FutureStyle {
+    #[default]
+    Faded,
+    Dashed,
+    Hidden,
+}
+
+// 画布上尚未建造与已拆除 / 出售的建筑的显示方式，保存在资源根目录的 view_options.json 中，属于用户自己的偏好
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct BuildingVisibility {
+    pub future: FutureStyle,
+    pub future_alpha: f32,
+    pub hide_demolished: bool,
+    pub demolished_alpha: f32,
+}
+
+impl Default for BuildingVisibility {
+    fn default() -> Self {
+        Self { future: FutureStyle::Faded, future_alpha: 0.3, hide_demolished: false, demolished_alpha: 0.05 }
+    }
+}
+
+// 🔥 蓝图：在地图与用户之间分享建筑组的独立 JSON 文件，内容与图章相同（相对坐标 + 模板 + 相对建造时间）
+pub const BLUEPRINT_FORMAT: &str = "minke-blueprint";
+pub const BLUEPRINT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BlueprintFile {
+    // 固定为 BLUEPRINT_FORMAT，用来和其他 JSON 文件区分
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    pub buildings: Vec<StampBuilding>,
+}
+
+pub const PROJECT_EXTENSION: &str = "minke";
+
+// 数据格式版本，升级规则见 migration.rs
+pub const FORMAT_VERSION: u32 = 2;
+
+// 🔥 单文件工程：一次保存/打开整个关卡（地形、策略、防御塔列表及底图来源）
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProjectFile {
+    #[serde(default)]
+    pub format_version: u32,
+    pub project_name: String,
+    #[serde(default)]
+    pub base_image_path: Option<String>,
+    #[serde(default)]
+    pub preset_name: Option<String>,
+    #[serde(default)]
+    pub map_filename: String,
+    pub terrain: MapTerrainExport,
+    pub strategy: MapBuildingsExport,
+    #[serde(default)]
+    pub building_configs: Vec<BuildingConfig>,
+    // 叠加在底图之上的参考图，按从下到上的顺序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_images: Vec<ReferenceImageEntry>,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ReferenceImageEntry {
+    pub path: String,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+}
+
+fn default_opacity() -> f32 { 0.5 }
+fn default_true() -> bool { true }
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
 pub struct BuildingConfig {
     pub name: String,
     #[serde(default = "default_building_type")]
@@ -275,6 +923,100 @@ pub struct BuildingConfig {
     pub color: [u8; 4],
     pub icon_path: String,
     pub cost: i32,
+    // 升级费用，未配置时按建造费用计
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_cost: Option<i32>,
+    // 射程半径（格），以建筑中心为圆心；未配置时不显示射程
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<f32>,
+    // 建筑模式下选择列表按分类分组，标签用于筛选；均可省略
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub category: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // 升级等级：第 N 次升级使用第 N 项；为空时不限升级次数，每次按 upgrade_cost 计费
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upgrades: Vec<UpgradeLevel>,
+}
+
+impl BuildingConfig {
+    // 完成 tier 次升级后的射程：取已达到的最高等级中配置了射程的一级，否则为基础射程
+    pub fn range_at_tier(&self, tier: usize) -> Option<f32> {
+        self.upgrades.iter().take(tier).rev().find_map(|l| l.range).or(self.range)
+    }
+
+    pub fn icon_path_at_tier(&self, tier: usize) -> &str {
+        self.upgrades.iter().take(tier).rev().find_map(|l| l.icon_path.as_deref()).unwrap_or(&self.icon_path)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct UpgradeLevel {
+    pub cost: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<f32>,
+    // 该等级的图标，未配置时沿用上一级
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_path: Option<String>,
+}
+
+// 地形类型调色板（maps/terrain_types.json），网格中的值即 id
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct TerrainType {
+    pub id: i8,
+    pub name: String,
+    pub color: [u8; 4],
+    #[serde(default)]
+    pub buildable: bool,
+    // 从颜色图导入地形时对应的像素颜色 (RGB)，未配置时使用 color 的 RGB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_color: Option<[u8; 3]>,
+}
+
+impl TerrainType {
+    fn new(id: i8, name: &str, color: [u8; 4], buildable: bool) -> Self {
+        TerrainType { id, name: name.to_string(), color, buildable, import_color: None }
+    }
+
+    pub fn import_rgb(&self) -> [u8; 3] {
+        self.import_color.unwrap_or([self.color[0], self.color[1], self.color[2]])
+    }
+}
+
+// 没有 terrain_types.json 时使用的内置调色板
+pub fn default_terrain_types() -> Vec<TerrainType> {
+    vec![
+        TerrainType::new(-1, "障碍", [255, 0, 0, 100], false),
+        TerrainType::new(0, "平地", [0, 255, 0, 40], true),
+        TerrainType::new(1, "高台1", [255, 255, 0, 100], true),
+        TerrainType::new(2, "高台2", [0, 150, 255, 100], true),
+        TerrainType::new(3, "高台3", [150, 0, 255, 100], true),
+    ]
+}
+
+// 🔥 子层一致性规则：layer 子层的格子取 ids 中的地形值（为空时为任意可建造地形）时，
+// 同一格的 requires 子层中至少要有一个是可建造地形，否则在一致性检查中标出。保存在资源根目录的 consistency_rules.json 中
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConsistencyRule {
+    pub name: String,
+    pub layer: BuildingType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<i8>,
+    pub requires: Vec<BuildingType>,
+}
+
+impl ConsistencyRule {
+    fn new(name: &str, layer: BuildingType, requires: &[BuildingType]) -> Self {
+        ConsistencyRule { name: name.to_string(), layer, ids: Vec::new(), requires: requires.to_vec() }
+    }
+}
+
+// 没有 consistency_rules.json 时使用的内置规则
+pub fn default_consistency_rules() -> Vec<ConsistencyRule> {
+    vec![
+        ConsistencyRule::new("墙壁下方的地面是障碍", BuildingType::Wall, &[BuildingType::Floor]),
+        ConsistencyRule::new("吊顶下方没有地面或墙壁支撑", BuildingType::Ceiling, &[BuildingType::Floor, BuildingType::Wall]),
+    ]
 }
 
 #[derive(Deserialize, Clone)]
@@ -294,9 +1036,25 @@ pub struct BuildingTemplate {
     pub height: usize,
     pub color: Color32,
     pub icon: Option<TextureHandle>,
+    pub category: String,
+    pub tags: Vec<String>,
+    // 与 BuildingConfig::upgrades 一一对应
+    pub tier_icons: Vec<Option<TextureHandle>>,
 }
 
-#[derive(Clone)]
+impl BuildingTemplate {
+    pub fn icon_at_tier(&self, tier: usize) -> Option<&TextureHandle> {
+        self.tier_icons.iter().take(tier).rev().find_map(|i| i.as_ref()).or(self.icon.as_ref())
+    }
+
+    // 名称、分类或任一标签包含关键字即匹配（不区分大小写）
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        filter.is_empty() || [&self.name, &self.category].into_iter().chain(&self.tags).any(|s| s.to_lowercase().contains(&filter))
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct PlacedBuilding {
     pub uid: usize,
     pub template_name: String,
@@ -308,7 +1066,23 @@ pub struct PlacedBuilding {
     pub color: Color32,
     pub wave_num: i32,
     pub is_late: bool,
+    pub viewport: Option<String>,
+    pub comment: Option<String>,
+    pub offset: [f32; 2],
+    pub offset_seconds: Option<f32>,
+    pub order: Option<u32>,
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
-pub enum EditMode { Terrain, Building, Upgrade, Demolish, BuildingConfig, PrepActions }
\ No newline at end of file
+pub enum EditMode { Terrain, Route, Building, Select, Upgrade, Demolish, Sell, Relocate, Ability, BuildingConfig, PrepActions }
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TerrainTool { Brush, Rect, Line, Noise, Scatter, Smooth }
+
+// 选择模式下正在拖动的建筑，grab_dx/dy 为抓取点相对建筑左上角的格子偏移
+#[derive(Debug, Copy, Clone)]
+pub struct BuildingDrag {
+    pub uid: usize,
+    pub grab_dx: i32,
+    pub grab_dy: i32,
+}
\ No newline at end of file
@@ -0,0 +1,60 @@
+use eframe::egui::{self, Color32, FontId, Painter, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+
+// 🔥 备注：每波一段说明文字、每座建筑一条说明，随策略文件保存，便于他人理解策略意图
+
+const BANNER_MAX_WIDTH: f32 = 520.0;
+
+impl MapEditor {
+    // 绑定当前波次的备注；清空文本即删除
+    pub(crate) fn show_wave_note_editor(&mut self, ui: &mut egui::Ui) {
+        let wave = self.current_wave_num;
+        let mut note = self.wave_notes.get(&wave).cloned().unwrap_or_default();
+        ui.label(trf!("第 {} 波备注:", wave));
+        let response = ui.add(egui::TextEdit::multiline(&mut note).hint_text(tr("例如：本波空袭左路，先补两座减速塔")).desired_rows(2).desired_width(f32::INFINITY));
+        if response.changed() {
+            if note.trim().is_empty() { self.wave_notes.remove(&wave); } else { self.wave_notes.insert(wave, note); }
+        }
+    }
+
+    // 当前波次有备注时在画布顶部居中显示横幅
+    pub(crate) fn draw_wave_note_banner(&self, painter: &Painter, panel_rect: Rect) {
+        let Some(note) = self.wave_notes.get(&self.current_wave_num).map(|n| n.trim()).filter(|n| !n.is_empty()) else { return; };
+        let text = format!("📝 W{}  {}", self.current_wave_num, note);
+        let max_width = BANNER_MAX_WIDTH.min(panel_rect.width() - 40.0).max(100.0);
+        let galley = painter.layout(text, FontId::proportional(15.0), Color32::WHITE, max_width);
+        let top = panel_rect.center_top() + Vec2::new(0.0, 10.0 + self.ruler_inset());
+        let bg_rect = Rect::from_center_size(top + Vec2::new(0.0, galley.size().y / 2.0 + 6.0), galley.size() + Vec2::new(16.0, 12.0));
+        painter.rect_filled(bg_rect, 6.0, Color32::from_rgba_unmultiplied(40, 30, 0, 200));
+        painter.rect_stroke(bg_rect, 6.0, Stroke::new(1.0, Color32::from_rgb(255, 200, 0)));
+        painter.galley(bg_rect.min + Vec2::new(8.0, 6.0), galley, Color32::WHITE);
+    }
+
+    pub(crate) fn open_comment_editor(&mut self, uid: usize) {
+        let current = self.placed_buildings.iter().find(|b| b.uid == uid).and_then(|b| b.comment.clone()).unwrap_or_default();
+        self.comment_edit = Some((uid, current));
+    }
+
+    // 建筑备注弹窗：保存时写回建筑，清空即删除备注
+    pub(crate) fn show_comment_editor(&mut self, ctx: &egui::Context) {
+        let Some((uid, mut text)) = self.comment_edit.take() else { return; };
+        let Some(title) = self.placed_buildings.iter().find(|b| b.uid == uid).map(|b| trf!("{} (UID {}) 的备注", b.template_name, b.uid)) else { return; };
+        let (mut open, mut save, mut cancel) = (true, false, false);
+        egui::Window::new(tr("建筑备注")).id(egui::Id::new("building_comment")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(title);
+            ui.add(egui::TextEdit::multiline(&mut text).hint_text(tr("例如：卡住左路拐角，别挪")).desired_rows(3).desired_width(280.0));
+            ui.horizontal(|ui| {
+                save = ui.button(tr("保存")).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if save {
+            let comment = Some(text.trim().to_string()).filter(|c| !c.is_empty());
+            if let Some(b) = self.placed_buildings.iter_mut().find(|b| b.uid == uid) { b.comment = comment; }
+        } else if open && !cancel {
+            self.comment_edit = Some((uid, text));
+        }
+    }
+}
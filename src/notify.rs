@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+use eframe::egui::{self, Align2, Color32, Vec2};
+
+use crate::i18n::{tr, trf};
+
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum NoticeLevel { Info, Warning, Error }
+
+impl NoticeLevel {
+    fn color(self) -> Color32 {
+        match self {
+            NoticeLevel::Info => Color32::from_rgb(120, 200, 255),
+            NoticeLevel::Warning => Color32::from_rgb(255, 200, 0),
+            NoticeLevel::Error => Color32::from_rgb(255, 90, 90),
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            NoticeLevel::Info => "INFO",
+            NoticeLevel::Warning => "WARN",
+            NoticeLevel::Error => "ERROR",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Notice {
+    pub level: NoticeLevel,
+    pub message: String,
+    pub created: Instant,
+}
+
+// 右下角浮动提示 + 可随时查看的完整日志窗口
+pub struct Notifications {
+    started: Instant,
+    toasts: Vec<Notice>,
+    pub log: Vec<Notice>,
+    pub show_log: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self { started: Instant::now(), toasts: Vec::new(), log: Vec::new(), show_log: false }
+    }
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: NoticeLevel, message: impl Into<String>) {
+        let notice = Notice { level, message: message.into(), created: Instant::now() };
+        println!("[{}] {}", level.tag(), notice.message);
+        self.toasts.push(notice.clone());
+        self.log.push(notice);
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) { self.push(NoticeLevel::Info, message); }
+    pub fn warn(&mut self, message: impl Into<String>) { self.push(NoticeLevel::Warning, message); }
+    pub fn error(&mut self, message: impl Into<String>) { self.push(NoticeLevel::Error, message); }
+
+    pub fn error_count(&self) -> usize {
+        self.log.iter().filter(|n| n.level == NoticeLevel::Error).count()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.toasts.retain(|t| t.created.elapsed() < TOAST_DURATION);
+        if !self.toasts.is_empty() {
+            egui::Area::new("toasts")
+                .anchor(Align2::RIGHT_BOTTOM, Vec2::new(-300.0, -10.0))
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    ui.set_max_width(420.0);
+                    for toast in &self.toasts {
+                        egui::Frame::popup(ui.style()).stroke(egui::Stroke::new(1.5, toast.level.color())).show(ui, |ui| {
+                            ui.colored_label(toast.level.color(), &toast.message);
+                        });
+                    }
+                });
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+
+        let mut open = self.show_log;
+        egui::Window::new(tr("日志")).open(&mut open).default_size([520.0, 300.0]).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(tr("清空")).clicked() { self.log.clear(); }
+                ui.label(trf!("共 {} 条，错误 {} 条", self.log.len(), self.error_count()));
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                for notice in &self.log {
+                    let secs = notice.created.duration_since(self.started).as_secs_f32();
+                    ui.colored_label(notice.level.color(), format!("[{:>7.1}s] [{}] {}", secs, notice.level.tag(), notice.message));
+                }
+            });
+        });
+        self.show_log = open;
+    }
+}
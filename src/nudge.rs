@@ -0,0 +1,33 @@
+use eframe::egui::{self, Key, Modifiers};
+
+use crate::app::MapEditor;
+use crate::i18n::tr;
+use crate::models::EditMode;
+
+// 🔥 方向键微移：选择模式下有选中建筑时，方向键把选中的建筑整体移动一格，按住 Shift 一次移动 5 格。
+// 与拖动相同，整条时间线（含迁移、拆除、出售记录的位置）一起平移，并逐段检查放置是否合法；不合法时不移动。
+// 此时方向键不再移动观察框（W/A/S/D 仍可用）
+
+const SHIFT_STEP: i32 = 5;
+
+impl MapEditor {
+    pub(crate) fn arrow_keys_nudge(&self) -> bool {
+        self.mode == EditMode::Select && !self.selected_uids.is_empty()
+    }
+
+    pub(crate) fn handle_nudge_keys(&mut self, ctx: &egui::Context) {
+        if !self.arrow_keys_nudge() || ctx.wants_keyboard_input() { return; }
+        let (mut dx, mut dy) = (0, 0);
+        ctx.input_mut(|i| {
+            for (key, kx, ky) in [(Key::ArrowLeft, -1, 0), (Key::ArrowRight, 1, 0), (Key::ArrowUp, 0, -1), (Key::ArrowDown, 0, 1)] {
+                // consume_key 会忽略多余的 Shift，先匹配带 Shift 的
+                let step = if i.consume_key(Modifiers::SHIFT, key) { SHIFT_STEP } else if i.consume_key(Modifiers::NONE, key) { 1 } else { 0 };
+                (dx, dy) = (dx + kx * step, dy + ky * step);
+            }
+        });
+        if (dx, dy) == (0, 0) { return; }
+        let uids = self.selected_uids.clone();
+        if self.refuse_locked(uids.iter().any(|uid| self.building_locked(*uid))) { return; }
+        if !self.move_buildings(&uids, dx, dy) { self.notifications.warn(tr("无法移动：目标位置超出网格或与其它建筑 / 地形冲突")); }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+use crate::events::position_at;
+use crate::models::*;
+use crate::utils::{get_time_value, time_order};
+
+// 把策略中的建造 / 升级 / 拆除 / 出售 / 迁移 / 技能按时间顺序展平成一条操作序列
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum OperationAction { Build, Upgrade, Demolish, Sell, Relocate, Ability }
+
+impl OperationAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationAction::Build => "build", OperationAction::Upgrade => "upgrade", OperationAction::Demolish => "demolish",
+            OperationAction::Sell => "sell", OperationAction::Relocate => "relocate", OperationAction::Ability => "ability",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Operation {
+    pub time_value: i32,
+    pub wave_num: i32,
+    pub is_late: bool,
+    pub action: OperationAction,
+    pub building_name: String,
+    // 全局升级没有坐标与 uid，指定建筑的升级为该建筑当时所在的位置；技能的 building_name 为技能名称，坐标为可选的目标格子
+    pub grid_x: Option<usize>,
+    pub grid_y: Option<usize>,
+    pub uid: Option<usize>,
+    // 仅迁移：目标左上角格子（grid_x/grid_y 为迁移前的位置）
+    pub target: Option<(usize, usize)>,
+    // 建造、升级、拆除可带的秒数偏移，见 BuildingExport::offset_seconds
+    pub offset_seconds: Option<f32>,
+    // 建造、升级、拆除在同一时刻内的先后，见 BuildingExport::order
+    pub order: Option<u32>,
+    // 在策略中对应列表（buildings / upgrades / demolishes …）里的下标
+    pub index: usize,
+}
+
+// 同一时间段内先按秒数偏移，同一时刻内设置了 order 的按 order 排在前面，其余按 建造 → 升级 → 拆除 → 出售 → 迁移 → 技能 排列，各自保持原有顺序
+pub fn flatten_operations(strategy: &MapBuildingsExport) -> Vec<Operation> {
+    let builds = strategy.buildings.iter().enumerate().map(|(index, b)| Operation {
+        time_value: get_time_value(b.wave_num, b.is_late), wave_num: b.wave_num, is_late: b.is_late, action: OperationAction::Build,
+        building_name: b.name.clone(), grid_x: Some(b.grid_x), grid_y: Some(b.grid_y), uid: Some(b.uid), target: None, offset_seconds: b.offset_seconds, order: b.order, index,
+    });
+    let upgrades = strategy.upgrades.iter().enumerate().map(|(index, u)| {
+        let time_value = get_time_value(u.wave_num, u.is_late);
+        let pos = u.uid.and_then(|uid| strategy.buildings.iter().find(|b| b.uid == uid)).map(|b| position_at(b.uid, (b.grid_x, b.grid_y), time_value, &strategy.relocations));
+        Operation {
+            time_value, wave_num: u.wave_num, is_late: u.is_late, action: OperationAction::Upgrade,
+            building_name: u.building_name.clone(), grid_x: pos.map(|p| p.0), grid_y: pos.map(|p| p.1), uid: u.uid, target: None, offset_seconds: u.offset_seconds, order: u.order, index,
+        }
+    });
+    let demolishes = strategy.demolishes.iter().enumerate().map(|(index, d)| Operation {
+        time_value: get_time_value(d.wave_num, d.is_late), wave_num: d.wave_num, is_late: d.is_late, action: OperationAction::Demolish,
+        building_name: d.name.clone(), grid_x: Some(d.grid_x), grid_y: Some(d.grid_y), uid: Some(d.uid), target: None, offset_seconds: d.offset_seconds, order: d.order, index,
+    });
+    let sells = strategy.sells.iter().enumerate().map(|(index, s)| Operation {
+        time_value: get_time_value(s.wave_num, s.is_late), wave_num: s.wave_num, is_late: s.is_late, action: OperationAction::Sell,
+        building_name: s.name.clone(), grid_x: Some(s.grid_x), grid_y: Some(s.grid_y), uid: Some(s.uid), target: None, offset_seconds: None, order: s.order, index,
+    });
+    let relocations = strategy.relocations.iter().enumerate().map(|(index, r)| Operation {
+        time_value: get_time_value(r.wave_num, r.is_late), wave_num: r.wave_num, is_late: r.is_late, action: OperationAction::Relocate,
+        building_name: r.name.clone(), grid_x: Some(r.from_x), grid_y: Some(r.from_y), uid: Some(r.uid), target: Some((r.to_x, r.to_y)), offset_seconds: None, order: r.order, index,
+    });
+    let abilities = strategy.abilities.iter().enumerate().map(|(index, a)| Operation {
+        time_value: get_time_value(a.wave_num, a.is_late), wave_num: a.wave_num, is_late: a.is_late, action: OperationAction::Ability,
+        building_name: a.name.clone(), grid_x: a.target.map(|t| t.0), grid_y: a.target.map(|t| t.1), uid: None, target: None, offset_seconds: None, order: a.order, index,
+    });
+    let mut ops: Vec<Operation> = builds.chain(upgrades).chain(demolishes).chain(sells).chain(relocations).chain(abilities).collect();
+    ops.sort_by_key(|op| (time_order(op.wave_num, op.is_late, op.offset_seconds), op.order.unwrap_or(u32::MAX)));
+    ops
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) { format!("\"{}\"", value.replace('"', "\"\"")) } else { value.to_string() }
+}
+
+// 带 UTF-8 BOM，Excel 直接打开时中文名不会乱码
+pub fn operations_csv(ops: &[Operation]) -> String {
+    let opt = |v: Option<usize>| v.map(|v| v.to_string()).unwrap_or_default();
+    let mut csv = String::from("\u{feff}time_value,wave,late,action,building_name,grid_x,grid_y,uid,target_x,target_y,offset_seconds\n");
+    for op in ops {
+        csv += &format!("{},{},{},{},{},{},{},{},{},{},{}\n", op.time_value, op.wave_num, op.is_late, op.action.as_str(), csv_field(&op.building_name), opt(op.grid_x), opt(op.grid_y), opt(op.uid),
+            opt(op.target.map(|t| t.0)), opt(op.target.map(|t| t.1)), op.offset_seconds.map(|s| s.to_string()).unwrap_or_default());
+    }
+    csv
+}
+
+// 绝对时间序列中的一条操作，time_seconds 为从关卡开始算起的秒数，执行端按它依次执行即可
+#[derive(Serialize)]
+pub struct TimedOperation {
+    pub time_seconds: f32,
+    pub wave_num: i32,
+    pub action: &'static str,
+    pub building_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid_x: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid_y: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<(usize, usize)>,
+}
+
+// TOML 不支持根数组，操作列表包在 operations 键下
+#[derive(Serialize)]
+pub struct TimelineExport {
+    pub map_name: String,
+    pub operations: Vec<TimedOperation>,
+}
+
+// 按时间模型中的每波时长换算成绝对时间；没有配置每波时长时返回 None
+pub fn timed_operations(strategy: &MapBuildingsExport, model: &TimeModel) -> Option<TimelineExport> {
+    let mut operations = flatten_operations(strategy).into_iter().map(|op| Some(TimedOperation {
+        time_seconds: model.absolute_seconds(op.wave_num, op.is_late, op.offset_seconds)?,
+        wave_num: op.wave_num, action: op.action.as_str(), building_name: op.building_name, grid_x: op.grid_x, grid_y: op.grid_y, uid: op.uid, target: op.target,
+    })).collect::<Option<Vec<_>>>()?;
+    // 秒数偏移可能越过后期的开始，按绝对时间重新排一次（稳定排序，同一时刻保持原有顺序）
+    operations.sort_by(|a, b| a.time_seconds.total_cmp(&b.time_seconds));
+    Some(TimelineExport { map_name: strategy.map_name.clone(), operations })
+}
+
+// operations 导出中一条操作的类型与各自的字段，按 type 区分
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TypedOperation {
+    Build { uid: usize, name: String, grid_x: usize, grid_y: usize },
+    // 全局升级没有 uid 与坐标
+    Upgrade {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        uid: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        grid_x: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        grid_y: Option<usize>,
+    },
+    Demolish { uid: usize, name: String, grid_x: usize, grid_y: usize },
+    Sell { uid: usize, name: String, grid_x: usize, grid_y: usize },
+    Relocate { uid: usize, name: String, from: (usize, usize), to: (usize, usize) },
+    Ability {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target: Option<(usize, usize)>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct OperationEntry {
+    pub time_value: i32,
+    pub wave_num: i32,
+    pub is_late: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_seconds: Option<f32>,
+    #[serde(flatten)]
+    pub operation: TypedOperation,
+}
+
+#[derive(Serialize)]
+pub struct OperationsExport {
+    pub format_version: u32,
+    pub map_name: String,
+    pub operations: Vec<OperationEntry>,
+}
+
+impl Operation {
+    fn typed(self) -> TypedOperation {
+        // 建造、拆除、出售、迁移总带 uid 与坐标，缺失时记为 0 而不是丢掉这条操作
+        let (uid, grid_x, grid_y) = (self.uid.unwrap_or_default(), self.grid_x.unwrap_or_default(), self.grid_y.unwrap_or_default());
+        let name = self.building_name;
+        match self.action {
+            OperationAction::Build => TypedOperation::Build { uid, name, grid_x, grid_y },
+            OperationAction::Upgrade => TypedOperation::Upgrade { name, uid: self.uid, grid_x: self.grid_x, grid_y: self.grid_y },
+            OperationAction::Demolish => TypedOperation::Demolish { uid, name, grid_x, grid_y },
+            OperationAction::Sell => TypedOperation::Sell { uid, name, grid_x, grid_y },
+            OperationAction::Relocate => TypedOperation::Relocate { uid, name, from: (grid_x, grid_y), to: self.target.unwrap_or_default() },
+            OperationAction::Ability => TypedOperation::Ability { name, target: self.grid_x.zip(self.grid_y) },
+        }
+    }
+}
+
+// 与 CSV 同样的合并与排序，按类型带上各自的字段，读取方不必再自己合并各个列表
+pub fn operations_export(strategy: &MapBuildingsExport) -> OperationsExport {
+    let operations = flatten_operations(strategy).into_iter().map(|op| OperationEntry {
+        time_value: op.time_value, wave_num: op.wave_num, is_late: op.is_late, offset_seconds: op.offset_seconds, operation: op.typed(),
+    }).collect();
+    OperationsExport { format_version: FORMAT_VERSION, map_name: strategy.map_name.clone(), operations }
+}
+
+// 按波次拆开的 operations 导出，每波一份（前期与后期在同一份中，顺序与整体导出相同），供只按波读取的执行器使用
+pub fn operations_by_wave(strategy: &MapBuildingsExport) -> Vec<(i32, OperationsExport)> {
+    let mut waves: BTreeMap<i32, Vec<OperationEntry>> = BTreeMap::new();
+    for entry in operations_export(strategy).operations { waves.entry(entry.wave_num).or_default().push(entry); }
+    waves.into_iter().map(|(wave, operations)| (wave, OperationsExport { format_version: FORMAT_VERSION, map_name: strategy.map_name.clone(), operations })).collect()
+}
@@ -0,0 +1,84 @@
+use std::collections::{BTreeSet, HashSet};
+use eframe::egui::{self, Color32};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::wave_label;
+
+// 🔥 清理孤立事件：删除建筑后残留的拆除 / 出售 / 迁移事件（UID 已不存在）、引用了不存在 UID 的单体升级，
+// 以及模板已不在防御塔列表中的升级。先在窗口中列出将删除的每一条，确认后才应用；锁定波次中的事件不清理
+
+// 预览中最多列出的条目数
+const MAX_LISTED: usize = 30;
+
+pub(crate) struct OrphanCleanupDialog {
+    pruned: Vec<String>,
+    locked: usize,
+}
+
+// 删除孤立事件，返回每条被删除事件的说明与因所在波次被锁定而保留的条数；templates 为空（防御塔列表未加载）时不按模板清理升级
+pub(crate) fn prune_orphan_events(data: &mut MapBuildingsExport, templates: &[String], locked_waves: &BTreeSet<i32>) -> (Vec<String>, usize) {
+    let uids: HashSet<usize> = data.buildings.iter().map(|b| b.uid).collect();
+    let (mut pruned, mut locked) = (Vec::new(), 0);
+    let mut keep = |orphan: bool, wave: i32, describe: &dyn Fn() -> String| {
+        if !orphan { return true; }
+        if locked_waves.contains(&wave) { locked += 1; return true; }
+        pruned.push(describe());
+        false
+    };
+    data.demolishes.retain(|e| keep(!uids.contains(&e.uid), e.wave_num, &|| trf!("{}: 拆除 {} (UID {} 已不存在)", wave_label(e.wave_num, e.is_late), e.name, e.uid)));
+    data.sells.retain(|e| keep(!uids.contains(&e.uid), e.wave_num, &|| trf!("{}: 出售 {} (UID {} 已不存在)", wave_label(e.wave_num, e.is_late), e.name, e.uid)));
+    data.relocations.retain(|e| keep(!uids.contains(&e.uid), e.wave_num, &|| trf!("{}: 迁移 {} (UID {} 已不存在)", wave_label(e.wave_num, e.is_late), e.name, e.uid)));
+    data.upgrades.retain(|e| {
+        let missing_uid = e.uid.filter(|uid| !uids.contains(uid));
+        let unknown_template = !templates.is_empty() && !templates.contains(&e.building_name);
+        keep(missing_uid.is_some() || unknown_template, e.wave_num, &|| match missing_uid {
+            Some(uid) => trf!("{}: 升级 {} (UID {} 已不存在)", wave_label(e.wave_num, e.is_late), e.building_name, uid),
+            None => trf!("{}: 升级 {} (模板不在防御塔列表中)", wave_label(e.wave_num, e.is_late), e.building_name),
+        })
+    });
+    (pruned, locked)
+}
+
+impl MapEditor {
+    fn preview_orphan_cleanup(&self) -> (MapBuildingsExport, Vec<String>, usize) {
+        let mut data = self.build_buildings_export();
+        let templates: Vec<String> = self.building_templates.iter().map(|t| t.name.clone()).collect();
+        let (pruned, locked) = prune_orphan_events(&mut data, &templates, &self.locked_waves);
+        (data, pruned, locked)
+    }
+
+    pub(crate) fn open_orphan_cleanup(&mut self) {
+        let (_, pruned, locked) = self.preview_orphan_cleanup();
+        if pruned.is_empty() && locked == 0 { self.notifications.info(tr("没有孤立事件")); return; }
+        self.orphan_cleanup_dialog = Some(OrphanCleanupDialog { pruned, locked });
+    }
+
+    pub(crate) fn show_orphan_cleanup_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.orphan_cleanup_dialog.take() else { return; };
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        egui::Window::new(tr("清理孤立事件")).id(egui::Id::new("orphan_cleanup")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(trf!("将删除 {} 条事件:", dialog.pruned.len()));
+            egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                for line in dialog.pruned.iter().take(MAX_LISTED) { ui.label(format!("• {}", line)); }
+                if dialog.pruned.len() > MAX_LISTED { ui.weak(trf!("…等 {} 条", dialog.pruned.len())); }
+            });
+            if dialog.locked > 0 { ui.colored_label(Color32::YELLOW, trf!("另有 {} 条位于已锁定的波次，不会删除", dialog.locked)); }
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(!dialog.pruned.is_empty(), egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if apply {
+            // 按当前数据重新清理，打开窗口后的编辑不会被覆盖
+            let (data, pruned, _) = self.preview_orphan_cleanup();
+            self.apply_buildings_data(data);
+            self.checked_events.clear();
+            self.editing_event = None;
+            self.notifications.info(trf!("已删除 {} 条孤立事件", pruned.len()));
+        } else if open && !cancel {
+            self.orphan_cleanup_dialog = Some(dialog);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use eframe::egui::{self, Color32};
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::cli::{load_data_file, DataFile};
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::orphans::prune_orphan_events;
+use crate::storage::DATA_EXTENSIONS;
+use crate::tabs::file_stem;
+
+// 🔥 部分导入：从策略、地形或工程文件中只取建筑、升级、拆除或准备动作中的某几部分，替换当前数据中对应的部分，其余保持不变。
+// uid 按原样保留（适合从同一方案的另一个版本取回一部分）；锁定波次中的当前条目保留、文件中落在锁定波次的条目不导入，
+// 文件中与保留条目 uid 相同的建筑（以及拆除）也跳过，否则同一 uid 会出现两座建筑，事件会挂到错误的那一座上
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section { Buildings, Upgrades, Demolishes, PrepActions }
+
+const SECTIONS: [Section; 4] = [Section::Buildings, Section::Upgrades, Section::Demolishes, Section::PrepActions];
+
+impl Section {
+    fn label(self) -> &'static str {
+        match self {
+            Section::Buildings => tr("建筑"),
+            Section::Upgrades => tr("升级"),
+            Section::Demolishes => tr("拆除"),
+            Section::PrepActions => tr("准备动作"),
+        }
+    }
+}
+
+pub(crate) struct PartialImportDialog {
+    path: PathBuf,
+    strategy: Option<MapBuildingsExport>,
+    prep_actions: Option<Vec<PrepAction>>,
+    selected: Vec<Section>,
+}
+
+// 锁定波次中的当前条目保留，文件中落在锁定波次或 uid（unique 不为 None 时）与保留条目相同的条目不导入；返回 (保留的当前条目数, 跳过的文件条目数)
+fn replace_unlocked<T: Clone>(current: &mut Vec<T>, imported: &[T], wave: fn(&T) -> i32, unique: Option<fn(&T) -> usize>, locked: &BTreeSet<i32>) -> (usize, usize) {
+    current.retain(|x| locked.contains(&wave(x)));
+    let kept = current.len();
+    let kept_uids: HashSet<usize> = unique.map(|uid| current.iter().map(uid).collect()).unwrap_or_default();
+    current.extend(imported.iter().filter(|x| !locked.contains(&wave(x)) && unique.is_none_or(|uid| !kept_uids.contains(&uid(x)))).cloned());
+    (kept, imported.len() - (current.len() - kept))
+}
+
+// 用 incoming 中被选中的部分替换 data 中对应的部分，返回锁定波次中保留与跳过的条目总数
+fn replace_sections(data: &mut MapBuildingsExport, incoming: &MapBuildingsExport, selected: &[Section], locked: &BTreeSet<i32>) -> (usize, usize) {
+    let mut results = Vec::new();
+    if selected.contains(&Section::Buildings) { results.push(replace_unlocked(&mut data.buildings, &incoming.buildings, |b| b.wave_num, Some(|b| b.uid), locked)); }
+    if selected.contains(&Section::Upgrades) { results.push(replace_unlocked(&mut data.upgrades, &incoming.upgrades, |e| e.wave_num, None, locked)); }
+    if selected.contains(&Section::Demolishes) { results.push(replace_unlocked(&mut data.demolishes, &incoming.demolishes, |e| e.wave_num, Some(|e| e.uid), locked)); }
+    results.into_iter().fold((0, 0), |(k, s), (kept, skipped)| (k + kept, s + skipped))
+}
+
+struct PartialPreview {
+    data: MapBuildingsExport,
+    kept: usize,
+    skipped: usize,
+    // 导入后引用了不存在 uid 的事件数
+    orphans: usize,
+}
+
+impl MapEditor {
+    pub(crate) fn open_partial_import(&mut self) {
+        let Some(path) = FileDialog::new().set_directory("output").add_filter(tr("策略 / 地形 / 工程文件"), &[DATA_EXTENSIONS, &[PROJECT_EXTENSION]].concat()).pick_file() else { return; };
+        let (data, report) = match load_data_file(&path) {
+            Ok(loaded) => loaded,
+            Err(e) => { self.notifications.error(e); return; }
+        };
+        self.report_migration(&path, &report);
+        let (strategy, prep_actions) = match data {
+            DataFile::Strategy(s) => (Some(s), None),
+            DataFile::Terrain(t) => (None, Some(t.meta.prep_actions)),
+            DataFile::Project(p) => (Some(p.strategy), Some(p.terrain.meta.prep_actions)),
+            DataFile::BuildingConfigs(_) | DataFile::TerrainTypes(_) => { self.notifications.error(trf!("{} 中没有建筑、事件或准备动作", path.display())); return; }
+        };
+        self.partial_import_dialog = Some(PartialImportDialog { path, strategy, prep_actions, selected: Vec::new() });
+    }
+
+    fn preview_partial_import(&self, dialog: &PartialImportDialog) -> Option<PartialPreview> {
+        let incoming = dialog.strategy.as_ref()?;
+        let mut data = self.build_buildings_export();
+        let (kept, skipped) = replace_sections(&mut data, incoming, &dialog.selected, &self.locked_waves);
+        let (orphans, _) = prune_orphan_events(&mut data.clone(), &[], &BTreeSet::new());
+        Some(PartialPreview { data, kept, skipped, orphans: orphans.len() })
+    }
+
+    pub(crate) fn show_partial_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.partial_import_dialog.take() else { return; };
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        let preview = self.preview_partial_import(&dialog);
+        egui::Window::new(tr("部分导入")).id(egui::Id::new("partial_import")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(trf!("从 {} 导入以下部分（替换当前对应的部分）:", file_stem(&dialog.path)));
+            egui::Grid::new("partial_import_sections").num_columns(2).show(ui, |ui| {
+                for section in SECTIONS {
+                    let counts = match (section, &dialog.strategy, &dialog.prep_actions) {
+                        (Section::Buildings, Some(s), _) => Some((self.placed_buildings.len(), s.buildings.len())),
+                        (Section::Upgrades, Some(s), _) => Some((self.upgrade_events.len(), s.upgrades.len())),
+                        (Section::Demolishes, Some(s), _) => Some((self.demolish_events.len(), s.demolishes.len())),
+                        (Section::PrepActions, _, Some(p)) => Some((self.prep_actions.len(), p.len())),
+                        _ => None,
+                    };
+                    let mut checked = dialog.selected.contains(&section);
+                    if ui.add_enabled(counts.is_some(), egui::Checkbox::new(&mut checked, section.label())).changed() {
+                        if checked { dialog.selected.push(section); } else { dialog.selected.retain(|s| *s != section); }
+                    }
+                    match counts {
+                        Some((current, file)) => ui.label(trf!("当前 {} 条 → 文件中 {} 条", current, file)),
+                        None => ui.weak(tr("文件中没有这一部分")),
+                    };
+                    ui.end_row();
+                }
+            });
+            if let Some(preview) = preview.as_ref().filter(|_| dialog.selected.iter().any(|s| *s != Section::PrepActions)) {
+                if preview.kept + preview.skipped > 0 { ui.colored_label(Color32::YELLOW, trf!("已锁定的波次：保留当前 {} 条，跳过文件中 {} 条（落在锁定波次，或 UID 与保留的条目重复）", preview.kept, preview.skipped)); }
+                if preview.orphans > 0 { ui.colored_label(Color32::YELLOW, trf!("导入后有 {} 条事件引用的 UID 不存在，可用 🧹 清理孤立事件 删除", preview.orphans)); }
+            }
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(!dialog.selected.is_empty(), egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if apply {
+            // 按当前数据重新计算，打开窗口后的编辑不会被覆盖
+            if let Some(preview) = self.preview_partial_import(&dialog).filter(|_| dialog.selected.iter().any(|s| *s != Section::PrepActions)) {
+                self.apply_buildings_data(preview.data);
+                self.checked_events.clear();
+                self.editing_event = None;
+            }
+            if let Some(prep_actions) = dialog.prep_actions.filter(|_| dialog.selected.contains(&Section::PrepActions)) { self.prep_actions = prep_actions; }
+            let names: Vec<&str> = SECTIONS.iter().filter(|s| dialog.selected.contains(s)).map(|s| s.label()).collect();
+            self.notifications.info(trf!("已从 {} 导入: {}", dialog.path.display(), names.join(" / ")));
+        } else if open && !cancel {
+            self.partial_import_dialog = Some(dialog);
+        }
+    }
+}
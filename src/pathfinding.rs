@@ -0,0 +1,46 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::models::Grid;
+
+// 网格 A* 寻路（四邻接，曼哈顿距离启发），负值地形视为不可通行
+// 返回 (列, 行) 序列，包含起点与终点；不可达时返回 None
+pub fn find_path(grid: &Grid, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+    let (rows, cols) = (grid.rows(), grid.cols());
+    let passable = |(c, r): (usize, usize)| r < rows && c < cols && grid.get(r, c) >= 0;
+    if !passable(start) || !passable(goal) { return None; }
+
+    let index = |(c, r): (usize, usize)| r * cols + c;
+    let heuristic = |(c, r): (usize, usize)| c.abs_diff(goal.0) + r.abs_diff(goal.1);
+    let mut cost = vec![usize::MAX; rows * cols];
+    let mut came_from = vec![usize::MAX; rows * cols];
+    let mut open = BinaryHeap::new();
+    cost[index(start)] = 0;
+    open.push(Reverse((heuristic(start), 0, start)));
+
+    while let Some(Reverse((_, g, cell))) = open.pop() {
+        if cell == goal {
+            let mut path = vec![goal];
+            let mut i = index(goal);
+            while came_from[i] != usize::MAX {
+                i = came_from[i];
+                path.push((i % cols, i / cols));
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if g > cost[index(cell)] { continue; }
+        let (c, r) = cell;
+        let neighbours = [(c.wrapping_sub(1), r), (c + 1, r), (c, r.wrapping_sub(1)), (c, r + 1)];
+        for next in neighbours {
+            if !passable(next) { continue; }
+            let ng = g + 1;
+            if ng < cost[index(next)] {
+                cost[index(next)] = ng;
+                came_from[index(next)] = index(cell);
+                open.push(Reverse((ng + heuristic(next), ng, next)));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,216 @@
+
+use std::path::{Path, PathBuf};
+use eframe::egui::{self, Painter, Pos2, Rect, Vec2};
+
+use crate::app::MapEditor;
+use crate::events::position_at;
+use crate::i18n::tr;
+use crate::models::*;
+use crate::storage::write_bytes;
+use crate::utils::get_time_value;
+use crate::validation::Finding;
+
+// 🔥 插件接口：游戏特有的导出格式、校验规则与画布叠加层不必都进核心代码
+// 插件在编译期注册：实现下面的 trait，在 register_plugins 中加入注册表即可，GUI 与命令行共用同一份注册表；
+// 仓库自带的示例插件由 `example-plugins` 特性开启
+
+// 导出与校验时插件看到的数据，与游戏端导出文件的内容一致；字段只由插件读取，没有编译插件时不会用到
+#[cfg_attr(not(feature = "example-plugins"), allow(dead_code))]
+pub struct PluginData<'a> {
+    pub terrain: &'a MapTerrainExport,
+    pub strategy: &'a MapBuildingsExport,
+    pub building_configs: &'a [BuildingConfig],
+}
+
+// 额外的导出格式：输出写到 output/<地图名>/<地图名><插件名>.<扩展名>，与内置的三个导出文件放在一起
+pub trait ExporterPlugin {
+    fn name(&self) -> &str;
+    // 不含点
+    fn extension(&self) -> &str;
+    fn export(&self, data: &PluginData) -> Result<Vec<u8>, String>;
+}
+
+// 额外的校验规则：结果与内置校验一起显示，消息前会加上插件名
+pub trait ValidatorPlugin {
+    fn name(&self) -> &str;
+    fn validate(&self, data: &PluginData) -> Vec<Finding>;
+}
+
+// 画布叠加层：每帧在建筑之上绘制，可在顶部工具栏单独开关
+pub trait OverlayPlugin {
+    fn name(&self) -> &str;
+    fn draw(&self, view: &OverlayView, painter: &Painter);
+}
+
+#[cfg_attr(not(feature = "example-plugins"), allow(dead_code))]
+pub struct OverlayView<'a> {
+    pub strategy: &'a MapBuildingsExport,
+    // 当前时间值，见 get_time_value
+    pub time: i32,
+    origin: Pos2,
+    cell: Vec2,
+}
+
+// 提供给叠加层插件的辅助方法
+#[cfg_attr(not(feature = "example-plugins"), allow(dead_code))]
+impl OverlayView<'_> {
+    // 从格子 (x, y) 开始、w×h 格的屏幕矩形
+    pub fn cell_rect(&self, x: usize, y: usize, w: usize, h: usize) -> Rect {
+        Rect::from_min_size(self.origin + Vec2::new(x as f32 * self.cell.x, y as f32 * self.cell.y), Vec2::new(w as f32 * self.cell.x, h as f32 * self.cell.y))
+    }
+
+    // 建筑在当前时间所在的左上角格子（考虑迁移）
+    pub fn position(&self, b: &BuildingExport) -> (usize, usize) {
+        position_at(b.uid, (b.grid_x, b.grid_y), self.time, &self.strategy.relocations)
+    }
+
+    // 建筑在当前时间存在（已建造且未被拆除或出售）
+    pub fn is_active(&self, b: &BuildingExport) -> bool {
+        let removed = self.strategy.demolishes.iter().filter(|d| d.uid == b.uid).map(|d| get_time_value(d.wave_num, d.is_late))
+            .chain(self.strategy.sells.iter().filter(|s| s.uid == b.uid).map(|s| get_time_value(s.wave_num, s.is_late)))
+            .min().unwrap_or(i32::MAX);
+        get_time_value(b.wave_num, b.is_late) <= self.time && self.time < removed
+    }
+}
+
+#[derive(Default)]
+pub struct PluginRegistry {
+    exporters: Vec<Box<dyn ExporterPlugin>>,
+    validators: Vec<Box<dyn ValidatorPlugin>>,
+    // (插件, 是否显示)
+    overlays: Vec<(Box<dyn OverlayPlugin>, bool)>,
+}
+
+impl PluginRegistry {
+    // 由 register_plugins 中编译进来的插件调用
+    #[cfg_attr(not(feature = "example-plugins"), allow(dead_code))]
+    pub fn register_exporter(&mut self, plugin: impl ExporterPlugin + 'static) { self.exporters.push(Box::new(plugin)); }
+    #[cfg_attr(not(feature = "example-plugins"), allow(dead_code))]
+    pub fn register_validator(&mut self, plugin: impl ValidatorPlugin + 'static) { self.validators.push(Box::new(plugin)); }
+    #[cfg_attr(not(feature = "example-plugins"), allow(dead_code))]
+    pub fn register_overlay(&mut self, plugin: impl OverlayPlugin + 'static) { self.overlays.push((Box::new(plugin), false)); }
+
+    pub fn validate(&self, data: &PluginData) -> Vec<Finding> {
+        self.validators.iter().flat_map(|v| v.validate(data).into_iter().map(|mut f| { f.message = format!("[{}] {}", v.name(), f.message); f })).collect()
+    }
+
+    // 每个导出插件的名称与输出路径 <root>/<地图名>/<地图名><插件名>.<扩展名>，顺序与 export_one 的下标一致
+    pub fn export_paths(&self, root: impl AsRef<Path>, map_name: &str) -> Vec<(String, PathBuf)> {
+        self.exporters.iter().map(|e| (e.name().to_string(), root.as_ref().join(map_name).join(format!("{}{}.{}", map_name, e.name(), e.extension())))).collect()
+    }
+
+    pub fn export_one(&self, index: usize, path: &Path, data: &PluginData) -> Result<(), String> {
+        let Some(e) = self.exporters.get(index) else { return Err(format!("导出插件 {} 不存在", index)); };
+        let bytes = e.export(data).map_err(|err| format!("{}: {}", e.name(), err))?;
+        write_bytes(path, &bytes)
+    }
+
+    // 运行全部导出插件，返回每个插件的输出路径或错误
+    pub fn export_all(&self, root: impl AsRef<Path>, map_name: &str, data: &PluginData) -> Vec<Result<PathBuf, String>> {
+        self.export_paths(root, map_name).into_iter().enumerate().map(|(i, (_, path))| self.export_one(i, &path, data).map(|()| path)).collect()
+    }
+}
+
+// 所有编译进来的插件
+pub fn registry() -> PluginRegistry {
+    let mut registry = PluginRegistry::default();
+    register_plugins(&mut registry);
+    registry
+}
+
+#[allow(unused_variables)]
+fn register_plugins(registry: &mut PluginRegistry) {
+    #[cfg(feature = "example-plugins")]
+    examples::register(registry);
+}
+
+impl MapEditor {
+    pub(crate) fn plugin_findings(&self) -> Vec<Finding> {
+        if self.plugins.validators.is_empty() { return Vec::new(); }
+        let (terrain, strategy) = (self.build_terrain_export(), self.build_buildings_export());
+        self.plugins.validate(&PluginData { terrain: &terrain, strategy: &strategy, building_configs: &self.building_configs })
+    }
+
+    pub(crate) fn export_plugin(&self, index: usize, path: &Path) -> Result<(), String> {
+        let (terrain, strategy) = (self.build_terrain_export(), self.build_buildings_export());
+        self.plugins.export_one(index, path, &PluginData { terrain: &terrain, strategy: &strategy, building_configs: &self.building_configs })
+    }
+
+    pub(crate) fn show_plugin_overlay_toggles(&mut self, ui: &mut egui::Ui) {
+        for (overlay, enabled) in &mut self.plugins.overlays {
+            ui.toggle_value(enabled, overlay.name()).on_hover_text(tr("插件提供的叠加层"));
+        }
+    }
+
+    pub(crate) fn draw_plugin_overlays(&self, painter: &Painter, origin: Pos2, cell: Vec2) {
+        if !self.plugins.overlays.iter().any(|(_, enabled)| *enabled) { return; }
+        let strategy = self.build_buildings_export();
+        let view = OverlayView { strategy: &strategy, time: get_time_value(self.current_wave_num, self.current_is_late), origin, cell };
+        for (overlay, _) in self.plugins.overlays.iter().filter(|(_, enabled)| *enabled) { overlay.draw(&view, painter); }
+    }
+}
+
+// 示例插件：演示三种扩展点的写法，也可以直接使用
+#[cfg(feature = "example-plugins")]
+mod examples {
+    use std::collections::HashMap;
+    use eframe::egui::{Align2, Color32, FontId, Painter};
+
+    use super::*;
+    use crate::economy::build_cost;
+    use crate::utils::{time_order, wave_label};
+
+    pub fn register(registry: &mut PluginRegistry) {
+        registry.register_exporter(BuildingListTsv);
+        registry.register_validator(TemplateLimit { max: 20 });
+        registry.register_overlay(BuildOrder);
+    }
+
+    // 每座建筑一行的制表符分隔清单，便于粘贴到表格软件
+    struct BuildingListTsv;
+
+    impl ExporterPlugin for BuildingListTsv {
+        fn name(&self) -> &str { "建筑清单" }
+        fn extension(&self) -> &str { "tsv" }
+        fn export(&self, data: &PluginData) -> Result<Vec<u8>, String> {
+            let mut out = format!("# {}\nuid\tname\tx\ty\twidth\theight\twave\tcost\n", data.terrain.map_name);
+            for b in &data.strategy.buildings {
+                let cost = build_cost(data.building_configs, &b.name);
+                out += &format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n", b.uid, b.name, b.grid_x, b.grid_y, b.width, b.height, wave_label(b.wave_num, b.is_late), cost);
+            }
+            Ok(out.into_bytes())
+        }
+    }
+
+    // 同一模板的建筑超过 max 座时给出警告
+    struct TemplateLimit { max: usize }
+
+    impl ValidatorPlugin for TemplateLimit {
+        fn name(&self) -> &str { "模板数量上限" }
+        fn validate(&self, data: &PluginData) -> Vec<Finding> {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for b in &data.strategy.buildings { *counts.entry(&b.name).or_default() += 1; }
+            let mut findings: Vec<Finding> = counts.into_iter().filter(|(_, n)| *n > self.max)
+                .map(|(name, n)| Finding::warning(format!("{} 共 {} 座，超过上限 {}", name, n, self.max)))
+                .collect();
+            findings.sort_by(|a, b| a.message.cmp(&b.message));
+            findings
+        }
+    }
+
+    // 在当前存在的建筑上标出它在整个策略中的建造顺序
+    struct BuildOrder;
+
+    impl OverlayPlugin for BuildOrder {
+        fn name(&self) -> &str { "建造顺序" }
+        fn draw(&self, view: &OverlayView, painter: &Painter) {
+            let mut order: Vec<&BuildingExport> = view.strategy.buildings.iter().collect();
+            order.sort_by_key(|b| (time_order(b.wave_num, b.is_late, b.offset_seconds), b.order.unwrap_or(u32::MAX), b.uid));
+            for (i, b) in order.into_iter().enumerate().filter(|(_, b)| view.is_active(b)) {
+                let (x, y) = view.position(b);
+                let rect = view.cell_rect(x, y, b.width, b.height);
+                painter.text(rect.center(), Align2::CENTER_CENTER, (i + 1).to_string(), FontId::monospace(14.0), Color32::WHITE);
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use eframe::egui;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::prep_run::PrepRun;
+
+// 🔥 准备动作：地图加载前由外部程序依次执行的键盘/鼠标操作，坐标为游戏窗口客户区内的像素坐标
+
+fn button_combo(ui: &mut egui::Ui, id: usize, button: &mut MouseButton) {
+    egui::ComboBox::from_id_source(("prep_button", id)).width(60.0).selected_text(button.label()).show_ui(ui, |ui| {
+        for b in MouseButton::ALL { ui.selectable_value(button, b, b.label()); }
+    });
+}
+
+fn point_edit(ui: &mut egui::Ui, x: &mut i32, y: &mut i32) {
+    ui.add(egui::DragValue::new(x).prefix("x "));
+    ui.add(egui::DragValue::new(y).prefix("y "));
+}
+
+// egui 按键 → 准备动作中的按键写法（与 prep_run::parse_key 对应），无法表示的键返回 None
+fn key_name(key: egui::Key) -> Option<String> {
+    use egui::Key as K;
+    let name = match key {
+        K::Space => " ", K::Minus => "-", K::Enter => "enter", K::Escape => "esc", K::Tab => "tab", K::Backspace => "backspace",
+        K::Delete => "delete", K::Home => "home", K::End => "end", K::PageUp => "pageup", K::PageDown => "pagedown",
+        K::ArrowUp => "up", K::ArrowDown => "down", K::ArrowLeft => "left", K::ArrowRight => "right",
+        _ => {
+            let s = key.symbol_or_name();
+            let f_key = s.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()).is_some_and(|n| (1..=12).contains(&n));
+            return (s.chars().count() == 1 || f_key).then(|| s.to_ascii_lowercase());
+        }
+    };
+    Some(name.to_string())
+}
+
+// 录制中的按键序列：相邻事件的间隔记为 Wait，精度约为一帧
+#[derive(Default)]
+pub(crate) struct PrepRecording {
+    last_time: Option<f64>,
+    modifiers: egui::Modifiers,
+    held: Vec<String>,
+    actions: Vec<PrepAction>,
+    skipped: usize,
+}
+
+impl PrepRecording {
+    fn push(&mut self, time: f64, action: PrepAction) {
+        if let Some(last) = self.last_time {
+            let ms = ((time - last) * 1000.0).round().max(0.0) as u64;
+            if ms > 0 { self.actions.push(PrepAction::Wait { ms }); }
+        }
+        self.last_time = Some(time);
+        self.actions.push(action);
+    }
+
+    fn key(&mut self, time: f64, key: String, pressed: bool) {
+        let held = self.held.contains(&key);
+        if pressed && !held {
+            self.held.push(key.clone());
+            self.push(time, PrepAction::KeyDown { key });
+        } else if !pressed && held {
+            self.held.retain(|k| *k != key);
+            self.push(time, PrepAction::KeyUp { key });
+        }
+    }
+
+    // Shift/Ctrl/Alt 单独按下时没有按键事件，按修饰键状态的变化记录
+    fn modifiers(&mut self, time: f64, now: egui::Modifiers) {
+        for (name, before, after) in [("shift", self.modifiers.shift, now.shift), ("ctrl", self.modifiers.ctrl, now.ctrl), ("alt", self.modifiers.alt, now.alt)] {
+            if before != after { self.key(time, name.to_string(), after); }
+        }
+        self.modifiers = now;
+    }
+}
+
+impl MapEditor {
+    pub(crate) fn show_prep_panel(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("准备动作序列:"));
+            ui.label(tr("在地图加载前执行的键盘/鼠标操作序列"));
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button(tr("添加 Log")).clicked() {
+                    self.prep_actions.push(PrepAction::Log { msg: String::new() });
+                }
+                if ui.button(tr("添加 KeyDown")).clicked() {
+                    self.prep_actions.push(PrepAction::KeyDown { key: String::new() });
+                }
+                if ui.button(tr("添加 KeyUp")).clicked() {
+                    self.prep_actions.push(PrepAction::KeyUp { key: String::new() });
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button(tr("添加 Wait")).clicked() {
+                    self.prep_actions.push(PrepAction::Wait { ms: 100 });
+                }
+                if ui.button(tr("添加 KeyUpAll")).clicked() {
+                    self.prep_actions.push(PrepAction::KeyUpAll);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button(tr("添加 MouseMove")).clicked() {
+                    self.prep_actions.push(PrepAction::MouseMove { x: 0, y: 0 });
+                }
+                if ui.button(tr("添加 MouseClick")).clicked() {
+                    self.prep_actions.push(PrepAction::MouseClick { button: MouseButton::Left });
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button(tr("添加 MouseDrag")).clicked() {
+                    self.prep_actions.push(PrepAction::MouseDrag { button: MouseButton::Left, from_x: 0, from_y: 0, to_x: 0, to_y: 0, duration_ms: 200 });
+                }
+                if ui.button(tr("添加 Scroll")).clicked() {
+                    self.prep_actions.push(PrepAction::Scroll { delta: -1 });
+                }
+            });
+        });
+
+        ui.separator();
+        self.show_prep_recording(ui);
+        ui.separator();
+        self.show_prep_test_run(ui);
+        ui.separator();
+
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("动作列表:"));
+
+            let mut delete_idx = None;
+            let mut move_up_idx = None;
+            let mut move_down_idx = None;
+            let actions_count = self.prep_actions.len();
+
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                if self.prep_actions.is_empty() {
+                    ui.label(tr("暂无准备动作"));
+                }
+                for i in 0..actions_count {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", i + 1));
+
+                        match &mut self.prep_actions[i] {
+                            PrepAction::Log { msg } => {
+                                ui.label("Log:");
+                                ui.text_edit_singleline(msg);
+                            }
+                            PrepAction::KeyDown { key } => {
+                                ui.label("KeyDown:");
+                                ui.add(egui::TextEdit::singleline(key).desired_width(40.0));
+                            }
+                            PrepAction::KeyUp { key } => {
+                                ui.label("KeyUp:");
+                                ui.add(egui::TextEdit::singleline(key).desired_width(40.0));
+                            }
+                            PrepAction::Wait { ms } => {
+                                ui.label("Wait:");
+                                ui.add(egui::DragValue::new(ms).speed(10.0));
+                                ui.label("ms");
+                            }
+                            PrepAction::KeyUpAll => {
+                                ui.label("KeyUpAll");
+                            }
+                            PrepAction::MouseMove { x, y } => {
+                                ui.label("MouseMove:");
+                                point_edit(ui, x, y);
+                            }
+                            PrepAction::MouseClick { button } => {
+                                ui.label("MouseClick:");
+                                button_combo(ui, i, button);
+                            }
+                            PrepAction::MouseDrag { button, from_x, from_y, to_x, to_y, duration_ms } => {
+                                ui.vertical(|ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("MouseDrag:");
+                                        button_combo(ui, i, button);
+                                        ui.add(egui::DragValue::new(duration_ms).speed(10.0).suffix(" ms"));
+                                    });
+                                    ui.horizontal(|ui| {
+                                        point_edit(ui, from_x, from_y);
+                                        ui.label("→");
+                                        point_edit(ui, to_x, to_y);
+                                    });
+                                });
+                            }
+                            PrepAction::Scroll { delta } => {
+                                ui.label("Scroll:");
+                                ui.add(egui::DragValue::new(delta)).on_hover_text(tr("滚轮格数，正数向上、负数向下"));
+                            }
+                        }
+
+                        if ui.small_button("↑").clicked() && i > 0 {
+                            move_up_idx = Some(i);
+                        }
+                        if ui.small_button("↓").clicked() && i < actions_count - 1 {
+                            move_down_idx = Some(i);
+                        }
+                        if ui.small_button("×").clicked() {
+                            delete_idx = Some(i);
+                        }
+                    });
+                }
+            });
+
+            if let Some(idx) = delete_idx {
+                self.prep_actions.remove(idx);
+            }
+            if let Some(idx) = move_up_idx {
+                self.prep_actions.swap(idx, idx - 1);
+            }
+            if let Some(idx) = move_down_idx {
+                self.prep_actions.swap(idx, idx + 1);
+            }
+        });
+    }
+
+    // 在其它界面处理输入之前调用：录制期间吞掉键盘事件，不触发快捷键和文本输入
+    pub(crate) fn capture_prep_recording(&mut self, ctx: &egui::Context) {
+        if self.prep_recording.is_none() { return; }
+        if self.mode != EditMode::PrepActions { self.finish_prep_recording(); return; }
+        let Some(rec) = &mut self.prep_recording else { return; };
+        ctx.input_mut(|i| {
+            rec.modifiers(i.time, i.modifiers);
+            for event in &i.events {
+                if let egui::Event::Key { key, physical_key, pressed, repeat: false, .. } = event {
+                    match key_name(physical_key.unwrap_or(*key)) {
+                        Some(name) => rec.key(i.time, name, *pressed),
+                        None => rec.skipped += usize::from(*pressed),
+                    }
+                }
+            }
+            i.events.retain(|e| !matches!(e, egui::Event::Key { .. } | egui::Event::Text(_) | egui::Event::Copy | egui::Event::Cut | egui::Event::Paste(_)));
+        });
+    }
+
+    // 录制结果追加到序列末尾；停止时仍按住的键用 KeyUpAll 收尾
+    fn finish_prep_recording(&mut self) {
+        let Some(mut rec) = self.prep_recording.take() else { return; };
+        if !rec.held.is_empty() { rec.actions.push(PrepAction::KeyUpAll); }
+        let count = rec.actions.len();
+        self.prep_actions.append(&mut rec.actions);
+        match rec.skipped {
+            0 => self.notifications.info(trf!("已录制 {} 个准备动作", count)),
+            n => self.notifications.warn(trf!("已录制 {} 个准备动作，{} 个无法表示的按键被忽略", count, n)),
+        }
+    }
+
+    fn show_prep_recording(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("录制按键:"));
+            match &self.prep_recording {
+                None => {
+                    if ui.button(tr("⏺ 开始录制")).on_hover_text(tr("在编辑器窗口中按键，按下/松开与间隔会被记录为 KeyDown/KeyUp/Wait")).clicked() {
+                        self.prep_recording = Some(PrepRecording::default());
+                    }
+                }
+                Some(rec) => {
+                    let count = rec.actions.len();
+                    ui.horizontal(|ui| {
+                        if ui.button(tr("⏹ 停止录制")).clicked() { self.finish_prep_recording(); }
+                        ui.colored_label(egui::Color32::RED, trf!("● 录制中 ({} 个动作)", count));
+                    });
+                    ui.label(tr("录制期间按键不会触发编辑器快捷键"));
+                    ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+                }
+            }
+        });
+    }
+
+    // 试运行：倒计时后把当前序列发送给焦点窗口，不需要启动完整的外部程序
+    fn show_prep_test_run(&mut self, ui: &mut egui::Ui) {
+        if let Some(run) = &self.prep_run {
+            self.prep_run_log.extend(run.drain_log());
+            if run.is_running() { ui.ctx().request_repaint_after(std::time::Duration::from_millis(100)); }
+        }
+        let running = self.prep_run.as_ref().is_some_and(PrepRun::is_running);
+
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("试运行:"));
+            ui.add_enabled_ui(!running, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(tr("倒计时"));
+                    ui.add(egui::DragValue::new(&mut self.prep_run_countdown).speed(0.1).clamp_range(0.0..=30.0).suffix(" s"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("客户区原点"));
+                    let (x, y) = &mut self.prep_run_origin;
+                    point_edit(ui, x, y);
+                }).response.on_hover_text(tr("游戏窗口客户区左上角的屏幕坐标，鼠标动作的坐标会加上它"));
+            });
+            ui.horizontal(|ui| {
+                if running {
+                    if ui.button(tr("⏹ 停止")).clicked() {
+                        if let Some(run) = &self.prep_run { run.stop(); }
+                    }
+                    ui.spinner();
+                } else if ui.add_enabled(!self.prep_actions.is_empty(), egui::Button::new(tr("▶ 试运行"))).clicked() {
+                    self.prep_run_log.clear();
+                    self.prep_run = Some(PrepRun::start(self.prep_actions.clone(), std::time::Duration::from_secs_f32(self.prep_run_countdown), self.prep_run_origin));
+                    self.notifications.info(trf!("准备动作试运行已开始，共 {} 步", self.prep_actions.len()));
+                }
+                if !running && !self.prep_run_log.is_empty() && ui.button(tr("清空日志")).clicked() { self.prep_run_log.clear(); }
+            });
+            if !self.prep_run_log.is_empty() {
+                egui::ScrollArea::vertical().id_source("prep_run_log").max_height(120.0).stick_to_bottom(true).show(ui, |ui| {
+                    for line in &self.prep_run_log { ui.monospace(line); }
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+
+use crate::i18n::{tr, trf};
+use crate::models::{MouseButton, PrepAction};
+
+// 🔥 准备动作试运行：后台线程通过系统输入向当前焦点窗口发送按键/鼠标事件，逐条回传日志
+// 倒计时用于切换到游戏窗口；鼠标坐标为窗口客户区坐标，加上 origin（客户区左上角的屏幕坐标）后发送
+
+const TICK: Duration = Duration::from_millis(16);
+
+// 按键写法与外部程序一致：单个字符，或 shift/ctrl/alt/tab/enter/esc/space/方向键/f1-f12 等名称（不区分大小写）
+pub fn parse_key(key: &str) -> Result<Key, String> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(if c == ' ' { Key::Space } else { Key::Unicode(c.to_ascii_lowercase()) });
+    }
+    let lower = key.trim().to_ascii_lowercase();
+    let named = match lower.as_str() {
+        "shift" => Key::Shift, "ctrl" | "control" => Key::Control, "alt" => Key::Alt, "meta" | "win" | "super" => Key::Meta,
+        "tab" => Key::Tab, "enter" | "return" => Key::Return, "esc" | "escape" => Key::Escape, "space" => Key::Space,
+        "backspace" => Key::Backspace, "delete" | "del" => Key::Delete, "capslock" => Key::CapsLock,
+        "home" => Key::Home, "end" => Key::End, "pageup" => Key::PageUp, "pagedown" => Key::PageDown,
+        "up" => Key::UpArrow, "down" => Key::DownArrow, "left" => Key::LeftArrow, "right" => Key::RightArrow,
+        "f1" => Key::F1, "f2" => Key::F2, "f3" => Key::F3, "f4" => Key::F4, "f5" => Key::F5, "f6" => Key::F6,
+        "f7" => Key::F7, "f8" => Key::F8, "f9" => Key::F9, "f10" => Key::F10, "f11" => Key::F11, "f12" => Key::F12,
+        _ => return Err(trf!("无法识别的按键 \"{}\"", key)),
+    };
+    Ok(named)
+}
+
+fn to_button(button: MouseButton) -> Button {
+    match button { MouseButton::Left => Button::Left, MouseButton::Right => Button::Right, MouseButton::Middle => Button::Middle }
+}
+
+pub struct PrepRun {
+    cancel: Arc<AtomicBool>,
+    log_rx: Receiver<String>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PrepRun {
+    pub fn start(actions: Vec<PrepAction>, countdown: Duration, origin: (i32, i32)) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (log_tx, log_rx) = mpsc::channel();
+        let flag = cancel.clone();
+        let handle = thread::spawn(move || {
+            let mut runner = Runner { cancel: flag, log: log_tx, started: Instant::now(), origin, held_keys: Vec::new(), held_buttons: Vec::new() };
+            match runner.run(&actions, countdown) {
+                Ok(()) => runner.log(tr("试运行完成")),
+                Err(e) => runner.log(&e),
+            }
+        });
+        Self { cancel, log_rx, handle: Some(handle) }
+    }
+
+    pub fn stop(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    // 取出后台线程新产生的日志行
+    pub fn drain_log(&self) -> Vec<String> {
+        self.log_rx.try_iter().collect()
+    }
+}
+
+impl Drop for PrepRun {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(handle) = self.handle.take() { let _ = handle.join(); }
+    }
+}
+
+struct Runner {
+    cancel: Arc<AtomicBool>,
+    log: Sender<String>,
+    started: Instant,
+    origin: (i32, i32),
+    held_keys: Vec<Key>,
+    held_buttons: Vec<Button>,
+}
+
+impl Runner {
+    fn log(&self, msg: &str) {
+        let _ = self.log.send(format!("[{:>6.2}s] {}", self.started.elapsed().as_secs_f32(), msg));
+    }
+
+    // 分片等待，期间响应停止
+    fn wait(&self, duration: Duration) -> Result<(), String> {
+        let end = Instant::now() + duration;
+        while let Some(left) = end.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+            if self.cancel.load(Ordering::Relaxed) { return Err(tr("已停止").to_string()); }
+            thread::sleep(left.min(TICK));
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, actions: &[PrepAction], countdown: Duration) -> Result<(), String> {
+        // 先解析全部按键，避免执行到一半才发现写错
+        for (i, action) in actions.iter().enumerate() {
+            if let PrepAction::KeyDown { key } | PrepAction::KeyUp { key } = action {
+                parse_key(key).map_err(|e| trf!("第 {} 步: {}", i + 1, e))?;
+            }
+        }
+        let mut enigo = Enigo::new(&Settings { release_keys_when_dropped: true, ..Settings::default() }).map_err(|e| trf!("无法初始化系统输入: {}", e))?;
+        self.log(&trf!("{} 秒后开始，请切换到游戏窗口", format!("{:.1}", countdown.as_secs_f32())));
+        self.wait(countdown)?;
+        let result = actions.iter().enumerate().try_for_each(|(i, action)| self.step(&mut enigo, i, action));
+        self.release_all(&mut enigo);
+        result
+    }
+
+    fn step(&mut self, enigo: &mut Enigo, i: usize, action: &PrepAction) -> Result<(), String> {
+        let input_err = |e: enigo::InputError| trf!("第 {} 步执行失败: {}", i + 1, e);
+        let (ox, oy) = self.origin;
+        match action {
+            PrepAction::Log { msg } => self.log(&format!("{}. Log: {}", i + 1, msg)),
+            PrepAction::KeyDown { key } => {
+                self.log(&format!("{}. KeyDown {}", i + 1, key));
+                let k = parse_key(key)?;
+                enigo.key(k, Direction::Press).map_err(input_err)?;
+                if !self.held_keys.contains(&k) { self.held_keys.push(k); }
+            }
+            PrepAction::KeyUp { key } => {
+                self.log(&format!("{}. KeyUp {}", i + 1, key));
+                let k = parse_key(key)?;
+                enigo.key(k, Direction::Release).map_err(input_err)?;
+                self.held_keys.retain(|h| *h != k);
+            }
+            PrepAction::Wait { ms } => {
+                self.log(&format!("{}. Wait {} ms", i + 1, ms));
+                self.wait(Duration::from_millis(*ms))?;
+            }
+            PrepAction::KeyUpAll => {
+                self.log(&format!("{}. KeyUpAll", i + 1));
+                self.release_all(enigo);
+            }
+            PrepAction::MouseMove { x, y } => {
+                self.log(&format!("{}. MouseMove ({}, {})", i + 1, x, y));
+                enigo.move_mouse(ox + x, oy + y, Coordinate::Abs).map_err(input_err)?;
+            }
+            PrepAction::MouseClick { button } => {
+                self.log(&format!("{}. MouseClick {}", i + 1, button.label()));
+                enigo.button(to_button(*button), Direction::Click).map_err(input_err)?;
+            }
+            PrepAction::MouseDrag { button, from_x, from_y, to_x, to_y, duration_ms } => {
+                self.log(&format!("{}. MouseDrag {} ({}, {}) → ({}, {}) {} ms", i + 1, button.label(), from_x, from_y, to_x, to_y, duration_ms));
+                let b = to_button(*button);
+                enigo.move_mouse(ox + from_x, oy + from_y, Coordinate::Abs).map_err(input_err)?;
+                enigo.button(b, Direction::Press).map_err(input_err)?;
+                self.held_buttons.push(b);
+                let total = Duration::from_millis(*duration_ms);
+                let start = Instant::now();
+                loop {
+                    let t = if total.is_zero() { 1.0 } else { (start.elapsed().as_secs_f32() / total.as_secs_f32()).min(1.0) };
+                    let x = from_x + ((to_x - from_x) as f32 * t).round() as i32;
+                    let y = from_y + ((to_y - from_y) as f32 * t).round() as i32;
+                    enigo.move_mouse(ox + x, oy + y, Coordinate::Abs).map_err(input_err)?;
+                    if t >= 1.0 { break; }
+                    self.wait(TICK)?;
+                }
+                enigo.button(b, Direction::Release).map_err(input_err)?;
+                self.held_buttons.retain(|h| *h != b);
+            }
+            PrepAction::Scroll { delta } => {
+                self.log(&format!("{}. Scroll {}", i + 1, delta));
+                // enigo 中正数向下，与准备动作的约定相反
+                enigo.scroll(-delta, Axis::Vertical).map_err(input_err)?;
+            }
+        }
+        Ok(())
+    }
+
+    // 结束或中途停止时松开仍按住的键和鼠标按钮，避免卡键
+    fn release_all(&mut self, enigo: &mut Enigo) {
+        for k in self.held_keys.drain(..) { let _ = enigo.key(k, Direction::Release); }
+        for b in self.held_buttons.drain(..) { let _ = enigo.button(b, Direction::Release); }
+    }
+}
@@ -0,0 +1,108 @@
+use std::f32::consts::{SQRT_2, TAU};
+use eframe::egui;
+
+use crate::app::MapEditor;
+use crate::i18n::tr;
+use crate::models::TerrainTool;
+
+// 🔥 程序化笔刷：快速铺出自然形状的障碍区或零散的地形
+// 噪声笔刷只画笔刷范围内 Perlin 噪声不低于阈值的格子，散布笔刷按密度随机挑选格子；
+// 两者都由格子坐标与种子决定，同一处反复涂抹结果不变，一笔之内的图案连贯。换一个种子即可换一种图案
+
+// 噪声叠加的倍频数，每层频率翻倍、幅度减半
+const OCTAVES: u32 = 3;
+
+#[derive(Clone, Copy)]
+pub(crate) struct ProceduralBrush {
+    // 噪声一个周期覆盖的格子数，越大斑块越大
+    scale: f32,
+    // 噪声值（0~1）不低于阈值的格子才会被涂上，越高涂得越少
+    threshold: f32,
+    // 散布笔刷涂上的格子比例
+    density: f32,
+    seed: u32,
+}
+
+impl Default for ProceduralBrush {
+    fn default() -> Self { Self { scale: 8.0, threshold: 0.55, density: 0.2, seed: 1 } }
+}
+
+fn hash(x: i32, y: i32, seed: u32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x27d4_eb2d) ^ (y as u32).wrapping_mul(0x1656_67b1) ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+// 0~1 的均匀随机数，只由格子与种子决定
+fn cell_random(x: i32, y: i32, seed: u32) -> f32 {
+    hash(x, y, seed) as f32 / u32::MAX as f32
+}
+
+// 单层 Perlin 噪声，约在 -0.7~0.7 之间
+fn perlin(x: f32, y: f32, seed: u32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (ix, iy) = (x0 as i32, y0 as i32);
+    let (fx, fy) = (x - x0, y - y0);
+    let dot = |gx: i32, gy: i32, dx: f32, dy: f32| { let a = cell_random(gx, gy, seed) * TAU; a.cos() * dx + a.sin() * dy };
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let (u, v) = (fade(fx), fade(fy));
+    lerp(lerp(dot(ix, iy, fx, fy), dot(ix + 1, iy, fx - 1.0, fy), u), lerp(dot(ix, iy + 1, fx, fy - 1.0), dot(ix + 1, iy + 1, fx - 1.0, fy - 1.0), u), v)
+}
+
+// 多层叠加后映射到 0~1
+fn fractal_noise(x: f32, y: f32, seed: u32) -> f32 {
+    let (mut sum, mut amplitude, mut total, mut frequency) = (0.0, 1.0, 0.0, 1.0);
+    for octave in 0..OCTAVES {
+        sum += perlin(x * frequency, y * frequency, seed.wrapping_add(octave)) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    ((sum / total) * SQRT_2 * 0.5 + 0.5).clamp(0.0, 1.0)
+}
+
+impl ProceduralBrush {
+    // 该格子是否会被当前工具涂上；普通笔刷等其它工具不做筛选
+    pub(crate) fn selects(&self, tool: TerrainTool, c: i32, r: i32) -> bool {
+        match tool {
+            TerrainTool::Noise => fractal_noise((c as f32 + 0.5) / self.scale, (r as f32 + 0.5) / self.scale, self.seed) >= self.threshold,
+            TerrainTool::Scatter => cell_random(c, r, self.seed) < self.density,
+            _ => true,
+        }
+    }
+}
+
+impl MapEditor {
+    // 与普通笔刷相同的方形范围，只涂上被噪声或散布选中的格子
+    pub(crate) fn paint_procedural(&mut self, c: i32, r: i32, val: i8) {
+        let (brush, tool, radius) = (self.procedural_brush, self.terrain_tool, self.brush_radius);
+        let (rows, cols) = (self.grid_rows as i32, self.grid_cols as i32);
+        let grid = self.layers_data.get_mut(&self.current_major_z).unwrap().get_grid_mut(self.current_edit_layer_type);
+        for y in (r - radius).max(0)..(r + radius + 1).min(rows) {
+            for x in (c - radius).max(0)..(c + radius + 1).min(cols) {
+                if brush.selects(tool, x, y) { grid.set(y as usize, x as usize, val); }
+            }
+        }
+    }
+
+    pub(crate) fn show_procedural_brush_settings(&mut self, ui: &mut egui::Ui) {
+        let brush = &mut self.procedural_brush;
+        match self.terrain_tool {
+            TerrainTool::Noise => {
+                ui.add(egui::Slider::new(&mut brush.scale, 2.0..=40.0).text(tr("斑块大小"))).on_hover_text(tr("噪声一个周期覆盖的格子数"));
+                ui.add(egui::Slider::new(&mut brush.threshold, 0.0..=1.0).text(tr("阈值"))).on_hover_text(tr("噪声不低于阈值的格子才会被涂上，越高涂得越少"));
+            }
+            TerrainTool::Scatter => { ui.add(egui::Slider::new(&mut brush.density, 0.0..=1.0).text(tr("密度"))).on_hover_text(tr("笔刷范围内被涂上的格子比例")); }
+            _ => return,
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr("种子:"));
+            ui.add(egui::DragValue::new(&mut brush.seed));
+            if ui.small_button("🎲").on_hover_text(tr("换一个随机种子")).clicked() { brush.seed = fastrand::u32(..); }
+        });
+    }
+}
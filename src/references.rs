@@ -0,0 +1,86 @@
+use std::path::Path;
+use eframe::egui::{self, Color32, Painter, Pos2, Rect, TextureHandle};
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::assets::{portable_path, resolve_relative_to};
+use crate::i18n::{tr, trf};
+use crate::models::ReferenceImageEntry;
+use crate::storage::{read_image, IMAGE_EXTENSIONS};
+
+// 🔥 参考图层：在底图之上叠加多张图片（例如干净的地图 + 带标注的截图），各自调整透明度、显隐与上下顺序
+// 与底图一样按底图像素坐标从 (0, 0) 开始绘制，位于网格与建筑之下；随工程文件保存
+
+pub(crate) struct ReferenceImage {
+    pub(crate) entry: ReferenceImageEntry,
+    texture: Option<TextureHandle>,
+}
+
+fn load_texture(ctx: &egui::Context, path: &str) -> Result<TextureHandle, String> {
+    let img = read_image(path)?;
+    let size = [img.width() as _, img.height() as _];
+    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, img.to_rgba8().as_flat_samples().as_slice());
+    Ok(ctx.load_texture(path, color_image, Default::default()))
+}
+
+impl MapEditor {
+    // 加载失败的图片仍保留在列表中（显示为缺失），避免保存工程时丢掉条目
+    fn push_reference_image(&mut self, ctx: &egui::Context, entry: ReferenceImageEntry) {
+        let texture = load_texture(ctx, &entry.path).map_err(|e| self.notifications.error(e)).ok();
+        self.reference_images.push(ReferenceImage { entry, texture });
+    }
+
+    fn add_reference_images(&mut self, ctx: &egui::Context) {
+        let Some(paths) = FileDialog::new().add_filter(tr("图片文件"), IMAGE_EXTENSIONS).pick_files() else { return; };
+        for path in paths {
+            self.push_reference_image(ctx, ReferenceImageEntry { path: path.to_string_lossy().into_owned(), opacity: 0.5, visible: true });
+        }
+    }
+
+    // 工程文件中的路径相对于工程目录保存
+    pub(crate) fn reference_image_entries(&self, project_dir: &Path) -> Vec<ReferenceImageEntry> {
+        self.reference_images.iter().map(|r| ReferenceImageEntry { path: portable_path(project_dir, Path::new(&r.entry.path)), ..r.entry.clone() }).collect()
+    }
+
+    pub(crate) fn load_reference_images(&mut self, ctx: &egui::Context, project_dir: &Path, entries: Vec<ReferenceImageEntry>) {
+        self.reference_images.clear();
+        for entry in entries {
+            let path = resolve_relative_to(project_dir, &entry.path).to_string_lossy().into_owned();
+            self.push_reference_image(ctx, ReferenceImageEntry { path, ..entry });
+        }
+    }
+
+    pub(crate) fn draw_reference_images(&self, painter: &Painter, map_origin: Pos2) {
+        for r in self.reference_images.iter().filter(|r| r.entry.visible) {
+            let Some(tex) = &r.texture else { continue; };
+            painter.image(tex.id(), Rect::from_min_size(map_origin, tex.size_vec2() * self.zoom), Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::from_white_alpha((r.entry.opacity.clamp(0.0, 1.0) * 255.0).round() as u8));
+        }
+    }
+
+    pub(crate) fn show_reference_images(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        egui::CollapsingHeader::new(trf!("参考图层 ({})", self.reference_images.len())).id_source("reference_images").show(ui, |ui| {
+            if ui.button(tr("＋ 添加参考图…")).clicked() { self.add_reference_images(ctx); }
+            let (mut swap, mut remove) = (None, None);
+            let count = self.reference_images.len();
+            // 列表按从上到下显示，与绘制顺序相反
+            for i in (0..count).rev() {
+                let r = &mut self.reference_images[i];
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut r.entry.visible, "");
+                    let name = Path::new(&r.entry.path).file_name().map_or_else(|| r.entry.path.clone(), |n| n.to_string_lossy().into_owned());
+                    let label = if r.texture.is_some() { egui::RichText::new(name) } else { egui::RichText::new(trf!("{} (缺失)", name)).color(Color32::LIGHT_RED) };
+                    ui.label(label).on_hover_text(&r.entry.path);
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut r.entry.opacity, 0.0..=1.0).text(tr("不透明度")));
+                    if ui.add_enabled(i + 1 < count, egui::Button::new("⬆")).on_hover_text(tr("上移一层")).clicked() { swap = Some((i, i + 1)); }
+                    if ui.add_enabled(i > 0, egui::Button::new("⬇")).on_hover_text(tr("下移一层")).clicked() { swap = Some((i, i - 1)); }
+                    if ui.button("✕").on_hover_text(tr("移除")).clicked() { remove = Some(i); }
+                });
+                ui.separator();
+            }
+            if let Some((a, b)) = swap { self.reference_images.swap(a, b); }
+            if let Some(i) = remove { self.reference_images.remove(i); }
+        });
+    }
+}
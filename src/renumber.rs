@@ -0,0 +1,119 @@
+use std::collections::{BTreeSet, HashMap};
+use eframe::egui;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::{time_order, wave_label};
+use crate::wave_lock::locked_changes;
+
+// 🔥 UID 重新编号：大量编辑后 uid 稀疏、起点随意，按建造时间（同一时间按原 uid）或原 uid 的顺序从起始值开始连续编号；
+// 拆除 / 出售 / 迁移事件与单独升级一起改写，应用前可以预览新旧 uid 对照。已无建筑的残留事件改用排在新编号之后的 uid，不会挂到别的建筑上；
+// 会改写锁定波次中建筑或事件 uid 的编号不能应用
+
+pub(crate) struct RenumberDialog {
+    start: usize,
+    by_build_time: bool,
+    preview: bool,
+}
+
+// 旧 uid → 新 uid，按新 uid 的顺序排列
+pub(crate) fn renumber_mapping(buildings: &[BuildingExport], start: usize, by_build_time: bool) -> Vec<(usize, usize)> {
+    let mut order: Vec<&BuildingExport> = buildings.iter().collect();
+    if by_build_time { order.sort_by_key(|b| (time_order(b.wave_num, b.is_late, b.offset_seconds), b.order.unwrap_or(u32::MAX), b.uid)); } else { order.sort_by_key(|b| b.uid); }
+    order.into_iter().enumerate().map(|(i, b)| (b.uid, start + i)).collect()
+}
+
+// 按对照表改写建筑与引用它们的事件；对照表之外的 uid（已无建筑的残留事件）改为排在新编号之后的 uid，不会与重新编号的建筑撞号
+pub(crate) fn renumber_strategy(data: &mut MapBuildingsExport, mapping: &[(usize, usize)]) {
+    let mut map: HashMap<usize, usize> = mapping.iter().copied().collect();
+    let event_uids = data.demolishes.iter().map(|e| e.uid).chain(data.sells.iter().map(|e| e.uid)).chain(data.relocations.iter().map(|e| e.uid)).chain(data.upgrades.iter().filter_map(|e| e.uid));
+    let orphans: BTreeSet<usize> = event_uids.filter(|uid| !map.contains_key(uid)).collect();
+    let next = map.values().map(|uid| uid + 1).max().unwrap_or(0);
+    map.extend(orphans.into_iter().enumerate().map(|(i, uid)| (uid, next + i)));
+    let remap = |uid: &mut usize| if let Some(new) = map.get(uid) { *uid = *new; };
+    for b in &mut data.buildings { remap(&mut b.uid); }
+    for e in &mut data.demolishes { remap(&mut e.uid); }
+    for e in &mut data.sells { remap(&mut e.uid); }
+    for e in &mut data.relocations { remap(&mut e.uid); }
+    for e in &mut data.upgrades { if let Some(uid) = &mut e.uid { remap(uid); } }
+}
+
+impl MapEditor {
+    pub(crate) fn open_renumber_dialog(&mut self) {
+        let start = self.placed_buildings.iter().map(|b| b.uid).min().unwrap_or(1000).min(1000);
+        self.renumber_dialog = Some(RenumberDialog { start, by_build_time: true, preview: false });
+    }
+
+    // 按对照表改写后锁定波次中 uid 变化的建筑与事件条数
+    fn renumber_locked(&self, mapping: &[(usize, usize)]) -> usize {
+        let before = self.build_buildings_export();
+        let mut after = before.clone();
+        renumber_strategy(&mut after, mapping);
+        locked_changes(&before, &after, &self.locked_waves)
+    }
+
+    // 返回 uid 发生变化的建筑数量
+    pub(crate) fn renumber_uids(&mut self, start: usize, by_build_time: bool) -> usize {
+        let mut data = self.build_buildings_export();
+        let mapping = renumber_mapping(&data.buildings, start, by_build_time);
+        renumber_strategy(&mut data, &mapping);
+        let event_uid = data.demolishes.iter().map(|e| e.uid).chain(data.sells.iter().map(|e| e.uid)).chain(data.relocations.iter().map(|e| e.uid)).chain(data.upgrades.iter().filter_map(|e| e.uid)).max();
+        self.apply_buildings_data(data);
+        self.next_uid = self.next_uid.max(event_uid.map_or(0, |uid| uid + 1));
+        let map: HashMap<usize, usize> = mapping.iter().copied().collect();
+        self.selected_uids = self.selected_uids.iter().filter_map(|uid| map.get(uid).copied()).collect();
+        self.building_drag = None;
+        self.context_menu_uid = None;
+        self.upgrade_hover_uid = None;
+        self.comment_edit = None;
+        self.building_edit = None;
+        mapping.iter().filter(|(old, new)| old != new).count()
+    }
+
+    pub(crate) fn show_renumber_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.renumber_dialog.take() else { return; };
+        let mapping = renumber_mapping(&self.build_buildings_export().buildings, dialog.start, dialog.by_build_time);
+        let changed = mapping.iter().filter(|(old, new)| old != new).count();
+        let locked = self.renumber_locked(&mapping);
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        egui::Window::new(tr("重新编号 UID")).id(egui::Id::new("renumber_uids")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("起始 UID:"));
+                ui.add(egui::DragValue::new(&mut dialog.start).clamp_range(0..=usize::MAX / 2));
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut dialog.by_build_time, true, tr("按建造时间"));
+                ui.radio_value(&mut dialog.by_build_time, false, tr("按原 UID"));
+            });
+            ui.label(trf!("{} 座建筑中 {} 座的 UID 会改变，拆除 / 出售 / 迁移事件与单独升级一并改写", mapping.len(), changed));
+            ui.checkbox(&mut dialog.preview, tr("预览对照表"));
+            if dialog.preview {
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    egui::Grid::new("renumber_preview").num_columns(4).striped(true).show(ui, |ui| {
+                        let buildings: HashMap<usize, &PlacedBuilding> = self.placed_buildings.iter().map(|b| (b.uid, b)).collect();
+                        for (old, new) in &mapping {
+                            let Some(b) = buildings.get(old) else { continue; };
+                            ui.label(&b.template_name);
+                            ui.label(wave_label(b.wave_num, b.is_late));
+                            ui.label(old.to_string());
+                            if old == new { ui.weak(format!("→ {}", new)); } else { ui.strong(format!("→ {}", new)); }
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+            if locked > 0 { ui.colored_label(egui::Color32::RED, trf!("其中 {} 条建筑或事件属于已锁定的波次，请先在波次设置中解锁", locked)); }
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(changed > 0 && locked == 0, egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if apply {
+            let changed = self.renumber_uids(dialog.start, dialog.by_build_time);
+            self.notifications.info(trf!("已重新编号 {} 座建筑的 UID", changed));
+        } else if open && !cancel {
+            self.renumber_dialog = Some(dialog);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+use eframe::egui::{self, Color32, Rect};
+use serde_json::Value;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::storage::{decode_data, encode_data, DataFormat};
+
+// 🔥 往返自检：把当前工程按每种导出格式编码后再解析成新的模型，与导出前逐字段比较，
+// 任何字段丢失或变化（skip_serializing、默认值、TOML 省略 null、浮点精度等）都列出具体路径，JSON 表示相同但结构体不等的部分也列出；
+// 界面中还会把重新导入的 JSON 工程与编辑器内存中的数据逐项对照，防止导出时漏掉编辑器里的字段
+
+// 每种格式最多列出的差异数
+const MAX_DIFFS: usize = 20;
+
+pub struct RoundTripResult {
+    pub format: DataFormat,
+    // 编码或解析失败时为错误信息，否则为逐字段差异
+    pub problems: Vec<String>,
+}
+
+fn describe(value: Option<&Value>) -> String {
+    match value {
+        None => "(缺失)".into(),
+        Some(v) => {
+            let text = v.to_string();
+            if text.chars().count() > 60 { format!("{}…", text.chars().take(60).collect::<String>()) } else { text }
+        }
+    }
+}
+
+// 递归比较两棵 JSON 树，记录不同之处的路径（如 terrain.layers[2].floor_grid[14]）
+pub fn diff_values(before: &Value, after: &Value, path: &str, out: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))).collect();
+            keys.sort();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_values(x, y, &child, out),
+                    (x, y) => out.push(format!("{}: {} → {}", child, describe(x), describe(y))),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() { diff_values(x, y, &format!("{}[{}]", path, i), out); }
+        }
+        (Value::Array(a), Value::Array(b)) => out.push(format!("{}: 长度 {} → {}", path, a.len(), b.len())),
+        (a, b) if a != b => out.push(format!("{}: {} → {}", path, describe(Some(a)), describe(Some(b)))),
+        _ => {}
+    }
+}
+
+fn roundtrip(project: &ProjectFile, before: &Value, format: DataFormat) -> Result<(ProjectFile, Vec<String>), String> {
+    let bytes = encode_data(project, format).map_err(|e| format!("编码失败: {}", e))?;
+    let reimported: ProjectFile = decode_data(&bytes, format).map_err(|e| format!("重新解析失败: {}", e))?;
+    let after = serde_json::to_value(&reimported).map_err(|e| e.to_string())?;
+    let mut problems = Vec::new();
+    diff_values(before, &after, "", &mut problems);
+    // 不参与序列化的字段在 JSON 树中看不到，再按结构体比较一次
+    if problems.is_empty() && reimported != *project { problems.extend(struct_diff(project, &reimported)); }
+    Ok((reimported, problems))
+}
+
+// 结构体层面不相等的顶层部分
+fn struct_diff(before: &ProjectFile, after: &ProjectFile) -> Vec<String> {
+    let parts = [
+        ("terrain.meta", before.terrain.meta == after.terrain.meta),
+        ("terrain.layers", before.terrain.layers == after.terrain.layers),
+        ("terrain.routes", before.terrain.routes == after.terrain.routes),
+        ("strategy", before.strategy == after.strategy),
+        ("building_configs", before.building_configs == after.building_configs),
+        ("reference_images", before.reference_images == after.reference_images),
+    ];
+    let mut diffs: Vec<String> = parts.into_iter().filter(|(_, same)| !same).map(|(name, _)| trf!("{}: JSON 相同但结构体不一致（可能有未序列化的字段）", name)).collect();
+    if diffs.is_empty() { diffs.push(tr("工程: JSON 相同但结构体不一致（可能有未序列化的字段）").to_string()); }
+    diffs
+}
+
+// 按每种导出格式往返一次；第一项为 JSON，同时返回重新导入的 JSON 工程
+pub fn check_project_roundtrip(project: &ProjectFile) -> (Vec<RoundTripResult>, Option<ProjectFile>) {
+    let before = match serde_json::to_value(project) {
+        Ok(value) => value,
+        Err(e) => return (DataFormat::ALL.iter().map(|&format| RoundTripResult { format, problems: vec![format!("编码失败: {}", e)] }).collect(), None),
+    };
+    let mut reimported_json = None;
+    let results = DataFormat::ALL.iter().map(|&format| {
+        let problems = match roundtrip(project, &before, format) {
+            Ok((reimported, problems)) => {
+                if format == DataFormat::Json { reimported_json = Some(reimported); }
+                problems
+            }
+            Err(e) => vec![e],
+        };
+        RoundTripResult { format, problems }
+    }).collect();
+    (results, reimported_json)
+}
+
+pub(crate) struct RoundTripDialog {
+    results: Vec<RoundTripResult>,
+    // 重新导入的工程与编辑器内存数据的差异
+    editor: Vec<String>,
+}
+
+impl MapEditor {
+    // 逐项对照重新导入的工程与编辑器中的数据；导入时会重新计算的 next_uid 不算在内
+    fn editor_roundtrip_diff(&self, project: &ProjectFile) -> Vec<String> {
+        let (terrain, strategy, meta) = (&project.terrain, &project.strategy, &project.terrain.meta);
+        let buildings_match = self.build_buildings_export().buildings == strategy.buildings;
+        let layers_match = self.layers_data.len() == terrain.layers.len() && terrain.layers.iter().all(|l| self.layers_data.get(&l.major_z) == Some(l));
+        let safe_areas: Vec<Rect> = meta.viewport_safe_areas.iter().map(|a| (*a).into()).collect();
+        let checks = [
+            ("grid_rows / grid_cols", (self.grid_rows, self.grid_cols) == terrain.grid_size() || self.layers_data.values().all(|l| l.floor_grid.is_empty())),
+            ("grid_pixel_width / grid_pixel_height", (self.grid_width, self.grid_height) == (meta.grid_pixel_width, meta.grid_pixel_height)),
+            ("offset_x / offset_y", (self.offset_x, self.offset_y) == (meta.offset_x, meta.offset_y)),
+            ("camera_speed_*", (self.camera_speed_up, self.camera_speed_down, self.camera_speed_left, self.camera_speed_right) == (meta.camera_speed_up, meta.camera_speed_down, meta.camera_speed_left, meta.camera_speed_right)),
+            ("layers", layers_match),
+            ("routes", self.enemy_routes == terrain.routes),
+            ("viewport_safe_areas", self.viewport_safe_areas == safe_areas),
+            ("prep_actions", self.prep_actions == meta.prep_actions),
+            ("camera_keyframes", self.camera_keyframes == meta.camera_keyframes),
+            ("viewport_presets", self.viewport_presets == meta.viewport_presets),
+            ("time_model", self.time_model == meta.time_model),
+            ("buildings", buildings_match),
+            ("upgrades", self.upgrade_events == strategy.upgrades),
+            ("demolishes", self.demolish_events == strategy.demolishes),
+            ("sells", self.sell_events == strategy.sells),
+            ("relocations", self.relocate_events == strategy.relocations),
+            ("abilities", self.ability_events == strategy.abilities),
+            ("wave_notes", self.wave_notes == strategy.wave_notes),
+            ("locked_waves", self.locked_waves == strategy.locked_waves),
+            ("economy", self.economy == strategy.economy),
+            ("building_configs", self.building_configs == project.building_configs),
+        ];
+        checks.into_iter().filter(|(_, ok)| !ok).map(|(name, _)| trf!("{} 与编辑器中的数据不一致", name)).collect()
+    }
+
+    pub(crate) fn run_roundtrip_check(&mut self) {
+        let project = self.build_project_file(Path::new("output"));
+        let (results, reimported) = check_project_roundtrip(&project);
+        let editor = reimported.map_or_else(|| vec![tr("JSON 往返失败，无法与编辑器对照").to_string()], |p| self.editor_roundtrip_diff(&p));
+        let failed = results.iter().filter(|r| !r.problems.is_empty()).count() + usize::from(!editor.is_empty());
+        if failed == 0 { self.notifications.info(tr("往返自检通过：所有格式导出后重新导入均无差异")); } else { self.notifications.error(trf!("往返自检发现 {} 项问题", failed)); }
+        self.roundtrip_dialog = Some(RoundTripDialog { results, editor });
+    }
+
+    pub(crate) fn show_roundtrip_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.roundtrip_dialog.take() else { return; };
+        let (mut open, mut close) = (true, false);
+        egui::Window::new(tr("往返自检")).id(egui::Id::new("roundtrip_check")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                for result in &dialog.results {
+                    if result.problems.is_empty() {
+                        ui.colored_label(Color32::GREEN, trf!("✔ {}: 无差异", result.format.label()));
+                        continue;
+                    }
+                    ui.colored_label(Color32::RED, trf!("✖ {}: {} 处差异", result.format.label(), result.problems.len()));
+                    for p in result.problems.iter().take(MAX_DIFFS) { ui.label(format!("  • {}", p)); }
+                    if result.problems.len() > MAX_DIFFS { ui.weak(trf!("…等 {} 条", result.problems.len())); }
+                }
+                if dialog.editor.is_empty() {
+                    ui.colored_label(Color32::GREEN, tr("✔ 重新导入的工程与编辑器中的数据一致"));
+                } else {
+                    for line in &dialog.editor { ui.colored_label(Color32::RED, format!("✖ {}", line)); }
+                }
+            });
+            close = ui.button(tr("关闭")).clicked();
+        });
+        if open && !close { self.roundtrip_dialog = Some(dialog); }
+    }
+}
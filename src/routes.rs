@@ -0,0 +1,173 @@
+use eframe::egui::{self, Align2, Color32, FontId, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::models::*;
+use crate::pathfinding::find_path;
+use crate::i18n::{tr, trf};
+
+// 🔥 敌人路线编辑：出生点 / 路径点 / 出口，随地形数据一起导出
+
+const ROUTE_COLORS: [Color32; 4] = [Color32::from_rgb(255, 120, 0), Color32::from_rgb(0, 200, 255), Color32::from_rgb(255, 0, 200), Color32::from_rgb(200, 255, 0)];
+
+// 按 出生点 → 路径点 → 出口 逐段寻路的结果；blocked 表示某一段不可达，cells 只含可达部分
+pub struct RoutePreview {
+    pub cells: Vec<(usize, usize)>,
+    pub blocked: bool,
+}
+
+fn kind_label(kind: RoutePointKind) -> &'static str {
+    match kind { RoutePointKind::Spawn => tr("出生点"), RoutePointKind::Waypoint => tr("路径点"), RoutePointKind::Exit => tr("出口") }
+}
+
+impl MapEditor {
+    pub(crate) fn show_route_panel(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("敌人路线:"));
+            let mut delete_idx = None;
+            for (i, route) in self.enemy_routes.iter_mut().enumerate() {
+                ui.push_id(i, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.selected_route_idx, i, "");
+                        ui.add(egui::TextEdit::singleline(&mut route.name).desired_width(120.0));
+                        if ui.button("[X]").clicked() { delete_idx = Some(i); }
+                    });
+                });
+            }
+            if let Some(idx) = delete_idx {
+                self.enemy_routes.remove(idx);
+                self.selected_route_idx = self.selected_route_idx.min(self.enemy_routes.len().saturating_sub(1));
+            }
+            if ui.button(tr("➕ 新建路线")).clicked() { self.add_route(); }
+            ui.separator();
+
+            ui.label(tr("放置类型:"));
+            ui.horizontal(|ui| {
+                for kind in [RoutePointKind::Spawn, RoutePointKind::Waypoint, RoutePointKind::Exit] {
+                    ui.radio_value(&mut self.route_point_kind, kind, kind_label(kind));
+                }
+            });
+            ui.separator();
+
+            ui.checkbox(&mut self.show_route_preview, tr("寻路预览 (地面层, 障碍不可通行)"));
+            if self.show_route_preview {
+                if let Some(previews) = &self.route_preview {
+                    for (route, preview) in self.enemy_routes.iter().zip(previews) {
+                        if preview.blocked { ui.colored_label(Color32::RED, trf!("{}: 无法到达", route.name)); }
+                        else if !preview.cells.is_empty() { ui.label(trf!("{}: {} 格", route.name, preview.cells.len() - 1)); }
+                    }
+                }
+            }
+        });
+
+        let Some(route) = self.enemy_routes.get_mut(self.selected_route_idx) else { return; };
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(trf!("{} 的路径点:", route.name));
+            let mut delete_idx = None;
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                if route.points.is_empty() { ui.label(tr("在地图上左键放置")); }
+                for (i, p) in route.points.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button("[X]").clicked() { delete_idx = Some(i); }
+                        ui.label(format!("{}. {} ({}, {})", i + 1, kind_label(p.kind), p.grid_x, p.grid_y));
+                    });
+                }
+            });
+            if let Some(idx) = delete_idx { route.points.remove(idx); }
+            if ui.button(tr("清空路径点")).clicked() { route.points.clear(); }
+        });
+    }
+
+    fn add_route(&mut self) {
+        self.enemy_routes.push(EnemyRoute { name: format!("路线{}", self.enemy_routes.len() + 1), points: Vec::new() });
+        self.selected_route_idx = self.enemy_routes.len() - 1;
+    }
+
+    // 寻路结果缓存到数据变化（见 update 末尾）或切换主层级为止
+    pub(crate) fn refresh_route_preview(&mut self) {
+        if !self.show_route_preview || self.route_preview.is_some() { return; }
+        let Some(layer) = self.layers_data.get(&self.current_major_z) else { return; };
+        let grid = layer.get_grid(BuildingType::Floor);
+        let previews = self.enemy_routes.iter().map(|route| {
+            let mut preview = RoutePreview { cells: Vec::new(), blocked: false };
+            for pair in route.points.windows(2) {
+                match find_path(grid, (pair[0].grid_x, pair[0].grid_y), (pair[1].grid_x, pair[1].grid_y)) {
+                    Some(segment) => {
+                        let skip = usize::from(!preview.cells.is_empty());
+                        preview.cells.extend(segment.into_iter().skip(skip));
+                    }
+                    None => { preview.blocked = true; break; }
+                }
+            }
+            preview
+        }).collect();
+        self.route_preview = Some(previews);
+    }
+
+    // 各路线按顺序连线；非路线模式下淡化显示
+    pub(crate) fn draw_routes(&self, painter: &Painter, origin: Pos2, cell: Vec2) {
+        let editing = self.mode == EditMode::Route;
+        let center = |p: &RoutePoint| origin + Vec2::new((p.grid_x as f32 + 0.5) * cell.x, (p.grid_y as f32 + 0.5) * cell.y);
+        let radius = (cell.x.min(cell.y) * 0.4).max(3.0);
+        if let (true, Some(previews)) = (self.show_route_preview, &self.route_preview) {
+            for (i, preview) in previews.iter().enumerate() {
+                let color = ROUTE_COLORS[i % ROUTE_COLORS.len()];
+                for &(c, r) in &preview.cells {
+                    let rect = Rect::from_min_size(origin + Vec2::new(c as f32 * cell.x, r as f32 * cell.y), cell);
+                    painter.rect_filled(rect.shrink(cell.x.min(cell.y) * 0.3), 1.0, color.linear_multiply(0.5));
+                }
+                if preview.blocked {
+                    if let Some(&(c, r)) = preview.cells.last() {
+                        let pos = origin + Vec2::new((c as f32 + 0.5) * cell.x, (r as f32 + 0.5) * cell.y);
+                        painter.text(pos, Align2::CENTER_BOTTOM, "✖", FontId::proportional(18.0), Color32::RED);
+                    }
+                }
+            }
+        }
+        for (i, route) in self.enemy_routes.iter().enumerate() {
+            let selected = editing && i == self.selected_route_idx;
+            let color = ROUTE_COLORS[i % ROUTE_COLORS.len()].linear_multiply(if editing && !selected { 0.4 } else if editing { 1.0 } else { 0.6 });
+            let line: Vec<Pos2> = route.points.iter().map(center).collect();
+            painter.add(egui::Shape::line(line, Stroke::new(if selected { 3.0 } else { 2.0 }, color)));
+            for (n, p) in route.points.iter().enumerate() {
+                let pos = center(p);
+                match p.kind {
+                    RoutePointKind::Spawn => {
+                        painter.circle_filled(pos, radius, Color32::from_rgb(0, 180, 0));
+                        painter.text(pos, Align2::CENTER_CENTER, "S", FontId::proportional(radius * 1.5), Color32::WHITE);
+                    }
+                    RoutePointKind::Exit => {
+                        painter.rect_filled(Rect::from_center_size(pos, Vec2::splat(radius * 2.0)), 2.0, Color32::from_rgb(200, 0, 0));
+                        painter.text(pos, Align2::CENTER_CENTER, "E", FontId::proportional(radius * 1.5), Color32::WHITE);
+                    }
+                    RoutePointKind::Waypoint => {
+                        painter.circle_filled(pos, radius * 0.6, color);
+                        if selected { painter.text(pos + Vec2::new(radius, -radius), Align2::LEFT_BOTTOM, n.to_string(), FontId::proportional(12.0), Color32::WHITE); }
+                    }
+                }
+            }
+        }
+    }
+
+    // 路线模式的画布交互：左键放置、拖动已有点移动、右键删除
+    pub(crate) fn handle_route_input(&mut self, response: &egui::Response, input: &egui::InputState, cx: i32, ry: i32) {
+        if cx < 0 || ry < 0 || cx as usize >= self.grid_cols || ry as usize >= self.grid_rows { return; }
+        let (x, y) = (cx as usize, ry as usize);
+        if self.enemy_routes.is_empty() && input.pointer.primary_pressed() { self.add_route(); }
+        let Some(route) = self.enemy_routes.get_mut(self.selected_route_idx) else { return; };
+
+        if let Some(idx) = self.route_drag {
+            if input.pointer.primary_down() {
+                if let Some(p) = route.points.get_mut(idx) { p.grid_x = x; p.grid_y = y; }
+            }
+        } else if input.pointer.primary_pressed() {
+            match route.point_at(x, y) {
+                Some(idx) => self.route_drag = Some(idx),
+                None => route.insert_point(self.route_point_kind, x, y),
+            }
+        } else if response.clicked_by(egui::PointerButton::Secondary) {
+            if let Some(idx) = route.point_at(x, y) { route.points.remove(idx); }
+        }
+    }
+}
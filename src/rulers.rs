@@ -0,0 +1,90 @@
+use eframe::egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Stroke, Vec2};
+
+use crate::app::MapEditor;
+
+// 🔥 画布标尺：上/左两条，外侧半边为底图像素刻度，内侧半边为网格行列号，随缩放与平移刷新
+
+pub const RULER_SIZE: f32 = 24.0;
+const LABEL_SPACING: f32 = 60.0;
+const GRID_LABEL_SPACING: f32 = 24.0;
+const PIXEL_COLOR: Color32 = Color32::from_gray(200);
+const GRID_COLOR: Color32 = Color32::from_rgb(120, 230, 120);
+
+// 1/2/5 × 10^k 中屏幕间距不小于 min_screen 的最小步长
+fn nice_step(scale: f32, min_screen: f32) -> f32 {
+    let mut step = 10f32.powf((min_screen / scale).log10().floor());
+    for mult in [1.0, 2.0, 5.0, 10.0] {
+        if step * mult * scale >= min_screen { step *= mult; break; }
+    }
+    step
+}
+
+impl MapEditor {
+    // 被标尺占用的画布边距，其它叠加层（悬浮信息、小地图）据此避让
+    pub(crate) fn ruler_inset(&self) -> f32 {
+        if self.show_rulers { RULER_SIZE } else { 0.0 }
+    }
+
+    pub(crate) fn draw_rulers(&self, painter: &Painter, panel_rect: Rect, origin: Pos2, cell: Vec2, hover: Option<Pos2>) {
+        if !self.show_rulers { return; }
+        let top = Rect::from_min_max(panel_rect.min, Pos2::new(panel_rect.max.x, panel_rect.min.y + RULER_SIZE));
+        let left = Rect::from_min_max(panel_rect.min, Pos2::new(panel_rect.min.x + RULER_SIZE, panel_rect.max.y));
+        let bg = Color32::from_black_alpha(190);
+        painter.rect_filled(top, 0.0, bg);
+        painter.rect_filled(left, 0.0, bg);
+        let font = FontId::monospace(10.0);
+        let half = RULER_SIZE / 2.0;
+
+        // 底图像素刻度：屏幕坐标 = panel_rect.min + pan + 像素 * zoom
+        let image_origin = panel_rect.min + self.pan;
+        let step = nice_step(self.zoom, LABEL_SPACING);
+        let minor = step / 5.0;
+        for (axis, range) in [(0, top.x_range()), (1, left.y_range())] {
+            let start = ((range.min - image_origin[axis]) / self.zoom / minor).floor() as i64;
+            let end = ((range.max - image_origin[axis]) / self.zoom / minor).ceil() as i64;
+            for i in start..=end {
+                let value = i as f32 * minor;
+                let s = image_origin[axis] + value * self.zoom;
+                if s < range.min + RULER_SIZE { continue; }
+                let major = i % 5 == 0;
+                let len = if major { half } else { half * 0.4 };
+                if axis == 0 {
+                    painter.line_segment([Pos2::new(s, top.min.y), Pos2::new(s, top.min.y + len)], Stroke::new(1.0, PIXEL_COLOR));
+                    if major { painter.text(Pos2::new(s + 2.0, top.min.y), Align2::LEFT_TOP, format!("{}", value), font.clone(), PIXEL_COLOR); }
+                } else {
+                    painter.line_segment([Pos2::new(left.min.x, s), Pos2::new(left.min.x + len, s)], Stroke::new(1.0, PIXEL_COLOR));
+                    if major { painter.text(Pos2::new(left.min.x + 1.0, s + 1.0), Align2::LEFT_TOP, format!("{}", value), font.clone(), PIXEL_COLOR); }
+                }
+            }
+        }
+
+        // 网格行列号：每条格线一个短刻度，标签按间距抽稀
+        for (axis, count, size) in [(0, self.grid_cols, cell.x), (1, self.grid_rows, cell.y)] {
+            if size <= 0.0 { continue; }
+            let label_every = nice_step(size, GRID_LABEL_SPACING).max(1.0) as usize;
+            let draw_every = if size < 4.0 { label_every } else { 1 };
+            for i in (0..=count).step_by(draw_every) {
+                let s = origin[axis] + i as f32 * size;
+                let range = if axis == 0 { top.x_range() } else { left.y_range() };
+                if s < range.min + RULER_SIZE || s > range.max { continue; }
+                let labeled = i % label_every == 0 && i < count;
+                let len = if labeled { half } else { half * 0.4 };
+                if axis == 0 {
+                    painter.line_segment([Pos2::new(s, top.max.y - len), Pos2::new(s, top.max.y)], Stroke::new(1.0, GRID_COLOR));
+                    if labeled { painter.text(Pos2::new(s + size / 2.0, top.max.y), Align2::CENTER_BOTTOM, i.to_string(), font.clone(), GRID_COLOR); }
+                } else {
+                    painter.line_segment([Pos2::new(left.max.x - len, s), Pos2::new(left.max.x, s)], Stroke::new(1.0, GRID_COLOR));
+                    if labeled { painter.text(Pos2::new(left.max.x - 1.0, s + size / 2.0), Align2::RIGHT_CENTER, i.to_string(), font.clone(), GRID_COLOR); }
+                }
+            }
+        }
+
+        // 光标位置指示
+        if let Some(p) = hover.filter(|p| panel_rect.contains(*p)) {
+            let marker = Stroke::new(1.0, Color32::YELLOW);
+            if p.x > left.max.x { painter.line_segment([Pos2::new(p.x, top.min.y), Pos2::new(p.x, top.max.y)], marker); }
+            if p.y > top.max.y { painter.line_segment([Pos2::new(left.min.x, p.y), Pos2::new(left.max.x, p.y)], marker); }
+        }
+        painter.rect_filled(Rect::from_min_size(panel_rect.min, Vec2::splat(RULER_SIZE)), 0.0, Color32::from_black_alpha(230));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+use eframe::egui::{self, Color32, RichText};
+use mlua::{HookTriggers, Lua, LuaOptions, LuaSerdeExt, SerializeOptions, StdLib, Table, Value};
+use serde::Serialize;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::orphans::prune_orphan_events;
+use crate::wave_lock::locked_changes;
+
+// 🔥 脚本控制台：用 Lua 批量修改策略与当前层地形，不必等专门的界面
+// 脚本在数据副本上运行：建筑与各类事件是与导出格式相同的表，地形是按行排列的二维数组；
+// 运行成功后整体写回（可撤销），出错、超时或改动了锁定波次中的建筑与事件则不做任何修改
+
+const TIME_LIMIT: Duration = Duration::from_secs(5);
+
+// 坐标与导出格式一致从 0 开始；Lua 数组从 1 开始，由辅助函数换算
+const PRELUDE: &str = r##"
+_output = {}
+function print(...)
+  local parts = {}
+  for i = 1, select("#", ...) do parts[#parts + 1] = tostring((select(i, ...))) end
+  _output[#_output + 1] = table.concat(parts, "\t")
+end
+
+local grids = { Floor = floor, Wall = wall, Ceiling = ceiling }
+function get_terrain(x, y, kind)
+  local row = grids[kind or "Floor"][y + 1]
+  return row and row[x + 1]
+end
+function set_terrain(x, y, value, kind)
+  local row = grids[kind or "Floor"][y + 1]
+  if row and row[x + 1] ~= nil then row[x + 1] = value end
+end
+
+function find_building(uid)
+  for _, b in ipairs(buildings) do if b.uid == uid then return b end end
+end
+function place(name, x, y, wave, late)
+  local t = templates[name] or error("未知的建筑模板: " .. tostring(name))
+  if late == nil then late = current_late end
+  local uid = next_uid
+  next_uid = next_uid + 1
+  buildings[#buildings + 1] = { uid = uid, name = name, b_type = t.b_type, grid_x = x, grid_y = y, width = t.width, height = t.height, wave_num = wave or current_wave, is_late = late, offset = { 0, 0 } }
+  return uid
+end
+function demolish(uid, wave, late)
+  local b = find_building(uid) or error("找不到建筑 UID " .. tostring(uid))
+  if late == nil then late = current_late end
+  demolishes[#demolishes + 1] = { uid = uid, name = b.name, grid_x = b.grid_x, grid_y = b.grid_y, width = b.width, height = b.height, wave_num = wave or current_wave, is_late = late }
+end
+"##;
+
+const EXAMPLES: &[(&str, &str)] = &[
+    ("在 (x, y) 周围放一圈墙", r#"-- 在 (cx, cy) 周围放一圈建筑，模板名称改成实际的墙
+local name, cx, cy, r = "墙", 10, 10, 2
+for x = cx - r, cx + r do
+  for y = cy - r, cy + r do
+    if math.max(math.abs(x - cx), math.abs(y - cy)) == r then place(name, x, y) end
+  end
+end
+"#),
+    ("把 W3 的建筑改到 W4", r#"for _, b in ipairs(buildings) do
+  if b.wave_num == 3 then b.wave_num = 4 end
+end
+for _, d in ipairs(demolishes) do
+  if d.wave_num == 3 then d.wave_num = 4 end
+end
+"#),
+    ("统计各模板数量", r#"local count = {}
+for _, b in ipairs(buildings) do count[b.name] = (count[b.name] or 0) + 1 end
+for name, n in pairs(count) do print(name, n) end
+"#),
+];
+
+pub(crate) struct ScriptConsole {
+    code: String,
+    // (是否为错误, 文本)
+    output: Vec<(bool, String)>,
+}
+
+impl Default for ScriptConsole {
+    fn default() -> Self { Self { code: EXAMPLES[2].1.to_string(), output: Vec::new() } }
+}
+
+struct ScriptOutcome {
+    strategy: MapBuildingsExport,
+    grids: [Grid; 3],
+}
+
+const LAYER_TYPES: [BuildingType; 3] = [BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling];
+
+// None 转为 nil 而不是 null，脚本里可以直接判断 b.comment == nil
+fn lua_value<'lua, T: Serialize + ?Sized>(lua: &'lua Lua, value: &T) -> mlua::Result<Value<'lua>> {
+    lua.to_value_with(value, SerializeOptions::new().serialize_none_to_null(false).serialize_unit_to_null(false))
+}
+
+impl MapEditor {
+    fn set_script_globals(&self, lua: &Lua, strategy: &MapBuildingsExport) -> mlua::Result<()> {
+        let globals = lua.globals();
+        globals.set("buildings", lua_value(lua, &strategy.buildings)?)?;
+        globals.set("upgrades", lua_value(lua, &strategy.upgrades)?)?;
+        globals.set("demolishes", lua_value(lua, &strategy.demolishes)?)?;
+        globals.set("sells", lua_value(lua, &strategy.sells)?)?;
+        globals.set("relocations", lua_value(lua, &strategy.relocations)?)?;
+        globals.set("abilities", lua_value(lua, &strategy.abilities)?)?;
+        if let Some(layer) = self.layers_data.get(&self.current_major_z) {
+            for (name, b_type) in ["floor", "wall", "ceiling"].into_iter().zip(LAYER_TYPES) { globals.set(name, lua_value(lua, layer.get_grid(b_type))?)?; }
+        }
+        let templates = lua.create_table()?;
+        for t in &self.building_templates {
+            let entry = lua.create_table()?;
+            entry.set("b_type", lua_value(lua, &t.b_type)?)?;
+            entry.set("width", t.width)?;
+            entry.set("height", t.height)?;
+            templates.set(t.name.as_str(), entry)?;
+        }
+        globals.set("templates", templates)?;
+        globals.set("selected", lua_value(lua, &self.selected_uids)?)?;
+        globals.set("rows", self.grid_rows)?;
+        globals.set("cols", self.grid_cols)?;
+        globals.set("current_wave", self.current_wave_num)?;
+        globals.set("current_late", self.current_is_late)?;
+        globals.set("next_uid", self.next_uid)?;
+        Ok(())
+    }
+
+    // 读回脚本修改后的数据，并检查 uid 唯一、建筑不越界、地形尺寸不变
+    fn read_script_results(&self, lua: &Lua, mut strategy: MapBuildingsExport) -> mlua::Result<ScriptOutcome> {
+        let globals = lua.globals();
+        strategy.buildings = lua.from_value(globals.get("buildings")?)?;
+        strategy.upgrades = lua.from_value(globals.get("upgrades")?)?;
+        strategy.demolishes = lua.from_value(globals.get("demolishes")?)?;
+        strategy.sells = lua.from_value(globals.get("sells")?)?;
+        strategy.relocations = lua.from_value(globals.get("relocations")?)?;
+        strategy.abilities = lua.from_value(globals.get("abilities")?)?;
+        let mut uids = std::collections::HashSet::new();
+        for b in &strategy.buildings {
+            if !uids.insert(b.uid) { return Err(mlua::Error::RuntimeError(format!("建筑 UID {} 重复", b.uid))); }
+            if b.width == 0 || b.height == 0 || b.grid_x + b.width > self.grid_cols || b.grid_y + b.height > self.grid_rows {
+                return Err(mlua::Error::RuntimeError(format!("建筑 {} (UID {}) 超出网格范围", b.name, b.uid)));
+            }
+        }
+        let grid = |name: &str| -> mlua::Result<Grid> {
+            let grid: Grid = lua.from_value(globals.get(name)?)?;
+            if grid.rows() != self.grid_rows || grid.cols() != self.grid_cols { return Err(mlua::Error::RuntimeError(format!("{} 的尺寸被修改", name))); }
+            Ok(grid)
+        };
+        Ok(ScriptOutcome { strategy, grids: [grid("floor")?, grid("wall")?, grid("ceiling")?] })
+    }
+
+    fn execute_script(&self, code: &str, output: &mut Vec<String>) -> mlua::Result<ScriptOutcome> {
+        // 只加载纯计算的标准库：分享来的脚本不能通过 io / os 读写、删除文件或执行命令；基础库总会加载，去掉其中读取文件的 dofile / loadfile
+        let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8, LuaOptions::default())?;
+        for name in ["dofile", "loadfile"] { lua.globals().raw_set(name, Value::Nil)?; }
+        let start = Instant::now();
+        lua.set_hook(HookTriggers::new().every_nth_instruction(10_000), move |_, _| {
+            if start.elapsed() > TIME_LIMIT { Err(mlua::Error::RuntimeError("脚本运行超过 5 秒，已中止".into())) } else { Ok(()) }
+        });
+        let strategy = self.build_buildings_export();
+        self.set_script_globals(&lua, &strategy)?;
+        lua.load(PRELUDE).set_name("prelude").exec()?;
+        let result = lua.load(code).set_name("script").exec();
+        if let Ok(printed) = lua.globals().get::<_, Table>("_output") {
+            output.extend(printed.sequence_values::<String>().filter_map(Result::ok));
+        }
+        result?;
+        self.read_script_results(&lua, strategy)
+    }
+
+    // 比较运行前后（都去掉孤立事件，即连带删除的事件也算在内）锁定波次中的建筑与事件，有变化则整体拒绝
+    fn check_script_locks(&self, outcome: ScriptOutcome) -> mlua::Result<ScriptOutcome> {
+        let (mut before, mut after) = (self.build_buildings_export(), outcome.strategy.clone());
+        for data in [&mut before, &mut after] { prune_orphan_events(data, &[], &BTreeSet::new()); }
+        match locked_changes(&before, &after, &self.locked_waves) {
+            0 => Ok(outcome),
+            n => Err(mlua::Error::RuntimeError(format!("改动了已锁定波次中的 {} 条建筑或事件，请先在波次设置中解锁", n))),
+        }
+    }
+
+    fn run_script(&mut self) {
+        let code = self.script_console.code.clone();
+        let mut printed = Vec::new();
+        let result = self.execute_script(&code, &mut printed);
+        let console = &mut self.script_console;
+        console.output.extend(printed.into_iter().map(|line| (false, line)));
+        match result.and_then(|outcome| self.check_script_locks(outcome)) {
+            Ok(outcome) => {
+                let before = self.placed_buildings.len();
+                let after = outcome.strategy.buildings.len();
+                self.apply_buildings_data(outcome.strategy);
+                self.prune_orphan_events();
+                self.selected_uids.retain(|uid| self.placed_buildings.iter().any(|b| b.uid == *uid));
+                self.building_drag = None;
+                if let Some(layer) = self.layers_data.get_mut(&self.current_major_z) {
+                    for (b_type, grid) in LAYER_TYPES.into_iter().zip(outcome.grids) { *layer.get_grid_mut(b_type) = grid; }
+                }
+                self.script_console.output.push((false, trf!("✔ 执行完成，建筑 {} → {} 座", before, after)));
+            }
+            Err(e) => self.script_console.output.push((true, trf!("✘ {}（未做任何修改）", e))),
+        }
+    }
+
+    pub(crate) fn show_script_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_script_console;
+        let mut run = false;
+        egui::Window::new(tr("📜 脚本控制台")).open(&mut open).default_size([520.0, 480.0]).show(ctx, |ui| {
+            let console = &mut self.script_console;
+            ui.horizontal(|ui| {
+                run = ui.button(tr("▶ 运行 (Ctrl+Enter)")).clicked();
+                egui::ComboBox::from_id_source("script_examples").selected_text(tr("示例")).show_ui(ui, |ui| {
+                    for (title, code) in EXAMPLES {
+                        if ui.selectable_label(false, tr(title)).clicked() { console.code = code.to_string(); }
+                    }
+                });
+                if ui.button(tr("清空输出")).clicked() { console.output.clear(); }
+            });
+            ui.weak(tr("全局: buildings / upgrades / demolishes / sells / relocations / abilities（与导出格式相同）、floor / wall / ceiling（当前层地形）、templates、selected、rows、cols、current_wave、current_late"));
+            ui.weak(tr("函数: place(模板, x, y[, 波次, 后期]) → uid、demolish(uid[, 波次, 后期])、find_building(uid)、get_terrain(x, y[, 层])、set_terrain(x, y, 值[, 层])、print(...)"));
+            let editor = ui.add(egui::TextEdit::multiline(&mut console.code).code_editor().desired_rows(14).desired_width(f32::INFINITY));
+            if editor.has_focus() && ui.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::Enter)) { run = true; }
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(160.0).stick_to_bottom(true).show(ui, |ui| {
+                for (error, line) in &console.output {
+                    let text = RichText::new(line).monospace();
+                    ui.label(if *error { text.color(Color32::RED) } else { text });
+                }
+            });
+        });
+        self.show_script_console = open;
+        if run { self.run_script(); }
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::storage::{write_data, DATA_EXTENSIONS};
+
+// 🔥 导出选中：只把选中的建筑以及引用它们的拆除 / 出售 / 迁移 / 单体升级写成一份策略文件，便于分享某一路的布局；
+// 按模板的升级、技能、波次备注与经济设置不属于任何一座建筑，不导出。得到的文件可以用导入策略的合并方式拼回别的方案
+
+// 只保留 uids 中的建筑与引用它们的事件
+pub(crate) fn selection_strategy(data: &MapBuildingsExport, uids: &HashSet<usize>) -> MapBuildingsExport {
+    MapBuildingsExport {
+        format_version: data.format_version,
+        map_name: data.map_name.clone(),
+        buildings: data.buildings.iter().filter(|b| uids.contains(&b.uid)).cloned().collect(),
+        upgrades: data.upgrades.iter().filter(|e| e.uid.is_some_and(|uid| uids.contains(&uid))).cloned().collect(),
+        demolishes: data.demolishes.iter().filter(|e| uids.contains(&e.uid)).cloned().collect(),
+        sells: data.sells.iter().filter(|e| uids.contains(&e.uid)).cloned().collect(),
+        relocations: data.relocations.iter().filter(|e| uids.contains(&e.uid)).cloned().collect(),
+        abilities: Vec::new(),
+        wave_notes: HashMap::new(),
+        locked_waves: BTreeSet::new(),
+        economy: None,
+    }
+}
+
+impl MapEditor {
+    pub(crate) fn export_selection(&mut self) {
+        if self.selected_uids.is_empty() { return; }
+        let map_name = self.export_map_name();
+        let ext = self.export_format.extension();
+        let dialog = FileDialog::new().set_directory(format!("output/{}", map_name)).set_file_name(format!("{}策略_选中.{}", map_name, ext)).add_filter(tr("策略文件"), DATA_EXTENSIONS);
+        let Some(path) = dialog.save_file() else { return; };
+        let uids: HashSet<usize> = self.selected_uids.iter().copied().collect();
+        let data = selection_strategy(&self.build_buildings_export(), &uids);
+        let events = data.upgrades.len() + data.demolishes.len() + data.sells.len() + data.relocations.len();
+        match write_data(&path, &data) {
+            Ok(()) => self.notifications.info(trf!("已导出选中的 {} 个建筑与 {} 条事件: {}", data.buildings.len(), events, path.display())),
+            Err(e) => self.notifications.error(e),
+        }
+    }
+}
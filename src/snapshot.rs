@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use eframe::egui::Color32;
+use image::{imageops, RgbaImage};
+
+use crate::app::MapEditor;
+use crate::assets::resolve_asset;
+use crate::models::*;
+use crate::storage::read_image;
+use crate::utils::{bresenham_line, get_layer_color, get_time_value};
+
+// 🔥 画布快照：在 CPU 上按底图像素尺寸重新绘制当前层地形、当前波次的建筑与敌人路线，导出为 PNG
+// 与画布的差异：不受缩放/平移影响，不绘制文字标签
+
+const ROUTE_COLORS: [[u8; 4]; 4] = [[255, 120, 0, 255], [0, 200, 255, 255], [255, 0, 200, 255], [200, 255, 0, 255]];
+
+fn rgba(c: Color32) -> [u8; 4] {
+    let [r, g, b, a] = c.to_srgba_unmultiplied();
+    [r, g, b, a]
+}
+
+struct Canvas {
+    img: RgbaImage,
+}
+
+impl Canvas {
+    fn blend(&mut self, x: i32, y: i32, [r, g, b, a]: [u8; 4]) {
+        if x < 0 || y < 0 || x as u32 >= self.img.width() || y as u32 >= self.img.height() || a == 0 { return; }
+        let px = self.img.get_pixel_mut(x as u32, y as u32);
+        let alpha = a as f32 / 255.0;
+        for (dst, src) in px.0.iter_mut().zip([r, g, b]) {
+            *dst = (src as f32 * alpha + *dst as f32 * (1.0 - alpha)).round() as u8;
+        }
+        px.0[3] = px.0[3].max(a);
+    }
+
+    fn fill_rect(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: [u8; 4]) {
+        for y in y0.round() as i32..y1.round() as i32 {
+            for x in x0.round() as i32..x1.round() as i32 { self.blend(x, y, color); }
+        }
+    }
+
+    fn stroke_rect(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, color: [u8; 4]) {
+        self.fill_rect(x0, y0, x1, y0 + width, color);
+        self.fill_rect(x0, y1 - width, x1, y1, color);
+        self.fill_rect(x0, y0 + width, x0 + width, y1 - width, color);
+        self.fill_rect(x1 - width, y0 + width, x1, y1 - width, color);
+    }
+
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), width: i32, color: [u8; 4]) {
+        let half = width / 2;
+        for (x, y) in bresenham_line((from.0 as i32, from.1 as i32), (to.0 as i32, to.1 as i32)) {
+            for dy in -half..=half {
+                for dx in -half..=half { self.blend(x + dx, y + dy, color); }
+            }
+        }
+    }
+
+    fn disc(&mut self, (cx, cy): (f32, f32), radius: f32, color: [u8; 4]) {
+        let r = radius.ceil() as i32;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if (dx * dx + dy * dy) as f32 <= radius * radius { self.blend(cx as i32 + dx, cy as i32 + dy, color); }
+            }
+        }
+    }
+
+    fn image(&mut self, src: &RgbaImage, x: f32, y: f32, w: f32, h: f32) {
+        let (w, h) = (w.round().max(1.0) as u32, h.round().max(1.0) as u32);
+        let scaled = imageops::resize(src, w, h, imageops::FilterType::Triangle);
+        for (dx, dy, p) in scaled.enumerate_pixels() { self.blend(x.round() as i32 + dx as i32, y.round() as i32 + dy as i32, p.0); }
+    }
+}
+
+impl MapEditor {
+    // 返回绘制结果与失败信息（底图或图标读取失败时继续绘制其余内容）
+    pub(crate) fn render_snapshot(&self) -> (RgbaImage, Vec<String>) {
+        let mut errors = Vec::new();
+        let base = self.base_image_path.as_ref().and_then(|p| read_image(p).map_err(|e| errors.push(e)).ok()).map(|img| img.to_rgba8());
+        let grid_w = self.offset_x + self.grid_cols as f32 * self.grid_width;
+        let grid_h = self.offset_y + self.grid_rows as f32 * self.grid_height;
+        let (base_w, base_h) = base.as_ref().map_or((0, 0), |b| b.dimensions());
+        let width = (grid_w.ceil() as u32).max(base_w).max(1);
+        let height = (grid_h.ceil() as u32).max(base_h).max(1);
+        let mut canvas = Canvas { img: RgbaImage::from_pixel(width, height, image::Rgba([30, 30, 30, 255])) };
+        if let Some(base) = &base { imageops::overlay(&mut canvas.img, base, 0, 0); }
+
+        let cell_rect = |c: usize, r: usize, w: usize, h: usize| {
+            let x0 = self.offset_x + c as f32 * self.grid_width;
+            let y0 = self.offset_y + r as f32 * self.grid_height;
+            (x0, y0, x0 + w as f32 * self.grid_width, y0 + h as f32 * self.grid_height)
+        };
+
+        // 当前主层级中可见的子层，与画布相同的着色
+        if let Some(layer) = self.layers_data.get(&self.current_major_z).filter(|_| !self.hidden_major_z.contains(&self.current_major_z)) {
+            for l_type in [BuildingType::Floor, BuildingType::Wall, BuildingType::Ceiling] {
+                if self.hidden_layer_types.contains(&l_type) { continue; }
+                let grid = layer.get_grid(l_type);
+                for r in 0..grid.rows() {
+                    for (c, val) in grid.row(r).enumerate() {
+                        if val < -1 || (l_type != BuildingType::Floor && val < 0) { continue; }
+                        let mut color = get_layer_color(&self.terrain_types, val);
+                        match l_type {
+                            BuildingType::Floor => {}
+                            BuildingType::Wall => color = Color32::from_rgba_unmultiplied(color.r(), color.g() / 2, color.b(), 220),
+                            BuildingType::Ceiling => color = Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b() / 2, 220),
+                        }
+                        let (x0, y0, x1, y1) = cell_rect(c, r, 1, 1);
+                        canvas.fill_rect(x0, y0, x1, y1, rgba(color.linear_multiply(self.terrain_opacity)));
+                    }
+                }
+            }
+        }
+        for c in 0..=self.grid_cols {
+            let x = self.offset_x + c as f32 * self.grid_width;
+            canvas.line((x, self.offset_y), (x, grid_h), 1, [0, 0, 0, 40]);
+        }
+        for r in 0..=self.grid_rows {
+            let y = self.offset_y + r as f32 * self.grid_height;
+            canvas.line((self.offset_x, y), (grid_w, y), 1, [0, 0, 0, 40]);
+        }
+
+        // 当前波次存在的建筑（迁移过的画在当前位置）；之后会被拆除或出售的画红叉
+        let mut icons: HashMap<&str, Option<RgbaImage>> = HashMap::new();
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        for b in &self.placed_buildings {
+            let t_demolish = self.get_building_demolish_time(b.uid);
+            if t_current < get_time_value(b.wave_num, b.is_late) || t_current >= t_demolish { continue; }
+            let (bx, by) = self.building_pos_at(b, t_current);
+            let (x0, y0, x1, y1) = cell_rect(bx, by, b.width, b.height);
+            let (dx, dy) = (b.offset[0] * self.grid_width, b.offset[1] * self.grid_height);
+            let (x0, y0, x1, y1) = (x0 + dx, y0 + dy, x1 + dx, y1 + dy);
+            // 图标随建筑自身的升级等级变化，按图标路径缓存
+            let icon_path = self.building_configs.iter().find(|c| c.name == b.template_name).map(|c| c.icon_path_at_tier(self.upgrade_tier_at(&b.template_name, Some(b.uid), t_current)));
+            let icon = icon_path.and_then(|p| icons.entry(p).or_insert_with(|| read_image(resolve_asset(&self.asset_root, p)).map_err(|e| errors.push(e)).ok().map(|i| i.to_rgba8())).as_ref());
+            match icon {
+                Some(icon) => canvas.image(icon, x0, y0, x1 - x0, y1 - y0),
+                None => canvas.fill_rect(x0, y0, x1, y1, rgba(b.color)),
+            }
+            canvas.stroke_rect(x0, y0, x1, y1, 1.5, [0, 0, 0, 180]);
+            if t_demolish != i32::MAX {
+                canvas.line((x0, y0), (x1, y1), 2, [255, 0, 0, 200]);
+                canvas.line((x0, y1), (x1, y0), 2, [255, 0, 0, 200]);
+            }
+        }
+
+        for (i, route) in self.enemy_routes.iter().enumerate() {
+            let color = ROUTE_COLORS[i % ROUTE_COLORS.len()];
+            let center = |p: &RoutePoint| (self.offset_x + (p.grid_x as f32 + 0.5) * self.grid_width, self.offset_y + (p.grid_y as f32 + 0.5) * self.grid_height);
+            for pair in route.points.windows(2) { canvas.line(center(&pair[0]), center(&pair[1]), 3, color); }
+            let radius = self.grid_width.min(self.grid_height) * 0.4;
+            for p in &route.points {
+                let (kind_color, r) = match p.kind {
+                    RoutePointKind::Spawn => ([0, 180, 0, 255], radius),
+                    RoutePointKind::Exit => ([200, 0, 0, 255], radius),
+                    RoutePointKind::Waypoint => (color, radius * 0.6),
+                };
+                canvas.disc(center(p), r, kind_color);
+            }
+        }
+
+        errors.dedup();
+        (canvas.img, errors)
+    }
+}
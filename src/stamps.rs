@@ -0,0 +1,215 @@
+use std::fs;
+use eframe::egui::{self, Align2, Color32, FontId, Painter, Pos2, Rect, Stroke, Vec2};
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::assets::{resolve_asset, STAMPS_FILE};
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::{get_time_value, wave_from_time_value, wave_label};
+use crate::storage::{read_json, write_data, write_text};
+
+// 🔥 图章库：把选中的一组建筑（例如一套标准的卡口布置）连同相对位置与相对建造时间存成有名字的图章，
+// 保存在资源根目录的 stamps.json 中、所有地图共用；在布局模式的图章库里选中后整组放置，组内最早的建筑放在当前波次
+// 图章可以导出为蓝图文件分享给别人，导入的蓝图加入图章库并立即进入放置，在点击的位置重新定位
+
+// 组内建筑的坐标与时间都相对于组，anchor 为组左上角所在的格子
+fn stamp_size(buildings: &[StampBuilding]) -> (usize, usize) {
+    (buildings.iter().map(|b| b.dx + b.width).max().unwrap_or(0), buildings.iter().map(|b| b.dy + b.height).max().unwrap_or(0))
+}
+
+// 图章跨关卡共用，相对时间固定按默认的每波两段计，而不是按当前关卡的时间模型，否则切换模型后已存的图章时间会变
+fn stamp_time(wave: i32, late: bool) -> i32 { TimeModel::default().time_value(wave, late) }
+
+// 选中建筑组成的图章：坐标相对于组的左上角，时间相对于组内最早建造的建筑
+pub(crate) fn stamp_buildings(group: &[&PlacedBuilding]) -> Vec<StampBuilding> {
+    let (Some(min_x), Some(min_y)) = (group.iter().map(|b| b.grid_x).min(), group.iter().map(|b| b.grid_y).min()) else { return Vec::new(); };
+    let t0 = group.iter().map(|b| stamp_time(b.wave_num, b.is_late)).min().unwrap_or(0);
+    group.iter().map(|b| StampBuilding {
+        name: b.template_name.clone(),
+        b_type: b.b_type,
+        dx: b.grid_x - min_x, dy: b.grid_y - min_y, width: b.width, height: b.height,
+        time_offset: stamp_time(b.wave_num, b.is_late) - t0,
+        comment: b.comment.clone(), offset: b.offset,
+    }).collect()
+}
+
+// 解析蓝图文件；坐标与时间重新归一到组的左上角与最早的建筑，手写的蓝图不必从 0 开始
+pub(crate) fn parse_blueprint(text: &str) -> Result<Stamp, String> {
+    let file: BlueprintFile = serde_json::from_str(text).map_err(|e| format!("解析蓝图失败: {}", e))?;
+    if file.format != BLUEPRINT_FORMAT { return Err(format!("不是蓝图文件（format 应为 {}）", BLUEPRINT_FORMAT)); }
+    if file.version > BLUEPRINT_VERSION { return Err(format!("蓝图版本 {} 高于当前支持的 {}，请升级编辑器", file.version, BLUEPRINT_VERSION)); }
+    if file.buildings.is_empty() { return Err("蓝图中没有建筑".into()); }
+    if let Some(b) = file.buildings.iter().find(|b| b.width == 0 || b.height == 0) { return Err(format!("蓝图中 {} 的尺寸为 0", b.name)); }
+    let mut buildings = file.buildings;
+    let (min_x, min_y) = (buildings.iter().map(|b| b.dx).min().unwrap_or(0), buildings.iter().map(|b| b.dy).min().unwrap_or(0));
+    let t0 = buildings.iter().map(|b| b.time_offset).min().unwrap_or(0);
+    for b in &mut buildings { b.dx -= min_x; b.dy -= min_y; b.time_offset -= t0; }
+    Ok(Stamp { name: file.name, buildings })
+}
+
+impl MapEditor {
+    pub(crate) fn load_stamps(&mut self) {
+        self.active_stamp = None;
+        let path = resolve_asset(&self.asset_root, STAMPS_FILE);
+        if !path.exists() { self.stamps.clear(); return; }
+        match read_json::<Vec<Stamp>>(path) {
+            Ok(stamps) => self.stamps = stamps,
+            Err(e) => self.notifications.warn(e),
+        }
+    }
+
+    fn save_stamps(&mut self) {
+        if let Err(e) = write_data(resolve_asset(&self.asset_root, STAMPS_FILE), &self.stamps) { self.notifications.error(e); }
+    }
+
+    // 以 anchor 为组左上角、t_base 为组内最早建筑的建造时间放置时每座建筑的位置、时间（按当前时间模型）与是否可放置
+    pub(crate) fn stamp_placements(&self, buildings: &[StampBuilding], anchor: (i32, i32), t_base: i32) -> Vec<(usize, usize, i32, bool)> {
+        let base = { let (wave, late) = wave_from_time_value(t_base); stamp_time(wave, late) };
+        buildings.iter().map(|b| {
+            let (x, y) = ((anchor.0 + b.dx as i32).max(0) as usize, (anchor.1 + b.dy as i32).max(0) as usize);
+            let t = { let (wave, late) = TimeModel::default().wave_at(base + b.time_offset); get_time_value(wave, late) };
+            let known = self.building_templates.iter().any(|t| t.name == b.name);
+            let valid = anchor.0 >= 0 && anchor.1 >= 0 && known && !self.time_locked(t) && self.can_place_building_during(y, x, b.width, b.height, b.b_type, t, t + 1, &[]);
+            (x, y, t, valid)
+        }).collect()
+    }
+
+    // 整组放置，分配新 uid；任一建筑不可放置则整组放弃。返回新建筑的 uid
+    pub(crate) fn place_stamp(&mut self, buildings: &[StampBuilding], anchor: (i32, i32), t_base: i32) -> Option<Vec<usize>> {
+        let placements = self.stamp_placements(buildings, anchor, t_base);
+        if buildings.is_empty() || !placements.iter().all(|p| p.3) { return None; }
+        let viewport = self.current_viewport_preset();
+        let mut uids = Vec::new();
+        for (b, (x, y, t, _)) in buildings.iter().zip(placements) {
+            let color = self.building_templates.iter().find(|t| t.name == b.name).map(|t| t.color).unwrap_or(Color32::GRAY);
+            let (wave_num, is_late) = wave_from_time_value(t);
+            self.placed_buildings.push(PlacedBuilding {
+                uid: self.next_uid,
+                template_name: b.name.clone(),
+                b_type: b.b_type,
+                grid_x: x, grid_y: y, width: b.width, height: b.height,
+                color, wave_num, is_late, viewport: viewport.clone(), comment: b.comment.clone(), offset: b.offset, offset_seconds: None, order: None,
+            });
+            uids.push(self.next_uid);
+            self.next_uid += 1;
+        }
+        Some(uids)
+    }
+
+    fn export_blueprint(&mut self, stamp: &Stamp) {
+        let file = BlueprintFile { format: BLUEPRINT_FORMAT.to_string(), version: BLUEPRINT_VERSION, name: stamp.name.clone(), buildings: stamp.buildings.clone() };
+        let dialog = FileDialog::new().set_directory("output").set_file_name(format!("{}.blueprint.json", stamp.name)).add_filter(tr("蓝图"), &["json"]);
+        let Some(path) = dialog.save_file() else { return; };
+        let result = serde_json::to_string_pretty(&file).map_err(|e| e.to_string()).and_then(|text| write_text(&path, &text));
+        match result {
+            Ok(()) => self.notifications.info(trf!("已导出: {}", path.display())),
+            Err(e) => self.notifications.error(e),
+        }
+    }
+
+    // 导入的蓝图加入图章库（重名时加序号）并立即进入放置
+    fn import_blueprint(&mut self) {
+        let Some(path) = FileDialog::new().add_filter(tr("蓝图"), &["json"]).pick_file() else { return; };
+        let result = fs::read_to_string(&path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e)).and_then(|text| parse_blueprint(&text));
+        let mut stamp = match result {
+            Ok(stamp) => stamp,
+            Err(e) => { self.notifications.error(e); return; }
+        };
+        let base = stamp.name.clone();
+        let mut n = 1;
+        while self.stamps.iter().any(|s| s.name == stamp.name) { n += 1; stamp.name = format!("{} ({})", base, n); }
+        let mut missing: Vec<&str> = stamp.buildings.iter().map(|b| b.name.as_str()).filter(|name| !self.building_templates.iter().any(|t| t.name == *name)).collect();
+        missing.sort_unstable();
+        missing.dedup();
+        if !missing.is_empty() { self.notifications.warn(trf!("蓝图中的模板在当前建筑列表中不存在: {}", missing.join(", "))); }
+        self.notifications.info(trf!("已导入蓝图「{}」，在画布上点击放置", stamp.name));
+        self.stamps.push(stamp);
+        self.active_stamp = Some(self.stamps.len() - 1);
+        self.save_stamps();
+    }
+
+    // 选择模式面板：把选中的建筑存为图章，同名时覆盖
+    pub(crate) fn show_save_stamp(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.stamp_name).hint_text(tr("图章名称")).desired_width(120.0));
+            let name = self.stamp_name.trim().to_string();
+            let existing = self.stamps.iter().position(|s| s.name == name);
+            let label = if existing.is_some() { tr("覆盖图章") } else { tr("存为图章") };
+            let enabled = !name.is_empty() && !self.selected_uids.is_empty();
+            if ui.add_enabled(enabled, egui::Button::new(label)).on_hover_text(tr("把选中的建筑连同相对位置与建造时间存入图章库，在布局模式中整组放置")).clicked() {
+                let group: Vec<&PlacedBuilding> = self.placed_buildings.iter().filter(|b| self.selected_uids.contains(&b.uid)).collect();
+                let stamp = Stamp { name: name.clone(), buildings: stamp_buildings(&group) };
+                let count = stamp.buildings.len();
+                match existing {
+                    Some(i) => self.stamps[i] = stamp,
+                    None => self.stamps.push(stamp),
+                }
+                self.save_stamps();
+                self.stamp_name.clear();
+                self.notifications.info(trf!("已保存图章「{}」（{} 座建筑）", name, count));
+            }
+        });
+    }
+
+    // 布局模式面板：选中图章后在画布上整组放置，再次点击取消
+    pub(crate) fn show_stamp_library(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(tr("图章库:"));
+                if ui.button(tr("📥 导入蓝图…")).on_hover_text(tr("导入别人分享的蓝图文件，加入图章库后在画布上点击放置")).clicked() { self.import_blueprint(); }
+            });
+            if self.stamps.is_empty() {
+                ui.weak(tr("在选择模式中选中建筑后点击「存为图章」"));
+                return;
+            }
+            let (mut remove_idx, mut export_idx) = (None, None);
+            for (i, stamp) in self.stamps.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let (w, h) = stamp_size(&stamp.buildings);
+                    let text = format!("{} ({}×{}, {})", stamp.name, w, h, stamp.buildings.len());
+                    let hover = trf!("{} 座建筑；选中后在画布上左键整组放置，右键或 Esc 取消", stamp.buildings.len());
+                    if ui.selectable_label(self.active_stamp == Some(i), text).on_hover_text(hover).clicked() {
+                        self.active_stamp = if self.active_stamp == Some(i) { None } else { Some(i) };
+                    }
+                    if ui.small_button("📤").on_hover_text(tr("导出为蓝图文件")).clicked() { export_idx = Some(i); }
+                    if ui.small_button("×").on_hover_text(tr("删除图章")).clicked() { remove_idx = Some(i); }
+                });
+            }
+            if let Some(stamp) = export_idx.and_then(|i| self.stamps.get(i)).cloned() { self.export_blueprint(&stamp); }
+            if let Some(i) = remove_idx {
+                self.stamps.remove(i);
+                self.active_stamp = None;
+                self.save_stamps();
+            }
+        });
+    }
+
+    // 布局模式下选中图章时接管画布：幽灵框显示整组位置（组的中心跟随指针），左键放置，右键或 Esc 取消
+    pub(crate) fn handle_stamp_placement(&mut self, painter: &Painter, response: &egui::Response, input: &egui::InputState, origin: Pos2, cell: Vec2, rel: Vec2) {
+        let Some(stamp) = self.active_stamp.and_then(|i| self.stamps.get(i)).cloned() else { self.active_stamp = None; return; };
+        let (w, h) = stamp_size(&stamp.buildings);
+        let anchor = (((rel.x / cell.x) - w as f32 / 2.0).round() as i32, ((rel.y / cell.y) - h as f32 / 2.0).round() as i32);
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let placements = self.stamp_placements(&stamp.buildings, anchor, t_current);
+        for (b, (_, _, t, valid)) in stamp.buildings.iter().zip(&placements) {
+            let min = origin + Vec2::new((anchor.0 + b.dx as i32) as f32 + b.offset[0], (anchor.1 + b.dy as i32) as f32 + b.offset[1]) * cell;
+            let rect = Rect::from_min_size(min, Vec2::new(b.width as f32, b.height as f32) * cell);
+            let color = self.building_templates.iter().find(|t| t.name == b.name).map(|t| t.color).unwrap_or(Color32::GRAY);
+            painter.rect_filled(rect, 0.0, color.gamma_multiply(0.4));
+            painter.rect_stroke(rect, 0.0, Stroke::new(2.0, if *valid { Color32::GREEN } else { Color32::RED }));
+            if *t != t_current {
+                let (wave_num, is_late) = wave_from_time_value(*t);
+                painter.text(rect.center(), Align2::CENTER_CENTER, wave_label(wave_num, is_late), FontId::proportional(12.0), Color32::WHITE);
+            }
+        }
+        let group = Rect::from_min_size(origin + Vec2::new(anchor.0 as f32, anchor.1 as f32) * cell, Vec2::new(w as f32, h as f32) * cell);
+        painter.text(group.center_bottom() + Vec2::new(0.0, 4.0), Align2::CENTER_TOP, &stamp.name, FontId::proportional(14.0), Color32::WHITE);
+        if response.clicked_by(egui::PointerButton::Primary) {
+            if self.place_stamp(&stamp.buildings, anchor, t_current).is_none() { self.notifications.warn(trf!("图章「{}」有建筑无法放置在此处", stamp.name)); }
+        } else if response.clicked_by(egui::PointerButton::Secondary) || input.key_pressed(egui::Key::Escape) {
+            self.active_stamp = None;
+        }
+    }
+}
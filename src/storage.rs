@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{de::DeserializeOwned, Deserializer, Serialize};
+
+use crate::models::{default_consistency_rules, default_terrain_types, ConsistencyRule, TerrainType};
+
+// 统一的文件读写入口：错误信息带上文件路径与解析位置，交给通知系统展示。
+// 数据文件（含资源目录下的防御塔列表与地图预设）按严格模式解析：类型错误指出具体字段路径（如 layers[2].floor_grid[14]），未知字段同样报错，而不是被悄悄忽略；
+// 地形类型、语言包、图章等编辑器自身的设置文件用 read_json 宽松解析
+
+pub fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, String> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析 {} 失败: {}", path.display(), e))
+}
+
+// 数据文件格式按扩展名区分，未知扩展名按 JSON 处理；MessagePack 为紧凑的二进制格式，适合大地图
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum DataFormat { Json, Yaml, Toml, MessagePack }
+
+pub const DATA_EXTENSIONS: &[&str] = &["json", "yaml", "yml", "toml", "msgpack"];
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp"];
+
+impl DataFormat {
+    pub const ALL: [DataFormat; 4] = [DataFormat::Json, DataFormat::Yaml, DataFormat::Toml, DataFormat::MessagePack];
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("yaml") | Some("yml") => DataFormat::Yaml,
+            Some("toml") => DataFormat::Toml,
+            Some("msgpack") => DataFormat::MessagePack,
+            _ => DataFormat::Json,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self { DataFormat::Json => "json", DataFormat::Yaml => "yaml", DataFormat::Toml => "toml", DataFormat::MessagePack => "msgpack" }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self { DataFormat::Json => "JSON", DataFormat::Yaml => "YAML", DataFormat::Toml => "TOML", DataFormat::MessagePack => "MessagePack (二进制)" }
+    }
+}
+
+// TOML 的根必须是表：数组（如防御塔列表）包一层 items，null 字段直接省略
+const TOML_LIST_KEY: &str = "items";
+
+fn strip_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map.into_iter().filter(|(_, v)| !v.is_null()).map(|(k, v)| (k, strip_nulls(v))).collect(),
+        serde_json::Value::Array(items) => items.into_iter().map(strip_nulls).collect(),
+        other => other,
+    }
+}
+
+fn to_toml<T: Serialize>(value: &T) -> Result<String, String> {
+    let mut value = strip_nulls(serde_json::to_value(value).map_err(|e| e.to_string())?);
+    if value.is_array() { value = serde_json::json!({ TOML_LIST_KEY: value }); }
+    toml::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn from_toml<T: DeserializeOwned>(content: &str) -> Result<T, String> {
+    let mut value: serde_json::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+    if let Some(items) = value.as_object().filter(|m| m.len() == 1).and_then(|m| m.get(TOML_LIST_KEY)).filter(|v| v.is_array()) {
+        value = items.clone();
+    }
+    from_value_strict(value)
+}
+
+// 与 serde_path_to_error 相同的写法：layers[0].extra
+fn ignored_path(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => format!("{}[{}]", ignored_path(parent), index),
+        serde_ignored::Path::Map { parent, key } => match ignored_path(parent) {
+            p if p.is_empty() => key.clone(),
+            p => format!("{}.{}", p, key),
+        },
+        serde_ignored::Path::Some { parent } | serde_ignored::Path::NewtypeStruct { parent } | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+// 出错时带上字段路径；解析成功但有未知字段时列出全部未知字段
+fn deserialize_strict<'de, D: Deserializer<'de>, T: DeserializeOwned>(deserializer: D) -> Result<T, String> {
+    let mut unknown = Vec::new();
+    let mut record = |path: serde_ignored::Path| unknown.push(ignored_path(&path));
+    let value = serde_path_to_error::deserialize(serde_ignored::Deserializer::new(deserializer, &mut record)).map_err(|e| {
+        let path = e.path().to_string();
+        if path == "." { e.into_inner().to_string() } else { format!("{}: {}", path, e.into_inner()) }
+    })?;
+    if !unknown.is_empty() { return Err(format!("未知字段 {}", unknown.join(", "))); }
+    Ok(value)
+}
+
+// 迁移后的数据以 JSON 值的形式解析，同样按严格模式
+pub fn from_value_strict<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, String> {
+    deserialize_strict(value)
+}
+
+pub fn decode_data<T: DeserializeOwned>(bytes: &[u8], format: DataFormat) -> Result<T, String> {
+    let text = || std::str::from_utf8(bytes).map_err(|e| e.to_string());
+    match format {
+        DataFormat::Json => text().and_then(|t| {
+            let mut deserializer = serde_json::Deserializer::from_str(t);
+            let value = deserialize_strict(&mut deserializer)?;
+            deserializer.end().map_err(|e| e.to_string())?;
+            Ok(value)
+        }),
+        DataFormat::Yaml => text().and_then(|t| deserialize_strict(serde_yaml::Deserializer::from_str(t))),
+        DataFormat::Toml => text().and_then(from_toml),
+        DataFormat::MessagePack => deserialize_strict(&mut rmp_serde::Deserializer::new(bytes)),
+    }
+}
+
+// 结构体按字段名编码（to_vec_named），带 tag 的枚举与字段默认值才能正确读回
+pub fn encode_data<T: Serialize>(value: &T, format: DataFormat) -> Result<Vec<u8>, String> {
+    match format {
+        DataFormat::Json => serde_json::to_vec_pretty(value).map_err(|e| e.to_string()),
+        DataFormat::Yaml => serde_yaml::to_string(value).map(String::into_bytes).map_err(|e| e.to_string()),
+        DataFormat::Toml => to_toml(value).map(String::into_bytes),
+        DataFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+    }
+}
+
+pub fn read_data<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, String> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+    decode_data(&bytes, DataFormat::from_path(path)).map_err(|e| format!("解析 {} 失败: {}", path.display(), e))
+}
+
+pub fn write_data<T: Serialize>(path: impl AsRef<Path>, value: &T) -> Result<(), String> {
+    let path = path.as_ref();
+    let content = encode_data(value, DataFormat::from_path(path)).map_err(|e| format!("序列化 {} 失败: {}", path.display(), e))?;
+    write_bytes(path, &content)
+}
+
+pub fn write_text(path: impl AsRef<Path>, content: &str) -> Result<(), String> {
+    write_bytes(path, content.as_bytes())
+}
+
+pub fn write_bytes(path: impl AsRef<Path>, content: &[u8]) -> Result<(), String> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("创建目录 {} 失败: {}", dir.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("写入 {} 失败: {}", path.display(), e))
+}
+
+pub fn read_image(path: impl AsRef<Path>) -> Result<image::DynamicImage, String> {
+    let path = path.as_ref();
+    image::io::Reader::open(path)
+        .map_err(|e| format!("打开图片 {} 失败: {}", path.display(), e))?
+        .decode()
+        .map_err(|e| format!("解码图片 {} 失败: {}", path.display(), e))
+}
+
+pub fn write_png(path: impl AsRef<Path>, img: &image::RgbaImage) -> Result<(), String> {
+    let path = path.as_ref();
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut bytes, image::ImageOutputFormat::Png).map_err(|e| format!("编码图片 {} 失败: {}", path.display(), e))?;
+    write_bytes(path, bytes.get_ref())
+}
+
+// 文件不存在时返回内置调色板；存在但无法解析或为空时报错
+pub fn load_terrain_types(path: impl AsRef<Path>) -> Result<Vec<TerrainType>, String> {
+    let path = path.as_ref();
+    if !path.exists() { return Ok(default_terrain_types()); }
+    let types: Vec<TerrainType> = read_json(path)?;
+    if types.is_empty() { return Err(format!("{} 没有定义任何地形类型", path.display())); }
+    Ok(types)
+}
+
+pub fn load_consistency_rules(path: impl AsRef<Path>) -> Result<Vec<ConsistencyRule>, String> {
+    let path = path.as_ref();
+    if !path.exists() { return Ok(default_consistency_rules()); }
+    read_json(path)
+}
+
+// 游戏端读取的三个导出文件：output/<地图名>/<地图名>地图.json 等，扩展名随导出格式变化
+pub struct ExportPaths {
+    pub terrain: PathBuf,
+    pub strategy: PathBuf,
+    pub building_configs: PathBuf,
+    pub timeline: PathBuf,
+    pub operations: PathBuf,
+    // 按波次拆分的操作序列所在目录
+    pub waves: PathBuf,
+}
+
+pub fn export_paths(root: impl AsRef<Path>, map_name: &str, format: DataFormat) -> ExportPaths {
+    let dir = root.as_ref().join(map_name);
+    let ext = format.extension();
+    ExportPaths {
+        terrain: dir.join(format!("{}地图.{}", map_name, ext)),
+        strategy: dir.join(format!("{}策略.{}", map_name, ext)),
+        building_configs: dir.join(format!("{}防御塔列表.{}", map_name, ext)),
+        timeline: dir.join(format!("{}时间序列.{}", map_name, ext)),
+        operations: dir.join(format!("{}_operations.{}", map_name, ext)),
+        waves: dir.join("waves"),
+    }
+}
+
+// 按波次拆分的操作序列：<waves 目录>/wave_03.json，每波一个文件
+pub fn wave_file(dir: &Path, wave: i32, format: DataFormat) -> PathBuf {
+    dir.join(format!("wave_{:02}.{}", wave, format.extension()))
+}
+
+// 写入前删除目录中上次导出留下的同格式 wave_* 文件，波次减少或平移后执行器不会读到过期的波次
+pub fn write_wave_files<T: Serialize>(dir: &Path, waves: &[(i32, T)], format: DataFormat) -> Result<Vec<PathBuf>, String> {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let stale = path.file_stem().and_then(|s| s.to_str()).is_some_and(|s| s.starts_with("wave_")) && path.extension().and_then(|e| e.to_str()) == Some(format.extension());
+            if stale { fs::remove_file(&path).map_err(|e| format!("删除 {} 失败: {}", path.display(), e))?; }
+        }
+    }
+    waves.iter().map(|(wave, value)| {
+        let path = wave_file(dir, *wave, format);
+        write_data(&path, value).map(|()| path)
+    }).collect()
+}
@@ -0,0 +1,172 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use eframe::egui::{self, Color32};
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::dirty::DiscardAction;
+use crate::i18n::{tr, trf};
+use crate::migration::{migrate_strategy, read_migrated};
+use crate::models::*;
+use crate::renumber::{renumber_mapping, renumber_strategy};
+use crate::storage::DATA_EXTENSIONS;
+use crate::tabs::file_stem;
+use crate::validation::{validate_strategy, Severity};
+
+// 🔥 导入策略时选择替换或合并：替换沿用原来的整体覆盖；合并把文件中的建筑与事件追加到当前策略，
+// 导入的 uid 从当前最大 uid 之后连续重新编号（引用它们的事件一起改写），合并前列出新出现的问题（占地重叠等）供确认；
+// 文件中落在当前已锁定波次的建筑与事件不合并
+
+// 预览中最多列出的问题数
+const MAX_LISTED: usize = 30;
+
+pub(crate) struct StrategyImportDialog {
+    path: PathBuf,
+    data: MapBuildingsExport,
+    merge: bool,
+    // 合并后出现了新问题仍要合并
+    accept_problems: bool,
+    // 第一次选择合并时计算的预览，之后的帧沿用；应用时按当前数据重新合并
+    preview: Option<MergePreview>,
+}
+
+pub(crate) struct MergePreview {
+    pub(crate) data: MapBuildingsExport,
+    pub(crate) buildings: usize,
+    pub(crate) events: usize,
+    // 引用的 uid 在导入文件中没有对应建筑、因而被跳过的事件数
+    pub(crate) skipped: usize,
+    // 落在锁定波次、因而不合并的建筑与事件数
+    pub(crate) locked: usize,
+    pub(crate) problems: Vec<String>,
+}
+
+// 把 incoming 的建筑 uid 改为 base 中已用 uid 之后的连续编号，返回被跳过的事件数；
+// 引用不存在建筑的事件合并后会被错误地指向 base 中的建筑，直接跳过
+pub(crate) fn remap_for_merge(base: &MapBuildingsExport, incoming: &mut MapBuildingsExport) -> usize {
+    let used = base.buildings.iter().map(|b| b.uid)
+        .chain(base.demolishes.iter().map(|e| e.uid)).chain(base.sells.iter().map(|e| e.uid)).chain(base.relocations.iter().map(|e| e.uid))
+        .chain(base.upgrades.iter().filter_map(|e| e.uid)).max();
+    let start = used.map_or(1000, |uid| uid + 1);
+    let uids: HashSet<usize> = incoming.buildings.iter().map(|b| b.uid).collect();
+    let before = incoming.demolishes.len() + incoming.sells.len() + incoming.relocations.len() + incoming.upgrades.len();
+    incoming.demolishes.retain(|e| uids.contains(&e.uid));
+    incoming.sells.retain(|e| uids.contains(&e.uid));
+    incoming.relocations.retain(|e| uids.contains(&e.uid));
+    incoming.upgrades.retain(|e| e.uid.is_none_or(|uid| uids.contains(&uid)));
+    let skipped = before - (incoming.demolishes.len() + incoming.sells.len() + incoming.relocations.len() + incoming.upgrades.len());
+    let mapping = renumber_mapping(&incoming.buildings, start, false);
+    renumber_strategy(incoming, &mapping);
+    skipped
+}
+
+// 去掉 incoming 中落在锁定波次的建筑与事件，返回去掉的条数；引用被去掉的建筑的事件随后由 remap_for_merge 跳过
+fn drop_locked(incoming: &mut MapBuildingsExport, locked: &BTreeSet<i32>) -> usize {
+    let count = |d: &MapBuildingsExport| d.buildings.len() + d.upgrades.len() + d.demolishes.len() + d.sells.len() + d.relocations.len() + d.abilities.len();
+    let before = count(incoming);
+    incoming.buildings.retain(|b| !locked.contains(&b.wave_num));
+    incoming.upgrades.retain(|e| !locked.contains(&e.wave_num));
+    incoming.demolishes.retain(|e| !locked.contains(&e.wave_num));
+    incoming.sells.retain(|e| !locked.contains(&e.wave_num));
+    incoming.relocations.retain(|e| !locked.contains(&e.wave_num));
+    incoming.abilities.retain(|e| !locked.contains(&e.wave_num));
+    before - count(incoming)
+}
+
+// 把已重新编号的 incoming 追加到 base
+pub(crate) fn append_strategy(base: &MapBuildingsExport, incoming: MapBuildingsExport) -> MapBuildingsExport {
+    let mut merged = base.clone();
+    merged.buildings.extend(incoming.buildings);
+    merged.upgrades.extend(incoming.upgrades);
+    merged.demolishes.extend(incoming.demolishes);
+    merged.sells.extend(incoming.sells);
+    merged.relocations.extend(incoming.relocations);
+    merged.abilities.extend(incoming.abilities);
+    // 同一波都有备注时拼接，不丢内容；锁定波次取并集，经济设置以当前策略为准
+    for (wave, note) in incoming.wave_notes {
+        merged.wave_notes.entry(wave).and_modify(|n| if *n != note { n.push('\n'); n.push_str(&note); }).or_insert(note);
+    }
+    merged.locked_waves.extend(incoming.locked_waves);
+    if merged.economy.is_none() { merged.economy = incoming.economy; }
+    merged
+}
+
+impl MapEditor {
+    pub(crate) fn import_buildings(&mut self) {
+        let Some(path) = FileDialog::new().set_directory("output").add_filter(tr("策略文件"), DATA_EXTENSIONS).pick_file() else { return; };
+        match read_migrated::<MapBuildingsExport>(&path, migrate_strategy) {
+            Ok((data, report)) => {
+                self.report_migration(&path, &report);
+                self.strategy_import_dialog = Some(StrategyImportDialog { path, data, merge: false, accept_problems: false, preview: None });
+            }
+            Err(e) => self.notifications.error(e),
+        }
+    }
+
+    // 整体替换当前策略（导入前已确认过未保存的修改）
+    pub(crate) fn replace_strategy(&mut self, path: PathBuf, data: MapBuildingsExport) {
+        self.apply_buildings_data(data);
+        self.rename_active_tab(&path);
+        self.notifications.info(trf!("已导入策略: {}", path.display()));
+    }
+
+    pub(crate) fn preview_strategy_merge(&self, incoming: &MapBuildingsExport) -> MergePreview {
+        let base = self.build_buildings_export();
+        let grid = Some((self.grid_rows, self.grid_cols));
+        let errors = |data: &MapBuildingsExport| validate_strategy(data, grid, None).into_iter().filter(|f| f.severity == Severity::Error).map(|f| f.message).collect::<Vec<_>>();
+        let mut incoming = incoming.clone();
+        let locked = drop_locked(&mut incoming, &self.locked_waves);
+        let skipped = remap_for_merge(&base, &mut incoming);
+        // 两边各自原有的问题不算冲突
+        let existing: HashSet<String> = errors(&base).into_iter().chain(errors(&incoming)).collect();
+        let data = append_strategy(&base, incoming);
+        let buildings = data.buildings.len() - base.buildings.len();
+        let count = |d: &MapBuildingsExport| d.upgrades.len() + d.demolishes.len() + d.sells.len() + d.relocations.len() + d.abilities.len();
+        let events = count(&data) - count(&base);
+        let problems = errors(&data).into_iter().filter(|m| !existing.contains(m)).collect();
+        MergePreview { data, buildings, events, skipped, locked, problems }
+    }
+
+    pub(crate) fn show_strategy_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.strategy_import_dialog.take() else { return; };
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        egui::Window::new(tr("导入策略")).id(egui::Id::new("strategy_import")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(trf!("{}: {} 个建筑", file_stem(&dialog.path), dialog.data.buildings.len()));
+            ui.radio_value(&mut dialog.merge, false, tr("替换当前策略"));
+            ui.radio_value(&mut dialog.merge, true, tr("合并到当前策略（追加建筑与事件）"));
+            if dialog.merge && dialog.preview.is_none() { dialog.preview = Some(self.preview_strategy_merge(&dialog.data)); }
+            let preview = dialog.preview.as_ref().filter(|_| dialog.merge);
+            if let Some(preview) = preview {
+                ui.separator();
+                ui.label(trf!("将追加 {} 个建筑、{} 条事件，导入的 UID 重新编号", preview.buildings, preview.events));
+                if preview.locked > 0 { ui.colored_label(Color32::YELLOW, trf!("{} 条建筑或事件位于已锁定的波次，将跳过", preview.locked)); }
+                if preview.skipped > 0 { ui.colored_label(Color32::YELLOW, trf!("{} 条事件引用的 UID 在导入文件中没有建筑，将跳过", preview.skipped)); }
+                if preview.problems.is_empty() {
+                    ui.colored_label(Color32::GREEN, tr("✔ 没有冲突"));
+                } else {
+                    ui.colored_label(Color32::RED, trf!("合并后出现 {} 个新问题:", preview.problems.len()));
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for p in preview.problems.iter().take(MAX_LISTED) { ui.label(format!("• {}", p)); }
+                        if preview.problems.len() > MAX_LISTED { ui.weak(trf!("…等 {} 条", preview.problems.len())); }
+                    });
+                    ui.checkbox(&mut dialog.accept_problems, tr("仍然合并"));
+                }
+            }
+            ui.horizontal(|ui| {
+                let ok = preview.is_none_or(|p| p.problems.is_empty() || dialog.accept_problems);
+                apply = ui.add_enabled(ok, egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if apply && dialog.merge {
+            // 按当前数据重新合并，打开窗口后的编辑不会被覆盖
+            let preview = self.preview_strategy_merge(&dialog.data);
+            self.apply_buildings_data(preview.data);
+            self.notifications.info(trf!("已合并策略: {} (+{} 个建筑, +{} 条事件)", dialog.path.display(), preview.buildings, preview.events));
+        } else if apply {
+            self.request_discard(ctx, DiscardAction::ReplaceStrategy(dialog.path, Box::new(dialog.data)));
+        } else if open && !cancel {
+            self.strategy_import_dialog = Some(dialog);
+        }
+    }
+}
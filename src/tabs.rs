@@ -0,0 +1,181 @@
+use std::mem;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use eframe::egui;
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::dirty::saved_form;
+use crate::history::{History, StrategySnapshot};
+use crate::i18n::{tr, trf};
+use crate::migration::{migrate_strategy, read_migrated};
+use crate::models::*;
+use crate::storage::DATA_EXTENSIONS;
+
+// 🔥 策略标签页：同一份地形上同时打开多份策略（例如保留基准方案的同时试验变体）
+// 当前标签页的策略就是编辑器里的数据；切换时把它存回标签页，再载入目标标签页，策略的撤销历史与选中状态也随标签页保存（地形等共用数据的撤销历史各标签页共用）
+
+// 标签页 id 不随关闭其他标签页而变化，共用撤销历史按 id 找回标签页
+static NEXT_TAB_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) struct StrategyTab {
+    pub(crate) id: usize,
+    pub(crate) name: String,
+    // 非当前标签页保存的策略；当前标签页为 None
+    pub(crate) data: Option<MapBuildingsExport>,
+    history: History<StrategySnapshot>,
+    selected_uids: Vec<usize>,
+    // 最近一次保存或打开时的策略（见 dirty::saved_form），用于判断是否有未保存的修改
+    pub(crate) saved: Option<MapBuildingsExport>,
+}
+
+impl StrategyTab {
+    pub(crate) fn new(name: String, max_depth: usize) -> Self {
+        Self { id: NEXT_TAB_ID.fetch_add(1, Ordering::Relaxed), name, data: None, history: History::new(max_depth), selected_uids: Vec::new(), saved: None }
+    }
+}
+
+// 与撤销/重做一样在帧开始时执行，切换本身不会被记录成一次编辑
+pub(crate) enum TabRequest {
+    Switch(usize),
+    Open { name: String, data: Box<MapBuildingsExport> },
+    Close(usize),
+}
+
+// 共用撤销历史中 recorded 所列标签页现在的策略；active 为当前标签页的策略
+pub(crate) fn current_tab_strategies(tabs: &[StrategyTab], active: &MapBuildingsExport, recorded: &[(usize, MapBuildingsExport)]) -> Vec<(usize, MapBuildingsExport)> {
+    recorded.iter().filter_map(|(id, _)| {
+        let tab = tabs.iter().find(|t| t.id == *id)?;
+        Some((*id, tab.data.clone().unwrap_or_else(|| active.clone())))
+    }).collect()
+}
+
+pub(crate) fn file_stem(path: &Path) -> String {
+    path.file_stem().map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().into_owned())
+}
+
+impl MapEditor {
+    pub(crate) fn apply_tab_request(&mut self, req: TabRequest) {
+        match req {
+            TabRequest::Switch(idx) => self.switch_tab(idx),
+            TabRequest::Open { name, data } => {
+                // 新打开的标签页尚无修改
+                let mut tab = StrategyTab::new(name, self.history.max_depth);
+                tab.saved = Some(saved_form(&data));
+                tab.data = Some(*data);
+                self.strategy_tabs.push(tab);
+                self.switch_tab(self.strategy_tabs.len() - 1);
+            }
+            TabRequest::Close(idx) => {
+                if self.strategy_tabs.len() <= 1 || idx >= self.strategy_tabs.len() { return; }
+                if idx == self.active_tab { self.switch_tab(if idx == 0 { 1 } else { idx - 1 }); }
+                self.strategy_tabs.remove(idx);
+                if idx < self.active_tab { self.active_tab -= 1; }
+            }
+        }
+    }
+
+    fn switch_tab(&mut self, idx: usize) {
+        if idx == self.active_tab || idx >= self.strategy_tabs.len() { return; }
+        let stash = self.build_buildings_export();
+        let max_depth = self.history.max_depth;
+        let current = &mut self.strategy_tabs[self.active_tab];
+        current.data = Some(stash);
+        current.history = mem::replace(&mut self.history, History::new(max_depth));
+        current.selected_uids = mem::take(&mut self.selected_uids);
+
+        let target = &mut self.strategy_tabs[idx];
+        let data = target.data.take().unwrap_or_default();
+        self.history = mem::replace(&mut target.history, History::new(max_depth));
+        self.history.max_depth = max_depth;
+        self.history.trim();
+        self.selected_uids = mem::take(&mut target.selected_uids);
+        self.active_tab = idx;
+        self.apply_buildings_data(data);
+        self.building_drag = None;
+        self.selection_box_origin = None;
+        self.demolish_box_origin = None;
+        self.context_menu_uid = None;
+        self.comment_edit = None;
+        self.building_edit = None;
+        self.renumber_dialog = None;
+    }
+
+    // 所有标签页的策略，当前标签页在前
+    pub(crate) fn all_strategies(&self) -> Vec<MapBuildingsExport> {
+        std::iter::once(self.build_buildings_export()).chain(self.strategy_tabs.iter().filter_map(|t| t.data.clone())).collect()
+    }
+
+    // 整图变换、调整尺寸时所有标签页的策略一起变换；变换前的策略记入共用撤销历史，在任一标签页撤销都会一起恢复
+    pub(crate) fn transform_all_strategies(&mut self, mut transform: impl FnMut(&mut MapBuildingsExport)) {
+        let mut active = self.build_buildings_export();
+        let mut before = vec![(self.strategy_tabs[self.active_tab].id, active.clone())];
+        transform(&mut active);
+        self.apply_buildings_data(active);
+        for tab in &mut self.strategy_tabs {
+            let Some(data) = tab.data.as_mut() else { continue; };
+            before.push((tab.id, data.clone()));
+            transform(data);
+        }
+        // 同一帧内多次变换时保留最早的状态
+        self.pending_tab_undo.get_or_insert(before);
+    }
+
+    // 撤销 / 重做共用历史中记下的各标签页策略；已关闭的标签页跳过
+    pub(crate) fn restore_tab_strategies(&mut self, strategies: Vec<(usize, MapBuildingsExport)>) {
+        for (id, data) in strategies {
+            let Some(idx) = self.strategy_tabs.iter().position(|t| t.id == id) else { continue; };
+            if idx == self.active_tab {
+                self.apply_buildings_data(data);
+                self.selected_uids.retain(|uid| self.placed_buildings.iter().any(|b| b.uid == *uid));
+            } else {
+                self.strategy_tabs[idx].data = Some(data);
+            }
+        }
+    }
+
+    // 导入的策略文件以文件名作为当前标签页的名称
+    pub(crate) fn rename_active_tab(&mut self, path: &Path) {
+        if let Some(tab) = self.strategy_tabs.get_mut(self.active_tab) { tab.name = file_stem(path); }
+    }
+
+    fn open_strategy_in_new_tab(&mut self) {
+        let Some(path) = FileDialog::new().set_directory("output").add_filter(tr("策略文件"), DATA_EXTENSIONS).pick_file() else { return; };
+        match read_migrated::<MapBuildingsExport>(&path, migrate_strategy) {
+            Ok((data, report)) => {
+                self.report_migration(&path, &report);
+                self.tab_request = Some(TabRequest::Open { name: file_stem(&path), data: Box::new(data) });
+                self.notifications.info(trf!("已在新标签页打开策略: {}", path.display()));
+            }
+            Err(e) => self.notifications.error(e),
+        }
+    }
+
+    pub(crate) fn show_strategy_tabs(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let closable = self.strategy_tabs.len() > 1;
+            for i in 0..self.strategy_tabs.len() {
+                let active = i == self.active_tab;
+                if active {
+                    ui.add(egui::TextEdit::singleline(&mut self.strategy_tabs[i].name).desired_width(110.0));
+                } else if ui.selectable_label(false, &self.strategy_tabs[i].name).on_hover_text(tr("切换到此策略")).clicked() {
+                    self.tab_request = Some(TabRequest::Switch(i));
+                }
+                if closable && ui.small_button("×").on_hover_text(tr("关闭标签页（未导出的修改会丢失）")).clicked() {
+                    self.tab_request = Some(TabRequest::Close(i));
+                }
+                ui.separator();
+            }
+            if ui.button(tr("＋ 空白策略")).clicked() {
+                let name = trf!("策略 {}", self.strategy_tabs.len() + 1);
+                let data = MapBuildingsExport { format_version: FORMAT_VERSION, map_name: self.export_map_name(), ..Default::default() };
+                self.tab_request = Some(TabRequest::Open { name, data: Box::new(data) });
+            }
+            if ui.button(tr("⧉ 复制当前")).on_hover_text(tr("以当前策略为基准新建一个变体")).clicked() {
+                let name = trf!("{} 副本", self.strategy_tabs[self.active_tab].name);
+                self.tab_request = Some(TabRequest::Open { name, data: Box::new(self.build_buildings_export()) });
+            }
+            if ui.button(tr("📂 在新标签页打开…")).clicked() { self.open_strategy_in_new_tab(); }
+        });
+    }
+}
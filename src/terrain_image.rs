@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+use eframe::egui;
+use image::{Rgba, RgbaImage};
+use rfd::FileDialog;
+
+use crate::app::MapEditor;
+use crate::assets::{resolve_asset, TERRAIN_TYPES_FILE};
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::storage::{read_image, IMAGE_EXTENSIONS};
+use crate::transform::CutCounts;
+
+// 🔥 颜色图导入地形：读取按颜色标注地形的图片（例如在绘图软件里描出的地图），按地形类型的导入颜色对照表填充当前层的地面网格
+// 图片与网格尺寸不同时按每格中心取样；也可以把网格尺寸改成图片尺寸（每像素一格）
+
+pub(crate) struct TerrainImageImport {
+    path: PathBuf,
+    image: RgbaImage,
+    // 每个通道允许的最大色差
+    tolerance: u8,
+    fit_grid: bool,
+    keep_unmatched: bool,
+    // 改为图片尺寸会让内容移出网格时，须确认删除
+    allow_cut: bool,
+    // 对照表的编辑副本，导入或保存时才写回地形类型，取消则丢弃
+    types: Vec<TerrainType>,
+}
+
+// 容差内最接近的导入颜色对应的地形值；半透明像素视为未标注
+fn match_color(types: &[TerrainType], px: Rgba<u8>, tolerance: u8) -> Option<i8> {
+    if px[3] < 128 { return None; }
+    types.iter()
+        .map(|t| { let rgb = t.import_rgb(); (t.id, [0, 1, 2].map(|i| rgb[i].abs_diff(px[i]))) })
+        .filter(|(_, diff)| diff.iter().all(|d| *d <= tolerance))
+        .min_by_key(|(_, diff)| diff.iter().map(|d| (*d as u32).pow(2)).sum::<u32>())
+        .map(|(id, _)| id)
+}
+
+// 按颜色对照表生成 rows×cols 的网格；未匹配的格子保留 base 中的值（base 为空或尺寸不符时填障碍），返回网格与未匹配格数
+pub(crate) fn grid_from_image(image: &RgbaImage, types: &[TerrainType], tolerance: u8, rows: usize, cols: usize, base: Option<&Grid>) -> (Grid, usize) {
+    let base = base.filter(|g| g.rows() == rows && g.cols() == cols);
+    let mut grid = base.cloned().unwrap_or_else(|| Grid::new(rows, cols, -1));
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let mut unmatched = 0;
+    for r in 0..rows {
+        for c in 0..cols {
+            let px = *image.get_pixel(((2 * c + 1) * w / (2 * cols)) as u32, ((2 * r + 1) * h / (2 * rows)) as u32);
+            match match_color(types, px, tolerance) {
+                Some(val) => grid.set(r, c, val),
+                None => {
+                    unmatched += 1;
+                    if base.is_none() { grid.set(r, c, -1); }
+                }
+            }
+        }
+    }
+    (grid, unmatched)
+}
+
+impl MapEditor {
+    pub(crate) fn open_terrain_image_import(&mut self) {
+        let Some(path) = FileDialog::new().add_filter(tr("图片文件"), IMAGE_EXTENSIONS).pick_file() else { return; };
+        match read_image(&path) {
+            Ok(img) => {
+                let fit_grid = img.width() as usize != self.grid_cols || img.height() as usize != self.grid_rows;
+                self.terrain_image_import = Some(TerrainImageImport { path, image: img.to_rgba8(), tolerance: 24, fit_grid: fit_grid && img.width().max(img.height()) <= 512, keep_unmatched: true, allow_cut: false, types: self.terrain_types.clone() });
+            }
+            Err(e) => self.notifications.error(e),
+        }
+    }
+
+    // 把对照表副本中的导入颜色按地形 id 写回
+    fn commit_import_colors(&mut self, types: &[TerrainType]) {
+        for t in &mut self.terrain_types {
+            if let Some(edited) = types.iter().find(|e| e.id == t.id) { t.import_color = edited.import_color; }
+        }
+    }
+
+    fn apply_terrain_image_import(&mut self, import: &TerrainImageImport) {
+        if import.fit_grid {
+            let counts = self.resize_map(import.image.height() as usize, import.image.width() as usize, 0, 0);
+            if !counts.is_empty() { self.notifications.info(trf!("已删除移出网格的 {} 座建筑、{} 个技能事件、{} 个路线点（所有标签页合计）", counts.buildings, counts.abilities, counts.route_points)); }
+        }
+        let (rows, cols) = (self.grid_rows, self.grid_cols);
+        let Some(layer) = self.layers_data.get_mut(&self.current_major_z) else { return; };
+        let base = import.keep_unmatched.then_some(&layer.floor_grid);
+        let (grid, unmatched) = grid_from_image(&import.image, &import.types, import.tolerance, rows, cols, base);
+        layer.floor_grid = grid;
+        self.notifications.info(trf!("已从 {} 导入 {} 层地面：{} 格，未匹配 {} 格", import.path.display(), layer.name, rows * cols, unmatched));
+    }
+
+    pub(crate) fn show_terrain_image_import(&mut self, ctx: &egui::Context) {
+        let Some(mut import) = self.terrain_image_import.take() else { return; };
+        let (mut open, mut apply, mut cancel, mut save_types) = (true, false, false, false);
+        let cut = if import.fit_grid { self.resize_cut_counts(import.image.height() as usize, import.image.width() as usize, [0, 0]) } else { CutCounts::default() };
+        egui::Window::new(tr("从颜色图导入地形")).id(egui::Id::new("terrain_image_import")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(import.path.display().to_string());
+            ui.label(trf!("图片 {}×{} 像素，网格 {} 列 × {} 行", import.image.width(), import.image.height(), self.grid_cols, self.grid_rows));
+            ui.checkbox(&mut import.fit_grid, trf!("网格尺寸改为图片尺寸（{} 列 × {} 行，每像素一格）", import.image.width(), import.image.height()));
+            if !import.fit_grid { ui.weak(tr("按每个格子中心的像素取样")); }
+            if !cut.is_empty() {
+                ui.colored_label(egui::Color32::YELLOW, trf!("将移出网格并删除（所有标签页合计）: {} 座建筑（连同其事件）、{} 个技能事件、{} 个路线点", cut.buildings, cut.abilities, cut.route_points));
+                ui.checkbox(&mut import.allow_cut, tr("确认删除这些内容"));
+            }
+            if cut.locked > 0 { ui.colored_label(egui::Color32::RED, trf!("其中 {} 条建筑或事件属于已锁定的波次，请先在波次设置中解锁", cut.locked)); }
+            ui.separator();
+            ui.label(tr("颜色对照表:"));
+            egui::Grid::new("terrain_import_colors").num_columns(2).show(ui, |ui| {
+                for t in &mut import.types {
+                    ui.label(format!("{} ({})", t.name, t.id));
+                    let mut rgb = t.import_rgb();
+                    if ui.color_edit_button_srgb(&mut rgb).changed() { t.import_color = Some(rgb); }
+                    ui.end_row();
+                }
+            });
+            ui.add(egui::Slider::new(&mut import.tolerance, 0..=128).text(tr("颜色容差")));
+            ui.checkbox(&mut import.keep_unmatched, tr("未匹配的格子保留原地形（否则设为障碍）"));
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled((cut.is_empty() || import.allow_cut) && cut.locked == 0, egui::Button::new(tr("导入到当前层地面"))).clicked();
+                save_types = ui.button(tr("💾 保存对照表")).on_hover_text(tr("写入资源目录下的 terrain_types.json")).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if save_types || apply { self.commit_import_colors(&import.types); }
+        if save_types {
+            let path = resolve_asset(&self.asset_root, TERRAIN_TYPES_FILE);
+            let types = self.terrain_types.clone();
+            self.write_output(path, &types);
+        }
+        if apply {
+            self.apply_terrain_image_import(&import);
+        } else if open && !cancel {
+            self.terrain_image_import = Some(import);
+        }
+    }
+}
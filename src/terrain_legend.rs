@@ -0,0 +1,39 @@
+use eframe::egui::{self, Color32, Sense, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+
+// 🔥 地形图例：浮动窗口按 terrain_types.json 列出各地形的色块、名称、数值与能否建造，
+// 并高亮光标所在格子（当前编辑层）的地形，不必再记每种颜色的含义。可折叠、拖动，顶部工具栏开关
+
+impl MapEditor {
+    pub(crate) fn show_terrain_legend(&mut self, ctx: &egui::Context) {
+        if !self.show_terrain_legend { return; }
+        let mut open = true;
+        let hovered = self.hovered_terrain;
+        let highlight = ctx.style().visuals.selection.bg_fill;
+        egui::Window::new(tr("地形图例")).open(&mut open).collapsible(true).resizable(false)
+            .pivot(egui::Align2::RIGHT_TOP).default_pos(ctx.screen_rect().right_top() + Vec2::new(-300.0, 80.0))
+            .show(ctx, |ui| {
+                for t in &self.terrain_types {
+                    let active = hovered == Some(t.id);
+                    egui::Frame::none().fill(if active { highlight } else { Color32::TRANSPARENT }).rounding(3.0).inner_margin(2.0).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let (rect, _) = ui.allocate_exact_size(Vec2::new(14.0, 14.0), Sense::hover());
+                            ui.painter().rect_filled(rect, 2.0, Color32::from_rgb(t.color[0], t.color[1], t.color[2]));
+                            let text = format!("{} ({})", t.name, t.id);
+                            if active { ui.strong(text); } else { ui.label(text); }
+                            ui.weak(if t.buildable { tr("可建造") } else { tr("不可建造") });
+                        });
+                    });
+                }
+                ui.separator();
+                match hovered {
+                    Some(v) if self.terrain_types.iter().all(|t| t.id != v) => { ui.colored_label(Color32::YELLOW, trf!("光标处的值 {} 未在地形类型中定义", v)); }
+                    Some(_) => { ui.weak(trf!("高亮为光标处 {} 层的地形", format!("{:?}", self.current_edit_layer_type))); }
+                    None => { ui.weak(tr("光标不在网格内")); }
+                }
+            });
+        if !open { self.show_terrain_legend = false; }
+    }
+}
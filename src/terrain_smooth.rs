@@ -0,0 +1,25 @@
+use crate::app::MapEditor;
+
+// 🔥 平滑笔刷：清理导入或手绘地形中的孤立杂点与锯齿边缘
+// 对笔刷范围内的每个格子取 3x3 邻域（含自身，网格外的不计）的多数值：某个值占到一半以上时改为该值，否则保持不变。
+// 孤立的单格会被周围的值吞掉，两种地形之间的锯齿边缘被拉直；按住拖动时每帧再做一遍，多抹几下会更平整
+
+impl MapEditor {
+    pub(crate) fn smooth_terrain(&mut self, c: i32, r: i32) {
+        let (rows, cols, radius) = (self.grid_rows as i32, self.grid_cols as i32, self.brush_radius);
+        let grid = self.layers_data.get_mut(&self.current_major_z).unwrap().get_grid_mut(self.current_edit_layer_type);
+        // 先全部算完再写回，结果不受遍历顺序影响
+        let mut changes = Vec::new();
+        for y in (r - radius).max(0)..(r + radius + 1).min(rows) {
+            for x in (c - radius).max(0)..(c + radius + 1).min(cols) {
+                let neighbours: Vec<i8> = (y - 1..=y + 1).filter(|ny| (0..rows).contains(ny))
+                    .flat_map(|ny| (x - 1..=x + 1).filter(|nx| (0..cols).contains(nx)).map(move |nx| (ny, nx)))
+                    .map(|(ny, nx)| grid.get(ny as usize, nx as usize)).collect();
+                let majority = neighbours.iter().copied().find(|v| neighbours.iter().filter(|n| *n == v).count() * 2 > neighbours.len());
+                let current = grid.get(y as usize, x as usize);
+                if let Some(v) = majority.filter(|&v| v != current) { changes.push((y as usize, x as usize, v)); }
+            }
+        }
+        for (y, x, v) in changes { grid.set(y, x, v); }
+    }
+}
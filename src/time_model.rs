@@ -0,0 +1,67 @@
+use std::sync::RwLock;
+use eframe::egui;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::TimeModel;
+use crate::utils::{get_time_value, wave_from_time_value};
+
+// 🔥 可配置的时间模型：时间值的换算（get_time_value / wave_from_time_value）与波次标签都经过当前关卡的 TimeModel，
+// 放置检查、画布、校验与导出的先后比较随之改变。模型保存在地形文件的 meta.time_model 中；
+// 编辑器每帧把当前设置写入全局，命令行在读入地形或工程后写入，之后的换算都按它进行
+
+static CURRENT: RwLock<Option<TimeModel>> = RwLock::new(None);
+
+pub fn set_time_model(model: &TimeModel) {
+    let mut current = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    if current.as_ref() != Some(model) { *current = Some(model.clone()); }
+}
+
+// 未设置时为默认的每波前期 + 后期
+pub fn with_time_model<R>(f: impl FnOnce(&TimeModel) -> R) -> R {
+    match &*CURRENT.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(model) => f(model),
+        None => f(&TimeModel::default()),
+    }
+}
+
+impl MapEditor {
+    // 每波时长表：导出绝对时间序列时用来把波次换算成秒
+    fn show_wave_seconds(&mut self, ui: &mut egui::Ui) {
+        let seconds = &mut self.time_model.wave_seconds;
+        egui::CollapsingHeader::new(trf!("每波时长（{} 项）", seconds.len())).id_source("wave_seconds").show(ui, |ui| {
+            ui.weak(tr("导出绝对时间序列时使用，超出表长的波次沿用最后一项"));
+            ui.horizontal_wrapped(|ui| {
+                for (i, s) in seconds.iter_mut().enumerate() {
+                    ui.add(egui::DragValue::new(s).speed(1.0).clamp_range(0.0..=3600.0).prefix(format!("W{} ", i + 1)).suffix("s"));
+                }
+                if ui.small_button("＋").on_hover_text(tr("添加一波，时长沿用最后一项")).clicked() { seconds.push(seconds.last().copied().unwrap_or(60.0)); }
+                if !seconds.is_empty() && ui.small_button("－").on_hover_text(tr("删除最后一波")).clicked() { seconds.pop(); }
+            });
+        });
+    }
+
+    pub(crate) fn show_time_model_settings(&mut self, ui: &mut egui::Ui) {
+        let before = self.time_model.clone();
+        ui.horizontal(|ui| {
+            ui.label(tr("时间划分:"));
+            ui.radio_value(&mut self.time_model.slots_per_wave, 2, tr("前期 + 后期"));
+            ui.radio_value(&mut self.time_model.slots_per_wave, 1, tr("整波")).on_hover_text(tr("不区分前期与后期，后期事件与同波前期视为同一时刻"));
+        });
+        if self.time_model.has_late_phase() {
+            ui.horizontal(|ui| {
+                let mut enabled = self.time_model.late_offset_seconds.is_some();
+                if ui.checkbox(&mut enabled, tr("后期开始于第")).on_hover_text(tr("设置后波次标签显示为 W3+30s 而不是 W3L")).changed() {
+                    self.time_model.late_offset_seconds = enabled.then_some(30.0);
+                }
+                if let Some(seconds) = &mut self.time_model.late_offset_seconds { ui.add(egui::DragValue::new(seconds).clamp_range(0.0..=3600.0).suffix(tr(" 秒"))); }
+            });
+        }
+        self.show_wave_seconds(ui);
+        if self.time_model != before {
+            set_time_model(&self.time_model);
+            // 改为整波时当前时间落到对应波次
+            (self.current_wave_num, self.current_is_late) = wave_from_time_value(get_time_value(self.current_wave_num, self.current_is_late));
+        }
+    }
+}
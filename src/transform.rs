@@ -0,0 +1,341 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::ops::Range;
+use eframe::egui::{self, Pos2, Rect, Vec2};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+
+// 🔥 整图变换：地形各层网格、建筑与全部事件、敌人路线、镜头数据一起翻转或旋转，便于把现有方案套用到镜像/旋转后的地图上
+// 网格坐标按网格尺寸变换；镜头关键帧、观察框预设、安全区域等底图坐标绕网格中心变换（它们记录观察框左上角，按观察框中心换算）
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub(crate) enum MapTransform { FlipHorizontal, FlipVertical, RotateClockwise, RotateCounterClockwise }
+
+// 翻转一段 [pos + offset, pos + offset + size) 的区间，返回新的整格位置与小数偏移；超出网格的部分贴到边缘
+fn flip_span(pos: usize, offset: f32, size: usize, extent: usize) -> (usize, f32) {
+    if offset == 0.0 { return (extent.saturating_sub(pos + size), 0.0); }
+    let start = (extent as f32 - size as f32 - pos as f32 - offset).max(0.0);
+    (start.floor() as usize, start.fract())
+}
+
+impl MapTransform {
+    fn rotates(self) -> bool { matches!(self, MapTransform::RotateClockwise | MapTransform::RotateCounterClockwise) }
+
+    // 变换前尺寸为 w×h、位于 (x, y) + offset 的矩形在变换后的左上角与小数偏移；旋转时宽高由调用方互换
+    #[allow(clippy::too_many_arguments)]
+    fn rect(self, x: usize, y: usize, offset: [f32; 2], w: usize, h: usize, rows: usize, cols: usize) -> (usize, usize, [f32; 2]) {
+        match self {
+            MapTransform::FlipHorizontal => { let (nx, ox) = flip_span(x, offset[0], w, cols); (nx, y, [ox, offset[1]]) }
+            MapTransform::FlipVertical => { let (ny, oy) = flip_span(y, offset[1], h, rows); (x, ny, [offset[0], oy]) }
+            MapTransform::RotateClockwise => { let (nx, ox) = flip_span(y, offset[1], h, rows); (nx, x, [ox, offset[0]]) }
+            MapTransform::RotateCounterClockwise => { let (ny, oy) = flip_span(x, offset[0], w, cols); (y, ny, [offset[1], oy]) }
+        }
+    }
+
+    fn cell(self, x: usize, y: usize, rows: usize, cols: usize) -> (usize, usize) {
+        let (nx, ny, _) = self.rect(x, y, [0.0; 2], 1, 1, rows, cols);
+        (nx, ny)
+    }
+
+    // 相对网格中心的底图坐标（y 轴向下）
+    fn point(self, d: Vec2) -> Vec2 {
+        match self {
+            MapTransform::FlipHorizontal => Vec2::new(-d.x, d.y),
+            MapTransform::FlipVertical => Vec2::new(d.x, -d.y),
+            MapTransform::RotateClockwise => Vec2::new(-d.y, d.x),
+            MapTransform::RotateCounterClockwise => Vec2::new(d.y, -d.x),
+        }
+    }
+
+    // 镜头方向键跟着换向，录制的镜头轨迹才能按变换后的方向移动
+    fn key(self, k: char) -> char {
+        let [w, a, s, d] = match self {
+            MapTransform::FlipHorizontal => ['w', 'd', 's', 'a'],
+            MapTransform::FlipVertical => ['s', 'a', 'w', 'd'],
+            MapTransform::RotateClockwise => ['d', 'w', 'a', 's'],
+            MapTransform::RotateCounterClockwise => ['a', 's', 'd', 'w'],
+        };
+        match k { 'w' => w, 'a' => a, 's' => s, 'd' => d, _ => k }
+    }
+}
+
+// 按变换前的网格尺寸变换一份策略；事件里的位置按对应建筑的小数偏移换算，保证与建筑变换后的位置一致
+pub(crate) fn transform_strategy(data: &mut MapBuildingsExport, t: MapTransform, rows: usize, cols: usize) {
+    let offsets: HashMap<usize, [f32; 2]> = data.buildings.iter().map(|b| (b.uid, b.offset)).collect();
+    let rect = |uid: usize, x: &mut usize, y: &mut usize, w: usize, h: usize| {
+        (*x, *y, _) = t.rect(*x, *y, offsets.get(&uid).copied().unwrap_or_default(), w, h, rows, cols);
+    };
+    let swap = |w: &mut usize, h: &mut usize| if t.rotates() { mem::swap(w, h); };
+    for e in &mut data.demolishes { rect(e.uid, &mut e.grid_x, &mut e.grid_y, e.width, e.height); swap(&mut e.width, &mut e.height); }
+    for e in &mut data.sells { rect(e.uid, &mut e.grid_x, &mut e.grid_y, e.width, e.height); swap(&mut e.width, &mut e.height); }
+    for e in &mut data.relocations {
+        rect(e.uid, &mut e.from_x, &mut e.from_y, e.width, e.height);
+        rect(e.uid, &mut e.to_x, &mut e.to_y, e.width, e.height);
+        swap(&mut e.width, &mut e.height);
+    }
+    for b in &mut data.buildings {
+        (b.grid_x, b.grid_y, b.offset) = t.rect(b.grid_x, b.grid_y, b.offset, b.width, b.height, rows, cols);
+        swap(&mut b.width, &mut b.height);
+    }
+    for (x, y) in data.abilities.iter_mut().filter_map(|a| a.target.as_mut()) { (*x, *y) = t.cell(*x, *y, rows, cols); }
+}
+
+// 按锚点调整尺寸时因平移移出网格而被删除的内容；locked 为其中属于锁定波次的建筑与事件条数，不为 0 时不允许调整
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub(crate) struct CutCounts { pub(crate) buildings: usize, pub(crate) abilities: usize, pub(crate) route_points: usize, pub(crate) locked: usize }
+
+impl CutCounts {
+    pub(crate) fn is_empty(&self) -> bool { *self == CutCounts::default() }
+
+    fn add(&mut self, other: CutCounts) {
+        self.buildings += other.buildings;
+        self.abilities += other.abilities;
+        self.route_points += other.route_points;
+        self.locked += other.locked;
+    }
+}
+
+// 策略中落在锁定波次的建筑与事件条数
+fn locked_entries(data: &MapBuildingsExport) -> usize {
+    let locked = |wave: i32| data.locked_waves.contains(&wave);
+    data.buildings.iter().filter(|b| locked(b.wave_num)).count() + data.upgrades.iter().filter(|e| locked(e.wave_num)).count()
+        + data.demolishes.iter().filter(|e| locked(e.wave_num)).count() + data.sells.iter().filter(|e| locked(e.wave_num)).count()
+        + data.relocations.iter().filter(|e| locked(e.wave_num)).count() + data.abilities.iter().filter(|e| locked(e.wave_num)).count()
+}
+
+// 锚点 0/1/2 表示内容贴住起始边/居中/末尾边，返回内容需要平移的格数
+fn anchor_shift(old: usize, new: usize, anchor: usize) -> isize { (new as isize - old as isize) * anchor as isize / 2 }
+
+// 把策略整体平移 (dx, dy) 格放进 rows×cols 的网格：任一时刻（初始位置或迁移目标）超出网格的建筑连同它的事件一起删除，目标格超出网格的技能事件也删除
+pub(crate) fn shift_strategy(data: &mut MapBuildingsExport, dx: isize, dy: isize, rows: usize, cols: usize) -> CutCounts {
+    let fits = |x: usize, y: usize, w: usize, h: usize| x.checked_add_signed(dx).filter(|nx| nx + w <= cols).zip(y.checked_add_signed(dy).filter(|ny| ny + h <= rows));
+    let moved = |x: &mut usize, y: &mut usize, w: usize, h: usize| fits(*x, *y, w, h).map(|(nx, ny)| (*x, *y) = (nx, ny)).is_some();
+    let mut cut: HashSet<usize> = data.buildings.iter().filter(|b| fits(b.grid_x, b.grid_y, b.width, b.height).is_none()).map(|b| b.uid).collect();
+    cut.extend(data.relocations.iter().filter(|e| fits(e.to_x, e.to_y, e.width, e.height).is_none()).map(|e| e.uid));
+    let (buildings, abilities, locked) = (data.buildings.len(), data.abilities.len(), locked_entries(data));
+    data.buildings.retain_mut(|b| !cut.contains(&b.uid) && moved(&mut b.grid_x, &mut b.grid_y, b.width, b.height));
+    data.demolishes.retain_mut(|e| !cut.contains(&e.uid) && moved(&mut e.grid_x, &mut e.grid_y, e.width, e.height));
+    data.sells.retain_mut(|e| !cut.contains(&e.uid) && moved(&mut e.grid_x, &mut e.grid_y, e.width, e.height));
+    data.relocations.retain_mut(|e| !cut.contains(&e.uid) && moved(&mut e.from_x, &mut e.from_y, e.width, e.height) && moved(&mut e.to_x, &mut e.to_y, e.width, e.height));
+    data.upgrades.retain(|e| e.uid.is_none_or(|uid| !cut.contains(&uid)));
+    data.abilities.retain_mut(|a| a.target.as_mut().is_none_or(|(x, y)| moved(x, y, 1, 1)));
+    CutCounts { buildings: buildings - data.buildings.len(), abilities: abilities - data.abilities.len(), route_points: 0, locked: locked - locked_entries(data) }
+}
+
+fn shift_routes(routes: &mut [EnemyRoute], dx: isize, dy: isize, rows: usize, cols: usize) -> usize {
+    let mut removed = 0;
+    for route in routes {
+        let before = route.points.len();
+        route.points.retain_mut(|p| match p.grid_x.checked_add_signed(dx).filter(|&x| x < cols).zip(p.grid_y.checked_add_signed(dy).filter(|&y| y < rows)) {
+            Some((x, y)) => { (p.grid_x, p.grid_y) = (x, y); true }
+            None => false,
+        });
+        removed += before - route.points.len();
+    }
+    removed
+}
+
+// 调整网格尺寸对话框；anchor 为 [列方向, 行方向] 的锚点
+pub(crate) struct ResizeDialog {
+    rows: usize,
+    cols: usize,
+    anchor: [usize; 2],
+    allow_cut: bool,
+}
+
+impl MapEditor {
+    fn grid_center(&self) -> Pos2 {
+        Pos2::new(self.offset_x + self.grid_cols as f32 * self.grid_width / 2.0, self.offset_y + self.grid_rows as f32 * self.grid_height / 2.0)
+    }
+
+    pub(crate) fn transform_map(&mut self, t: MapTransform) {
+        let (rows, cols) = (self.grid_rows, self.grid_cols);
+        let old_center = self.grid_center();
+        for layer in self.layers_data.values_mut() {
+            for grid in [&mut layer.floor_grid, &mut layer.wall_grid, &mut layer.ceiling_grid] {
+                *grid = match t {
+                    MapTransform::FlipHorizontal | MapTransform::FlipVertical => grid.flipped(t == MapTransform::FlipHorizontal),
+                    MapTransform::RotateClockwise | MapTransform::RotateCounterClockwise => grid.rotated(t == MapTransform::RotateClockwise),
+                };
+            }
+        }
+        for p in self.enemy_routes.iter_mut().flat_map(|r| r.points.iter_mut()) { (p.grid_x, p.grid_y) = t.cell(p.grid_x, p.grid_y, rows, cols); }
+        if t.rotates() {
+            mem::swap(&mut self.grid_rows, &mut self.grid_cols);
+            mem::swap(&mut self.grid_width, &mut self.grid_height);
+        }
+
+        self.transform_all_strategies(|data| transform_strategy(data, t, rows, cols));
+        self.building_drag = None;
+        self.selection_box_origin = None;
+        self.demolish_box_origin = None;
+
+        // 底图坐标：观察框中心相对旧网格中心变换后，平移到新网格中心（旋转后网格的像素尺寸会变）
+        let new_center = self.grid_center();
+        let half = Vec2::new(self.viewport_width, self.viewport_height) / 2.0;
+        let map_view = |p: Pos2| new_center + t.point(p + half - old_center) - half;
+        for k in &mut self.camera_keyframes {
+            (k.x, k.y) = map_view(Pos2::new(k.x, k.y)).into();
+            k.keys = k.keys.chars().map(|c| t.key(c)).collect();
+        }
+        for p in &mut self.viewport_presets { (p.x, p.y) = map_view(Pos2::new(p.x, p.y)).into(); }
+        for area in &mut self.viewport_safe_areas { *area = Rect::from_two_pos(map_view(area.min), map_view(area.max)); }
+        self.viewport_pos = map_view(self.viewport_pos.to_pos2()).to_vec2();
+
+        self.notifications.info(match t {
+            MapTransform::FlipHorizontal => tr("已水平翻转地形与策略，请加载镜像后的底图"),
+            MapTransform::FlipVertical => tr("已垂直翻转地形与策略，请加载镜像后的底图"),
+            MapTransform::RotateClockwise => tr("已顺时针旋转地形与策略，请加载旋转后的底图"),
+            MapTransform::RotateCounterClockwise => tr("已逆时针旋转地形与策略，请加载旋转后的底图"),
+        });
+    }
+
+    pub(crate) fn show_map_transforms(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(tr("整图变换:"));
+            let hint = tr("地形、建筑、事件、路线与镜头数据一起变换（包括其他标签页的策略）");
+            for (t, label) in [
+                (MapTransform::FlipHorizontal, tr("↔ 水平翻转")),
+                (MapTransform::FlipVertical, tr("↕ 垂直翻转")),
+                (MapTransform::RotateClockwise, tr("↻ 顺时针 90°")),
+                (MapTransform::RotateCounterClockwise, tr("↺ 逆时针 90°")),
+            ] {
+                if ui.button(label).on_hover_text(hint).clicked() { self.transform_map(t); }
+            }
+        });
+    }
+}
+
+impl MapEditor {
+    pub(crate) fn open_resize_dialog(&mut self) {
+        self.resize_dialog = Some(ResizeDialog { rows: self.grid_rows, cols: self.grid_cols, anchor: [0, 0], allow_cut: false });
+    }
+
+    fn resize_shift(&self, rows: usize, cols: usize, anchor: [usize; 2]) -> (isize, isize) {
+        (anchor_shift(self.grid_cols, cols, anchor[0]), anchor_shift(self.grid_rows, rows, anchor[1]))
+    }
+
+    // 直接修改网格行列：缩小后有内容会移出网格时不立即生效，改为打开调整尺寸对话框列出会删除的内容，确认后才应用
+    pub(crate) fn request_grid_size(&mut self, rows: usize, cols: usize) {
+        if self.resize_cut_counts(rows, cols, [0, 0]).is_empty() {
+            (self.grid_rows, self.grid_cols) = (rows, cols);
+            self.resize_grids();
+            return;
+        }
+        self.resize_dialog = Some(ResizeDialog { rows, cols, anchor: [0, 0], allow_cut: false });
+    }
+
+    // 预览：按当前设置调整后会被删除的内容（所有标签页合计）
+    pub(crate) fn resize_cut_counts(&self, rows: usize, cols: usize, anchor: [usize; 2]) -> CutCounts {
+        let (dx, dy) = self.resize_shift(rows, cols, anchor);
+        self.shift_cut_counts(rows, cols, dx, dy)
+    }
+
+    fn shift_cut_counts(&self, rows: usize, cols: usize, dx: isize, dy: isize) -> CutCounts {
+        if dx == 0 && dy == 0 && rows >= self.grid_rows && cols >= self.grid_cols { return CutCounts::default(); }
+        let mut counts = CutCounts { route_points: shift_routes(&mut self.enemy_routes.clone(), dx, dy, rows, cols), ..Default::default() };
+        for mut data in self.all_strategies() { counts.add(shift_strategy(&mut data, dx, dy, rows, cols)); }
+        counts
+    }
+
+    // 调整网格尺寸并把内容平移 (dx, dy) 格：地形、建筑、事件与路线一起平移，网格偏移反向调整，使保留下来的内容在底图上的位置不变
+    pub(crate) fn resize_map(&mut self, rows: usize, cols: usize, dx: isize, dy: isize) -> CutCounts {
+        for layer in self.layers_data.values_mut() {
+            for grid in [&mut layer.floor_grid, &mut layer.wall_grid, &mut layer.ceiling_grid] { *grid = grid.resized_with_shift(rows, cols, dy, dx, -1); }
+        }
+        let route_points = shift_routes(&mut self.enemy_routes, dx, dy, rows, cols);
+        let mut counts = CutCounts { route_points, ..Default::default() };
+        self.transform_all_strategies(|data| counts.add(shift_strategy(data, dx, dy, rows, cols)));
+        self.selected_uids.retain(|uid| self.placed_buildings.iter().any(|b| b.uid == *uid));
+        self.building_drag = None;
+        self.selection_box_origin = None;
+        self.demolish_box_origin = None;
+        self.offset_x -= dx as f32 * self.grid_width;
+        self.offset_y -= dy as f32 * self.grid_height;
+        (self.grid_rows, self.grid_cols) = (rows, cols);
+        counts
+    }
+
+    pub(crate) fn show_resize_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.resize_dialog.take() else { return; };
+        let cut = self.resize_cut_counts(dialog.rows, dialog.cols, dialog.anchor);
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        egui::Window::new(tr("调整网格尺寸")).id(egui::Id::new("grid_resize")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(trf!("当前: {} 行 × {} 列", self.grid_rows, self.grid_cols));
+            ui.horizontal(|ui| {
+                ui.label(tr("新尺寸:"));
+                ui.add(egui::DragValue::new(&mut dialog.rows).clamp_range(1..=4096).suffix(tr(" 行")));
+                ui.add(egui::DragValue::new(&mut dialog.cols).clamp_range(1..=4096).suffix(tr(" 列")));
+            });
+            ui.label(tr("锚点（原内容贴住的位置，其余方向扩展或裁剪）:"));
+            egui::Grid::new("resize_anchor").spacing([2.0, 2.0]).show(ui, |ui| {
+                for (row, arrows) in [["↖", "↑", "↗"], ["←", "•", "→"], ["↙", "↓", "↘"]].into_iter().enumerate() {
+                    for (col, arrow) in arrows.into_iter().enumerate() {
+                        if ui.add_sized([28.0, 24.0], egui::SelectableLabel::new(dialog.anchor == [col, row], arrow)).clicked() { dialog.anchor = [col, row]; }
+                    }
+                    ui.end_row();
+                }
+            });
+            let (dx, dy) = self.resize_shift(dialog.rows, dialog.cols, dialog.anchor);
+            ui.label(trf!("内容平移: 列 {}，行 {}", format!("{:+}", dx), format!("{:+}", dy)));
+            if !cut.is_empty() {
+                ui.colored_label(egui::Color32::YELLOW, trf!("将移出网格并删除（所有标签页合计）: {} 座建筑（连同其事件）、{} 个技能事件、{} 个路线点", cut.buildings, cut.abilities, cut.route_points));
+                ui.checkbox(&mut dialog.allow_cut, tr("确认删除这些内容"));
+            }
+            if cut.locked > 0 { ui.colored_label(egui::Color32::RED, trf!("其中 {} 条建筑或事件属于已锁定的波次，请先在波次设置中解锁", cut.locked)); }
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled((cut.is_empty() || dialog.allow_cut) && cut.locked == 0, egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        if apply {
+            let (dx, dy) = self.resize_shift(dialog.rows, dialog.cols, dialog.anchor);
+            let counts = self.resize_map(dialog.rows, dialog.cols, dx, dy);
+            self.notifications.info(trf!("网格已调整为 {} 行 × {} 列", dialog.rows, dialog.cols));
+            if !counts.is_empty() { self.notifications.info(trf!("已删除移出网格的 {} 座建筑、{} 个技能事件、{} 个路线点（所有标签页合计）", counts.buildings, counts.abilities, counts.route_points)); }
+        } else if open && !cancel {
+            self.resize_dialog = Some(dialog);
+        }
+    }
+}
+
+impl MapEditor {
+    // 有内容的范围 (行范围, 列范围)：任一层中非障碍的格子、任一标签页中建筑在任一时刻占用的格子（含小数偏移跨入的格子）、路线点与技能目标
+    fn content_bounds(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let mut bounds: Option<(Range<usize>, Range<usize>)> = None;
+        let mut include = |r: Range<usize>, c: Range<usize>| {
+            bounds = Some(match bounds.take() {
+                Some((br, bc)) => (r.start.min(br.start)..r.end.max(br.end), c.start.min(bc.start)..c.end.max(bc.end)),
+                None => (r, c),
+            });
+        };
+        for layer in self.layers_data.values() {
+            for grid in [&layer.floor_grid, &layer.wall_grid, &layer.ceiling_grid] {
+                if let Some((r, c)) = grid.content_bounds(-1) { include(r, c); }
+            }
+        }
+        for data in self.all_strategies() {
+            for b in &data.buildings {
+                let (w, h) = (b.width + usize::from(b.offset[0] > 0.0), b.height + usize::from(b.offset[1] > 0.0));
+                let moves = data.relocations.iter().filter(|e| e.uid == b.uid).map(|e| (e.to_x, e.to_y));
+                for (x, y) in std::iter::once((b.grid_x, b.grid_y)).chain(moves) { include(y..y + h, x..x + w); }
+            }
+            for &(x, y) in data.abilities.iter().filter_map(|a| a.target.as_ref()) { include(y..y + 1, x..x + 1); }
+        }
+        for p in self.enemy_routes.iter().flat_map(|r| &r.points) { include(p.grid_y..p.grid_y + 1, p.grid_x..p.grid_x + 1); }
+        bounds
+    }
+
+    // 把网格收缩到有内容的范围，导出文件因此更小；内容在底图上的位置不变
+    pub(crate) fn trim_to_content(&mut self) {
+        let Some((rows, cols)) = self.content_bounds() else { self.notifications.info(tr("网格中没有任何内容，无需裁剪")); return; };
+        let (rows, cols) = (rows.start..rows.end.min(self.grid_rows), cols.start..cols.end.min(self.grid_cols));
+        if rows.len() == self.grid_rows && cols.len() == self.grid_cols { self.notifications.info(tr("网格已紧贴内容，无需裁剪")); return; }
+        let (old_rows, old_cols) = (self.grid_rows, self.grid_cols);
+        if self.refuse_locked(self.shift_cut_counts(rows.len(), cols.len(), -(cols.start as isize), -(rows.start as isize)).locked > 0) { return; }
+        self.resize_map(rows.len(), cols.len(), -(cols.start as isize), -(rows.start as isize));
+        self.notifications.info(trf!("已裁剪到内容: {} 行 × {} 列 → {} 行 × {} 列", old_rows, old_cols, self.grid_rows, self.grid_cols));
+    }
+}
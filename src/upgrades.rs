@@ -0,0 +1,156 @@
+use eframe::egui::{self, Color32, Painter, Pos2, Stroke, Vec2};
+
+use crate::app::MapEditor;
+use crate::economy::{cumulative_upgrade_cost, upgrade_cost, upgrade_levels};
+use crate::events::{offset_seconds_editor, wave_editor, EventRef};
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::utils::{event_time_label, get_time_value};
+
+// 🔥 升级：默认按模板全局生效，也可以在画布上点选一座建筑只升级它；
+// 建筑的等级 = 截至当时该模板的全局升级次数 + 这座建筑的单独升级次数
+
+impl MapEditor {
+    // uid 为 None 时只统计全局升级，即模板本身的等级
+    pub(crate) fn upgrade_tier_at(&self, name: &str, uid: Option<usize>, t: i32) -> usize {
+        self.upgrade_events.iter().filter(|u| u.building_name == name && (u.uid.is_none() || u.uid == uid) && get_time_value(u.wave_num, u.is_late) <= t).count()
+    }
+
+    // 整个策略中规划的升级次数：全局升级加上该建筑的单独升级；未指定建筑时取同模板中单独升级最多的一座
+    pub(crate) fn planned_upgrades(&self, name: &str, uid: Option<usize>) -> usize {
+        let global = self.upgrade_events.iter().filter(|u| u.building_name == name && u.uid.is_none()).count();
+        let own = |uid: usize| self.upgrade_events.iter().filter(|u| u.uid == Some(uid)).count();
+        global + match uid {
+            Some(uid) => own(uid),
+            None => self.placed_buildings.iter().filter(|b| b.template_name == name).map(|b| own(b.uid)).max().unwrap_or(0),
+        }
+    }
+
+    // 升级模式下高亮将被升级的建筑：悬停列表中的单体升级或已点选建筑时只高亮那一座，否则高亮所选模板的所有建筑
+    pub(crate) fn is_upgrade_highlighted(&self, b: &PlacedBuilding) -> bool {
+        match self.upgrade_hover_uid.or(self.upgrade_target_uid) {
+            Some(uid) => b.uid == uid,
+            None => self.building_templates.get(self.selected_upgrade_target_idx).is_some_and(|t| t.name == b.template_name),
+        }
+    }
+
+    pub(crate) fn show_upgrade_panel(&mut self, ui: &mut egui::Ui) {
+        self.upgrade_hover_uid = None;
+        let picked = self.upgrade_target_uid.and_then(|uid| self.placed_buildings.iter().find(|b| b.uid == uid)).map(|b| (b.template_name.clone(), b.uid));
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(if picked.is_some() { tr("添加单体升级:") } else { tr("添加全局升级:") });
+            ui.vertical_centered_justified(|ui| {
+                let before = self.selected_upgrade_target_idx;
+                egui::ComboBox::from_label(tr("目标塔"))
+                    .selected_text(&self.building_templates[self.selected_upgrade_target_idx].name)
+                    .show_ui(ui, |ui| {
+                        for (i, t) in self.building_templates.iter().enumerate() {
+                            ui.selectable_value(&mut self.selected_upgrade_target_idx, i, &t.name);
+                        }
+                    });
+                if self.selected_upgrade_target_idx != before { self.upgrade_target_uid = None; }
+                match &picked {
+                    Some((name, uid)) => {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(Color32::GREEN, trf!("只升级 {} (UID {})", name, uid));
+                            if ui.small_button("×").on_hover_text(tr("改为升级该模板的所有建筑")).clicked() { self.upgrade_target_uid = None; }
+                        });
+                    }
+                    None => { ui.weak(tr("点击画布上的建筑可只升级这一座")); }
+                }
+                // 当前时间已达到的等级，以及整个策略中已规划的升级次数
+                let (target, target_uid) = match picked {
+                    Some((name, uid)) => (name, Some(uid)),
+                    None => (self.building_templates[self.selected_upgrade_target_idx].name.clone(), None),
+                };
+                let max_tier = self.building_configs.iter().find(|c| c.name == target).map_or(0, |c| c.upgrades.len());
+                let tier = self.upgrade_tier_at(&target, target_uid, get_time_value(self.current_wave_num, self.current_is_late));
+                let planned = self.planned_upgrades(&target, target_uid);
+                ui.label(if max_tier > 0 { trf!("当前等级: Lv{} / Lv{}", tier, max_tier) } else { trf!("当前等级: Lv{}（不限升级次数）", tier) });
+                ui.label(trf!("下一级费用: {} · 累计升级费用: {}", upgrade_cost(&self.building_configs, &target, tier + 1), cumulative_upgrade_cost(&self.building_configs, &target, tier)));
+                let maxed = max_tier > 0 && planned >= max_tier;
+                let locked = self.current_wave_locked();
+                let hint = if locked { trf!("第 {} 波已锁定", self.current_wave_num) } else { trf!("已规划 {} 次升级，达到最高等级", planned) };
+                if ui.add_enabled(!maxed && !locked, egui::Button::new(tr("[+] 添加升级指令"))).on_disabled_hover_text(hint).clicked() {
+                    self.upgrade_events.push(UpgradeEvent { building_name: target, wave_num: self.current_wave_num, is_late: self.current_is_late, uid: target_uid, offset_seconds: None, order: None });
+                }
+            });
+        });
+        ui.group(|ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(tr("已配置的升级序列:"));
+            let mut delete_idx = None;
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                if self.upgrade_events.is_empty() { ui.label(tr("暂无升级记录")); }
+                let levels = upgrade_levels(&self.upgrade_events);
+                for (i, &level) in levels.iter().enumerate() {
+                    if self.editing_event == Some(EventRef::Upgrade(i)) { self.edit_upgrade_row(ui, i); continue; }
+                    if !self.wave_filter.contains(self.upgrade_events[i].wave_num) { continue; }
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(!self.wave_locked(self.upgrade_events[i].wave_num), |ui| {
+                            self.event_checkbox(ui, EventRef::Upgrade(i));
+                            if ui.button("[X]").clicked() { delete_idx = Some(i); }
+                            if ui.small_button("✏").on_hover_text(tr("编辑波次与目标")).clicked() { self.editing_event = Some(EventRef::Upgrade(i)); }
+                        });
+                        let ev = &self.upgrade_events[i];
+                        let text = match ev.uid {
+                            Some(uid) => trf!("{}: 升级 {} (UID {}) → Lv{}", event_time_label(ev.wave_num, ev.is_late, ev.offset_seconds), ev.building_name, uid, level),
+                            None => trf!("{}: 升级 {} → Lv{}", event_time_label(ev.wave_num, ev.is_late, ev.offset_seconds), ev.building_name, level),
+                        };
+                        let max_tier = self.building_configs.iter().find(|c| c.name == ev.building_name).map_or(0, |c| c.upgrades.len());
+                        let label = if max_tier > 0 && level > max_tier { ui.colored_label(Color32::RED, text).on_hover_text(trf!("超过最高等级 Lv{}", max_tier)) } else { ui.label(text) };
+                        if label.hovered() && ev.uid.is_some() { self.upgrade_hover_uid = ev.uid; }
+                    });
+                }
+                self.show_filtered_count(ui, self.upgrade_events.iter().map(|e| e.wave_num));
+            });
+            if let Some(idx) = delete_idx { self.upgrade_events.remove(idx); self.editing_event = None; self.checked_events.clear(); }
+            self.show_bulk_event_bar(ui, false);
+        });
+    }
+
+    // 原地编辑升级事件：波次、目标模板，以及升级全部还是其中一座
+    fn edit_upgrade_row(&mut self, ui: &mut egui::Ui, i: usize) {
+        let Some(ev) = self.upgrade_events.get(i) else { self.editing_event = None; return; };
+        let names: Vec<String> = self.building_templates.iter().map(|t| t.name.clone()).collect();
+        let uids: Vec<usize> = self.placed_buildings.iter().filter(|b| b.template_name == ev.building_name).map(|b| b.uid).collect();
+        let before = (ev.wave_num, ev.is_late);
+        ui.group(|ui| {
+            let ev = &mut self.upgrade_events[i];
+            ui.horizontal(|ui| { wave_editor(ui, &mut ev.wave_num, &mut ev.is_late); offset_seconds_editor(ui, &mut ev.offset_seconds); });
+            ui.horizontal(|ui| {
+                ui.label(tr("目标:"));
+                egui::ComboBox::from_id_source(("upgrade_edit_template", i)).selected_text(&ev.building_name).show_ui(ui, |ui| {
+                    for name in &names {
+                        if ui.selectable_label(ev.building_name == *name, name).clicked() && ev.building_name != *name { ev.building_name = name.clone(); ev.uid = None; }
+                    }
+                });
+                let selected = ev.uid.map_or_else(|| tr("全部").to_string(), |uid| format!("UID {}", uid));
+                egui::ComboBox::from_id_source(("upgrade_edit_uid", i)).selected_text(selected).show_ui(ui, |ui| {
+                    ui.selectable_value(&mut ev.uid, None, tr("全部"));
+                    for uid in &uids { ui.selectable_value(&mut ev.uid, Some(*uid), format!("UID {}", uid)); }
+                });
+            });
+            if ui.button(tr("完成")).clicked() { self.editing_event = None; }
+        });
+        // 不能改到锁定的波次
+        let wave = self.upgrade_events[i].wave_num;
+        if wave != before.0 && self.refuse_locked(self.wave_locked(wave)) { (self.upgrade_events[i].wave_num, self.upgrade_events[i].is_late) = before; }
+        // 编辑期间在画布上高亮目标
+        self.upgrade_hover_uid = self.upgrade_events.get(i).and_then(|e| e.uid);
+    }
+
+    // 升级模式：左键点选当前波次存在的建筑作为单体升级目标，右键取消
+    pub(crate) fn handle_upgrade_input(&mut self, response: &egui::Response, painter: &Painter, origin: Pos2, cell: Vec2, at: Pos2) {
+        if response.clicked_by(egui::PointerButton::Secondary) { self.upgrade_target_uid = None; return; }
+        let t_current = get_time_value(self.current_wave_num, self.current_is_late);
+        let Some(b) = self.placed_buildings.iter().find(|b| self.building_covers_at(b, at, t_current)) else { return; };
+        painter.rect_stroke(self.building_screen_rect(b, t_current, origin, cell), 0.0, Stroke::new(3.0, Color32::YELLOW));
+        if response.clicked_by(egui::PointerButton::Primary) {
+            let uid = b.uid;
+            if let Some(idx) = self.building_templates.iter().position(|t| t.name == b.template_name) { self.selected_upgrade_target_idx = idx; }
+            self.upgrade_target_uid = Some(uid);
+        }
+    }
+}
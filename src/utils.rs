@@ -1,21 +1,87 @@
-use eframe::egui::Color32;
+use std::ops::Range;
+use eframe::egui::{Color32, Mesh, Pos2, Rect, Vec2, Visuals};
 
+use crate::models::TerrainType;
+use crate::time_model::with_time_model;
+
+// 时间值只用于比较先后，换算方式由当前关卡的时间模型决定（默认每波两段：wave * 2 + late）
 pub fn get_time_value(wave: i32, late: bool) -> i32 {
-    wave * 2 + if late { 1 } else { 0 }
+    with_time_model(|m| m.time_value(wave, late))
+}
+
+pub fn wave_from_time_value(t: i32) -> (i32, bool) {
+    with_time_model(|m| m.wave_at(t))
+}
+
+pub fn wave_label(wave_num: i32, is_late: bool) -> String {
+    with_time_model(|m| m.label(wave_num, is_late))
+}
+
+// 排序用的键：先按时间值，同一时间段内再按秒数偏移（未设置视为 0 秒）
+pub fn time_order(wave: i32, late: bool, offset_seconds: Option<f32>) -> (i32, i64) {
+    (get_time_value(wave, late), (offset_seconds.unwrap_or(0.0) * 1000.0).round() as i64)
+}
+
+// 带秒数偏移的时间标签，如 W3 +12s
+pub fn event_time_label(wave: i32, late: bool, offset_seconds: Option<f32>) -> String {
+    match offset_seconds {
+        Some(s) => format!("{} +{}s", wave_label(wave, late), s),
+        None => wave_label(wave, late),
+    }
 }
 
-pub fn fix_path(p: &str) -> String {
-    if p.starts_with("maps/") { p.to_string() }
-    else { format!("maps/{}", p) }
+// 逗号（中英文均可）分隔的标签文本，去掉空白与重复项
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split([',', '，']).map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t == tag) { tags.push(tag.to_string()); }
+    }
+    tags
+}
+
+// Bresenham 直线光栅化，返回 (列, 行) 序列，包含两个端点
+pub fn bresenham_line((c0, r0): (i32, i32), (c1, r1): (i32, i32)) -> Vec<(i32, i32)> {
+    let (dx, dy) = ((c1 - c0).abs(), -(r1 - r0).abs());
+    let (sx, sy) = (if c0 < c1 { 1 } else { -1 }, if r0 < r1 { 1 } else { -1 });
+    let (mut c, mut r, mut err) = (c0, r0, dx + dy);
+    let mut cells = Vec::new();
+    loop {
+        cells.push((c, r));
+        if c == c1 && r == r1 { break; }
+        let e2 = 2 * err;
+        if e2 >= dy { err += dy; c += sx; }
+        if e2 <= dx { err += dx; r += sy; }
+    }
+    cells
+}
+
+// 与 egui 深色/浅色主题的面板底色一致
+pub fn canvas_default_background(dark: bool) -> Color32 {
+    if dark { Visuals::dark().panel_fill } else { Visuals::light().panel_fill }
 }
 
-pub fn get_layer_color(val: i8) -> Color32 {
-    match val {
-        -1 => Color32::from_rgba_unmultiplied(255, 0, 0, 100),   
-         0 => Color32::from_rgba_unmultiplied(0, 255, 0, 40),    
-         1 => Color32::from_rgba_unmultiplied(255, 255, 0, 100), 
-         2 => Color32::from_rgba_unmultiplied(0, 150, 255, 100), 
-         3 => Color32::from_rgba_unmultiplied(150, 0, 255, 100), 
-         _ => Color32::TRANSPARENT,
+pub fn get_layer_color(types: &[TerrainType], val: i8) -> Color32 {
+    match types.iter().find(|t| t.id == val) {
+        Some(t) => Color32::from_rgba_unmultiplied(t.color[0], t.color[1], t.color[2], t.color[3]),
+        None => Color32::TRANSPARENT,
     }
-}
\ No newline at end of file
+}
+// 与 clip 相交的格子列/行范围（origin 为第 0 格左上角的屏幕坐标，cell 为屏幕上的格子尺寸）
+pub fn visible_cell_range(clip: Rect, origin: Pos2, cell: Vec2, cols: usize, rows: usize) -> (Range<usize>, Range<usize>) {
+    let span = |min: f32, max: f32, start: f32, size: f32, count: usize| {
+        if size <= 0.0 { return 0..0; }
+        let first = ((min - start) / size).floor().clamp(0.0, count as f32) as usize;
+        let last = ((max - start) / size).ceil().clamp(0.0, count as f32) as usize;
+        first..last.max(first)
+    };
+    (span(clip.min.x, clip.max.x, origin.x, cell.x, cols), span(clip.min.y, clip.max.y, origin.y, cell.y, rows))
+}
+
+// 矩形描边拆成四条细矩形加入网格，便于与填充一起批量提交
+pub fn add_rect_outline(mesh: &mut Mesh, rect: Rect, width: f32, color: Color32) {
+    let (min, max) = (rect.min, rect.max);
+    mesh.add_colored_rect(Rect::from_min_max(min, Pos2::new(max.x, min.y + width)), color);
+    mesh.add_colored_rect(Rect::from_min_max(Pos2::new(min.x, max.y - width), max), color);
+    mesh.add_colored_rect(Rect::from_min_max(Pos2::new(min.x, min.y + width), Pos2::new(min.x + width, max.y - width)), color);
+    mesh.add_colored_rect(Rect::from_min_max(Pos2::new(max.x - width, min.y + width), Pos2::new(max.x, max.y - width)), color);
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::economy::upgrade_levels;
+use crate::events::footprint_segments;
+use crate::models::*;
+use crate::utils::{get_time_value, wave_label};
+
+// 数据层面的校验规则，不依赖 UI，GUI 与命令行共用
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Severity { Warning, Error }
+
+// 问题所在位置，GUI 中点击结果时据此跳转
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum FindingTarget {
+    Building(usize),
+    Cell { grid_x: usize, grid_y: usize },
+    // 升级事件在 upgrades 中的下标
+    Upgrade(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub target: Option<FindingTarget>,
+}
+
+impl Finding {
+    pub fn error(message: impl Into<String>) -> Self { Finding { severity: Severity::Error, message: message.into(), target: None } }
+    pub fn warning(message: impl Into<String>) -> Self { Finding { severity: Severity::Warning, message: message.into(), target: None } }
+    pub fn at(mut self, target: FindingTarget) -> Self { self.target = Some(target); self }
+}
+
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+pub fn validate_terrain(terrain: &MapTerrainExport, terrain_types: &[TerrainType]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if terrain.meta.grid_pixel_width <= 0.0 || terrain.meta.grid_pixel_height <= 0.0 {
+        findings.push(Finding::error(format!("网格像素尺寸无效: {} x {}", terrain.meta.grid_pixel_width, terrain.meta.grid_pixel_height)));
+    }
+    if terrain.layers.is_empty() {
+        findings.push(Finding::error("地形不包含任何层"));
+        return findings;
+    }
+    let slots = terrain.meta.time_model.slots_per_wave;
+    if !(1..=2).contains(&slots) { findings.push(Finding::error(format!("meta.time_model.slots_per_wave 为 {}，只支持 1（整波）或 2（前期 + 后期），当前按 {} 处理", slots, slots.clamp(1, 2)))); }
+    let (rows, cols) = terrain.grid_size();
+    if rows == 0 || cols == 0 { findings.push(Finding::warning("地形网格为空")); }
+
+    for layer in &terrain.layers {
+        for (b_type, type_name) in [(BuildingType::Floor, "floor_grid"), (BuildingType::Wall, "wall_grid"), (BuildingType::Ceiling, "ceiling_grid")] {
+            let grid = layer.get_grid(b_type);
+            if grid.is_empty() { continue; }
+            if let Some((r, len)) = grid.ragged() {
+                findings.push(Finding::error(format!("层 {} 的 {}[{}] 列数为 {}，应为 {}（已按障碍补齐）", layer.major_z, type_name, r, len, grid.cols())));
+            }
+            if grid.rows() != rows {
+                findings.push(Finding::error(format!("层 {} 的 {} 行数为 {}，应为 {}", layer.major_z, type_name, grid.rows(), rows)));
+            }
+            if grid.cols() != cols {
+                findings.push(Finding::error(format!("层 {} 的 {} 列数为 {}，应为 {}", layer.major_z, type_name, grid.cols(), cols)));
+            }
+            let unknown = grid.values().filter(|v| !terrain_types.iter().any(|t| t.id == *v)).count();
+            if unknown > 0 {
+                findings.push(Finding::warning(format!("层 {} 的 {} 有 {} 个未知地形值", layer.major_z, type_name, unknown)));
+            }
+        }
+    }
+
+    for route in &terrain.routes {
+        if !route.points.iter().any(|p| p.kind == RoutePointKind::Spawn) { findings.push(Finding::warning(format!("路线 {} 没有出生点", route.name))); }
+        if !route.points.iter().any(|p| p.kind == RoutePointKind::Exit) { findings.push(Finding::warning(format!("路线 {} 没有出口", route.name))); }
+        if let Some(p) = route.points.iter().find(|p| p.grid_x >= cols || p.grid_y >= rows) {
+            findings.push(Finding::error(format!("路线 {} 的点 ({}, {}) 超出网格 {}x{}", route.name, p.grid_x, p.grid_y, cols, rows)).at(FindingTarget::Cell { grid_x: p.grid_x, grid_y: p.grid_y }));
+        }
+    }
+    findings
+}
+
+pub fn validate_terrain_types(types: &[TerrainType]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut ids = HashSet::new();
+    let mut import_colors = HashMap::new();
+    for t in types {
+        if !ids.insert(t.id) {
+            findings.push(Finding::error(format!("地形类型 id {} 重复", t.id)));
+        }
+        if let Some(other) = import_colors.insert(t.import_rgb(), t.name.as_str()) {
+            findings.push(Finding::warning(format!("地形类型 {} 与 {} 的导入颜色相同，从颜色图导入时无法区分", other, t.name)));
+        }
+    }
+    findings
+}
+
+pub fn validate_building_configs(configs: &[BuildingConfig]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut names = HashSet::new();
+    for cfg in configs {
+        if !names.insert(cfg.name.as_str()) {
+            findings.push(Finding::error(format!("防御塔名称重复: {}", cfg.name)));
+        }
+        if cfg.width == 0 || cfg.height == 0 {
+            findings.push(Finding::error(format!("防御塔 {} 尺寸无效: {}x{}", cfg.name, cfg.width, cfg.height)));
+        }
+    }
+    findings
+}
+
+// 建造/拆除/出售/迁移事件引用的观察框预设必须存在于地形文件的 meta.viewport_presets 中
+pub fn validate_viewport_refs(strategy: &MapBuildingsExport, presets: &[ViewportPreset]) -> Vec<Finding> {
+    let known = |name: &String| presets.iter().any(|p| &p.name == name);
+    let mut findings = Vec::new();
+    for b in &strategy.buildings {
+        if let Some(name) = b.viewport.as_ref().filter(|n| !known(n)) {
+            findings.push(Finding::warning(format!("{} (UID {}) 引用了不存在的观察框预设「{}」", b.name, b.uid, name)).at(FindingTarget::Building(b.uid)));
+        }
+    }
+    for d in &strategy.demolishes {
+        if let Some(name) = d.viewport.as_ref().filter(|n| !known(n)) {
+            findings.push(Finding::warning(format!("拆除事件 {} {} 引用了不存在的观察框预设「{}」", wave_label(d.wave_num, d.is_late), d.name, name))
+                .at(FindingTarget::Cell { grid_x: d.grid_x, grid_y: d.grid_y }));
+        }
+    }
+    for s in &strategy.sells {
+        if let Some(name) = s.viewport.as_ref().filter(|n| !known(n)) {
+            findings.push(Finding::warning(format!("出售事件 {} {} 引用了不存在的观察框预设「{}」", wave_label(s.wave_num, s.is_late), s.name, name))
+                .at(FindingTarget::Cell { grid_x: s.grid_x, grid_y: s.grid_y }));
+        }
+    }
+    for r in &strategy.relocations {
+        if let Some(name) = r.viewport.as_ref().filter(|n| !known(n)) {
+            findings.push(Finding::warning(format!("迁移事件 {} {} 引用了不存在的观察框预设「{}」", wave_label(r.wave_num, r.is_late), r.name, name))
+                .at(FindingTarget::Cell { grid_x: r.to_x, grid_y: r.to_y }));
+        }
+    }
+    findings
+}
+
+// grid_size 与 configs 可选：缺少地形或防御塔列表时跳过对应的交叉检查
+pub fn validate_strategy(strategy: &MapBuildingsExport, grid_size: Option<(usize, usize)>, configs: Option<&[BuildingConfig]>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let mut by_uid: HashMap<usize, &BuildingExport> = HashMap::new();
+    for b in &strategy.buildings {
+        if by_uid.insert(b.uid, b).is_some() {
+            findings.push(Finding::error(format!("UID {} 重复", b.uid)).at(FindingTarget::Cell { grid_x: b.grid_x, grid_y: b.grid_y }));
+        }
+        if let Some((rows, cols)) = grid_size {
+            if b.grid_x + b.width > cols || b.grid_y + b.height > rows {
+                findings.push(Finding::error(format!("{} (UID {}) 位于 ({}, {}) 超出网格 {}x{}", b.name, b.uid, b.grid_x, b.grid_y, cols, rows)).at(FindingTarget::Building(b.uid)));
+            }
+        }
+        if let Some(configs) = configs {
+            if !configs.iter().any(|c| c.name == b.name) {
+                findings.push(Finding::warning(format!("{} (UID {}) 的模板不在防御塔列表中", b.name, b.uid)).at(FindingTarget::Building(b.uid)));
+            }
+        }
+    }
+
+    // 秒数偏移只能向后延，必须是非负的有限数
+    let offsets = strategy.buildings.iter().map(|b| (format!("{} (UID {}) 建造时", b.name, b.uid), b.offset_seconds))
+        .chain(strategy.upgrades.iter().map(|u| (format!("升级事件 {} {}", wave_label(u.wave_num, u.is_late), u.building_name), u.offset_seconds)))
+        .chain(strategy.demolishes.iter().map(|d| (format!("拆除事件 {} {}", wave_label(d.wave_num, d.is_late), d.name), d.offset_seconds)));
+    for (what, seconds) in offsets {
+        if let Some(s) = seconds.filter(|s| !s.is_finite() || *s < 0.0) { findings.push(Finding::error(format!("{}的秒数偏移 {} 无效，应为非负数", what, s))); }
+    }
+
+    // 拆除与出售都会移除建筑，同一 UID 只应有一个；有多个时以最早的为准
+    let removals = strategy.demolishes.iter().map(|d| ("拆除", d.uid, &d.name, d.wave_num, d.is_late, d.grid_x, d.grid_y))
+        .chain(strategy.sells.iter().map(|s| ("出售", s.uid, &s.name, s.wave_num, s.is_late, s.grid_x, s.grid_y)));
+    let mut demolish_time: HashMap<usize, i32> = HashMap::new();
+    for (kind, uid, name, wave_num, is_late, grid_x, grid_y) in removals {
+        let t = get_time_value(wave_num, is_late);
+        match by_uid.get(&uid) {
+            None => findings.push(Finding::error(format!("{}事件 {} {} 引用了不存在的 UID {}", kind, wave_label(wave_num, is_late), name, uid))
+                .at(FindingTarget::Cell { grid_x, grid_y })),
+            Some(b) if t <= get_time_value(b.wave_num, b.is_late) => findings.push(Finding::error(format!(
+                "{} (UID {}) 的{}时间 {} 不晚于建造时间 {}", b.name, b.uid, kind, wave_label(wave_num, is_late), wave_label(b.wave_num, b.is_late))).at(FindingTarget::Building(b.uid))),
+            Some(_) => {}
+        }
+        if let Some(prev) = demolish_time.insert(uid, t) {
+            demolish_time.insert(uid, prev.min(t));
+            findings.push(Finding::warning(format!("UID {} 有多个拆除/出售事件", uid)).at(FindingTarget::Cell { grid_x, grid_y }));
+        }
+    }
+
+    for r in &strategy.relocations {
+        let t = get_time_value(r.wave_num, r.is_late);
+        let label = wave_label(r.wave_num, r.is_late);
+        match by_uid.get(&r.uid) {
+            None => findings.push(Finding::error(format!("迁移事件 {} {} 引用了不存在的 UID {}", label, r.name, r.uid))
+                .at(FindingTarget::Cell { grid_x: r.to_x, grid_y: r.to_y })),
+            Some(b) if t <= get_time_value(b.wave_num, b.is_late) => findings.push(Finding::error(format!(
+                "{} (UID {}) 的迁移时间 {} 不晚于建造时间 {}", b.name, b.uid, label, wave_label(b.wave_num, b.is_late))).at(FindingTarget::Building(b.uid))),
+            Some(b) if demolish_time.get(&r.uid).is_some_and(|removed| t >= *removed) => findings.push(Finding::error(format!(
+                "{} (UID {}) 在 {} 迁移时已被拆除或出售", b.name, b.uid, label)).at(FindingTarget::Building(b.uid))),
+            Some(_) => {}
+        }
+        if let Some((rows, cols)) = grid_size {
+            if r.to_x + r.width > cols || r.to_y + r.height > rows {
+                findings.push(Finding::error(format!("迁移事件 {} {} 的目标 ({}, {}) 超出网格 {}x{}", label, r.name, r.to_x, r.to_y, cols, rows))
+                    .at(FindingTarget::Cell { grid_x: r.to_x, grid_y: r.to_y }));
+            }
+        }
+    }
+
+    // 指定建筑的升级须在该建筑存在期间；未指定建筑的升级在该时刻须至少有一座同名建筑存在
+    for (i, u) in strategy.upgrades.iter().enumerate() {
+        let t = get_time_value(u.wave_num, u.is_late);
+        let label = wave_label(u.wave_num, u.is_late);
+        let target = FindingTarget::Upgrade(i);
+        let Some(uid) = u.uid else {
+            let alive = strategy.buildings.iter().any(|b| b.name == u.building_name && get_time_value(b.wave_num, b.is_late) <= t && demolish_time.get(&b.uid).is_none_or(|removed| t < *removed));
+            if !alive { findings.push(Finding::error(format!("升级事件 {} 执行时没有存在的 {}", label, u.building_name)).at(target)); }
+            continue;
+        };
+        match by_uid.get(&uid) {
+            None => findings.push(Finding::error(format!("升级事件 {} {} 引用了不存在的 UID {}", label, u.building_name, uid)).at(target)),
+            Some(b) if t < get_time_value(b.wave_num, b.is_late) => findings.push(Finding::error(format!(
+                "{} (UID {}) 的升级时间 {} 早于建造时间 {}", b.name, b.uid, label, wave_label(b.wave_num, b.is_late))).at(target)),
+            Some(b) if demolish_time.get(&uid).is_some_and(|removed| t >= *removed) => findings.push(Finding::error(format!(
+                "{} (UID {}) 在 {} 升级时已被拆除或出售", b.name, b.uid, label)).at(target)),
+            Some(b) if b.name != u.building_name => findings.push(Finding::warning(format!(
+                "升级事件 {} 的模板 {} 与 UID {} 的模板 {} 不一致", label, u.building_name, uid, b.name)).at(target)),
+            Some(_) => {}
+        }
+    }
+
+    for a in &strategy.abilities {
+        let label = wave_label(a.wave_num, a.is_late);
+        if a.name.trim().is_empty() { findings.push(Finding::error(format!("技能事件 {} 的名称为空", label))); }
+        if let (Some((x, y)), Some((rows, cols))) = (a.target, grid_size) {
+            if x >= cols || y >= rows {
+                findings.push(Finding::error(format!("技能事件 {} {} 的目标 ({}, {}) 超出网格 {}x{}", label, a.name, x, y, cols, rows)).at(FindingTarget::Cell { grid_x: x, grid_y: y }));
+            }
+        }
+    }
+
+    if let Some(configs) = configs {
+        for (i, (u, level)) in strategy.upgrades.iter().zip(upgrade_levels(&strategy.upgrades)).enumerate() {
+            match configs.iter().find(|c| c.name == u.building_name) {
+                None => findings.push(Finding::warning(format!("升级事件 {} 引用了未知模板 {}", wave_label(u.wave_num, u.is_late), u.building_name)).at(FindingTarget::Upgrade(i))),
+                Some(c) if !c.upgrades.is_empty() && level > c.upgrades.len() => {
+                    findings.push(Finding::error(format!("升级事件 {} 是 {} 的第 {} 次升级，超过最高等级 {}", wave_label(u.wave_num, u.is_late), u.building_name, level, c.upgrades.len())).at(FindingTarget::Upgrade(i)));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    // 同类型建筑在存活时间段重叠时不可占用相同格子；迁移过的建筑按各时间段的位置比较
+    let segments = |b: &BuildingExport| footprint_segments(b.uid, (b.grid_x, b.grid_y), get_time_value(b.wave_num, b.is_late),
+        demolish_time.get(&b.uid).copied().unwrap_or(i32::MAX), &strategy.relocations);
+    for (i, a) in strategy.buildings.iter().enumerate() {
+        let a_segments = segments(a);
+        for b in &strategy.buildings[i + 1..] {
+            if a.b_type != b.b_type { continue; }
+            let b_segments = segments(b);
+            let collides = a_segments.iter().any(|&(a_from, a_until, ax, ay)| b_segments.iter().any(|&(b_from, b_until, bx, by)| {
+                ax < bx + b.width && ax + a.width > bx && ay < by + b.height && ay + a.height > by && a_from < b_until && b_from < a_until
+            }));
+            if collides {
+                findings.push(Finding::error(format!("{} (UID {}) 与 {} (UID {}) 同时占用重叠的格子", a.name, a.uid, b.name, b.uid)).at(FindingTarget::Building(b.uid)));
+            }
+        }
+    }
+    findings
+}
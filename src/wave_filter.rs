@@ -0,0 +1,55 @@
+use eframe::egui;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::PlacedBuilding;
+use crate::utils::get_time_value;
+
+// 🔥 波次范围筛选：只绘制和列出指定波次范围内的建筑与事件，后期的布局不会挡住前期规划。
+// 事件按所在波次判断；建筑只要存在的时间段与范围有交集就显示
+
+#[derive(Clone, Copy)]
+pub(crate) struct WaveFilter {
+    pub(crate) enabled: bool,
+    pub(crate) min: i32,
+    pub(crate) max: i32,
+}
+
+impl Default for WaveFilter {
+    fn default() -> Self { Self { enabled: false, min: 1, max: 10 } }
+}
+
+impl WaveFilter {
+    pub(crate) fn contains(&self, wave_num: i32) -> bool {
+        !self.enabled || (self.min..=self.max).contains(&wave_num)
+    }
+
+    // 存在时间 [t_create, t_removed) 与范围（min 前期 ~ max 后期）相交
+    pub(crate) fn overlaps(&self, t_create: i32, t_removed: i32) -> bool {
+        !self.enabled || (t_create <= get_time_value(self.max, true) && t_removed > get_time_value(self.min, false))
+    }
+}
+
+impl MapEditor {
+    pub(crate) fn show_wave_filter(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.wave_filter.enabled, tr("⏳ 波次范围")).on_hover_text(tr("只显示并列出该范围内的建筑与事件"));
+            if !self.wave_filter.enabled { return; }
+            let filter = &mut self.wave_filter;
+            ui.add(egui::DragValue::new(&mut filter.min).clamp_range(1..=filter.max));
+            ui.label("–");
+            ui.add(egui::DragValue::new(&mut filter.max).clamp_range(filter.min..=999));
+            if ui.small_button(tr("仅当前波次")).clicked() { (filter.min, filter.max) = (self.current_wave_num, self.current_wave_num); }
+        });
+    }
+
+    pub(crate) fn building_in_wave_filter(&self, b: &PlacedBuilding) -> bool {
+        self.wave_filter.overlaps(get_time_value(b.wave_num, b.is_late), self.get_building_demolish_time(b.uid))
+    }
+
+    // 事件列表末尾提示被筛掉的条数
+    pub(crate) fn show_filtered_count(&self, ui: &mut egui::Ui, waves: impl Iterator<Item = i32>) {
+        let hidden = waves.filter(|w| !self.wave_filter.contains(*w)).count();
+        if hidden > 0 { ui.weak(trf!("波次范围外的 {} 条已隐藏", hidden)); }
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+use eframe::egui;
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::{EditMode, MapBuildingsExport};
+use crate::utils::{get_time_value, wave_from_time_value};
+
+// 🔥 波次锁定：迭代后期布局时把已经定稿的前期波次锁住，防止误改。锁定随策略文件保存（locked_waves）。
+// 建造于锁定波次的建筑不能移动、删除或修改属性，锁定波次中的事件不能编辑、删除或批量操作；
+// 当前波次被锁定时也不能再往这一波放置建筑或添加事件。之后的波次对这些建筑添加拆除、迁移等事件不受影响，
+// 但会连带删除锁定波次中事件的操作（删除建筑、拆除或出售后删去之后的迁移）整体拒绝
+
+// 一个列表中新增、删除或修改、且修改前或后位于锁定波次的条目数
+fn locked_diff<T: PartialEq>(before: &[T], after: &[T], wave: fn(&T) -> i32, locked: &BTreeSet<i32>) -> usize {
+    let in_locked = |x: &&T| locked.contains(&wave(x));
+    before.iter().filter(in_locked).filter(|x| !after.contains(x)).count() + after.iter().filter(in_locked).filter(|x| !before.contains(x)).count()
+}
+
+// 整体改写策略（脚本、重新编号等）前后，锁定波次中变化的建筑与事件条数；不为 0 时应拒绝整个改写
+pub(crate) fn locked_changes(before: &MapBuildingsExport, after: &MapBuildingsExport, locked: &BTreeSet<i32>) -> usize {
+    locked_diff(&before.buildings, &after.buildings, |b| b.wave_num, locked) + locked_diff(&before.upgrades, &after.upgrades, |e| e.wave_num, locked)
+        + locked_diff(&before.demolishes, &after.demolishes, |e| e.wave_num, locked) + locked_diff(&before.sells, &after.sells, |e| e.wave_num, locked)
+        + locked_diff(&before.relocations, &after.relocations, |e| e.wave_num, locked) + locked_diff(&before.abilities, &after.abilities, |e| e.wave_num, locked)
+}
+
+impl MapEditor {
+    pub(crate) fn wave_locked(&self, wave: i32) -> bool {
+        self.locked_waves.contains(&wave)
+    }
+
+    pub(crate) fn time_locked(&self, t: i32) -> bool {
+        self.wave_locked(wave_from_time_value(t).0)
+    }
+
+    pub(crate) fn current_wave_locked(&self) -> bool {
+        self.wave_locked(self.current_wave_num)
+    }
+
+    // 在画布上点击会往当前波次添加事件的模式；布局模式仍保留右键菜单，放置预览会因锁定显示为不可放置
+    pub(crate) fn mode_adds_to_current_wave(&self) -> bool {
+        matches!(self.mode, EditMode::Upgrade | EditMode::Demolish | EditMode::Sell | EditMode::Relocate | EditMode::Ability)
+    }
+
+    // 建造波次被锁定的建筑
+    pub(crate) fn building_locked(&self, uid: usize) -> bool {
+        self.placed_buildings.iter().any(|b| b.uid == uid && self.wave_locked(b.wave_num))
+    }
+
+    // 删除建筑会连带删除引用它的全部事件
+    pub(crate) fn building_events_locked(&self, uid: usize) -> bool {
+        self.demolish_events.iter().any(|e| e.uid == uid && self.wave_locked(e.wave_num)) || self.sell_events.iter().any(|e| e.uid == uid && self.wave_locked(e.wave_num))
+            || self.upgrade_events.iter().any(|e| e.uid == Some(uid) && self.wave_locked(e.wave_num)) || self.relocations_locked_from(uid, i32::MIN)
+    }
+
+    // 拆除或出售会连带删除时间值 t 及之后的迁移
+    pub(crate) fn relocations_locked_from(&self, uid: usize, t: i32) -> bool {
+        self.relocate_events.iter().any(|r| r.uid == uid && get_time_value(r.wave_num, r.is_late) >= t && self.wave_locked(r.wave_num))
+    }
+
+    // locked 为 true 时提示并返回 true，调用方据此放弃操作
+    pub(crate) fn refuse_locked(&mut self, locked: bool) -> bool {
+        if locked { self.notifications.warn(tr("涉及已锁定的波次，请先在波次设置中解锁")); }
+        locked
+    }
+
+    pub(crate) fn show_wave_lock(&mut self, ui: &mut egui::Ui) {
+        let wave = self.current_wave_num;
+        let mut locked = self.wave_locked(wave);
+        if ui.checkbox(&mut locked, trf!("🔒 锁定第 {} 波", wave)).on_hover_text(tr("锁定后这一波的建筑与事件不能修改或删除，也不能再往这一波添加")).changed() {
+            if locked { self.locked_waves.insert(wave); } else { self.locked_waves.remove(&wave); }
+        }
+        if self.locked_waves.is_empty() { return; }
+        ui.horizontal_wrapped(|ui| {
+            ui.weak(tr("已锁定:"));
+            let mut unlock = None;
+            for &w in &self.locked_waves {
+                if ui.small_button(format!("W{} ✖", w)).on_hover_text(tr("解锁")).clicked() { unlock = Some(w); }
+            }
+            if let Some(w) = unlock { self.locked_waves.remove(&w); }
+        });
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeSet, HashMap};
+use eframe::egui::{self, Color32};
+
+use crate::app::MapEditor;
+use crate::i18n::{tr, trf};
+use crate::models::*;
+use crate::validation::{validate_strategy, Severity};
+
+// 🔥 整体平移波次：把全部（或波次范围内、指定种类的）建筑与事件一起提前或推后 N 波，用于重新安排整套策略的节奏。
+// 应用前在副本上平移并校验：任何条目早于第 1 波、移出或移入锁定的波次，或平移后新增了时间先后 / 占地冲突（例如建造晚于拆除、升级时建筑已不存在）都不能应用
+
+// 预览中最多列出的问题条数
+const MAX_LISTED: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct WaveShift {
+    delta: i32,
+    from_wave: i32,
+    to_wave: i32,
+    buildings: bool,
+    events: bool,
+    notes: bool,
+}
+
+struct ShiftPreview {
+    data: MapBuildingsExport,
+    moved: usize,
+    below_first: usize,
+    locked: usize,
+    problems: Vec<String>,
+}
+
+pub(crate) struct WaveShiftDialog {
+    shift: WaveShift,
+    preview: Option<(WaveShift, ShiftPreview)>,
+}
+
+// 平移波次范围内的条目，返回平移的条目数与平移后早于第 1 波的条目数
+pub(crate) fn shift_strategy_waves(data: &mut MapBuildingsExport, shift: &WaveShift) -> (usize, usize) {
+    let (mut moved, mut below_first) = (0, 0);
+    let in_range = |wave: i32| (shift.from_wave..=shift.to_wave).contains(&wave);
+    let mut apply = |wave: &mut i32, enabled: bool| {
+        if !enabled || !in_range(*wave) { return; }
+        *wave += shift.delta;
+        moved += 1;
+        if *wave < 1 { below_first += 1; }
+    };
+    for b in &mut data.buildings { apply(&mut b.wave_num, shift.buildings); }
+    for e in &mut data.upgrades { apply(&mut e.wave_num, shift.events); }
+    for e in &mut data.demolishes { apply(&mut e.wave_num, shift.events); }
+    for e in &mut data.sells { apply(&mut e.wave_num, shift.events); }
+    for e in &mut data.relocations { apply(&mut e.wave_num, shift.events); }
+    for e in &mut data.abilities { apply(&mut e.wave_num, shift.events); }
+    if shift.notes {
+        // 平移后与范围外的备注落在同一波时合并，不丢内容
+        let (mut shifted, mut notes) = (Vec::new(), HashMap::new());
+        for (wave, note) in std::mem::take(&mut data.wave_notes) {
+            if in_range(wave) { shifted.push((wave, note)); } else { notes.insert(wave, note); }
+        }
+        for (mut wave, note) in shifted {
+            apply(&mut wave, true);
+            notes.entry(wave).and_modify(|n| { n.push('\n'); n.push_str(&note); }).or_insert(note);
+        }
+        data.wave_notes = notes;
+    }
+    (moved, below_first)
+}
+
+// 会被平移、且平移前或平移后处于锁定波次的条目数（不含波次备注）
+fn locked_shift_count(data: &MapBuildingsExport, shift: &WaveShift, locked: &BTreeSet<i32>) -> usize {
+    let hits = |wave: i32, enabled: bool| enabled && (shift.from_wave..=shift.to_wave).contains(&wave) && (locked.contains(&wave) || locked.contains(&(wave + shift.delta)));
+    let events = data.upgrades.iter().map(|e| e.wave_num).chain(data.demolishes.iter().map(|e| e.wave_num)).chain(data.sells.iter().map(|e| e.wave_num))
+        .chain(data.relocations.iter().map(|e| e.wave_num)).chain(data.abilities.iter().map(|e| e.wave_num));
+    data.buildings.iter().filter(|b| hits(b.wave_num, shift.buildings)).count() + events.filter(|&w| hits(w, shift.events)).count()
+}
+
+impl MapEditor {
+    pub(crate) fn open_wave_shift_dialog(&mut self) {
+        let data = self.build_buildings_export();
+        let last = data.buildings.iter().map(|b| b.wave_num)
+            .chain(data.upgrades.iter().map(|e| e.wave_num)).chain(data.demolishes.iter().map(|e| e.wave_num)).chain(data.sells.iter().map(|e| e.wave_num))
+            .chain(data.relocations.iter().map(|e| e.wave_num)).chain(data.abilities.iter().map(|e| e.wave_num)).max().unwrap_or(1);
+        let shift = WaveShift { delta: 1, from_wave: 1, to_wave: last, buildings: true, events: true, notes: true };
+        self.wave_shift_dialog = Some(WaveShiftDialog { shift, preview: None });
+    }
+
+    // 在副本上平移并校验，只保留平移后新增的错误
+    fn preview_wave_shift(&self, shift: &WaveShift) -> ShiftPreview {
+        let mut data = self.build_buildings_export();
+        let grid = Some((self.grid_rows, self.grid_cols));
+        let errors = |data: &MapBuildingsExport| validate_strategy(data, grid, None).into_iter().filter(|f| f.severity == Severity::Error).map(|f| f.message).collect::<Vec<_>>();
+        let before = errors(&data);
+        let locked = locked_shift_count(&data, shift, &self.locked_waves);
+        let (moved, below_first) = shift_strategy_waves(&mut data, shift);
+        let problems = if below_first > 0 { Vec::new() } else { errors(&data).into_iter().filter(|m| !before.contains(m)).collect() };
+        ShiftPreview { data, moved, below_first, locked, problems }
+    }
+
+    pub(crate) fn show_wave_shift_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.wave_shift_dialog.take() else { return; };
+        let (mut open, mut apply, mut cancel) = (true, false, false);
+        egui::Window::new(tr("整体平移波次")).id(egui::Id::new("wave_shift")).open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+            let shift = &mut dialog.shift;
+            ui.horizontal(|ui| {
+                ui.label(tr("平移:"));
+                ui.add(egui::DragValue::new(&mut shift.delta).clamp_range(-50..=50).prefix("±").suffix(tr(" 波")));
+            });
+            ui.horizontal(|ui| {
+                ui.label(tr("波次范围: 从"));
+                ui.add(egui::DragValue::new(&mut shift.from_wave).clamp_range(1..=shift.to_wave).prefix("W"));
+                ui.label(tr("到"));
+                ui.add(egui::DragValue::new(&mut shift.to_wave).clamp_range(shift.from_wave..=999).prefix("W"));
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut shift.buildings, tr("建筑"));
+                ui.checkbox(&mut shift.events, tr("升级 / 拆除 / 出售 / 迁移 / 技能"));
+                ui.checkbox(&mut shift.notes, tr("波次备注"));
+            });
+            let shift = dialog.shift;
+            if dialog.preview.as_ref().is_none_or(|(cached, _)| *cached != shift) { dialog.preview = Some((shift, self.preview_wave_shift(&shift))); }
+            let Some((_, preview)) = &dialog.preview else { return; };
+            ui.separator();
+            ui.label(trf!("将平移 {} 项", preview.moved));
+            if preview.below_first > 0 { ui.colored_label(Color32::RED, trf!("{} 项会早于第 1 波", preview.below_first)); }
+            if preview.locked > 0 { ui.colored_label(Color32::RED, trf!("{} 项会移出或移入已锁定的波次", preview.locked)); }
+            if !preview.problems.is_empty() {
+                ui.colored_label(Color32::RED, trf!("平移后新增 {} 个问题:", preview.problems.len()));
+                for problem in preview.problems.iter().take(MAX_LISTED) { ui.label(format!("• {}", problem)); }
+                if preview.problems.len() > MAX_LISTED { ui.weak(trf!("…等 {} 个", preview.problems.len())); }
+            }
+            let valid = shift.delta != 0 && preview.moved > 0 && preview.below_first == 0 && preview.locked == 0 && preview.problems.is_empty();
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(valid, egui::Button::new(tr("应用"))).clicked();
+                cancel = ui.button(tr("取消")).clicked();
+            });
+        });
+        // 应用时按当前数据重新平移一次，打开窗口后的编辑不会被预览时的副本覆盖
+        let preview = apply.then(|| self.preview_wave_shift(&dialog.shift)).filter(|p| p.below_first == 0 && p.locked == 0 && p.problems.is_empty());
+        if let Some(preview) = preview {
+            let moved = preview.moved;
+            self.apply_buildings_data(preview.data);
+            self.notifications.info(trf!("已平移 {} 项的波次", moved));
+        } else if open && !cancel {
+            self.wave_shift_dialog = Some(dialog);
+        }
+    }
+}